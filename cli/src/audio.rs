@@ -1,3 +1,5 @@
+mod resampler;
+
 use cpal::traits::HostTrait;
 use derive_more::Debug;
 use faad2::{version, Decoder};
@@ -9,6 +11,8 @@ use std::thread;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::UnboundedSender;
 
+use resampler::Resampler;
+
 #[derive(Debug)]
 pub enum AudioEvent {
     LevelsUpdated(AudioLevels),
@@ -109,9 +113,20 @@ impl AudioLevels {
     }
 }
 
+// JACK output (linux / jack feature only)
+#[derive(Debug, Clone)]
+#[cfg_attr(not(all(feature = "jack", target_os = "linux")), allow(dead_code))]
+pub struct JackOptions {
+    // client name, cpal appends "_out"
+    pub name: String,
+    // connect the output ports to the first two system:playback ports
+    pub connect: bool,
+}
+
 #[derive(Debug)]
 pub struct AudioDecoder {
     scid: u8,
+    jack: Option<JackOptions>,
     asc: Vec<u8>,
     audio_format: AudioFormat,
     #[debug(skip)]
@@ -120,9 +135,14 @@ pub struct AudioDecoder {
     _stream: OutputStream,
     #[debug(skip)]
     sink: Arc<Mutex<Sink>>,
+    // devices often run at a fixed rate, DAB+ is 32 or 48 kHz
+    #[debug(skip)]
+    resampler: Resampler,
     #[debug(skip)]
     tx: UnboundedSender<AudioEvent>,
     levels: AudioLevels,
+    muted: bool,
+    paused: bool,
 }
 
 impl AudioDecoder {
@@ -133,7 +153,7 @@ impl AudioDecoder {
 
     pub fn new(
         scid: u8,
-        #[allow(unused_variables)] use_jack: bool,
+        jack: Option<JackOptions>,
         initial_audio_format: AudioFormat,
         tx: UnboundedSender<AudioEvent>,
     ) -> Self {
@@ -142,39 +162,154 @@ impl AudioDecoder {
 
         tracing::debug!("Audio format: {}", initial_audio_format);
 
-        let host: cpal::Host = {
-            #[cfg(all(feature = "jack", target_os = "linux"))]
-            if use_jack {
-                cpal::host_from_id(cpal::HostId::Jack).expect("JACK host not available")
-            } else {
-                cpal::default_host()
-            }
-            #[cfg(not(all(feature = "jack", target_os = "linux")))]
-            {
-                cpal::default_host()
-            }
+        let (stream_handle, sink) = Self::open_output(jack.as_ref());
+        let resampler = Self::create_resampler(&initial_audio_format, &stream_handle);
+
+        let audio_decoder = Self {
+            scid,
+            jack,
+            asc,
+            audio_format: initial_audio_format,
+            decoder,
+            _stream: stream_handle,
+            sink,
+            resampler,
+            tx,
+            levels: AudioLevels::new(),
+            muted: false,
+            paused: false,
         };
 
+        audio_decoder.fade_in();
+
+        audio_decoder
+    }
+
+    fn open_output(
+        #[allow(unused_variables)] jack: Option<&JackOptions>,
+    ) -> (OutputStream, Arc<Mutex<Sink>>) {
+        #[cfg(all(feature = "jack", target_os = "linux"))]
+        if let Some(jack) = jack {
+            return Self::open_jack_output(jack);
+        }
+
+        let host = cpal::default_host();
+
         tracing::debug!("Available audio backends: {:?}", cpal::available_hosts());
         tracing::debug!("Selected audio backend: {:?}", host.id());
 
         let device = host
             .default_output_device()
             .expect("Unable to get default device");
+
+        Self::open_stream(device)
+    }
+
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    fn open_jack_output(options: &JackOptions) -> (OutputStream, Arc<Mutex<Sink>>) {
+        let mut host = cpal::platform::JackHost::new().expect("JACK host not available");
+        // connections are made below, so missing system ports are not an error
+        host.set_connect_automatically(false);
+
+        tracing::debug!(
+            "Selected audio backend: JACK - client: {}_out",
+            options.name
+        );
+
+        let device: cpal::Device = host
+            .output_device_with_name(&options.name)
+            .expect("Unable to create JACK device")
+            .into();
+
+        // ports are registered once the stream is open
+        let output = Self::open_stream(device);
+
+        if options.connect {
+            Self::connect_jack_ports(&format!("{}_out", options.name));
+        }
+
+        output
+    }
+
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    fn connect_jack_ports(client_name: &str) {
+        let client = match jack::Client::new(
+            &format!("{}_connect", client_name),
+            jack::ClientOptions::NO_START_SERVER,
+        ) {
+            Ok((client, _status)) => client,
+            Err(e) => {
+                tracing::warn!("JACK: could not connect ports: {}", e);
+                return;
+            }
+        };
+
+        let outputs = client.ports(
+            Some(&format!("^{}:", client_name)),
+            None,
+            jack::PortFlags::IS_OUTPUT,
+        );
+        let playback = client.ports(Some("^system:playback_"), None, jack::PortFlags::IS_INPUT);
+
+        // e.g. no soundcard attached (yet) to the JACK server
+        if playback.is_empty() {
+            tracing::warn!(
+                "JACK: no system:playback ports - connect {} manually",
+                client_name
+            );
+            return;
+        }
+
+        for (src, dst) in outputs.iter().zip(playback.iter().take(2)) {
+            match client.connect_ports_by_name(src, dst) {
+                Ok(()) => tracing::debug!("JACK: connected {} > {}", src, dst),
+                Err(e) => tracing::warn!("JACK: could not connect {} > {}: {}", src, dst, e),
+            }
+        }
+    }
+
+    fn open_stream(device: cpal::Device) -> (OutputStream, Arc<Mutex<Sink>>) {
         let stream_handle = OutputStreamBuilder::from_device(device)
             .and_then(|x| x.open_stream())
             .expect("Error creating output stream");
         let sink = Arc::new(Mutex::new(Sink::connect_new(stream_handle.mixer())));
 
-        Self {
-            scid,
-            asc,
-            audio_format: initial_audio_format,
-            decoder,
-            _stream: stream_handle,
-            sink,
-            tx,
-            levels: AudioLevels::new(),
+        (stream_handle, sink)
+    }
+
+    fn create_resampler(audio_format: &AudioFormat, stream_handle: &OutputStream) -> Resampler {
+        Resampler::new(
+            audio_format.sample_rate_hz(),
+            stream_handle.config().sample_rate(),
+            audio_format.channels() as usize,
+        )
+    }
+
+    // the decoder has to be re-created when switching to another subchannel
+    pub fn needs_rebuild(&self, scid: u8, audio_format: &AudioFormat) -> bool {
+        self.scid != scid || &self.audio_format != audio_format
+    }
+
+    // muted: decoding continues, output is silenced
+    pub fn set_muted(&mut self, muted: bool) {
+        if self.muted != muted {
+            tracing::debug!("Audio muted: {}", muted);
+            self.muted = muted;
+        }
+    }
+
+    // paused: decoding continues, output is stopped and no samples are queued
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            tracing::debug!("Audio paused: {}", paused);
+            self.paused = paused;
+
+            let sink = self.sink.lock().unwrap();
+            if paused {
+                sink.pause();
+            } else {
+                sink.play();
+            }
         }
     }
 
@@ -185,22 +320,67 @@ impl AudioDecoder {
         );
         match Decoder::new(&new_audio_format.asc) {
             Ok(new_decoder) => {
+                let output_changed = output_changed(&self.audio_format, new_audio_format);
+
                 self.decoder = new_decoder;
                 self.audio_format = new_audio_format.clone();
                 self.asc = new_audio_format.asc.clone();
                 self.sink.lock().unwrap().stop();
+
+                // samplerate / channels changed: rebuild the output stream
+                if output_changed {
+                    tracing::debug!("Rebuilding output stream: {}", new_audio_format);
+                    let (stream_handle, sink) = Self::open_output(self.jack.as_ref());
+                    self._stream = stream_handle;
+                    self.sink = sink;
+                    self.fade_in();
+                }
+
+                self.levels = AudioLevels::new();
+
                 Ok(())
             }
             Err(_e) => Err(std::io::Error::other("Decoder error")),
         }
     }
 
+    fn fade_in(&self) {
+        self.sink.lock().unwrap().set_volume(0.0);
+
+        let sink_clone = Arc::clone(&self.sink);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+
+            let fade_duration = Duration::from_millis(200);
+            let steps = 20; // Update volume every 10ms
+            let step_duration = fade_duration / steps;
+            let volume_step = 1.0 / steps as f32;
+
+            for i in 1..=steps {
+                thread::sleep(step_duration);
+                if let Ok(sink) = sink_clone.lock() {
+                    sink.set_volume(i as f32 * volume_step);
+                }
+            }
+            // ensure volume is exactly 1.0 at the end
+            if let Ok(sink) = sink_clone.lock() {
+                sink.set_volume(1.0);
+            }
+        });
+    }
+
     /*
     fn fade(vol: f32, duration_ms: u64) {
         // implement generic fade logic
     }
     */
 
+    #[tracing::instrument(
+        level = "debug",
+        name = "audio",
+        skip_all,
+        fields(scid = aac_result.scid, frames = aac_result.frames.len())
+    )]
     pub fn feed(&mut self, aac_result: &AacpResult) {
         if let Some(new_audio_format) = &aac_result.audio_format {
             if new_audio_format != &self.audio_format && self.reconfigure(new_audio_format).is_err()
@@ -211,34 +391,12 @@ impl AudioDecoder {
         }
 
         if aac_result.scid != self.scid {
-            tracing::info!("Changed SCID: {} > {}", self.scid, aac_result.scid);
-
-            self.sink.lock().unwrap().set_volume(0.0);
-
-            let sink_clone = Arc::clone(&self.sink);
-            thread::spawn(move || {
-                thread::sleep(Duration::from_millis(50));
-
-                let fade_duration = Duration::from_millis(200);
-                let steps = 20; // Update volume every 10ms
-                let step_duration = fade_duration / steps;
-                let volume_step = 1.0 / steps as f32;
-
-                for i in 1..=steps {
-                    thread::sleep(step_duration);
-                    if let Ok(sink) = sink_clone.lock() {
-                        sink.set_volume(i as f32 * volume_step);
-                    }
-                }
-                // ensure volume is exactly 1.0 at the end
-                if let Ok(sink) = sink_clone.lock() {
-                    sink.set_volume(1.0);
-                }
-            });
-
-            self.levels = AudioLevels::new();
-
-            self.scid = aac_result.scid;
+            tracing::warn!(
+                "Audio data for SCID {} fed to decoder for SCID {}",
+                aac_result.scid,
+                self.scid
+            );
+            return;
         }
 
         for frame in &aac_result.frames {
@@ -249,11 +407,29 @@ impl AudioDecoder {
     pub fn feed_au(&mut self, au_data: &[u8]) {
         match self.decoder.decode(au_data) {
             Ok(r) => {
-                self.sink.lock().unwrap().append(SamplesBuffer::new(
-                    r.channels as u16,
-                    r.sample_rate as u32,
-                    r.samples,
-                ));
+                // nothing is queued while paused
+                if !self.paused {
+                    // the decoder output is authoritative, e.g. after a service switch
+                    let output_hz = self._stream.config().sample_rate();
+                    self.resampler
+                        .reconfigure(r.sample_rate as u32, output_hz, r.channels);
+
+                    let samples = self.resampler.process(r.samples);
+
+                    if !samples.is_empty() {
+                        let output_hz = self.resampler.output_hz();
+                        let sink = self.sink.lock().unwrap();
+                        if self.muted {
+                            sink.append(SamplesBuffer::new(
+                                r.channels as u16,
+                                output_hz,
+                                vec![0.0; samples.len()],
+                            ));
+                        } else {
+                            sink.append(SamplesBuffer::new(r.channels as u16, output_hz, samples));
+                        }
+                    }
+                }
 
                 self.levels.feed(r.channels, r.samples);
 
@@ -269,3 +445,34 @@ impl AudioDecoder {
 }
 
 unsafe impl Send for AudioDecoder {}
+
+// samplerate / channels differ: the output stream has to be rebuilt, not only the decoder
+fn output_changed(current: &AudioFormat, new: &AudioFormat) -> bool {
+    new.sample_rate_hz() != current.sample_rate_hz() || new.channels() != current.channels()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // superframe header byte 2, see AudioFormat::from_bytes
+    fn audio_format(h: u8, sf_len: usize) -> AudioFormat {
+        AudioFormat::from_bytes(&[0x00, 0x00, h, 0x09, 0x61], sf_len).unwrap()
+    }
+
+    #[test]
+    fn output_changed_on_format() {
+        // 48 kHz HE-AAC stereo
+        let current = audio_format(0x70, 480);
+
+        // 32 kHz, mono, both
+        assert!(output_changed(&current, &audio_format(0x30, 480)));
+        assert!(output_changed(&current, &audio_format(0x60, 480)));
+        assert!(output_changed(&current, &audio_format(0x20, 480)));
+        // PS: still stereo
+        assert!(!output_changed(&current, &audio_format(0x68, 480)));
+        // bitrate only: the decoder is rebuilt, the output is kept
+        assert!(!output_changed(&current, &audio_format(0x70, 960)));
+        assert!(!output_changed(&current, &current));
+    }
+}
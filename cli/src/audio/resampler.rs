@@ -0,0 +1,169 @@
+use rubato::{FftFixedIn, Resampler as _};
+
+// input frames per resampler pass. AUs are 960 / 1920 frames, the remainder is kept for the next call
+const CHUNK_FRAMES: usize = 1024;
+
+// converts interleaved samples from the decoded (source) rate to the output device rate
+pub struct Resampler {
+    channels: usize,
+    source_hz: u32,
+    output_hz: u32,
+    // none: rates match (or setup failed), samples are passed through
+    inner: Option<FftFixedIn<f32>>,
+    // per channel input not yet resampled
+    pending: Vec<Vec<f32>>,
+}
+
+impl Resampler {
+    pub fn new(source_hz: u32, output_hz: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+
+        let inner = if source_hz == output_hz {
+            None
+        } else {
+            match FftFixedIn::<f32>::new(
+                source_hz as usize,
+                output_hz as usize,
+                CHUNK_FRAMES,
+                2,
+                channels,
+            ) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    tracing::warn!(
+                        "Could not create resampler {} > {} Hz: {} - passing through",
+                        source_hz,
+                        output_hz,
+                        e
+                    );
+                    None
+                }
+            }
+        };
+
+        tracing::debug!(
+            "Resampler: {} > {} Hz, {} channels{}",
+            source_hz,
+            output_hz,
+            channels,
+            if inner.is_none() {
+                " (passthrough)"
+            } else {
+                ""
+            }
+        );
+
+        Self {
+            channels,
+            source_hz,
+            output_hz,
+            inner,
+            pending: vec![Vec::new(); channels],
+        }
+    }
+
+    pub fn matches(&self, source_hz: u32, output_hz: u32, channels: usize) -> bool {
+        self.source_hz == source_hz && self.output_hz == output_hz && self.channels == channels
+    }
+
+    // rebuilt if the decoded format changed, e.g. after a service switch. true if rebuilt
+    pub fn reconfigure(&mut self, source_hz: u32, output_hz: u32, channels: usize) -> bool {
+        if self.matches(source_hz, output_hz, channels) {
+            return false;
+        }
+        *self = Self::new(source_hz, output_hz, channels);
+        true
+    }
+
+    // rate of the samples returned by process()
+    pub fn output_hz(&self) -> u32 {
+        if self.inner.is_some() {
+            self.output_hz
+        } else {
+            self.source_hz
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let Some(inner) = self.inner.as_mut() else {
+            return samples.to_vec();
+        };
+
+        for (i, sample) in samples.iter().enumerate() {
+            self.pending[i % self.channels].push(*sample);
+        }
+
+        let mut out = Vec::new();
+
+        while self.pending[0].len() >= inner.input_frames_next() {
+            let n = inner.input_frames_next();
+            let chunk: Vec<Vec<f32>> = self
+                .pending
+                .iter_mut()
+                .map(|c| c.drain(..n).collect())
+                .collect();
+
+            match inner.process(&chunk, None) {
+                Ok(resampled) => {
+                    let frames = resampled.first().map(|c| c.len()).unwrap_or(0);
+                    out.reserve(frames * self.channels);
+                    for f in 0..frames {
+                        for channel in &resampled {
+                            out.push(channel[f]);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Resampler: {}", e);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upsample_32k_to_48k() {
+        let mut resampler = Resampler::new(32_000, 48_000, 2);
+        assert_eq!(resampler.output_hz(), 48_000);
+
+        // 10 AUs of 960 stereo frames: 9 full passes, 384 frames pending
+        let au = vec![0.0f32; 960 * 2];
+        let out: usize = (0..10).map(|_| resampler.process(&au).len()).sum();
+
+        assert_eq!(out, 9 * CHUNK_FRAMES * 3 / 2 * 2);
+        assert_eq!(resampler.pending[0].len(), 10 * 960 - 9 * CHUNK_FRAMES);
+    }
+
+    #[test]
+    fn passthrough() {
+        let mut resampler = Resampler::new(48_000, 48_000, 2);
+        assert_eq!(resampler.output_hz(), 48_000);
+        assert!(resampler.matches(48_000, 48_000, 2));
+
+        let samples = [0.5f32; 960 * 2];
+        assert_eq!(resampler.process(&samples), samples);
+    }
+
+    #[test]
+    fn reconfigure_on_format_change() {
+        let mut resampler = Resampler::new(48_000, 48_000, 2);
+        assert!(!resampler.reconfigure(48_000, 48_000, 2));
+
+        // 32 kHz mono
+        assert!(resampler.reconfigure(32_000, 48_000, 1));
+        assert!(resampler.matches(32_000, 48_000, 1));
+        assert_eq!(resampler.output_hz(), 48_000);
+
+        // pending input of the old format is dropped
+        resampler.process(&[0.0f32; 960]);
+        assert!(resampler.reconfigure(48_000, 48_000, 2));
+        assert_eq!(resampler.pending, [Vec::<f32>::new(), Vec::new()]);
+        assert_eq!(resampler.output_hz(), 48_000);
+    }
+}
@@ -1,34 +1,90 @@
 mod audio;
 mod tui;
 
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Once};
+use std::time::Duration;
 
 use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::EnvFilter;
 
-use tokio::io::Interest;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufWriter};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use tokio::sync::RwLock;
+use tokio::time::MissedTickBehavior;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use clap_num::maybe_hex;
 
 use shared::dab::bus::{init_event_bus, DabEvent};
-use shared::dab::{DabSource, Ensemble};
+use shared::dab::pad::mot::MotImage;
+use shared::dab::{
+    DabSource, DabSourceBuilder, DabStats, Ensemble, PaceMode, PadSelection, Service,
+};
 use shared::edi_frame_extractor::EdiFrameExtractor;
+use shared::utils::{connect_tcp, Endpoint};
 
-use audio::{AudioDecoder, AudioEvent};
+use audio::{AudioDecoder, AudioEvent, JackOptions};
 use tui::{TuiCommand, TuiEvent};
 
+// reconnect backoff, doubled on every failed attempt
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+// selected subchannel without audio for this long: likely a data subchannel or wrong SCID
+const NO_AUDIO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    /// Human readable log output
+    Text,
+    /// Newline-delimited JSON events on stdout ({"type": ..., "version": 1, ...}), logs go to stderr
+    Json,
+}
+
 /// EDInburgh
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// EDI host:port to connect to
-    #[arg(long, short)]
-    addr: String,
+    /// EDI host:port to connect to, IPv6 addresses in brackets e.g. [::1]:9000
+    #[arg(long, short, required_unless_present_any = ["file", "stdin"])]
+    addr: Option<Endpoint>,
+
+    /// Raw EDI dump to replay instead of connecting to --addr
+    #[arg(long, short, conflicts_with_all = ["addr", "stdin"])]
+    file: Option<PathBuf>,
+
+    /// Read the EDI stream from stdin, e.g. piped from a tuner tool
+    #[arg(long, default_value_t = false, conflicts_with_all = ["addr", "file"])]
+    stdin: bool,
+
+    /// Replay --file at the DAB frame rate (24ms) instead of as fast as possible
+    #[arg(long, default_value_t = false, requires = "file")]
+    realtime: bool,
+
+    /// Speed factor for --realtime, e.g. 0.5 or 2.0 (clamped to 0.1 - 16). 0: as fast as possible
+    #[arg(long, default_value_t = 1.0, requires = "realtime")]
+    speed: f32,
+
+    /// Local address to connect to --addr from, e.g. on hosts with multiple interfaces
+    #[arg(long, value_name = "LOCAL_ADDR", requires = "addr")]
+    bind: Option<IpAddr>,
+
+    /// Reconnect to --addr with backoff when the connection is closed or fails
+    #[arg(long, default_value_t = false, requires = "addr")]
+    reconnect: bool,
+
+    /// Save the raw EDI stream (complete AF frames) to file, can be replayed with --file
+    #[arg(long)]
+    save_raw: Option<PathBuf>,
 
     /// Subchannel ID to select [optional]
     #[arg(long, short, conflicts_with = "sid")]
@@ -38,20 +94,161 @@ struct Args {
     #[arg(long, short = 'S', value_parser = maybe_hex::<u16>, conflicts_with = "scid")]
     sid: Option<u16>,
 
-    /// Use Jack output. Device name is: cpal_client_out
+    /// Service to select by label, case-insensitive substring e.g. "radio 1" [optional]
+    #[arg(long, conflicts_with_all = ["scid", "sid"])]
+    service: Option<String>,
+
+    /// Use Jack output. Device name is: <jack-name>_out
     #[cfg(all(feature = "jack", target_os = "linux"))]
     #[arg(long, short, default_value_t = false)]
     jack: bool,
 
+    /// Connect the Jack output to the first two system:playback ports. If there are no system
+    /// playback ports (yet), a warning is logged and the ports have to be connected manually
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    #[arg(long, default_value_t = false, requires = "jack")]
+    jack_connect: bool,
+
+    /// Jack client name, "_out" is appended
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    #[arg(long, default_value = "cpal_client", requires = "jack")]
+    jack_name: String,
+
     /// Enable TUI
     #[arg(long, short, default_value_t = false)]
     tui: bool,
 
+    /// Forward stats and SLS images to the TUI at most every <ms>, intermediate ones are dropped
+    #[arg(long, value_name = "MS", default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    tui_interval: u64,
+
+    /// Output format (without TUI)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "tui")]
+    format: OutputFormat,
+
+    /// Decode DL and SLS only, no audio. Follows all subchannels or the ones given with --pad-scids
+    #[arg(long, default_value_t = false, conflicts_with_all = ["tui", "scid", "sid", "service"])]
+    metadata_only: bool,
+
+    /// Fall back to the primary audio service when the selected subchannel delivers no audio,
+    /// select it if no subchannel / service is given
+    #[arg(long, default_value_t = false, conflicts_with_all = ["tui", "metadata_only", "list"])]
+    auto: bool,
+
+    /// Subchannel IDs to decode PAD (DL / SLS) from, e.g. 1,4,7 [optional]
+    #[arg(long, value_delimiter = ',', requires = "metadata_only")]
+    pad_scids: Vec<u8>,
+
+    /// Save received SLS images to <dir>/<scid>/[<content name>-]<md5>.<ext>, each image once [optional]
+    #[arg(long, value_name = "DIR")]
+    save_sls: Option<PathBuf>,
+
+    /// Print the ensemble once it is complete and exit, no audio. Fails if there is none
+    /// within --list-timeout
+    #[arg(long, default_value_t = false, conflicts_with_all = ["tui", "metadata_only", "scid", "sid", "service"])]
+    list: bool,
+
+    /// Seconds to wait for a complete ensemble with --list
+    #[arg(long, value_name = "SECONDS", default_value_t = 10, requires = "list")]
+    list_timeout: u64,
+
     /// Verbose logging
     #[arg(long = "verbose", short = 'v')]
     verbose: bool,
 }
 
+// first service with the query in its label (case-insensitive). no / ambiguous matches are logged
+// once, the lookup runs on every ensemble update
+fn find_service_by_label<'a>(
+    ensemble: &'a Ensemble,
+    query: &str,
+    warned: &mut bool,
+) -> Option<&'a Service> {
+    let query = query.to_lowercase();
+
+    let matches: Vec<&Service> = ensemble
+        .services
+        .iter()
+        .filter(|s| {
+            s.label
+                .as_ref()
+                .is_some_and(|l| l.to_lowercase().contains(&query))
+        })
+        .collect();
+
+    if *warned {
+        return matches.first().copied();
+    }
+
+    match matches.len() {
+        0 => tracing::warn!("No service matching \"{}\"", query),
+        1 => {}
+        _ => tracing::warn!(
+            "{} services matching \"{}\": {} - selecting the first",
+            matches.len(),
+            query,
+            matches
+                .iter()
+                .map(|s| s.label.as_deref().unwrap_or_default().trim())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+
+    *warned = matches.len() != 1;
+
+    matches.first().copied()
+}
+
+// decoding as selected by args: PAD, audio frames (not with --metadata-only), pacing
+fn source_builder(args: &Args) -> DabSourceBuilder {
+    // one AF frame per 24ms DAB frame
+    let pace = if args.realtime {
+        PaceMode::Realtime
+    } else {
+        PaceMode::Off
+    };
+
+    let pad_selection = if args.pad_scids.is_empty() {
+        PadSelection::All
+    } else {
+        PadSelection::Scids(args.pad_scids.clone())
+    };
+
+    if args.metadata_only {
+        tracing::info!("Metadata only - PAD from: {:?}", pad_selection);
+    }
+
+    let mut builder = DabSource::builder()
+        .pad_selection(pad_selection)
+        .audio_frames(!args.metadata_only)
+        .enable_audio(!args.list)
+        .pace(pace)
+        .pace_speed(args.speed);
+
+    // the no-audio timeout starts once a subchannel is selected (here or by SID / TUI)
+    if !args.metadata_only && !args.list {
+        builder = builder.no_audio_timeout(NO_AUDIO_TIMEOUT);
+    }
+    if let Some(scid) = args.scid {
+        builder = builder.scid(scid);
+    }
+
+    builder
+}
+
+// wait for the backoff of this attempt, then connect
+async fn reconnect(addr: &str, bind: Option<IpAddr>, attempt: u32) -> io::Result<TcpStream> {
+    let backoff = RECONNECT_BACKOFF_MIN
+        .saturating_mul(1 << attempt.min(5))
+        .min(RECONNECT_BACKOFF_MAX);
+
+    tracing::debug!("Reconnecting to {} in {:?}", addr, backoff);
+    tokio::time::sleep(backoff).await;
+
+    connect_tcp(addr, bind).await
+}
+
 fn install_panic_hook() {
     std::panic::set_hook(Box::new(|info| {
         let _ = ratatui::crossterm::terminal::disable_raw_mode();
@@ -90,7 +287,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map(|lvl| lvl >= LevelFilter::DEBUG)
         .unwrap_or(false);
 
+    // keep stdout clean for JSON output
+    let writer = if args.format == OutputFormat::Json {
+        BoxMakeWriter::new(io::stderr)
+    } else {
+        BoxMakeWriter::new(io::stdout)
+    };
+
     tracing_subscriber::fmt()
+        .with_writer(writer)
         .with_env_filter(filter)
         .with_level(show_level)
         .with_target(show_level && !args.verbose)
@@ -101,15 +306,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let scid = Arc::new(RwLock::new(args.scid));
     let sid = args.sid;
+    let service = args.service.clone();
 
-    let use_jack: bool = {
+    let muted = Arc::new(RwLock::new(false));
+    let paused = Arc::new(RwLock::new(false));
+
+    let jack: Option<JackOptions> = {
         #[cfg(all(feature = "jack", target_os = "linux"))]
         {
-            args.jack
+            args.jack.then(|| JackOptions {
+                name: args.jack_name.clone(),
+                connect: args.jack_connect,
+            })
         }
         #[cfg(not(all(feature = "jack", target_os = "linux")))]
         {
-            false
+            None
         }
     };
 
@@ -123,12 +335,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // TUI audio -> TUI
     let (audio_tx, audio_rx) = unbounded_channel::<AudioEvent>();
 
+    let source_name = match (&args.addr, &args.file) {
+        (Some(addr), _) => addr.to_string(),
+        (None, Some(file)) => file.display().to_string(),
+        _ if args.stdin => "stdin".to_string(),
+        _ => unreachable!("clap requires --addr, --file or --stdin"),
+    };
+
     let tui_enabled = args.tui;
+    let format = args.format;
 
     // check if this is a good idea?
     if tui_enabled {
         tokio::spawn({
-            let addr = args.addr.clone();
+            let addr = source_name.clone();
             let tui_tx = tui_tx.clone();
             let scid = *scid.read().await;
             async move {
@@ -140,27 +360,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
     }
 
-    #[allow(clippy::type_complexity)]
-    let on_ensemble_updated_callback: Option<Box<dyn FnMut(&Ensemble) + Send>> = Some(Box::new({
+    // set on the first complete ensemble, ends --list
+    let ensemble_complete = Arc::new(AtomicBool::new(false));
+
+    let on_ensemble_updated_callback = {
         let scid = Arc::clone(&scid);
+        let ensemble_complete = Arc::clone(&ensemble_complete);
+        let mut label_warned = false;
         move |e: &Ensemble| {
             if !e.complete {
                 return;
             }
 
-            if !tui_enabled {
+            ensemble_complete.store(true, Ordering::Relaxed);
+
+            if !tui_enabled && format == OutputFormat::Text {
                 print_ensemble(e);
             }
 
             // how ugly can it get ;)
-            if let Some(sid) = sid {
+            if sid.is_some() || service.is_some() {
                 let scid_selected = !scid.try_read().map(|g| g.is_none()).unwrap_or(false);
 
                 if !scid_selected {
-                    let svc = e.services.iter().find(|s| s.sid == sid);
-                    let component = svc.and_then(|s| s.components.first());
+                    let svc = match (sid, &service) {
+                        (Some(sid), _) => e.services.iter().find(|s| s.sid == sid),
+                        (None, Some(query)) => find_service_by_label(e, query, &mut label_warned),
+                        _ => None,
+                    };
+                    let component = svc.and_then(|s| s.primary_component());
 
-                    if let Some(c) = component {
+                    if let (Some(svc), Some(c)) = (svc, component) {
                         let scid = Arc::clone(&scid);
                         let selected_scid = c.subchannel_id;
 
@@ -168,83 +398,195 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             *scid.write().await = selected_scid;
                         });
 
-                        tracing::info!("Select SubCh {} for SID 0x{:4X}", c.scid, sid);
+                        tracing::info!("Select SubCh {} for SID 0x{:4X}", c.scid, svc.sid);
                     }
                 }
             }
         }
-    }));
+    };
+
+    if let Some(dir) = &args.save_sls {
+        tokio::fs::create_dir_all(dir).await?;
+    }
 
-    let mut source = DabSource::new(args.scid, on_ensemble_updated_callback, None);
+    let mut source = source_builder(&args)
+        .on_ensemble(on_ensemble_updated_callback)
+        .build();
 
     let edi_rx = init_event_bus();
 
-    // let stream = TcpStream::connect(args.addr).await?;
-
-    let stream = match TcpStream::connect(args.addr.clone()).await {
-        Ok(s) => s,
-        Err(e) => {
-            tracing::error!("Unable to connect to {}: {}", args.addr, e);
-            return Err(e.into());
+    let mut reader: Pin<Box<dyn AsyncRead + Send>> = if let Some(addr) = &args.addr {
+        match connect_tcp(&addr.to_string(), args.bind).await {
+            Ok(s) => Box::pin(s),
+            Err(e) => {
+                tracing::error!("Unable to connect to {}: {}", addr, e);
+                return Err(e.into());
+            }
+        }
+    } else if args.stdin {
+        Box::pin(tokio::io::stdin())
+    } else {
+        match File::open(args.file.as_ref().unwrap()).await {
+            Ok(f) => Box::pin(f),
+            Err(e) => {
+                tracing::error!("Unable to open {}: {}", source_name, e);
+                return Err(e.into());
+            }
         }
     };
 
+    let mut raw_writer = match &args.save_raw {
+        Some(path) => match File::create(path).await {
+            Ok(f) => {
+                tracing::info!("Saving raw EDI stream to {}", path.display());
+                Some(BufWriter::new(f))
+            }
+            Err(e) => {
+                tracing::error!("Unable to create {}: {}", path.display(), e);
+                return Err(e.into());
+            }
+        },
+        None => None,
+    };
+
     let mut filled = 0;
 
+    // reader is only polled while connected, otherwise the reconnect branch runs
+    let mut connected = true;
+    let mut reconnect_attempt: u32 = 0;
+    // the pending attempt, created once and kept across loop iterations: rebuilding it in the
+    // select would restart the backoff whenever another branch (e.g. the TUI) fires first
+    let mut reconnecting: Option<Pin<Box<dyn Future<Output = io::Result<TcpStream>> + '_>>> = None;
+
     let mut extractor = EdiFrameExtractor::new();
 
+    let connection_status = |event: TuiEvent| {
+        if tui_enabled {
+            let _ = tui_tx.send(event);
+        }
+    };
+
     let event_handler = DabEventHandler::new(
         Arc::clone(&scid),
-        use_jack,
+        Arc::clone(&muted),
+        Arc::clone(&paused),
+        jack,
+        format,
         edi_rx,
         tui_tx.clone(),
         audio_tx.clone(),
+    )
+    .metadata_output(args.metadata_only, args.save_sls.clone())
+    .tui_interval(Duration::from_millis(args.tui_interval))
+    .auto(
+        args.auto && args.scid.is_none() && args.sid.is_none() && args.service.is_none(),
+        args.auto,
     );
 
-    tokio::spawn(async move {
+    let event_handler = tokio::spawn(async move {
         event_handler.run().await;
     });
 
+    let list_deadline = tokio::time::sleep(Duration::from_secs(args.list_timeout));
+    tokio::pin!(list_deadline);
+
     loop {
+        if args.list && ensemble_complete.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if !connected && reconnecting.is_none() {
+            reconnecting = Some(Box::pin(reconnect(
+                &source_name,
+                args.bind,
+                reconnect_attempt,
+            )));
+        }
+
         tokio::select! {
 
-            // EDI TCP stream
-            ready = stream.ready(Interest::READABLE) => {
-                let ready = ready?;
-                if ready.is_readable() {
-                    match stream.try_read(&mut extractor.frame.data[filled..]) {
-                        Ok(0) => {
+            // EDI TCP stream / file
+            n = reader.read(&mut extractor.frame.data[filled..]), if connected => {
+                match n {
+                    Ok(0) => {
+                        if args.file.is_some() || args.stdin {
+                            tracing::info!("End of file reached");
+                        } else {
                             tracing::info!("Connection closed by peer");
+                        }
+                        if !args.reconnect {
                             break;
                         }
-                        Ok(n) => {
-                            filled += n;
-                            if filled < extractor.frame.data.len() {
-                                continue;
-                            }
-                            if let Some(offset) = extractor.frame.find_sync_magic() {
-                                if offset > 0 {
-                                    extractor.frame.data.copy_within(offset.., 0);
-                                    filled -= offset;
-                                    continue;
-                                }
-
-                                if extractor.frame.check_completed() {
-                                    source.feed(&extractor.frame.data).await;
-                                    // println!("frame completed: {}", extractor.frame);
-                                    extractor.frame.reset();
-                                    filled = 0;
-                                }
-                            }
+                        // start over with a clean extractor and ensemble
+                        extractor.frame.reset();
+                        filled = 0;
+                        source.reset();
+                        connected = false;
+                        reconnect_attempt = 0;
+                        connection_status(TuiEvent::Reconnecting(1));
+                    }
+                    Ok(n) => {
+                        if !frame_completed(&mut extractor, &mut filled, n) {
+                            continue;
+                        }
+                        // complete frames only, so the capture is self-syncing
+                        if let Some(ref mut w) = raw_writer {
+                            w.write_all(&extractor.frame.data).await?;
                         }
-                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
-                        Err(e) => {
-                            return Err(e.into());
-                        },
+                        // the SCID is changed from the TUI and the ensemble callback (--sid / --service)
+                        if let Some(selected) = scid.try_read().ok().and_then(|g| *g) {
+                            source.set_scid(selected);
+                        }
+                        source.feed(&extractor.frame.data).await;
+                        // println!("frame completed: {}", extractor.frame);
+                        extractor.frame.reset();
+                        filled = 0;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    Err(e) if args.reconnect => {
+                        tracing::warn!("Connection error: {}", e);
+                        extractor.frame.reset();
+                        filled = 0;
+                        source.reset();
+                        connected = false;
+                        reconnect_attempt = 0;
+                        connection_status(TuiEvent::Reconnecting(1));
+                    }
+                    Err(e) => {
+                        return Err(e.into());
+                    },
+                }
+            }
+
+            // EDI TCP reconnect (--reconnect)
+            stream = async { reconnecting.as_mut().expect("reconnect attempt").await }, if !connected => {
+                reconnecting = None;
+                match stream {
+                    Ok(s) => {
+                        tracing::info!("Reconnected to {}", source_name);
+                        reader = Box::pin(s);
+                        connected = true;
+                        connection_status(TuiEvent::Connected);
+                    }
+                    Err(e) => {
+                        reconnect_attempt += 1;
+                        tracing::warn!("Unable to reconnect to {}: {}", source_name, e);
+                        connection_status(TuiEvent::Reconnecting(reconnect_attempt + 1));
                     }
                 }
             }
 
+            // --list without a complete ensemble
+            _ = &mut list_deadline, if args.list => {
+                break;
+            }
+
+            // SIGINT
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Interrupted");
+                break;
+            }
+
             // TUI command handler
             Some(cmd) = tui_cmd_rx.recv() => {
                 match cmd {
@@ -252,6 +594,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let mut scid = scid.write().await;
                         *scid = Some(scid_val);
                     }
+                    TuiCommand::SetMuted(muted_val) => {
+                        *muted.write().await = muted_val;
+                    }
+                    TuiCommand::SetPaused(paused_val) => {
+                        *paused.write().await = paused_val;
+                    }
                     TuiCommand::Shutdown => {
                         break;
                     }
@@ -260,15 +608,79 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(ref mut w) = raw_writer {
+        w.flush().await?;
+    }
+
+    if args.list {
+        // let the handler drain the bus, e.g. the JSON ensemble event
+        source.shutdown();
+        let _ = event_handler.await;
+
+        if !ensemble_complete.load(Ordering::Relaxed) {
+            tracing::error!("No complete ensemble from {}", source_name);
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no complete ensemble").into());
+        }
+    }
+
     Ok(())
 }
 
+// n more bytes were read into extractor.frame.data[filled..]. true once the frame holds a complete
+// AF packet, the caller resets the frame and filled after using it
+fn frame_completed(extractor: &mut EdiFrameExtractor, filled: &mut usize, n: usize) -> bool {
+    *filled += n;
+    if *filled < extractor.frame.data.len() {
+        return false;
+    }
+
+    match extractor.frame.find_sync_magic() {
+        Some(offset) if offset > 0 => {
+            extractor.frame.data.copy_within(offset.., 0);
+            *filled -= offset;
+            false
+        }
+        Some(_) => match extractor.frame.check_completed() {
+            Ok(completed) => completed,
+            Err(e) => {
+                // corrupt header: resync on the next "AF"
+                tracing::warn!("{}", e);
+                *filled = extractor.resync(*filled);
+                false
+            }
+        },
+        None => {
+            // no sync in the buffer, keep a trailing 'A'
+            *filled = extractor.resync(*filled);
+            false
+        }
+    }
+}
+
 struct DabEventHandler {
     edi_rx: UnboundedReceiver<DabEvent>,
     scid: Arc<RwLock<Option<u8>>>,
-    use_jack: bool,
+    muted: Arc<RwLock<bool>>,
+    paused: Arc<RwLock<bool>>,
+    jack: Option<JackOptions>,
+    format: OutputFormat,
     audio_decoder: Option<AudioDecoder>,
+    // metadata only: DL text to stdout (text format)
+    print_dl: bool,
+    save_sls: Option<PathBuf>,
+    // (scid, md5) of the images already saved, the carousel repeats them
+    saved_sls: HashSet<(u8, [u8; 16])>,
+    // --auto: select the primary audio service initially / when the selected SCID has no audio
+    auto_select: bool,
+    auto_fallback: bool,
+    ensemble: Option<Ensemble>,
+    no_audio_scids: HashSet<u8>,
     // tui
+    tui_has_ensemble: bool,
+    // stats and MOT images are coalesced, the latest ones go out every tui_interval
+    tui_interval: Duration,
+    pending_stats: Option<DabStats>,
+    pending_mot: HashMap<u8, MotImage>,
     tui_tx: UnboundedSender<TuiEvent>,
     audio_tx: UnboundedSender<AudioEvent>,
 }
@@ -278,7 +690,10 @@ struct DabEventHandler {
 impl DabEventHandler {
     pub fn new(
         scid: Arc<RwLock<Option<u8>>>,
-        use_jack: bool,
+        muted: Arc<RwLock<bool>>,
+        paused: Arc<RwLock<bool>>,
+        jack: Option<JackOptions>,
+        format: OutputFormat,
         edi_rx: UnboundedReceiver<DabEvent>,
         tui_tx: UnboundedSender<TuiEvent>,
         audio_tx: UnboundedSender<AudioEvent>,
@@ -286,24 +701,129 @@ impl DabEventHandler {
         Self {
             edi_rx,
             scid,
-            use_jack,
+            muted,
+            paused,
+            jack,
+            format,
             audio_decoder: None,
+            print_dl: false,
+            save_sls: None,
+            saved_sls: HashSet::new(),
+            auto_select: false,
+            auto_fallback: false,
+            ensemble: None,
+            no_audio_scids: HashSet::new(),
+            tui_has_ensemble: false,
+            tui_interval: Duration::from_millis(100),
+            pending_stats: None,
+            pending_mot: HashMap::new(),
             tui_tx,
             audio_tx,
         }
     }
 
+    pub fn metadata_output(mut self, print_dl: bool, save_sls: Option<PathBuf>) -> Self {
+        self.print_dl = print_dl;
+        self.save_sls = save_sls;
+        self
+    }
+
+    pub fn tui_interval(mut self, interval: Duration) -> Self {
+        self.tui_interval = interval;
+        self
+    }
+
+    pub fn auto(mut self, select: bool, fallback: bool) -> Self {
+        self.auto_select = select;
+        self.auto_fallback = fallback;
+        self
+    }
+
+    // first service with an audio primary component, skipping SCIDs without audio
+    fn primary_audio_scid(&self) -> Option<(u8, String)> {
+        self.ensemble.as_ref()?.services.iter().find_map(|svc| {
+            let c = svc.primary_component()?;
+            let scid = c.subchannel_id?;
+            if c.ascty.is_none() || self.no_audio_scids.contains(&scid) {
+                return None;
+            }
+            Some((scid, svc.label.clone().unwrap_or_default()))
+        })
+    }
+
+    async fn select_primary_audio(&mut self, reason: &str) {
+        match self.primary_audio_scid() {
+            Some((scid, label)) => {
+                tracing::info!("{} - selecting SubCh {} ({})", reason, scid, label);
+                *self.scid.write().await = Some(scid);
+            }
+            None => tracing::warn!("{} - no other audio service to select", reason),
+        }
+    }
+
+    // send the coalesced events, DL and ensemble events are never held back
+    fn flush_tui(&mut self) {
+        let stats = self.pending_stats.take().map(TuiEvent::DabStatsUpdated);
+        let images = self
+            .pending_mot
+            .drain()
+            .map(|(_, m)| TuiEvent::MotImageReceived(m));
+
+        for event in stats.into_iter().chain(images) {
+            if let Err(e) = self.tui_tx.send(event) {
+                tracing::warn!("Could not send TUI update: {:?}", e);
+            }
+        }
+    }
+
     pub async fn run(mut self) {
-        while let Some(event) = self.edi_rx.recv().await {
+        let mut tui_flush = tokio::time::interval(self.tui_interval);
+        tui_flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let event = tokio::select! {
+                event = self.edi_rx.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = tui_flush.tick() => {
+                    self.flush_tui();
+                    continue;
+                }
+            };
+
+            if self.format == OutputFormat::Json {
+                print_json(&event);
+            }
+
             match event {
                 DabEvent::EnsembleUpdated(ensemble) => {
-                    if ensemble.complete {
+                    if self.auto_select || self.auto_fallback {
+                        self.ensemble = Some(ensemble.clone());
+                    }
+                    if self.auto_select && ensemble.complete {
+                        self.auto_select = false;
+                        if self.scid.read().await.is_none() {
+                            self.select_primary_audio("No service given").await;
+                        }
+                    }
+
+                    // the TUI gets the first complete snapshot, deltas after that
+                    if ensemble.complete && !self.tui_has_ensemble {
                         tracing::debug!("[0x{:4X}] Ensemble updated", ensemble.eid.unwrap_or(0));
+                        self.tui_has_ensemble = true;
                         if let Err(e) = self.tui_tx.send(TuiEvent::EnsembleUpdated(ensemble)) {
                             tracing::warn!("Could not send TUI update: {:?}", e);
                         }
                     }
                 }
+                DabEvent::EnsembleChanged(delta) => {
+                    if self.tui_has_ensemble {
+                        if let Err(e) = self.tui_tx.send(TuiEvent::EnsembleChanged(delta)) {
+                            tracing::warn!("Could not send TUI update: {:?}", e);
+                        }
+                    }
+                }
                 DabEvent::AacpFramesExtracted(r) => {
                     let scid = *self.scid.read().await;
                     if r.scid == scid.unwrap_or(0) {
@@ -314,11 +834,21 @@ impl DabEventHandler {
 
                         let audio_format = r.audio_format.as_ref().unwrap();
 
+                        // drop the decoder on SCID or format change
+                        if self
+                            .audio_decoder
+                            .as_ref()
+                            .is_some_and(|d| d.needs_rebuild(r.scid, audio_format))
+                        {
+                            tracing::info!("Rebuilding audio decoder for SCID: {}", r.scid);
+                            self.audio_decoder = None;
+                        }
+
                         // create aduio decoder if needed
                         if self.audio_decoder.is_none() {
                             let audio_decoder = AudioDecoder::new(
                                 r.scid,
-                                self.use_jack,
+                                self.jack.clone(),
                                 audio_format.clone(),
                                 self.audio_tx.clone(),
                             );
@@ -327,10 +857,40 @@ impl DabEventHandler {
 
                         // feed audio decoder
                         if let Some(ref mut audio_decoder) = self.audio_decoder {
+                            audio_decoder.set_muted(*self.muted.read().await);
+                            audio_decoder.set_paused(*self.paused.read().await);
                             audio_decoder.feed(&r);
                         }
                     }
                 }
+                DabEvent::SubchannelAdded(sc) => {
+                    tracing::debug!("[{:2}] SubCh added", sc.id);
+                }
+                DabEvent::SubchannelRemoved { id } => {
+                    tracing::info!("[{:2}] SubCh removed", id);
+                }
+                DabEvent::Mp2FramesExtracted(r) => {
+                    // no MP2 decoder (yet), the frames are only extracted
+                    tracing::trace!("[{:2}] MP2: {} frames", r.scid, r.frames.len());
+                }
+                DabEvent::AudioFormatDetected { scid, format } => {
+                    tracing::info!("[{:2}] Audio format: {}", scid, format);
+                }
+                DabEvent::NoAudio { scid } => {
+                    tracing::warn!(
+                        "[{:2}] No audio for {}s - data subchannel or wrong SCID?",
+                        scid,
+                        NO_AUDIO_TIMEOUT.as_secs()
+                    );
+                    if let Err(e) = self.tui_tx.send(TuiEvent::NoAudio(scid)) {
+                        tracing::warn!("Could not send TUI update: {:?}", e);
+                    }
+                    if self.auto_fallback {
+                        self.no_audio_scids.insert(scid);
+                        self.select_primary_audio(&format!("No audio on SubCh {}", scid))
+                            .await;
+                    }
+                }
                 DabEvent::MotImageReceived(m) => {
                     tracing::debug!(
                         "[{:2}] MOT {:9} - {} bytes",
@@ -338,9 +898,12 @@ impl DabEventHandler {
                         m.mimetype.to_uppercase(),
                         m.data.len(),
                     );
-                    if let Err(e) = self.tui_tx.send(TuiEvent::MotImageReceived(m)) {
-                        tracing::warn!("Could not send TUI update: {:?}", e);
+                    if let Some(ref dir) = self.save_sls {
+                        if self.saved_sls.insert((m.scid, m.md5)) {
+                            save_sls_image(dir, &m).await;
+                        }
                     }
+                    self.pending_mot.insert(m.scid, m);
                 }
                 DabEvent::DlObjectReceived(d) => {
                     tracing::debug!(
@@ -349,18 +912,127 @@ impl DabEventHandler {
                         if d.is_dl_plus() { "+" } else { " " },
                         d.decode_label()
                     );
+                    // JSON output has it already
+                    if self.print_dl && self.format == OutputFormat::Text {
+                        println!("[{:2}] {}", d.scid, d.decode_label());
+                    }
                     if let Err(e) = self.tui_tx.send(TuiEvent::DlObjectReceived(d)) {
                         tracing::warn!("Could not send TUI update: {:?}", e);
                     }
                 }
-                DabEvent::DabStatsUpdated(s) => {
-                    if let Err(e) = self.tui_tx.send(TuiEvent::DabStatsUpdated(s)) {
+                DabEvent::EpgScheduleReceived(s) => {
+                    tracing::debug!(
+                        "EPG {} - {} programmes",
+                        s.service.as_deref().unwrap_or("-"),
+                        s.entries.len()
+                    );
+                }
+                DabEvent::AnnouncementActive {
+                    cluster,
+                    announcement_type,
+                    subchid,
+                } => {
+                    tracing::info!(
+                        "Announcement: {} - cluster {} - SubCh {}",
+                        announcement_type,
+                        cluster,
+                        subchid
+                    );
+                }
+                DabEvent::DateTimeUpdated { utc, lto } => {
+                    tracing::trace!("Date / time: {:?} - LTO: {:?}", utc, lto);
+                    if let Err(e) = self.tui_tx.send(TuiEvent::DateTimeUpdated(utc, lto)) {
                         tracing::warn!("Could not send TUI update: {:?}", e);
                     }
                 }
+                DabEvent::EncoderInfo { text } => {
+                    tracing::info!("Encoder: {}", text);
+                }
+                DabEvent::FicReceived { fibs } => {
+                    tracing::trace!("FIC: {} bytes", fibs.len());
+                }
+                DabEvent::DabStatsUpdated(s) => {
+                    self.pending_stats = Some(s);
+                }
+            }
+        }
+    }
+}
+
+// <dir>/<scid>/[<content name>-]<md5>.<ext>, existing files (e.g. from a previous run) are kept
+async fn save_sls_image(dir: &Path, m: &MotImage) {
+    let ext = match m.mimetype.as_str() {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        _ => "bin",
+    };
+
+    let name = match m.content_name.as_deref().map(sanitize_file_name) {
+        Some(name) if !name.is_empty() => format!("{}-{}.{}", name, m.md5_hex(), ext),
+        _ => format!("{}.{}", m.md5_hex(), ext),
+    };
+
+    let dir = dir.join(m.scid.to_string());
+    let path = dir.join(name);
+
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return;
+    }
+
+    if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+        tracing::warn!("Could not create {}: {}", dir.display(), e);
+        return;
+    }
+
+    match tokio::fs::write(&path, &m.data).await {
+        Ok(()) => tracing::debug!("[{:2}] SLS saved: {}", m.scid, path.display()),
+        Err(e) => tracing::warn!("Could not save SLS image {}: {}", path.display(), e),
+    }
+}
+
+// content names are set by the broadcaster: drop the extension and anything but [A-Za-z0-9_-]
+fn sanitize_file_name(name: &str) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    stem.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
             }
+        })
+        .take(64)
+        .collect()
+}
+
+// one JSON object per line. audio frames are skipped, image data is elided (see "len")
+fn print_json(event: &DabEvent) {
+    match event {
+        DabEvent::AacpFramesExtracted(_) | DabEvent::Mp2FramesExtracted(_) => return,
+        DabEvent::EnsembleUpdated(e) if !e.complete => return,
+        DabEvent::EnsembleChanged(d) if !d.complete => return,
+        _ => {}
+    }
+
+    let mut value = match event.to_json() {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!("Could not serialize event: {}", e);
+            return;
+        }
+    };
+
+    if let DabEvent::MotImageReceived(_) = event {
+        if let Some(m) = value.as_object_mut() {
+            m.remove("data");
         }
     }
+
+    println!("{}", value);
 }
 
 // the print once logic here seems to be very ugly. think about a better way...
@@ -396,28 +1068,433 @@ fn print_ensemble(ensemble: &Ensemble) {
         sorted_services.sort_by_key(|svc| svc.label.as_deref().unwrap_or("").to_lowercase());
 
         for service in sorted_services {
-            let comp = service.components.first();
-
-            let (codec, bitrate, scid) = if let Some(c) = comp {
-                let af = c.audio_format.as_ref();
-                (
-                    af.map(|a| a.codec.as_str()).unwrap_or("-"),
-                    af.map(|a| a.bitrate).unwrap_or(0),
-                    c.scid,
-                )
-            } else {
-                ("-", 0, 0)
-            };
+            let scid = service.primary_component().map(|c| c.scid).unwrap_or(0);
 
             tracing::info!(
-                "SubCh {:4}   0x{:4X}   {:<16} ({})\t   {:<10}   {:3} kbps",
+                "SubCh {:4}   0x{:4X}   {:<16} ({})\t   {}",
                 scid,
                 service.sid,
                 service.label.as_deref().unwrap_or("<no label>"),
                 service.short_label.as_deref().unwrap_or(""),
-                codec,
-                bitrate
+                service
+                    .primary_audio()
+                    .map(|a| a.summary())
+                    .unwrap_or_else(|| "-".into()),
             );
+
+            for dc in &service.data_components {
+                tracing::info!(
+                    "SubCh {:4}   0x{:4X}     data: SCId {} - packet address {} - DSCTy {}",
+                    dc.subchannel_id.unwrap_or(0),
+                    service.sid,
+                    dc.scid,
+                    dc.packet_address.unwrap_or(0),
+                    dc.dscty.unwrap_or(0),
+                );
+            }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::dab::pad::dl::DlObject;
+    use shared::dab::ServiceComponent;
+
+    fn service(sid: u16, label: &str, scid: u8) -> Service {
+        Service {
+            sid,
+            label: Some(label.to_string()),
+            short_label: None,
+            components: vec![ServiceComponent {
+                scid: 0,
+                language: None,
+                subchannel_id: Some(scid),
+                user_apps: Vec::new(),
+                primary: true,
+                ascty: None,
+                audio_format: None,
+            }],
+            data_components: Vec::new(),
+            announcement_support: Vec::new(),
+            announcement_clusters: Vec::new(),
+        }
+    }
+
+    fn ensemble() -> Ensemble {
+        let mut ensemble = Ensemble::new();
+        ensemble.services = vec![
+            service(0x4DA1, "Radio Edinburgh ", 4),
+            service(0x4DA2, "Edinburgh Jazz", 7),
+            service(0x4DA3, "Leith FM", 9),
+        ];
+        ensemble
+    }
+
+    fn label_to_scid(ensemble: &Ensemble, query: &str, warned: &mut bool) -> Option<u8> {
+        find_service_by_label(ensemble, query, warned)
+            .and_then(|s| s.primary_component())
+            .and_then(|c| c.subchannel_id)
+    }
+
+    // raw capture of a synthetic ensemble, see shared/tests/data/make_replay_fixture.py
+    fn fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../shared/tests/data/replay.edi")
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("edinburgh-{}-{}", std::process::id(), name))
+    }
+
+    // the read branch of the main loop: the complete AF packets of reader, each also written to raw
+    async fn read_frames(
+        mut reader: impl AsyncRead + Unpin,
+        mut raw: Option<&mut BufWriter<File>>,
+    ) -> Vec<Vec<u8>> {
+        let mut extractor = EdiFrameExtractor::new();
+        let mut filled = 0;
+        let mut frames = Vec::new();
+
+        loop {
+            let n = reader
+                .read(&mut extractor.frame.data[filled..])
+                .await
+                .unwrap();
+            if n == 0 {
+                break;
+            }
+            if !frame_completed(&mut extractor, &mut filled, n) {
+                continue;
+            }
+            if let Some(w) = raw.as_mut() {
+                w.write_all(&extractor.frame.data).await.unwrap();
+            }
+            frames.push(extractor.frame.data.clone());
+            extractor.frame.reset();
+            filled = 0;
+        }
+
+        frames
+    }
+
+    // events of a source fed with frames, as JSON for comparison
+    async fn decode(frames: &[Vec<u8>]) -> Vec<serde_json::Value> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut source = DabSource::builder().scid(1).event_sink(tx).build();
+        for frame in frames {
+            source.feed(frame).await;
+        }
+        rx.try_iter()
+            .map(|event| event.to_json().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn save_raw_replay() {
+        let path = temp_path("save-raw.edi");
+
+        // --save-raw while decoding the capture
+        let mut w = BufWriter::new(File::create(&path).await.unwrap());
+        let received = read_frames(File::open(fixture()).await.unwrap(), Some(&mut w)).await;
+        w.flush().await.unwrap();
+
+        // --file of the recording
+        let replayed = read_frames(File::open(&path).await.unwrap(), None).await;
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(received.len(), 84);
+        assert_eq!(replayed, received);
+
+        let events = decode(&received).await;
+        assert!(events.iter().any(|e| e["type"] == "mp2_frames_extracted"));
+        assert_eq!(decode(&replayed).await, events);
+    }
+
+    // a handler without audio device or TUI, fed through the returned sender
+    fn handler() -> (
+        DabEventHandler,
+        UnboundedSender<DabEvent>,
+        UnboundedReceiver<TuiEvent>,
+    ) {
+        let (edi_tx, edi_rx) = unbounded_channel();
+        let (tui_tx, tui_rx) = unbounded_channel();
+        let (audio_tx, _) = unbounded_channel();
+
+        let handler = DabEventHandler::new(
+            Arc::new(RwLock::new(None)),
+            Arc::new(RwLock::new(false)),
+            Arc::new(RwLock::new(false)),
+            None,
+            OutputFormat::Text,
+            edi_rx,
+            tui_tx,
+            audio_tx,
+        );
+
+        (handler, edi_tx, tui_rx)
+    }
+
+    fn stats(rx_frames: u64) -> DabStats {
+        let mut stats = DabStats::new();
+        stats.rx_frames = rx_frames;
+        stats
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tui_coalescing() {
+        let (handler, edi_tx, mut tui_rx) = handler();
+        let handler = handler.tui_interval(Duration::from_millis(100));
+        let task = tokio::spawn(handler.run());
+
+        // the first tick fires immediately, let it pass before the burst
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        for n in 1..=10 {
+            edi_tx.send(DabEvent::DabStatsUpdated(stats(n))).unwrap();
+            edi_tx
+                .send(DabEvent::MotImageReceived(MotImage::new(
+                    (n % 2) as u8 + 1,
+                    1,
+                    vec![n as u8],
+                    None,
+                    None,
+                )))
+                .unwrap();
+            edi_tx
+                .send(DabEvent::DlObjectReceived(DlObject::new(1, 0, 0)))
+                .unwrap();
+        }
+
+        // nothing but DL before the next tick
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut events = Vec::new();
+        while let Ok(event) = tui_rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 10);
+        assert!(events
+            .iter()
+            .all(|e| matches!(e, TuiEvent::DlObjectReceived(_))));
+
+        // the latest stats and the latest image per SCID
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut rx_frames = Vec::new();
+        let mut images = Vec::new();
+        while let Ok(event) = tui_rx.try_recv() {
+            match event {
+                TuiEvent::DabStatsUpdated(s) => rx_frames.push(s.rx_frames),
+                TuiEvent::MotImageReceived(m) => images.push((m.scid, m.data[0])),
+                e => panic!("unexpected {:?}", e),
+            }
+        }
+        images.sort();
+        assert_eq!(rx_frames, [10]);
+        assert_eq!(images, [(1, 10), (2, 9)]);
+
+        drop(edi_tx);
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_close() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let data = std::fs::read(fixture()).unwrap();
+
+        let server = tokio::spawn(async move {
+            // the first connection is dropped in the middle of a packet
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(&data[..data.len() / 2]).await.unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(&data).await.unwrap();
+        });
+
+        let stream = connect_tcp(&addr, None).await.unwrap();
+        let first = read_frames(stream, None).await;
+
+        // a clean extractor after the reconnect, the partial packet is dropped
+        let stream = reconnect(&addr, None, 0).await.unwrap();
+        let second = read_frames(stream, None).await;
+        server.await.unwrap();
+
+        assert!(!first.is_empty() && first.len() < 84);
+        assert_eq!(second.len(), 84);
+        assert_eq!(first, second[..first.len()]);
+    }
+
+    // "Edinburgh One" on SubCh 1 (MP2) and "Edinburgh Two" on SubCh 2 (DAB+, no audio data)
+    async fn fixture_ensemble() -> Ensemble {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut source = DabSource::builder().event_sink(tx).build();
+        for frame in read_frames(File::open(fixture()).await.unwrap(), None).await {
+            source.feed(&frame).await;
+        }
+
+        rx.try_iter()
+            .filter_map(|event| match event {
+                DabEvent::EnsembleUpdated(ensemble) => Some(ensemble),
+                _ => None,
+            })
+            .last()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn auto_fallback() {
+        let (handler, edi_tx, _tui_rx) = handler();
+        let handler = handler.auto(false, true);
+        let scid = Arc::clone(&handler.scid);
+        *scid.write().await = Some(2);
+
+        edi_tx
+            .send(DabEvent::EnsembleUpdated(fixture_ensemble().await))
+            .unwrap();
+        // the selected SubCh never delivers audio: the primary audio service is selected
+        edi_tx.send(DabEvent::NoAudio { scid: 2 }).unwrap();
+        drop(edi_tx);
+        handler.run().await;
+
+        assert_eq!(*scid.read().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn auto_fallback_exhausted() {
+        let (handler, edi_tx, _tui_rx) = handler();
+        let handler = handler.auto(false, true);
+        let scid = Arc::clone(&handler.scid);
+        *scid.write().await = Some(2);
+
+        edi_tx
+            .send(DabEvent::EnsembleUpdated(fixture_ensemble().await))
+            .unwrap();
+        edi_tx.send(DabEvent::NoAudio { scid: 2 }).unwrap();
+        // no other audio service left, the selection is kept
+        edi_tx.send(DabEvent::NoAudio { scid: 1 }).unwrap();
+        drop(edi_tx);
+        handler.run().await;
+
+        assert_eq!(*scid.read().await, Some(1));
+    }
+
+    fn slide(data: &[u8]) -> MotImage {
+        let mut m = MotImage::new(4, 1, data.to_vec(), None, None);
+        m.content_name = Some("../Slide 1.jpg".to_string());
+        m
+    }
+
+    #[tokio::test]
+    async fn save_sls_once() {
+        let dir = temp_path("sls");
+        let (handler, edi_tx, _tui_rx) = handler();
+        let handler = handler.metadata_output(false, Some(dir.clone()));
+
+        // the carousel repeats the slides
+        for _ in 0..3 {
+            edi_tx
+                .send(DabEvent::MotImageReceived(slide(b"first")))
+                .unwrap();
+            edi_tx
+                .send(DabEvent::MotImageReceived(slide(b"second")))
+                .unwrap();
+        }
+        drop(edi_tx);
+        handler.run().await;
+
+        let mut files: Vec<String> = std::fs::read_dir(dir.join("4"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut expected = vec![
+            format!("Slide_1-{}.jpg", slide(b"first").md5_hex()),
+            format!("Slide_1-{}.jpg", slide(b"second").md5_hex()),
+        ];
+        expected.sort();
+        assert_eq!(files, expected);
+    }
+
+    // types of the events of the fixture decoded as configured by the command line
+    async fn decoded_types(cmdline: &[&str]) -> Vec<String> {
+        let args = Args::try_parse_from(cmdline).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut source = source_builder(&args).event_sink(tx).build();
+        for frame in read_frames(File::open(fixture()).await.unwrap(), None).await {
+            source.feed(&frame).await;
+        }
+        rx.try_iter()
+            .map(|event| {
+                event.to_json().unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn metadata_only() {
+        let types = decoded_types(&["edinburgh", "--file", "x", "--metadata-only"]).await;
+
+        // the MP2 subchannel is synced, but no audio frames go out
+        assert!(types.contains(&"ensemble_updated".to_string()));
+        assert!(types.contains(&"audio_format_detected".to_string()));
+        assert!(!types.contains(&"mp2_frames_extracted".to_string()));
+
+        let types = decoded_types(&["edinburgh", "--file", "x"]).await;
+        assert!(types.contains(&"mp2_frames_extracted".to_string()));
+    }
+
+    #[test]
+    fn metadata_only_args() {
+        let parse = |cmdline: &[&str]| Args::try_parse_from(cmdline);
+
+        let args = parse(&[
+            "edinburgh",
+            "-f",
+            "x",
+            "--metadata-only",
+            "--pad-scids",
+            "1,4",
+        ])
+        .unwrap();
+        assert_eq!(args.pad_scids, [1, 4]);
+
+        // no audio selection, no TUI
+        assert!(parse(&["edinburgh", "-f", "x", "--metadata-only", "--scid", "1"]).is_err());
+        assert!(parse(&["edinburgh", "-f", "x", "--metadata-only", "--tui"]).is_err());
+        assert!(parse(&["edinburgh", "-f", "x", "--pad-scids", "1"]).is_err());
+    }
+
+    #[test]
+    fn label_to_subchannel() {
+        let ensemble = ensemble();
+        let mut warned = false;
+
+        assert_eq!(label_to_scid(&ensemble, "leith", &mut warned), Some(9));
+        assert_eq!(label_to_scid(&ensemble, "JAZZ", &mut warned), Some(7));
+        assert!(!warned);
+    }
+
+    #[test]
+    fn label_ambiguous_or_missing() {
+        let ensemble = ensemble();
+
+        // the first match is selected
+        let mut warned = false;
+        assert_eq!(label_to_scid(&ensemble, "edinburgh", &mut warned), Some(4));
+        assert!(warned);
+
+        let mut warned = false;
+        assert_eq!(label_to_scid(&ensemble, "glasgow", &mut warned), None);
+        assert!(warned);
+
+        // already warned: same result, no new warning
+        assert_eq!(label_to_scid(&ensemble, "glasgow", &mut warned), None);
+        assert!(warned);
+    }
+}
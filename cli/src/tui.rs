@@ -1,3 +1,4 @@
+pub mod dl_history;
 pub mod meter;
 pub mod sls;
 
@@ -7,7 +8,10 @@ use term_guard::TermGuard;
 use humansize::{format_size, DECIMAL};
 use shared::dab::pad::dl::DlObject;
 use shared::dab::pad::mot::MotImage;
-use shared::dab::{DabStats, Ensemble, Subchannel};
+use shared::dab::{
+    DabStats, DateTimeUTC, Ensemble, EnsembleChange, EnsembleDelta, Service, ServiceComponent,
+    Subchannel,
+};
 use std::{io, time::Duration};
 
 use derive_more::Debug;
@@ -29,8 +33,11 @@ use ratatui::widgets::block::{BorderType, Padding};
 
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
+use dl_history::{DlHistory, DlHistoryWidget};
 use meter::LevelMeterWidget;
-use sls::{SLSImage, SLSWidget};
+#[cfg(feature = "sixel")]
+use sls::SixelOverlay;
+use sls::{SLSImage, SLSWidget, SlsThumbnail};
 
 fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
@@ -43,13 +50,24 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
 #[derive(Debug)]
 pub enum TuiEvent {
     EnsembleUpdated(Ensemble),
+    EnsembleChanged(EnsembleDelta),
     DlObjectReceived(DlObject),
     MotImageReceived(MotImage),
+    SlsImageDecoded(u8, SLSImage),
     DabStatsUpdated(DabStats),
+    // FIG 0/10 time, FIG 0/9 local time offset (half hours)
+    DateTimeUpdated(DateTimeUTC, Option<i8>),
+    // attempt number, the connection to the EDI source was lost
+    Reconnecting(u32),
+    Connected,
+    // the selected subchannel delivers no audio
+    NoAudio(u8),
 }
 
 pub enum TuiCommand {
     ScIDSelected(u8),
+    SetMuted(bool),
+    SetPaused(bool),
     Shutdown,
 }
 
@@ -62,9 +80,21 @@ pub struct TuiState {
     pub table_state: TableState,
     pub dl_objects: Vec<(u8, Option<DlObject>)>,
     pub sls_images: Vec<(u8, Option<SLSImage>)>,
+    pub sls_thumbnail: SlsThumbnail,
     pub edi_stats: DabStats,
+    pub reconnect_attempt: Option<u32>,
+    // SCID without audio, cleared when audio levels arrive
+    pub no_audio: Option<u8>,
+    pub datetime: Option<(DateTimeUTC, Option<i8>)>,
+    pub show_local_time: bool,
     pub show_meter: bool,
     pub show_sls: bool,
+    pub dl_history: DlHistory,
+    pub show_dl_history: bool,
+    pub dl_history_scroll: u16,
+    pub muted: bool,
+    pub paused: bool,
+    pub scid_input: Option<String>,
     pub levels: AudioLevels,
 }
 
@@ -81,46 +111,131 @@ impl TuiState {
             table_state,
             dl_objects: Vec::new(),
             sls_images: Vec::new(),
+            sls_thumbnail: SlsThumbnail::default(),
             edi_stats: DabStats::new(), // should we rather use option & none here?
+            reconnect_attempt: None,
+            no_audio: None,
+            datetime: None,
+            show_local_time: false,
             show_meter: false,
             show_sls: false,
+            dl_history: DlHistory::default(),
+            show_dl_history: false,
+            dl_history_scroll: 0,
+            muted: false,
+            paused: false,
+            scid_input: None,
             levels: AudioLevels::new(),
         }
     }
 
     pub fn update_services(&mut self, ensemble: Ensemble) {
-        self.current_ensemble = Some(ensemble.clone());
-
         self.services = ensemble
             .services
             .iter()
-            .map(|svc| {
-                let scid = svc.components.first().map(|c| c.scid).unwrap_or(0);
+            .map(|svc| Self::service_row(&ensemble, svc))
+            .collect();
 
-                let subchannel = ensemble
-                    .subchannels
-                    .iter()
-                    .find(|sub| sub.id == scid)
-                    .cloned();
-
-                let audio_format = svc.components.first().and_then(|c| c.audio_format.clone());
-
-                ServiceRow {
-                    sid: format!("0x{:04X}", svc.sid),
-                    label: svc
-                        .label
-                        .clone()
-                        .unwrap_or_else(|| "(no label)".to_string()),
-                    short_label: svc.short_label.clone().unwrap_or_default(),
-                    scid,
-                    subchannel,
-                    format: audio_format
-                        .map(|x| format!("{}", x))
-                        .unwrap_or_else(|| "-".into()),
+        self.current_ensemble = Some(ensemble);
+        self.sort_services();
+    }
+
+    // patch the rows affected by the delta instead of rebuilding the table
+    pub fn apply_ensemble_delta(&mut self, delta: EnsembleDelta) {
+        let Some(ensemble) = self.current_ensemble.as_mut() else {
+            return;
+        };
+
+        ensemble.apply(&delta);
+
+        if delta.initial {
+            self.services.clear();
+        }
+
+        for change in &delta.changes {
+            match change {
+                EnsembleChange::ServiceAdded(svc) | EnsembleChange::ServiceUpdated(svc) => {
+                    let row = Self::service_row(ensemble, svc);
+                    match self.services.iter_mut().find(|r| r.sid == row.sid) {
+                        Some(existing) => *existing = row,
+                        None => self.services.push(row),
+                    }
+                }
+                EnsembleChange::ServiceRemoved { sid } => {
+                    let sid = format!("0x{:04X}", sid);
+                    self.services.retain(|r| r.sid != sid);
+                }
+                EnsembleChange::SubchannelAdded(sc) | EnsembleChange::SubchannelUpdated(sc) => {
+                    for row in self.services.iter_mut().filter(|r| r.scid == sc.id) {
+                        row.subchannel = Some(sc.clone());
+                    }
                 }
-            })
+                _ => {}
+            }
+        }
+
+        self.sort_services();
+    }
+
+    fn service_row(ensemble: &Ensemble, svc: &Service) -> ServiceRow {
+        let scid = svc.primary_component().map(|c| c.scid).unwrap_or(0);
+
+        let user_apps = |c: &ServiceComponent| {
+            c.user_apps
+                .iter()
+                .map(|ua| format!(" {}", ua))
+                .collect::<String>()
+        };
+
+        let secondary_components = svc
+            .audio_components()
+            .filter(|c| c.scid != scid)
+            .map(|c| format!("SC {} audio{}", c.scid, user_apps(c)))
+            .chain(
+                svc.stream_data_components()
+                    .filter(|c| c.scid != scid)
+                    .map(|c| format!("SC {} data{}", c.scid, user_apps(c))),
+            )
+            .chain(svc.data_components.iter().map(|c| {
+                format!(
+                    "SC {} packet{}{}",
+                    c.subchannel_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| "?".into()),
+                    c.packet_address
+                        .map(|a| format!(" @{}", a))
+                        .unwrap_or_default(),
+                    c.dscty.map(|t| format!(" DSCTy {}", t)).unwrap_or_default()
+                )
+            }))
             .collect();
 
+        let subchannel = ensemble
+            .subchannels
+            .iter()
+            .find(|sub| sub.id == scid && sub.service_sids.contains(&svc.sid))
+            .cloned();
+
+        ServiceRow {
+            sid: format!("0x{:04X}", svc.sid),
+            label: svc
+                .label
+                .clone()
+                .unwrap_or_else(|| "(no label)".to_string()),
+            short_label: svc.short_label.clone().unwrap_or_default(),
+            scid,
+            subchannel,
+            // the FIG 0/2 codec until the audio format is detected
+            format: svc
+                .primary_audio()
+                .map(|a| a.summary())
+                .or_else(|| svc.codec().map(String::from))
+                .unwrap_or_else(|| "-".into()),
+            secondary_components,
+        }
+    }
+
+    fn sort_services(&mut self) {
         self.services.sort_by_key(|svc| svc.scid);
 
         if self.services.is_empty() {
@@ -132,28 +247,103 @@ impl TuiState {
         }
     }
 
+    pub fn select_scid(&mut self, scid: u8) -> bool {
+        match self.services.iter().position(|svc| svc.scid == scid) {
+            Some(index) => {
+                self.selected_scid = Some(scid);
+                self.table_state.select(Some(index));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // select the next (or previous) service, wrapping around
+    pub fn select_relative(&mut self, forward: bool) -> Option<u8> {
+        if self.services.is_empty() {
+            return None;
+        }
+
+        let len = self.services.len();
+        let current = self
+            .selected_scid
+            .and_then(|scid| self.services.iter().position(|svc| svc.scid == scid));
+
+        let index = match (current, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+
+        let scid = self.services[index].scid;
+        self.select_scid(scid);
+
+        Some(scid)
+    }
+
+    // SCID entry mode: returns the SCID to select once confirmed
+    pub fn handle_scid_input(&mut self, code: KeyCode) -> Option<u8> {
+        let input = self.scid_input.as_mut()?;
+
+        match code {
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                if input.len() < 2 {
+                    input.push(c);
+                }
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let scid = self.scid_input.take().and_then(|i| i.parse().ok())?;
+                if self.select_scid(scid) {
+                    return Some(scid);
+                }
+            }
+            KeyCode::Esc => {
+                self.scid_input = None;
+            }
+            _ => {}
+        }
+
+        None
+    }
+
     pub fn update_dl_object(&mut self, dl: DlObject) {
+        self.dl_history.record(dl.scid, dl.decode_label());
+
         match self
             .dl_objects
             .iter_mut()
             .find(|(scid, _)| *scid == dl.scid)
         {
+            // carousel repetition
+            Some((_, Some(obj))) if *obj == dl => {}
             Some((_, obj)) => *obj = Some(dl),
             None => self.dl_objects.push((dl.scid, Some(dl))),
         }
     }
 
-    pub fn update_mot_image(&mut self, m: MotImage) {
-        let s = SLSImage::new(
-            m.mimetype.clone().to_uppercase(),
-            m.len,
-            m.md5_hex().to_uppercase(),
-            m.data.clone(),
-        );
+    // decode off the draw path, the result is sent back as TuiEvent::SlsImageDecoded
+    pub fn update_mot_image(&mut self, m: MotImage, tx: &UnboundedSender<TuiEvent>) {
+        let tx = tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let s = SLSImage::new(
+                m.mimetype.clone().to_uppercase(),
+                m.len,
+                m.md5_hex().to_uppercase(),
+                m.data.clone(),
+                m.click_through_url.clone(),
+            );
+            let _ = tx.send(TuiEvent::SlsImageDecoded(m.scid, s));
+        });
+    }
 
-        match self.sls_images.iter_mut().find(|(scid, _)| *scid == m.scid) {
+    pub fn update_sls_image(&mut self, scid: u8, s: SLSImage) {
+        match self.sls_images.iter_mut().find(|(id, _)| *id == scid) {
             Some((_, obj)) => *obj = Some(s),
-            None => self.sls_images.push((m.scid, Some(s))),
+            None => self.sls_images.push((scid, Some(s))),
         }
     }
 
@@ -161,8 +351,34 @@ impl TuiState {
         self.edi_stats = stats;
     }
 
+    // broadcast clock for the ensemble panel, local time only if the LTO is known
+    fn clock(&self) -> String {
+        let Some((utc, lto)) = &self.datetime else {
+            return "-".to_string();
+        };
+
+        let ((year, month, day, hours, minutes, seconds), zone) = match lto {
+            Some(lto) if self.show_local_time => (
+                utc.to_local(*lto),
+                format!(
+                    "UTC{}{:02}:{:02}",
+                    if *lto < 0 { "-" } else { "+" },
+                    lto.abs() / 2,
+                    (lto.abs() % 2) * 30
+                ),
+            ),
+            _ => (utc.fields(), "UTC".to_string()),
+        };
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}",
+            year, month, day, hours, minutes, seconds, zone
+        )
+    }
+
     pub fn update_levels(&mut self, levels: AudioLevels) {
         self.levels = levels;
+        self.no_audio = None;
     }
 }
 
@@ -174,12 +390,14 @@ pub struct ServiceRow {
     pub scid: u8,
     pub subchannel: Option<Subchannel>,
     pub format: String,
+    // all but the primary component, e.g. a data component carrying SLS
+    pub secondary_components: Vec<String>,
 }
 
 pub async fn run_tui(
     addr: String,
     scid: Option<u8>,
-    #[allow(unused_variables)] tx: UnboundedSender<TuiEvent>,
+    tx: UnboundedSender<TuiEvent>,
     mut rx: UnboundedReceiver<TuiEvent>,
     cmd_tx: UnboundedSender<TuiCommand>,
     mut audio_rx: UnboundedReceiver<AudioEvent>,
@@ -200,6 +418,9 @@ pub async fn run_tui(
 
     let mut state = TuiState::new(addr, scid);
 
+    #[cfg(feature = "sixel")]
+    let mut sixel = SixelOverlay::default();
+
     loop {
         terminal.draw(|frame| {
             let area = frame.area();
@@ -215,7 +436,7 @@ pub async fn run_tui(
             let content_layout = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Length(4),
+                    Constraint::Length(5),
                     Constraint::Length(1),
                     Constraint::Min(0),
                     Constraint::Length(4),
@@ -228,7 +449,7 @@ pub async fn run_tui(
             // ensemble info
             ///////////////////////////////////////////////////////////
             let ensemble_left = Paragraph::new(format!(
-                "{}\n0x{:04X}",
+                "{}\n0x{:04X} • {}",
                 state
                     .current_ensemble
                     .as_ref()
@@ -239,6 +460,7 @@ pub async fn run_tui(
                     .as_ref()
                     .and_then(|e| e.eid)
                     .unwrap_or(0),
+                state.clock(),
             ))
             .block(
                 Block::default()
@@ -249,12 +471,27 @@ pub async fn run_tui(
             )
             .wrap(Wrap { trim: true });
 
+            let connection_info = match (state.reconnect_attempt, state.no_audio) {
+                (Some(attempt), _) => format!(" • reconnecting ({})", attempt),
+                (None, Some(scid)) if state.selected_scid == Some(scid) => {
+                    format!(" • no audio on SubCh {}", scid)
+                }
+                _ => "".to_string(),
+            };
+
             let ensemble_right = Paragraph::new(format!(
-                "tcp://{}\nRX: {:>5.0} kbits • {} frames • {}",
+                "tcp://{}{}\nRX: {:>5.0} kbits • {} frames • {}\nERR/s: FIB {} • AU {} • SF {} ({} total)",
                 state.addr,
+                connection_info,
                 state.edi_stats.rx_rate as f64 / 128.0,
                 state.edi_stats.rx_frames,
                 format_size(state.edi_stats.rx_bytes, DECIMAL),
+                state.edi_stats.errors_interval.fib_crc_errors,
+                state.edi_stats.errors_interval.au_crc_errors,
+                state.edi_stats.errors_interval.superframe_sync_losses,
+                state.edi_stats.errors.fib_crc_errors
+                    + state.edi_stats.errors.au_crc_errors
+                    + state.edi_stats.errors.superframe_sync_losses,
             ))
             .block(
                 Block::default()
@@ -276,7 +513,10 @@ pub async fn run_tui(
             ///////////////////////////////////////////////////////////
             // keyboard input display
             ///////////////////////////////////////////////////////////
-            let input_text = "q: quit • m: meter • s: SLS • Enter: select";
+            let input_text = match &state.scid_input {
+                Some(input) => format!("SCID: {}_ • Enter: select • Esc: cancel", input),
+                None => "q: quit • m: meter • s: SLS • h: DL history • t: UTC/local • x: mute • Space: pause • n/p: next/prev • 0-9: SCID • Enter: select".to_string(),
+            };
             let input_paragraph = Paragraph::new(input_text)
                 .block(
                     Block::default()
@@ -318,11 +558,13 @@ pub async fn run_tui(
                 };
 
                 let sc_info = if let Some(sc) = &svc.subchannel {
+                    // "+" marks a subchannel shared with other services
                     format!(
-                        "{} {:>3} {:>3} ",
+                        "{} {:>3} {:>3}{}",
                         sc.pl.clone().unwrap_or("-".to_string()),
                         sc.size.unwrap_or(0),
                         sc.start.unwrap_or(0),
+                        if sc.service_sids.len() > 1 { "+" } else { " " },
                     )
                 } else {
                     svc.scid.to_string()
@@ -424,14 +666,30 @@ pub async fn run_tui(
                 None
             };
 
+            let player_state = match (state.paused, state.muted) {
+                (true, _) => "(paused) ",
+                (false, true) => "(muted) ",
+                (false, false) => "",
+            };
+
             let player_title = match current_service {
-                Some(svc) => format!(" Player SC {:>2} - {} ", svc.scid, svc.format),
-                None => " Player ".to_string(),
+                Some(svc) => format!(
+                    " Player SC {:>2} - {} {}",
+                    svc.scid, svc.format, player_state
+                ),
+                None => format!(" Player {}", player_state),
             };
 
             let player_text = match current_service {
-                Some(svc) => svc.label.to_string(),
-                None => "No service selected".to_string(),
+                Some(svc) if !svc.secondary_components.is_empty() => Text::from(vec![
+                    Line::from(svc.label.to_string()),
+                    Line::from(vec![Span::styled(
+                        format!("+ {}", svc.secondary_components.join(" • ")),
+                        Style::default().fg(Color::DarkGray),
+                    )]),
+                ]),
+                Some(svc) => Text::from(svc.label.to_string()),
+                None => Text::from("No service selected"),
             };
 
             let player_dl = state
@@ -567,7 +825,27 @@ pub async fn run_tui(
 
                 let sls_widget = SLSWidget::new(sls_image);
 
-                frame.render_widget(sls_widget, sls_area);
+                frame.render_stateful_widget(sls_widget, sls_area, &mut state.sls_thumbnail);
+            }
+
+            ///////////////////////////////////////////////////////////
+            // DL history
+            ///////////////////////////////////////////////////////////
+            if state.show_dl_history {
+                let history_area = center(
+                    frame.area(),
+                    Constraint::Length(84.min(area.width)),
+                    Constraint::Length(20.min(area.height)),
+                );
+
+                let entries = state
+                    .selected_scid
+                    .and_then(|selected| state.dl_history.get(selected));
+
+                frame.render_widget(
+                    DlHistoryWidget::new(entries, state.dl_history_scroll),
+                    history_area,
+                );
             }
 
             ///////////////////////////////////////////////////////////
@@ -579,9 +857,39 @@ pub async fn run_tui(
             }
         })?;
 
+        #[cfg(feature = "sixel")]
+        {
+            let size = terminal.size()?;
+            let area = Rect::new(0, 0, size.width, size.height);
+            let sls_area = (state.show_sls && !state.show_dl_history).then(|| {
+                center(
+                    area,
+                    Constraint::Length(84.min(area.width)),
+                    Constraint::Length(28.min(area.height)),
+                )
+            });
+            let sls_image = state.selected_scid.and_then(|selected| {
+                state
+                    .sls_images
+                    .iter()
+                    .find(|(scid, _)| *scid == selected)
+                    .and_then(|(_, m)| m.as_ref())
+            });
+
+            if sixel.update(sls_area, sls_image)? {
+                terminal.clear()?;
+            }
+        }
+
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
                 match key.code {
+                    // SCID entry mode
+                    code if state.scid_input.is_some() => {
+                        if let Some(scid) = state.handle_scid_input(code) {
+                            let _ = cmd_tx.send(TuiCommand::ScIDSelected(scid));
+                        }
+                    }
                     KeyCode::Char('q') => {
                         let _ = cmd_tx.send(TuiCommand::Shutdown);
                         break;
@@ -590,6 +898,13 @@ pub async fn run_tui(
                         if state.show_sls {
                             state.show_sls = false;
                         }
+                        state.show_dl_history = false;
+                    }
+                    KeyCode::PageUp => {
+                        state.dl_history_scroll = state.dl_history_scroll.saturating_sub(5);
+                    }
+                    KeyCode::PageDown => {
+                        state.dl_history_scroll = state.dl_history_scroll.saturating_add(5);
                     }
                     KeyCode::Up => {
                         if let Some(selected) = state.table_state.selected() {
@@ -624,6 +939,34 @@ pub async fn run_tui(
                     KeyCode::Char('s') => {
                         state.show_sls = !state.show_sls;
                     }
+                    KeyCode::Char('h') => {
+                        state.show_dl_history = !state.show_dl_history;
+                        state.dl_history_scroll = 0;
+                    }
+                    KeyCode::Char('t') => {
+                        state.show_local_time = !state.show_local_time;
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() => {
+                        state.scid_input = Some(c.to_string());
+                    }
+                    KeyCode::Char('n') => {
+                        if let Some(scid) = state.select_relative(true) {
+                            let _ = cmd_tx.send(TuiCommand::ScIDSelected(scid));
+                        }
+                    }
+                    KeyCode::Char('p') => {
+                        if let Some(scid) = state.select_relative(false) {
+                            let _ = cmd_tx.send(TuiCommand::ScIDSelected(scid));
+                        }
+                    }
+                    KeyCode::Char('x') => {
+                        state.muted = !state.muted;
+                        let _ = cmd_tx.send(TuiCommand::SetMuted(state.muted));
+                    }
+                    KeyCode::Char(' ') => {
+                        state.paused = !state.paused;
+                        let _ = cmd_tx.send(TuiCommand::SetPaused(state.paused));
+                    }
                     _ => {}
                 }
             }
@@ -634,15 +977,33 @@ pub async fn run_tui(
                 TuiEvent::EnsembleUpdated(ensemble) => {
                     state.update_services(ensemble);
                 }
+                TuiEvent::EnsembleChanged(delta) => {
+                    state.apply_ensemble_delta(delta);
+                }
                 TuiEvent::DlObjectReceived(d) => {
                     state.update_dl_object(d);
                 }
                 TuiEvent::MotImageReceived(m) => {
-                    state.update_mot_image(m);
+                    state.update_mot_image(m, &tx);
+                }
+                TuiEvent::SlsImageDecoded(scid, s) => {
+                    state.update_sls_image(scid, s);
                 }
                 TuiEvent::DabStatsUpdated(s) => {
                     state.update_edi_stats(s);
                 }
+                TuiEvent::DateTimeUpdated(utc, lto) => {
+                    state.datetime = Some((utc, lto));
+                }
+                TuiEvent::Reconnecting(attempt) => {
+                    state.reconnect_attempt = Some(attempt);
+                }
+                TuiEvent::Connected => {
+                    state.reconnect_attempt = None;
+                }
+                TuiEvent::NoAudio(scid) => {
+                    state.no_audio = Some(scid);
+                }
                 #[allow(unreachable_patterns)]
                 _ => {}
             }
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use ratatui::widgets::block::{BorderType, Padding};
+
+// entries kept per SCID
+const DL_HISTORY_LEN: usize = 50;
+
+// DL text changes per SCID, oldest first
+#[derive(Debug, Default)]
+pub struct DlHistory {
+    entries: HashMap<u8, VecDeque<(Instant, String)>>,
+}
+
+impl DlHistory {
+    // returns false if the text is empty or did not change
+    pub fn record(&mut self, scid: u8, text: String) -> bool {
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return false;
+        }
+
+        let entries = self.entries.entry(scid).or_default();
+
+        if entries.back().is_some_and(|(_, last)| *last == text) {
+            return false;
+        }
+
+        if entries.len() == DL_HISTORY_LEN {
+            entries.pop_front();
+        }
+
+        entries.push_back((Instant::now(), text));
+        true
+    }
+
+    pub fn get(&self, scid: u8) -> Option<&VecDeque<(Instant, String)>> {
+        self.entries.get(&scid)
+    }
+}
+
+pub struct DlHistoryWidget<'a> {
+    entries: Option<&'a VecDeque<(Instant, String)>>,
+    scroll: u16,
+}
+
+impl<'a> DlHistoryWidget<'a> {
+    pub fn new(entries: Option<&'a VecDeque<(Instant, String)>>, scroll: u16) -> Self {
+        Self { entries, scroll }
+    }
+
+    // age as -h:mm:ss / -mm:ss
+    fn format_age(at: Instant) -> String {
+        let secs = at.elapsed().as_secs();
+        if secs >= 3600 {
+            format!("-{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+        } else {
+            format!("-{:02}:{:02}", secs / 60, secs % 60)
+        }
+    }
+}
+
+impl Widget for DlHistoryWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // newest first
+        let text = match self.entries {
+            Some(entries) if !entries.is_empty() => Text::from(
+                entries
+                    .iter()
+                    .rev()
+                    .map(|(at, text)| {
+                        Line::from(vec![
+                            Span::styled(
+                                format!("{:>8}  ", Self::format_age(*at)),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                            Span::raw(text.clone()),
+                        ])
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            _ => Text::from("NO DL"),
+        };
+
+        Clear.render(area, buf);
+
+        Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title(" DL history ")
+                    .style(Style::default().bg(Color::Black).fg(Color::White))
+                    .padding(Padding::horizontal(1))
+                    .border_type(BorderType::Double)
+                    .borders(Borders::ALL),
+            )
+            .wrap(Wrap { trim: true })
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(history: &DlHistory, scid: u8) -> Vec<&str> {
+        history
+            .get(scid)
+            .map(|entries| entries.iter().map(|(_, text)| text.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn record_suppresses_repeats() {
+        let mut history = DlHistory::default();
+
+        assert!(history.record(1, "Now playing: A".into()));
+        assert!(!history.record(1, "Now playing: A".into()));
+        assert!(history.record(1, "Now playing: B".into()));
+        assert!(history.record(1, "Now playing: A".into()));
+
+        // other SCIDs are tracked separately
+        assert!(history.record(2, "Now playing: B".into()));
+
+        assert_eq!(
+            texts(&history, 1),
+            ["Now playing: A", "Now playing: B", "Now playing: A"]
+        );
+        assert_eq!(texts(&history, 2), ["Now playing: B"]);
+        assert!(history.get(3).is_none());
+    }
+
+    #[test]
+    fn record_trims_whitespace() {
+        let mut history = DlHistory::default();
+
+        assert!(!history.record(1, "   ".into()));
+        assert!(!history.record(1, String::new()));
+        assert!(history.get(1).is_none_or(|entries| entries.is_empty()));
+
+        assert!(history.record(1, "  Edinburgh One \r\n".into()));
+        assert!(!history.record(1, "Edinburgh One".into()));
+        assert_eq!(texts(&history, 1), ["Edinburgh One"]);
+    }
+
+    #[test]
+    fn record_is_bounded() {
+        let mut history = DlHistory::default();
+
+        for i in 0..DL_HISTORY_LEN + 10 {
+            assert!(history.record(1, format!("text {i}")));
+        }
+
+        let texts = texts(&history, 1);
+        assert_eq!(texts.len(), DL_HISTORY_LEN);
+        assert_eq!(texts[0], "text 10");
+        assert_eq!(
+            texts[DL_HISTORY_LEN - 1],
+            format!("text {}", DL_HISTORY_LEN + 9)
+        );
+    }
+}
@@ -1,9 +1,8 @@
-use artem::{config::ConfigBuilder, convert};
 use derive_more::Debug;
 use humansize::{format_size, DECIMAL};
-use std::num::NonZeroU32;
+use image::{imageops, RgbImage};
+use std::sync::Arc;
 
-use ansi_to_tui::IntoText;
 use ratatui::text::Text;
 
 use ratatui::{
@@ -11,37 +10,136 @@ use ratatui::{
     layout::{Alignment, Rect},
     style::{Color, Style},
     text::Line,
-    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget, Wrap},
 };
 
 use ratatui::widgets::block::{BorderType, Padding};
 
+// upper half block: fg = upper pixel, bg = lower pixel
+const HALF_BLOCK: &str = "▀";
+
 pub struct SLSWidget {
     sls_image: Option<SLSImage>,
 }
 
+// the slide scaled for the last draw. the TUI redraws several times a second, the slide is
+// only rescaled when it or the target size changes
+#[derive(Debug, Default)]
+pub struct SlsThumbnail {
+    // md5 of the slide, max width and height it was scaled to
+    key: Option<(String, u32, u32)>,
+    #[debug(skip)]
+    scaled: RgbImage,
+}
+
+impl SlsThumbnail {
+    // pixels scaled to fit max_w x max_h, keeping the aspect ratio
+    pub fn fit(&mut self, md5: &str, pixels: &RgbImage, max_w: u32, max_h: u32) -> &RgbImage {
+        let cached = self
+            .key
+            .as_ref()
+            .is_some_and(|(m, w, h)| m == md5 && (*w, *h) == (max_w, max_h));
+
+        if !cached {
+            let scale = f32::min(
+                max_w as f32 / pixels.width() as f32,
+                max_h as f32 / pixels.height() as f32,
+            );
+
+            let w = ((pixels.width() as f32 * scale) as u32).clamp(1, max_w);
+            let h = ((pixels.height() as f32 * scale) as u32).clamp(1, max_h);
+
+            self.scaled = imageops::thumbnail(pixels, w, h);
+            self.key = Some((md5.to_string(), max_w, max_h));
+        }
+
+        &self.scaled
+    }
+}
+
 impl SLSWidget {
     pub fn new(sls_image: Option<SLSImage>) -> Self {
         Self { sls_image }
     }
+
+    // render the image into area using half blocks, letterboxed to keep the aspect ratio
+    fn render_pixels(
+        thumbnail: &mut SlsThumbnail,
+        md5: &str,
+        pixels: &RgbImage,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        if area.width == 0 || area.height == 0 || pixels.width() == 0 || pixels.height() == 0 {
+            return;
+        }
+
+        // every cell holds two vertical pixels
+        let max_w = area.width as u32;
+        let max_h = area.height as u32 * 2;
+
+        let scaled = thumbnail.fit(md5, pixels, max_w, max_h);
+        let (w, h) = scaled.dimensions();
+
+        let offset_x = area.x + ((max_w - w) / 2) as u16;
+        let offset_y = area.y + ((max_h - h) / 4) as u16;
+
+        for y in (0..h).step_by(2) {
+            for x in 0..w {
+                let upper = scaled.get_pixel(x, y);
+                let lower = if y + 1 < h {
+                    let p = scaled.get_pixel(x, y + 1);
+                    Color::Rgb(p[0], p[1], p[2])
+                } else {
+                    Color::Black
+                };
+
+                buf.set_string(
+                    offset_x + x as u16,
+                    offset_y + (y / 2) as u16,
+                    HALF_BLOCK,
+                    Style::default()
+                        .fg(Color::Rgb(upper[0], upper[1], upper[2]))
+                        .bg(lower),
+                );
+            }
+        }
+    }
 }
 
-impl Widget for SLSWidget {
-    fn render(self, area: Rect, buf: &mut Buffer) {
+impl StatefulWidget for SLSWidget {
+    type State = SlsThumbnail;
+
+    fn render(self, area: Rect, buf: &mut Buffer, thumbnail: &mut SlsThumbnail) {
         let has_sls_image = self.sls_image.is_some();
         let area_warning: bool = area.width < 84 || area.height < 28;
 
+        let pixels = if area_warning {
+            None
+        } else {
+            self.sls_image
+                .as_ref()
+                .and_then(|s| s.pixels.clone().map(|pixels| (s.md5.clone(), pixels)))
+        };
+
         let text = if area_warning {
             Text::from("TERMINAL TOO SMALL")
-        } else if let Some(sls_image) = self.sls_image.clone() {
-            sls_image
-                .ascii
-                .into_text()
-                .unwrap_or_else(|_| Text::from("ERROR"))
+        } else if pixels.is_some() {
+            Text::default()
+        } else if has_sls_image {
+            Text::from("ERROR")
         } else {
             Text::from("NO SLS")
         };
 
+        // link of the slide, if any
+        let text_header = self
+            .sls_image
+            .as_ref()
+            .and_then(|s| s.click_through_url.as_deref())
+            .map(|url| format!(" {} ", url))
+            .unwrap_or_default();
+
         let text_footer = if let Some(sls_image) = self.sls_image {
             format!(
                 " {} | {}x{} | {} ",
@@ -54,39 +152,42 @@ impl Widget for SLSWidget {
             "".to_string()
         };
 
-        let render_text = if area_warning || !has_sls_image {
-            Text::from(format!(
-                "{}{}",
-                "\n".repeat((area.height.saturating_sub(4) / 2) as usize),
-                text
-            ))
-        } else {
+        let render_text = Text::from(format!(
+            "{}{}",
+            "\n".repeat((area.height.saturating_sub(4) / 2) as usize),
             text
-        };
+        ));
 
         Clear.render(area, buf);
 
+        let block = Block::default()
+            // .title(format!(" {:?} ", area))
+            .title(Line::from(text_header).centered())
+            .title_bottom(Line::from(text_footer).centered())
+            .style(
+                Style::default()
+                    .bg(if area_warning {
+                        Color::Red
+                    } else {
+                        Color::Black
+                    })
+                    .fg(Color::White),
+            )
+            .padding(Padding::horizontal(1))
+            .border_type(BorderType::Double)
+            .borders(Borders::ALL);
+
+        let inner = block.inner(area);
+
         Paragraph::new(render_text)
             .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    // .title(format!(" {:?} ", area))
-                    .title_bottom(Line::from(text_footer).centered())
-                    .style(
-                        Style::default()
-                            .bg(if area_warning {
-                                Color::Red
-                            } else {
-                                Color::Black
-                            })
-                            .fg(Color::White),
-                    )
-                    .padding(Padding::horizontal(1))
-                    .border_type(BorderType::Double)
-                    .borders(Borders::ALL),
-            )
+            .block(block)
             .wrap(Wrap { trim: true })
             .render(area, buf);
+
+        if let Some((md5, pixels)) = pixels {
+            Self::render_pixels(thumbnail, &md5, &pixels, inner, buf);
+        }
     }
 }
 
@@ -97,31 +198,24 @@ pub struct SLSImage {
     pub md5: String,
     pub width: u32,
     pub height: u32,
-    pub ascii: String,
+    // MOT ClickThroughURL
+    pub click_through_url: Option<String>,
+    #[debug(skip)]
+    pub pixels: Option<Arc<RgbImage>>,
 }
 
 impl SLSImage {
-    pub fn new(mimetype: String, len: usize, md5: String, data: Vec<u8>) -> Self {
-        let (width, height, ascii) = match image::load_from_memory(&data) {
-            Ok(img) => {
-                let width = img.width();
-                let height = img.height();
-
-                let size = NonZeroU32::try_from(80).unwrap_or(NonZeroU32::new(1).unwrap()); // i don't get this ;)
-
-                let config = ConfigBuilder::new()
-                    .target_size(size)
-                    .characters("█▓▒░:+-. ".to_string())
-                    .hysteresis(true)
-                    .color(true)
-                    .invert(false)
-                    .build();
-
-                // artem::convert returns a String with ANSI escape codes
-                let ascii_art = convert(img, &config);
-                (width, height, ascii_art)
-            }
-            Err(_) => (0, 0, "ERROR".to_string()),
+    // decoding can be expensive for large images - don't call this on the draw path
+    pub fn new(
+        mimetype: String,
+        len: usize,
+        md5: String,
+        data: Vec<u8>,
+        click_through_url: Option<String>,
+    ) -> Self {
+        let (width, height, pixels) = match image::load_from_memory(&data) {
+            Ok(img) => (img.width(), img.height(), Some(Arc::new(img.to_rgb8()))),
+            Err(_) => (0, 0, None),
         };
 
         Self {
@@ -130,7 +224,242 @@ impl SLSImage {
             md5,
             width,
             height,
-            ascii,
+            click_through_url,
+            pixels,
         }
     }
 }
+
+// sixel output for the SLS overlay. drawn on top of the half block rendering after each
+// draw, only re-sent when the image or area changes (ratatui does not touch unchanged cells)
+#[cfg(feature = "sixel")]
+#[derive(Debug, Default)]
+pub struct SixelOverlay {
+    shown: Option<(String, Rect)>,
+    thumbnail: SlsThumbnail,
+}
+
+#[cfg(feature = "sixel")]
+impl SixelOverlay {
+    // returns true if a previously shown image has to be cleared first (terminal.clear()),
+    // the new one is drawn on the next call
+    pub fn update(
+        &mut self,
+        area: Option<Rect>,
+        sls_image: Option<&SLSImage>,
+    ) -> std::io::Result<bool> {
+        use ratatui::crossterm::{cursor::MoveTo, queue, terminal::window_size};
+        use std::io::Write;
+
+        let target = match (area, sls_image) {
+            (Some(area), Some(s)) if area.width >= 84 && area.height >= 28 => s
+                .pixels
+                .as_ref()
+                .map(|pixels| (s.md5.clone(), area, pixels)),
+            _ => None,
+        };
+
+        let Some((md5, area, pixels)) = target else {
+            return Ok(self.shown.take().is_some());
+        };
+
+        match self.shown {
+            Some(ref shown) if *shown == (md5.clone(), area) => return Ok(false),
+            Some(_) => {
+                self.shown = None;
+                return Ok(true);
+            }
+            None => {}
+        }
+
+        // cell size in pixels, not reported by every terminal: keep the half blocks then
+        let ws = window_size()?;
+        if ws.width == 0 || ws.height == 0 || ws.columns == 0 || ws.rows == 0 {
+            return Ok(false);
+        }
+        let cell_w = (ws.width / ws.columns) as u32;
+        let cell_h = (ws.height / ws.rows) as u32;
+
+        // inside the border and horizontal padding, see SLSWidget::render
+        let inner = Rect::new(
+            area.x + 2,
+            area.y + 1,
+            area.width.saturating_sub(4),
+            area.height.saturating_sub(2),
+        );
+
+        let max_w = inner.width as u32 * cell_w;
+        let max_h = inner.height as u32 * cell_h;
+
+        let scaled = self.thumbnail.fit(&md5, pixels, max_w, max_h);
+        let (w, h) = scaled.dimensions();
+
+        // letterbox, in whole cells
+        let x = inner.x + ((max_w - w) / 2 / cell_w) as u16;
+        let y = inner.y + ((max_h - h) / 2 / cell_h) as u16;
+
+        let mut stdout = std::io::stdout();
+        queue!(stdout, MoveTo(x, y))?;
+        stdout.write_all(encode_sixel(scaled).as_bytes())?;
+        stdout.flush()?;
+
+        self.shown = Some((md5, area));
+        Ok(false)
+    }
+}
+
+// 6x6x6 color cube, red / green / blue levels 0..5
+#[cfg(feature = "sixel")]
+fn sixel_color(p: &image::Rgb<u8>) -> usize {
+    let level = |v: u8| (v as usize * 5 + 127) / 255;
+    level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])
+}
+
+// DECSIXEL: palette, then bands of 6 pixel rows, one pass per color used in the band
+#[cfg(feature = "sixel")]
+pub fn encode_sixel(pixels: &RgbImage) -> String {
+    use std::fmt::Write;
+
+    let (w, h) = (pixels.width(), pixels.height());
+    let mut out = format!("\x1bPq\"1;1;{};{}", w, h);
+
+    for c in 0..216 {
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            c,
+            c / 36 * 20,
+            c / 6 % 6 * 20,
+            c % 6 * 20
+        );
+    }
+
+    for band in (0..h).step_by(6) {
+        let rows = (h - band).min(6);
+        let colors: Vec<Vec<usize>> = (0..rows)
+            .map(|dy| {
+                (0..w)
+                    .map(|x| sixel_color(pixels.get_pixel(x, band + dy)))
+                    .collect()
+            })
+            .collect();
+
+        let mut used = [false; 216];
+        colors.iter().flatten().for_each(|&c| used[c] = true);
+
+        for c in (0..216).filter(|&c| used[c]) {
+            let _ = write!(out, "#{}", c);
+
+            let sixels = (0..w as usize).map(|x| {
+                let bits = (0..rows as usize)
+                    .filter(|&dy| colors[dy][x] == c)
+                    .fold(0u8, |acc, dy| acc | (1 << dy));
+                (63 + bits) as char
+            });
+
+            // run length encoded: !<count><sixel>
+            let mut run: Option<(char, usize)> = None;
+            for s in sixels.chain(std::iter::once('\0')) {
+                match run {
+                    Some((prev, n)) if prev == s => run = Some((prev, n + 1)),
+                    _ => {
+                        match run {
+                            Some((prev, n)) if n > 3 => {
+                                let _ = write!(out, "!{}{}", n, prev);
+                            }
+                            Some((prev, n)) => out.extend(std::iter::repeat_n(prev, n)),
+                            None => {}
+                        }
+                        run = Some((s, 1));
+                    }
+                }
+            }
+
+            // back to the start of the band for the next color
+            out.push('$');
+        }
+
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnail_cached() {
+        let red = RgbImage::from_pixel(320, 240, image::Rgb([255, 0, 0]));
+        let blue = RgbImage::from_pixel(320, 240, image::Rgb([0, 0, 255]));
+        let mut thumbnail = SlsThumbnail::default();
+
+        let scaled = thumbnail.fit("a", &red, 80, 52);
+        assert_eq!(scaled.dimensions(), (69, 52));
+        assert_eq!(scaled.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+
+        // same slide and size: not rescaled
+        let scaled = thumbnail.fit("a", &blue, 80, 52);
+        assert_eq!(scaled.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+
+        // another slide
+        let scaled = thumbnail.fit("b", &blue, 80, 52);
+        assert_eq!(scaled.get_pixel(0, 0), &image::Rgb([0, 0, 255]));
+
+        // another size
+        assert_eq!(thumbnail.fit("b", &blue, 40, 52).dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn render_uses_thumbnail() {
+        let mut slide = SLSImage::new(
+            "image/png".to_string(),
+            0,
+            "a".to_string(),
+            Vec::new(),
+            None,
+        );
+        slide.pixels = Some(Arc::new(RgbImage::from_pixel(
+            320,
+            240,
+            image::Rgb([255, 0, 0]),
+        )));
+        let area = Rect::new(0, 0, 84, 28);
+        let mut thumbnail = SlsThumbnail::default();
+
+        let mut buf = Buffer::empty(area);
+        SLSWidget::new(Some(slide.clone())).render(area, &mut buf, &mut thumbnail);
+        // inside the border and padding: 80 x 26 cells, 80 x 52 pixels
+        assert_eq!(thumbnail.key, Some(("a".to_string(), 80, 52)));
+
+        // redraw with another area
+        let area = Rect::new(0, 0, 100, 28);
+        let mut buf = Buffer::empty(area);
+        SLSWidget::new(Some(slide)).render(area, &mut buf, &mut thumbnail);
+        assert_eq!(thumbnail.key, Some(("a".to_string(), 96, 52)));
+    }
+
+    #[cfg(feature = "sixel")]
+    #[test]
+    fn sixel_single_color_run_length() {
+        let pixels = RgbImage::from_pixel(8, 6, image::Rgb([255, 0, 0]));
+        let sixel = encode_sixel(&pixels);
+
+        assert!(sixel.starts_with("\x1bPq\"1;1;8;6"));
+        assert!(sixel.ends_with("\x1b\\"));
+        // red is cube index 5 * 36, all 6 rows set ('~') for 8 columns
+        assert!(sixel.contains("#180!8~$-"));
+    }
+
+    #[cfg(feature = "sixel")]
+    #[test]
+    fn sixel_partial_band() {
+        // 2 rows: only the lower two bits are set
+        let pixels = RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+        let sixel = encode_sixel(&pixels);
+
+        assert!(sixel.contains("#0BB$-"));
+    }
+}
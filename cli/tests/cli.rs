@@ -0,0 +1,75 @@
+// the edinburgh binary against the synthetic capture of shared/tests/data
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn fixture() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../shared/tests/data/replay.edi")
+}
+
+// run edinburgh with args, stdin is piped from a writer thread
+fn edinburgh(args: &[&str], stdin: Vec<u8>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_edinburgh"))
+        .args(args)
+        .env_remove("RUST_LOG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut pipe = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        // the reader may be gone already (e.g. --file), a broken pipe is fine
+        let _ = pipe.write_all(&stdin);
+    });
+
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+    output
+}
+
+#[test]
+fn stdin_pipe() {
+    let output = edinburgh(&["--stdin"], std::fs::read(fixture()).unwrap());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Ensemble: Edinburgh Test - EID 0xcedb"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("End of file reached"), "{}", stdout);
+}
+
+#[test]
+fn list_replayed_file() {
+    let file = fixture();
+    let output = edinburgh(&["--file", file.to_str().unwrap(), "--list"], Vec::new());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Ensemble: Edinburgh Test - EID 0xcedb"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Edinburgh One"), "{}", stdout);
+    assert!(stdout.contains("Edinburgh Two"), "{}", stdout);
+    // exits on the complete ensemble, before the end of the file
+    assert!(!stdout.contains("End of file reached"), "{}", stdout);
+}
+
+#[test]
+fn list_without_ensemble() {
+    let output = edinburgh(&["--stdin", "--list"], b"no EDI here".to_vec());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("No complete ensemble from stdin"),
+        "{}",
+        stdout
+    );
+}
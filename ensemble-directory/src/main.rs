@@ -1,7 +1,16 @@
 mod services;
 
-use axum::{extract::State, routing::get, Json, Router};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
 use clap::Parser;
+use serde::Deserialize;
+use shared::utils::Endpoint;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 use tower_http::cors::{Any, CorsLayer};
@@ -12,7 +21,7 @@ use services::{DirectoryService, ScanTarget};
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Server listening address
+    /// Server listening address, IPv6 with or without brackets (e.g. :: or [::])
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
 
@@ -34,19 +43,53 @@ struct Args {
     #[arg(long = "scan-timeout", default_value = "5")]
     scan_timeout: u64,
 
-    /// Scan parallelism: number of concurrent scans
-    #[arg(long = "scan-parallel", default_value = "8")]
+    /// Scan parallelism: number of concurrent scans (at least 1)
+    #[arg(
+        long = "scan-parallel",
+        default_value = "8",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
     scan_num_parallel: usize,
 
+    /// Local address for the scan connections, e.g. on hosts with multiple interfaces
+    #[arg(long, value_name = "LOCAL_ADDR")]
+    bind: Option<IpAddr>,
+
+    /// Decode X-PAD EPG during scans, served at /epg?sid=<sid>. Scans take --scan-timeout longer
+    #[cfg(feature = "epg")]
+    #[arg(long)]
+    epg: bool,
+
     /// Scan only once and print the result. Not starting a server
     #[arg(long = "once")]
     scan_once: bool,
 
+    /// Frame forwarder URL used in the playlists, entries point to <url>/ws/<edi-host>/<edi-port>?scid=<scid>
+    #[arg(long, default_value = "ws://127.0.0.1:9000")]
+    forwarder_url: String,
+
     /// Verbose logging
     #[arg(long = "verbose", short = 'v')]
     verbose: bool,
 }
 
+// /epg?sid=c221, hex with or without 0x
+#[derive(Deserialize)]
+struct EpgQuery {
+    sid: String,
+}
+
+impl EpgQuery {
+    fn sid(&self) -> Option<u16> {
+        let sid = self
+            .sid
+            .strip_prefix("0x")
+            .or_else(|| self.sid.strip_prefix("0X"))
+            .unwrap_or(&self.sid);
+        u16::from_str_radix(sid, 16).ok()
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
@@ -60,8 +103,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     let run_server = !args.scan_once;
+    let forwarder_url = args.forwarder_url.trim_end_matches('/').to_string();
 
-    let addr = format!("{}:{}", args.host, args.port.unwrap());
+    let addr = Endpoint::new(args.host.clone(), args.port.unwrap()).to_string();
 
     // validate that timeout is less than interval
     if args.scan_timeout >= args.scan_interval {
@@ -73,20 +117,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
+    #[cfg(feature = "epg")]
+    let scan_epg = args.epg;
+    #[cfg(not(feature = "epg"))]
+    let scan_epg = false;
+
     let svc = services::DirectoryService::new(
         args.scan_targets,
         args.scan_interval,
         args.scan_timeout,
         args.scan_num_parallel,
+        args.bind,
+        scan_epg,
     );
 
     // println!("{:?}", svc.ensembles);
 
     if args.scan_once {
+        // progress on stderr, stdout is the result
+        let mut last_completed = None;
+
         while svc.get_num_runs().await == 0 {
+            let status = svc.get_scan_status().await;
+            if last_completed != Some(status.completed) && status.total > 0 {
+                eprint!(
+                    "\rScanning: {}/{} ({} failed)",
+                    status.completed, status.total, status.failed
+                );
+                last_completed = Some(status.completed);
+            }
             sleep(Duration::from_millis(25)).await;
         }
 
+        if last_completed.is_some() {
+            eprintln!();
+        }
+
         let mut dir_ensembles = svc.get_ensembles().await;
 
         // dir_ensembles.sort_by_key(|e| (e.host.clone(), e.port));
@@ -103,7 +169,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 e.ensemble.eid.unwrap_or(0),
                 e.ensemble.label.unwrap_or_default()
             );
-            let host = format!("{}:{}", e.host, e.port);
+            let host = Endpoint::new(e.host.clone(), e.port).to_string();
 
             let mut services = e.ensemble.services;
             services.sort_by_key(|svc| svc.label.as_ref().unwrap_or(&"".into()).clone());
@@ -142,6 +208,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     Json(service.get_ensembles().await)
                 }),
             )
+            .route(
+                "/scan/status",
+                get(|State(service): State<Arc<DirectoryService>>| async move {
+                    Json(service.get_scan_status().await)
+                }),
+            )
+            .route(
+                "/epg",
+                get(
+                    |State(service): State<Arc<DirectoryService>>,
+                     Query(query): Query<EpgQuery>| async move {
+                        let Some(sid) = query.sid() else {
+                            return StatusCode::BAD_REQUEST.into_response();
+                        };
+                        match service.get_epg(sid).await {
+                            Some(schedule) => Json(schedule).into_response(),
+                            None => StatusCode::NOT_FOUND.into_response(),
+                        }
+                    },
+                ),
+            )
+            .route(
+                "/ensembles/{host}/{port}/playlist.m3u",
+                get(
+                    move |State(service): State<Arc<DirectoryService>>,
+                          Path((host, port)): Path<(String, u16)>| {
+                        let base_url = format!("{}/ws/{}/{}", forwarder_url, host, port);
+                        async move {
+                            match service.get_ensemble(&host, port).await {
+                                Some(e) => (
+                                    [(header::CONTENT_TYPE, "audio/x-mpegurl")],
+                                    e.ensemble.to_m3u(&base_url),
+                                )
+                                    .into_response(),
+                                None => StatusCode::NOT_FOUND.into_response(),
+                            }
+                        }
+                    },
+                ),
+            )
             .with_state(svc)
             .layer(cors);
 
@@ -151,3 +257,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epg_query_sid() {
+        let sid = |sid: &str| EpgQuery { sid: sid.into() }.sid();
+
+        assert_eq!(sid("c221"), Some(0xC221));
+        assert_eq!(sid("0xC221"), Some(0xC221));
+        assert_eq!(sid("1"), Some(0x0001));
+        assert_eq!(sid("e1c22100"), None);
+        assert_eq!(sid("x"), None);
+        assert_eq!(sid(""), None);
+    }
+}
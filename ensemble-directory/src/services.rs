@@ -1,22 +1,32 @@
 use futures::stream::{FuturesUnordered, StreamExt};
 use regex::Regex;
 use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
 use std::io;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::io::Interest;
-use tokio::net::TcpStream;
 use tokio::sync::RwLock;
 use tokio::sync::Semaphore;
+use tokio::task::JoinError;
 use tokio::time::{self, timeout, Duration};
 
+use shared::dab::bus::{init_event_bus, DabEvent};
+use shared::dab::epg::ProgrammeSchedule;
 use shared::dab::DabSource;
 use shared::dab::Ensemble;
 use shared::edi_frame_extractor::EdiFrameExtractor;
+use shared::utils::{connect_tcp, Endpoint};
 
 #[derive(Serialize, Clone, Debug)]
 pub struct DirectoryEnsemble {
     pub host: String,
     pub port: u16,
+    // resolved from EId + ECC, to group ensembles by country
+    pub country: Option<&'static str>,
     #[serde(flatten)]
     pub ensemble: Ensemble,
 }
@@ -24,12 +34,126 @@ pub struct DirectoryEnsemble {
 #[derive(Serialize)]
 pub struct ApiRoot {
     pub ensembles: String,
+    pub scan_status: String,
+    pub epg: String,
 }
 
-#[derive(Clone, Debug)]
-pub struct Endpoint {
+// progress of the current scan round, updated by the scan tasks
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    total: AtomicUsize,
+    completed: AtomicUsize,
+    in_flight: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanStatus {
+    // finished rounds, the counters are for the current (or last) round
+    pub runs: usize,
+    pub total: usize,
+    pub completed: usize,
+    pub in_flight: usize,
+    pub failed: usize,
+    // per target, from the last finished round
+    pub targets: Vec<ScanTargetStatus>,
+}
+
+// why a target is (or is not) listed in /ensembles
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanOutcome {
+    // connected, ensemble complete
+    Ensemble,
+    // connected, but the ensemble did not complete before the stream closed / stalled
+    ConnectedNoEnsemble,
+    // connected, but (mostly) undecodable data: AF sync / CRC errors
+    DecodeErrors,
+    // connect timed out, or connected but not a single byte before the scan timeout
+    Timeout,
+    Refused,
+    // any other connect error, e.g. unresolvable host, unreachable network
+    ConnectFailed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanTargetStatus {
     pub host: String,
     pub port: u16,
+    pub outcome: ScanOutcome,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ScanError {
+    pub outcome: ScanOutcome,
+    pub message: String,
+}
+
+impl ScanError {
+    fn new(outcome: ScanOutcome, message: impl Into<String>) -> Self {
+        Self {
+            outcome,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.outcome, self.message)
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+impl ScanProgress {
+    fn start_round(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.completed.store(0, Ordering::Relaxed);
+        self.failed.store(0, Ordering::Relaxed);
+    }
+
+    // completed includes failed
+    fn complete(&self, failed: bool) {
+        if failed {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+
+        tracing::debug!(
+            "Scan progress: {}/{} ({} in flight, {} failed)",
+            completed,
+            self.total.load(Ordering::Relaxed),
+            self.in_flight.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed)
+        );
+    }
+
+    // counted in flight until the guard is dropped, also if the scan panics
+    fn start_scan(&self) -> InFlightGuard<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(&self.in_flight)
+    }
+
+    fn status(&self, runs: usize, targets: Vec<ScanTargetStatus>) -> ScanStatus {
+        ScanStatus {
+            runs,
+            total: self.total.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            targets,
+        }
+    }
+}
+
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -42,14 +166,22 @@ impl std::str::FromStr for ScanTarget {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"^(?P<host>[^:]+):(?P<start>\d+)(?:-(?P<end>\d+))?$")
-            .map_err(|_| "Invalid regex".to_string())?;
+        // IPv6 addresses in brackets: [::1]:9000-9010
+        let re = Regex::new(
+            r"^(?:\[(?P<host6>[^\]]+)\]|(?P<host>[^:\[\]]+)):(?P<start>\d+)(?:-(?P<end>\d+))?$",
+        )
+        .map_err(|_| "Invalid regex".to_string())?;
 
         let caps = re
             .captures(s)
             .ok_or_else(|| "Invalid format: must be host:port or host:port-port".to_string())?;
 
-        let host = caps.name("host").unwrap().as_str().to_string();
+        let host = caps
+            .name("host6")
+            .or_else(|| caps.name("host"))
+            .unwrap()
+            .as_str()
+            .to_string();
         let start_port = caps
             .name("start")
             .unwrap()
@@ -78,7 +210,15 @@ pub struct DirectoryService {
     pub scan_interval: u64,
     pub scan_timeout: u64,
     pub scan_num_parallel: usize,
+    // local address for the scan connections
+    pub scan_bind: Option<IpAddr>,
     pub scan_num_run: Arc<RwLock<usize>>,
+    pub scan_progress: Arc<ScanProgress>,
+    pub scan_targets_status: Arc<RwLock<Vec<ScanTargetStatus>>>,
+    // decode X-PAD EPG during scans, see scan()
+    pub scan_epg: bool,
+    // latest programme information by SId
+    pub schedules: Arc<RwLock<HashMap<u16, ProgrammeSchedule>>>,
 }
 
 impl DirectoryService {
@@ -87,16 +227,50 @@ impl DirectoryService {
         scan_interval: u64,
         scan_timeout: u64,
         scan_num_parallel: usize,
+        scan_bind: Option<IpAddr>,
+        scan_epg: bool,
     ) -> Arc<Self> {
         let svc = Arc::new(Self {
             ensembles: Arc::new(RwLock::new(Vec::new())),
             scan_targets,
             scan_interval,
             scan_timeout,
-            scan_num_parallel,
+            // 0 permits would never scan
+            scan_num_parallel: scan_num_parallel.max(1),
+            scan_bind,
             scan_num_run: Arc::new(RwLock::new(0)),
+            scan_progress: Arc::new(ScanProgress::default()),
+            scan_targets_status: Arc::new(RwLock::new(Vec::new())),
+            scan_epg,
+            schedules: Arc::new(RwLock::new(HashMap::new())),
         });
 
+        // EPG objects are only emitted as events. the scans are the only sources in this process
+        if scan_epg {
+            let schedules = Arc::clone(&svc.schedules);
+            let mut events = init_event_bus();
+
+            tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    if let DabEvent::EpgScheduleReceived(schedule) = event {
+                        match schedule.sid() {
+                            Some(sid) => {
+                                tracing::debug!(
+                                    "EPG for 0x{:04X}: {} entries",
+                                    sid,
+                                    schedule.entries.len()
+                                );
+                                schedules.write().await.insert(sid, schedule);
+                            }
+                            None => {
+                                tracing::debug!("EPG without service scope: {:?}", schedule.service)
+                            }
+                        }
+                    }
+                }
+            });
+        }
+
         let svc_clone = Arc::clone(&svc);
 
         tokio::spawn(async move {
@@ -109,6 +283,8 @@ impl DirectoryService {
     pub fn get_root(&self) -> ApiRoot {
         ApiRoot {
             ensembles: "/ensembles".into(),
+            scan_status: "/scan/status".into(),
+            epg: "/epg?sid=<sid>".into(),
         }
     }
 
@@ -116,10 +292,29 @@ impl DirectoryService {
         self.ensembles.read().await.clone()
     }
 
+    pub async fn get_ensemble(&self, host: &str, port: u16) -> Option<DirectoryEnsemble> {
+        self.ensembles
+            .read()
+            .await
+            .iter()
+            .find(|e| e.host == host && e.port == port)
+            .cloned()
+    }
+
+    pub async fn get_epg(&self, sid: u16) -> Option<ProgrammeSchedule> {
+        self.schedules.read().await.get(&sid).cloned()
+    }
+
     pub async fn get_num_runs(&self) -> usize {
         *self.scan_num_run.read().await
     }
 
+    pub async fn get_scan_status(&self) -> ScanStatus {
+        let targets = self.scan_targets_status.read().await.clone();
+        self.scan_progress
+            .status(self.get_num_runs().await, targets)
+    }
+
     async fn run_scan(self: Arc<Self>) {
         let mut interval = time::interval(Duration::from_secs(self.scan_interval));
         interval.tick().await; // eat the first tick
@@ -129,35 +324,46 @@ impl DirectoryService {
             .iter()
             .flat_map(|target| {
                 let (start, end) = target.port_range;
-                (start..=end).map(move |port| Endpoint {
-                    host: target.host.clone(),
-                    port,
-                })
+                (start..=end).map(move |port| Endpoint::new(target.host.clone(), port))
             })
             .collect();
 
-        let semaphore = Arc::new(Semaphore::new(self.scan_num_parallel));
+        let scan_timeout = self.scan_timeout;
+        let scan_bind = self.scan_bind;
+        let scan_epg = self.scan_epg;
 
         loop {
-            let mut scans = FuturesUnordered::new();
-
-            for endpoint in &endpoints {
-                let permit = semaphore.clone().acquire_owned().await.unwrap();
-                let endpoint = endpoint.clone();
-                let scan_timeout = self.scan_timeout;
-
-                scans.push(tokio::spawn(async move {
-                    let result = scan(endpoint, scan_timeout).await;
-                    drop(permit); // release slot for next scan
-                    result
-                }));
-            }
+            self.scan_progress.start_round(endpoints.len());
+
+            let results =
+                scan_round(
+                    &endpoints,
+                    self.scan_num_parallel,
+                    &self.scan_progress,
+                    move |endpoint| async move {
+                        scan(&endpoint, scan_timeout, scan_bind, scan_epg).await
+                    },
+                )
+                .await;
 
             let mut ensembles = Vec::new();
+            let mut targets_status = Vec::new();
+
+            for result in results {
+                if let Ok((endpoint, result)) = &result {
+                    targets_status.push(ScanTargetStatus {
+                        host: endpoint.host.clone(),
+                        port: endpoint.port,
+                        outcome: match result {
+                            Ok(_) => ScanOutcome::Ensemble,
+                            Err(err) => err.outcome,
+                        },
+                        detail: result.as_ref().err().map(|err| err.message.clone()),
+                    });
+                }
 
-            while let Some(result) = scans.next().await {
                 match result {
-                    Ok(Ok(ensemble)) => {
+                    Ok((_, Ok(ensemble))) => {
                         tracing::debug!(
                             "Scanned endpoint: {} {} - 0x{:4X} - {}",
                             ensemble.host,
@@ -167,11 +373,12 @@ impl DirectoryService {
                         );
                         ensembles.push(ensemble);
                     }
-                    Ok(Err(err)) => {
+                    Ok((_, Err(err))) => {
                         tracing::error!("Failed to scan ensemble: {}", err);
                     }
                     Err(join_err) => {
                         tracing::error!("Join error in scan task: {}", join_err);
+                        self.scan_progress.complete(true);
                     }
                 }
             }
@@ -181,6 +388,9 @@ impl DirectoryService {
                 *lock = ensembles;
             }
 
+            targets_status.sort_by(|a, b| (&a.host, a.port).cmp(&(&b.host, b.port)));
+            *self.scan_targets_status.write().await = targets_status;
+
             *self.scan_num_run.write().await += 1;
 
             interval.tick().await;
@@ -188,31 +398,95 @@ impl DirectoryService {
     }
 }
 
-async fn scan(endpoint: Endpoint, scan_timeout: u64) -> anyhow::Result<DirectoryEnsemble> {
+type ScanResult = Result<DirectoryEnsemble, ScanError>;
+
+// scans all endpoints, results in completion order. a permit is taken before a scan task is
+// spawned and released when it ends, so at most num_parallel connections are open
+async fn scan_round<F, Fut>(
+    endpoints: &[Endpoint],
+    num_parallel: usize,
+    progress: &Arc<ScanProgress>,
+    scanner: F,
+) -> Vec<Result<(Endpoint, ScanResult), JoinError>>
+where
+    F: Fn(Endpoint) -> Fut,
+    Fut: Future<Output = ScanResult> + Send + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(num_parallel.max(1)));
+    let mut scans = FuturesUnordered::new();
+
+    for endpoint in endpoints {
+        let permit = semaphore.clone().acquire_owned().await.unwrap();
+        let endpoint = endpoint.clone();
+        let scan = scanner(endpoint.clone());
+        let progress = Arc::clone(progress);
+
+        scans.push(tokio::spawn(async move {
+            let in_flight = progress.start_scan();
+            let result = scan.await;
+            drop(in_flight);
+            progress.complete(result.is_err());
+            drop(permit); // release slot for next scan
+            (endpoint, result)
+        }));
+    }
+
+    scans.collect().await
+}
+
+#[tracing::instrument(skip_all, fields(host = %endpoint.host, port = endpoint.port))]
+// with epg, PAD is decoded as well and the scan continues for scan_timeout after the ensemble
+// is complete, EPG objects take a while (carousel)
+async fn scan(
+    endpoint: &Endpoint,
+    scan_timeout: u64,
+    bind: Option<IpAddr>,
+    epg: bool,
+) -> Result<DirectoryEnsemble, ScanError> {
     let timeout_ms = scan_timeout * 1000;
 
-    let uri = format!("{}:{}", endpoint.host, endpoint.port);
+    let uri = endpoint.to_string();
 
-    let stream = match timeout(
-        Duration::from_millis(timeout_ms),
-        TcpStream::connect(uri.clone()),
-    )
-    .await
-    {
+    let stream = match timeout(Duration::from_millis(timeout_ms), connect_tcp(&uri, bind)).await {
         Ok(Ok(stream)) => stream,
-        Ok(Err(e)) => anyhow::bail!("Failed to connect to {}: {}", uri, e),
-        Err(_) => anyhow::bail!("Timeout connecting to {}", uri),
+        Ok(Err(e)) => {
+            let outcome = if e.kind() == io::ErrorKind::ConnectionRefused {
+                ScanOutcome::Refused
+            } else {
+                ScanOutcome::ConnectFailed
+            };
+            return Err(ScanError::new(
+                outcome,
+                format!("Failed to connect to {}: {}", uri, e),
+            ));
+        }
+        Err(_) => {
+            return Err(ScanError::new(
+                ScanOutcome::Timeout,
+                format!("Timeout connecting to {}", uri),
+            ))
+        }
     };
 
     let mut filled = 0;
+    // bytes read in total, a silent endpoint is a Timeout
+    let mut received: usize = 0;
     let mut extractor = EdiFrameExtractor::new();
+    // AF frames failing the header / length check
+    let mut frame_errors: u64 = 0;
 
     let (done_tx, mut done_rx) = tokio::sync::oneshot::channel::<Ensemble>();
     let done_tx = Arc::new(Mutex::new(Some(done_tx)));
 
-    let mut source = DabSource::new(
-        None,
-        Some(Box::new({
+    // only the ensemble is needed here, skip audio (and PAD, unless for EPG) decoding
+    let builder = if epg {
+        DabSource::builder().audio_frames(false)
+    } else {
+        DabSource::builder().enable_audio(false)
+    };
+
+    let mut source = builder
+        .on_ensemble({
             let done_tx = Arc::clone(&done_tx);
             move |ensemble: &Ensemble| {
                 if ensemble.complete {
@@ -222,18 +496,53 @@ async fn scan(endpoint: Endpoint, scan_timeout: u64) -> anyhow::Result<Directory
                     }
                 }
             }
-        })),
-        None,
-    );
+        })
+        .build();
+
+    // connected, but no ensemble: mostly errors is DecodeErrors, else ConnectedNoEnsemble
+    let no_ensemble = |source: &DabSource, frame_errors: u64, reason: String| {
+        let metrics = source.metrics();
+        let errors = frame_errors + metrics.crc_errors;
+
+        if errors > 0 && errors >= metrics.frames {
+            ScanError::new(
+                ScanOutcome::DecodeErrors,
+                format!(
+                    "{} ({} frames, {} decode errors)",
+                    reason, metrics.frames, errors
+                ),
+            )
+        } else {
+            ScanError::new(
+                ScanOutcome::ConnectedNoEnsemble,
+                format!("{} ({} frames)", reason, metrics.frames),
+            )
+        }
+    };
+
+    let directory_ensemble = |ensemble: Ensemble| DirectoryEnsemble {
+        country: ensemble.country(),
+        ensemble,
+        host: endpoint.host.clone(),
+        port: endpoint.port,
+    };
+
+    // EPG: the complete ensemble, kept until the linger timer ends the scan
+    let mut found: Option<Ensemble> = None;
+    let linger = time::sleep(Duration::from_millis(timeout_ms));
+    tokio::pin!(linger);
 
     loop {
         tokio::select! {
-            Ok(ensemble) = &mut done_rx => {
-                return Ok(DirectoryEnsemble {
-                    ensemble,
-                    host: endpoint.host.clone(),
-                    port: endpoint.port,
-                });
+            Ok(ensemble) = &mut done_rx, if found.is_none() => {
+                if !epg {
+                    return Ok(directory_ensemble(ensemble));
+                }
+                linger.as_mut().reset(time::Instant::now() + Duration::from_millis(timeout_ms));
+                found = Some(ensemble);
+            }
+            _ = &mut linger, if found.is_some() => {
+                return Ok(directory_ensemble(found.take().unwrap()));
             }
             ready = timeout(Duration::from_millis(timeout_ms), stream.ready(Interest::READABLE)) => {
                 match ready {
@@ -242,10 +551,15 @@ async fn scan(endpoint: Endpoint, scan_timeout: u64) -> anyhow::Result<Directory
                         match stream.try_read(&mut extractor.frame.data[filled..]) {
                             Ok(0) => {
                                 tracing::info!("Connection closed by peer");
-                                anyhow::bail!("Connection closed before ensemble complete");
+                                return found.map(directory_ensemble).ok_or_else(|| no_ensemble(
+                                    &source,
+                                    frame_errors,
+                                    "Connection closed before ensemble complete".into(),
+                                ));
                             }
                             Ok(n) => {
                                 filled += n;
+                                received += n;
                                 if filled < extractor.frame.data.len() {
                                     continue;
                                 }
@@ -255,22 +569,247 @@ async fn scan(endpoint: Endpoint, scan_timeout: u64) -> anyhow::Result<Directory
                                         filled -= offset;
                                         continue;
                                     }
-                                    if extractor.frame.check_completed() {
-                                        source.feed(&extractor.frame.data).await;
-                                        extractor.frame.reset();
-                                        filled = 0;
+                                    match extractor.frame.check_completed() {
+                                        Ok(true) => {
+                                            source.feed(&extractor.frame.data).await;
+                                            extractor.frame.reset();
+                                            filled = 0;
+                                        }
+                                        Ok(false) => {}
+                                        Err(e) => {
+                                            // corrupt header: resync on the next "AF"
+                                            tracing::warn!("{}", e);
+                                            frame_errors += 1;
+                                            filled = extractor.resync(filled);
+                                        }
                                     }
+                                } else {
+                                    // no sync in the buffer, keep a trailing 'A'
+                                    filled = extractor.resync(filled);
                                 }
                             }
                             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
-                            Err(e) => anyhow::bail!("Read error: {}", e),
+                            Err(e) => {
+                                return found.map(directory_ensemble).ok_or_else(|| no_ensemble(
+                                    &source,
+                                    frame_errors,
+                                    format!("Read error: {}", e),
+                                ))
+                            }
                         }
                     }
                      }
-                    Ok(Err(e)) => anyhow::bail!("Stream error: {}", e),
-                    Err(_) => anyhow::bail!("No data from stream for 5s"),
+                    Ok(Err(e)) => {
+                        return found.map(directory_ensemble).ok_or_else(|| no_ensemble(
+                            &source,
+                            frame_errors,
+                            format!("Stream error: {}", e),
+                        ))
+                    }
+                    Err(_) if received == 0 => {
+                        return Err(ScanError::new(
+                            ScanOutcome::Timeout,
+                            format!("No data from stream for {}s", scan_timeout),
+                        ))
+                    }
+                    Err(_) => {
+                        return found.map(directory_ensemble).ok_or_else(|| no_ensemble(
+                            &source,
+                            frame_errors,
+                            format!("No data from stream for {}s", scan_timeout),
+                        ))
+                    }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_flight_guard() {
+        let progress = ScanProgress::default();
+
+        let a = progress.start_scan();
+        let b = progress.start_scan();
+        assert_eq!(progress.in_flight.load(Ordering::Relaxed), 2);
+        drop(a);
+        drop(b);
+        assert_eq!(progress.in_flight.load(Ordering::Relaxed), 0);
+
+        // released while unwinding
+        let result = std::panic::catch_unwind(|| {
+            let _in_flight = progress.start_scan();
+            panic!("scan failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(progress.in_flight.load(Ordering::Relaxed), 0);
+    }
+
+    fn endpoints(n: u16) -> Vec<Endpoint> {
+        (1..=n)
+            .map(|port| Endpoint::new("localhost", port))
+            .collect()
+    }
+
+    // counts the concurrently running scans, every scan fails with a timeout
+    async fn scan_parallel(num_parallel: usize) -> (usize, Arc<ScanProgress>) {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let progress = Arc::new(ScanProgress::default());
+        progress.start_round(10);
+
+        let results = scan_round(&endpoints(10), num_parallel, &progress, |endpoint| {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            async move {
+                let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(n, Ordering::SeqCst);
+                time::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Err(ScanError::new(ScanOutcome::Timeout, endpoint.to_string()))
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 10);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Ok((_, Err(e))) if e.outcome == ScanOutcome::Timeout)));
+
+        (max_running.load(Ordering::SeqCst), progress)
+    }
+
+    #[tokio::test]
+    async fn scan_round_limits_parallel() {
+        let (max_running, progress) = scan_parallel(3).await;
+        assert_eq!(max_running, 3);
+
+        let status = progress.status(0, Vec::new());
+        assert_eq!(status.total, 10);
+        assert_eq!(status.completed, 10);
+        assert_eq!(status.failed, 10);
+        assert_eq!(status.in_flight, 0);
+
+        // one at a time, 0 is treated as 1
+        assert_eq!(scan_parallel(1).await.0, 1);
+        assert_eq!(scan_parallel(0).await.0, 1);
+    }
+
+    #[tokio::test]
+    async fn scan_round_panic() {
+        let progress = Arc::new(ScanProgress::default());
+
+        let results = scan_round(&endpoints(3), 2, &progress, |endpoint| async move {
+            if endpoint.port == 2 {
+                panic!("scan failed");
+            }
+            Err(ScanError::new(ScanOutcome::Refused, ""))
+        })
+        .await;
+
+        assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+        assert_eq!(progress.in_flight.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn num_parallel_clamped() {
+        let svc = DirectoryService::new(Vec::new(), 60, 10, 0, None, false);
+        assert_eq!(svc.scan_num_parallel, 1);
+    }
+
+    // replay fixture of the shared crate as a stream of AF packets, optionally with the first
+    // FIB of every packet broken
+    fn fixture_stream(corrupt_fib: bool) -> Vec<u8> {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../shared/tests/data/replay.edi"
+        );
+        let data = std::fs::read(path).unwrap();
+
+        shared::dab::replay::split_packets(&data)
+            .into_iter()
+            .flat_map(|packet| {
+                let mut packet = packet.to_vec();
+                if corrupt_fib {
+                    packet[30] ^= 0x01;
+                }
+                packet
+            })
+            .collect()
+    }
+
+    // mock endpoint: sends `data` to the first connection, then keeps it open for `hold`
+    async fn serve(data: Vec<u8>, hold: Duration) -> Endpoint {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(&data).await.unwrap();
+            time::sleep(hold).await;
+        });
+
+        Endpoint::new("127.0.0.1", port)
+    }
+
+    #[tokio::test]
+    async fn scan_outcome_ensemble() {
+        let endpoint = serve(fixture_stream(false), Duration::ZERO).await;
+
+        let found = scan(&endpoint, 5, None, false).await.unwrap();
+        assert_eq!(found.ensemble.eid, Some(0xCEDB));
+        assert_eq!(found.port, endpoint.port);
+    }
+
+    #[tokio::test]
+    async fn scan_outcome_decode_errors() {
+        let endpoint = serve(fixture_stream(true), Duration::ZERO).await;
+
+        let err = scan(&endpoint, 5, None, false).await.unwrap_err();
+        assert_eq!(err.outcome, ScanOutcome::DecodeErrors);
+    }
+
+    #[tokio::test]
+    async fn scan_outcome_connected_no_ensemble() {
+        // closed right away
+        let endpoint = serve(Vec::new(), Duration::ZERO).await;
+        let err = scan(&endpoint, 5, None, false).await.unwrap_err();
+        assert_eq!(err.outcome, ScanOutcome::ConnectedNoEnsemble);
+        assert!(err.message.starts_with("Connection closed"));
+
+        // stalled in the middle of the first packet
+        let endpoint = serve(
+            fixture_stream(false)[..100].to_vec(),
+            Duration::from_secs(5),
+        )
+        .await;
+        let err = scan(&endpoint, 1, None, false).await.unwrap_err();
+        assert_eq!(err.outcome, ScanOutcome::ConnectedNoEnsemble);
+        assert!(err.message.starts_with("No data from stream for 1s"));
+    }
+
+    #[tokio::test]
+    async fn scan_outcome_timeout() {
+        // accepted, but silent
+        let endpoint = serve(Vec::new(), Duration::from_secs(5)).await;
+        let err = scan(&endpoint, 1, None, false).await.unwrap_err();
+        assert_eq!(err.outcome, ScanOutcome::Timeout);
+        assert!(err.message.starts_with("No data from stream for 1s"));
+    }
+
+    #[tokio::test]
+    async fn scan_outcome_refused() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = Endpoint::new("127.0.0.1", listener.local_addr().unwrap().port());
+        drop(listener);
+
+        let err = scan(&endpoint, 5, None, false).await.unwrap_err();
+        assert_eq!(err.outcome, ScanOutcome::Refused);
+    }
+}
@@ -1,36 +1,306 @@
 use bytes::Bytes;
 use clap::Parser;
 use dashmap::DashMap;
+#[cfg(feature = "pcm")]
+use faad2::Decoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use shared::dab::msc::AudioFormat;
+use shared::dab::DabSource;
 use shared::edi_frame_extractor::EdiFrameExtractor;
+use shared::utils::Endpoint;
+use socket2::{Domain, Protocol, Socket, Type};
 use std::io;
-use std::sync::Arc;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use tokio::io::Interest;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::time::{interval_at, timeout, Duration, Instant, MissedTickBehavior};
 use tokio_tungstenite::accept_hdr_async;
 use tokio_tungstenite::tungstenite::error::{Error as TungsteniteError, ProtocolError};
 use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::WebSocketStream;
 
 type SharedReceivers = Arc<
     DashMap<
         String,
         (
-            broadcast::Sender<Vec<u8>>,
+            broadcast::Sender<Arc<EdiFrame>>,
             tokio::task::JoinHandle<()>,
             Arc<Mutex<Option<oneshot::Receiver<Result<(), String>>>>>,
         ),
     >,
 >;
 
+// frames buffered per EDI source, a client further behind lags
+const CLIENT_QUEUE_LEN: usize = 100;
+
+// websocket subprotocol for raw-deflate compressed EDI frames (one deflate stream per message)
+const DEFLATE_PROTOCOL: &str = "edi-deflate";
+
+// EDI frame as broadcast to the clients of a source. compressed at most once (by the first
+// deflate client), the other clients share the result
+#[derive(Debug)]
+struct EdiFrame {
+    data: Bytes,
+    deflated: OnceLock<Bytes>,
+}
+
+impl EdiFrame {
+    fn new(data: Vec<u8>) -> Arc<Self> {
+        Arc::new(Self {
+            data: Bytes::from(data),
+            deflated: OnceLock::new(),
+        })
+    }
+
+    // the compression level is the same for all clients
+    fn deflated(&self, compression: Compression) -> Bytes {
+        self.deflated
+            .get_or_init(|| Bytes::from(compress(&self.data, compression)))
+            .clone()
+    }
+}
+
+// client control message, e.g. {"scid": 6} or {"scid": 6, "format": "pcm"} -
+// {"scid": null} switches back to raw EDI
+#[derive(Debug, Deserialize)]
+struct ControlMessage {
+    scid: Option<u8>,
+    #[serde(default)]
+    format: ForwardFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ForwardFormat {
+    // AAC access units
+    #[default]
+    Aac,
+    // decoded, interleaved f32 (little endian), e.g. for WebAudio without an AAC decoder
+    #[cfg(feature = "pcm")]
+    Pcm,
+}
+
+impl ControlMessage {
+    // same as a control message, from the ws URL query: ?scid=6[&format=pcm], e.g. the entries
+    // of the directory's M3U playlist. None without scid
+    fn from_query(query: &str) -> Result<Option<Self>, String> {
+        let mut scid = None;
+        let mut format = ForwardFormat::default();
+
+        for (name, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            match name {
+                "scid" => {
+                    scid = Some(
+                        value
+                            .parse::<u8>()
+                            .map_err(|_| format!("invalid scid: {}", value))?,
+                    );
+                }
+                "format" => {
+                    format = match value {
+                        "aac" => ForwardFormat::Aac,
+                        #[cfg(feature = "pcm")]
+                        "pcm" => ForwardFormat::Pcm,
+                        _ => return Err(format!("invalid format: {}", value)),
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        Ok(scid.map(|scid| Self {
+            scid: Some(scid),
+            format,
+        }))
+    }
+}
+
+// decodes AUs to PCM, one per client in PCM mode
+#[cfg(feature = "pcm")]
+struct PcmDecoder {
+    decoder: Decoder,
+    // sample rate / channels of the last header sent
+    output: Option<(u32, usize)>,
+}
+
+// SAFETY: faad2::Decoder is !Send only because it wraps a raw NeAACDecHandle. libfaad keeps
+// all decoder state behind that handle and has no thread affinity (no TLS, no thread-bound
+// callbacks), so it may be used from another thread. each decoder is owned by a single
+// client task's AacForwarder and never shared or cloned (PcmDecoder is not Sync), it only
+// moves between worker threads together with that task
+#[cfg(feature = "pcm")]
+unsafe impl Send for PcmDecoder {}
+
+#[cfg(feature = "pcm")]
+impl PcmDecoder {
+    fn new(audio_format: &AudioFormat) -> Option<Self> {
+        match Decoder::new(&audio_format.asc) {
+            Ok(decoder) => Some(Self {
+                decoder,
+                output: None,
+            }),
+            Err(e) => {
+                tracing::warn!("Unable to create decoder for {}: {:?}", audio_format, e);
+                None
+            }
+        }
+    }
+
+    // PCM header (JSON) before the first samples and whenever the decoder output changes
+    async fn feed(
+        &mut self,
+        scid: u8,
+        au_data: &[u8],
+        ws_stream: &mut WebSocketStream<TcpStream>,
+    ) -> Result<(), TungsteniteError> {
+        let r = match self.decoder.decode(au_data) {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::debug!("SubCh {}: decoder error: {}", scid, e);
+                return Ok(());
+            }
+        };
+
+        let output = (r.sample_rate as u32, r.channels);
+
+        if self.output != Some(output) {
+            let msg = serde_json::json!({
+                "scid": scid,
+                "format": "pcm",
+                "sample_rate": output.0,
+                "channels": output.1,
+            });
+            ws_stream
+                .send(WsMessage::Text(msg.to_string().into()))
+                .await?;
+            self.output = Some(output);
+        }
+
+        let data: Vec<u8> = r.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+        if !data.is_empty() {
+            ws_stream.send(WsMessage::Binary(Bytes::from(data))).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// decodes the EDI stream for a single client and forwards the AAC frames (or PCM) of one subchannel
+struct AacForwarder {
+    scid: u8,
+    source: DabSource,
+    frames: UnboundedReceiver<Vec<u8>>,
+    audio_format: Option<AudioFormat>,
+    format: ForwardFormat,
+    // PCM mode, (re)created on audio format changes
+    #[cfg(feature = "pcm")]
+    pcm_decoder: Option<PcmDecoder>,
+}
+
+impl AacForwarder {
+    fn new(scid: u8, format: ForwardFormat) -> Self {
+        let (tx, frames) = unbounded_channel();
+
+        // one decoder per client, skip the other subchannels
+        let source = DabSource::builder()
+            .scid(scid)
+            .decode_all(false)
+            .enable_pad(false)
+            .on_aac(move |frame| {
+                if frame.scid == scid {
+                    let _ = tx.send(frame.data.clone());
+                }
+            })
+            .build();
+
+        Self {
+            scid,
+            source,
+            frames,
+            audio_format: None,
+            format,
+            #[cfg(feature = "pcm")]
+            pcm_decoder: None,
+        }
+    }
+
+    async fn feed(
+        &mut self,
+        data: &[u8],
+        ws_stream: &mut WebSocketStream<TcpStream>,
+    ) -> Result<(), TungsteniteError> {
+        self.source.feed(data).await;
+
+        // audio format as JSON text message, before the first frame and on change.
+        // PCM clients get the PCM header from the decoder instead
+        let audio_format = self
+            .source
+            .subchannels()
+            .into_iter()
+            .find(|s| s.scid == self.scid)
+            .and_then(|s| s.audio_format);
+
+        if let Some(ref af) = audio_format {
+            if audio_format != self.audio_format {
+                match self.format {
+                    ForwardFormat::Aac => {
+                        let msg = serde_json::json!({
+                            "scid": self.scid,
+                            "audio_format": af,
+                        });
+                        ws_stream
+                            .send(WsMessage::Text(msg.to_string().into()))
+                            .await?;
+                    }
+                    #[cfg(feature = "pcm")]
+                    ForwardFormat::Pcm => {
+                        self.pcm_decoder = PcmDecoder::new(af);
+                    }
+                }
+                self.audio_format = audio_format;
+            }
+        }
+
+        while let Ok(frame) = self.frames.try_recv() {
+            match self.format {
+                ForwardFormat::Aac => {
+                    ws_stream
+                        .send(WsMessage::Binary(Bytes::from(frame)))
+                        .await?;
+                }
+                #[cfg(feature = "pcm")]
+                ForwardFormat::Pcm => {
+                    // frames before the audio format is known are dropped
+                    if let Some(ref mut decoder) = self.pcm_decoder {
+                        decoder.feed(self.scid, &frame, ws_stream).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// EDI Frame Forwarder
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Server listening address
+    /// Server listening address, IPv6 with or without brackets (e.g. :: or [::])
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
 
@@ -38,6 +308,34 @@ struct Args {
     #[arg(long, default_value = "9000")]
     port: Option<u16>,
 
+    /// Listen backlog (pending connections)
+    #[arg(long, default_value = "1024")]
+    backlog: i32,
+
+    /// Disconnect clients that lag behind this many times without catching up
+    #[arg(long, default_value = "10")]
+    max_lag: u32,
+
+    /// Drop an EDI upstream that sends nothing for this many seconds, its clients are disconnected
+    #[arg(long, value_name = "SECONDS", default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    upstream_idle_timeout: u64,
+
+    /// Compression level (0-9) for clients requesting the "edi-deflate" subprotocol, 0 disables it
+    #[arg(long, default_value = "6", value_parser = clap::value_parser!(u32).range(0..=9))]
+    compression_level: u32,
+
+    /// Send a websocket ping to clients every this many seconds, 0 disables it
+    #[arg(long, value_name = "SECONDS", default_value = "20")]
+    ping_interval: u64,
+
+    /// Disconnect clients that did not answer this many pings in a row
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    max_missed_pongs: u32,
+
+    /// Create this file once listening, removed on shutdown [optional]
+    #[arg(long, value_name = "PATH")]
+    ready_file: Option<PathBuf>,
+
     /// Verbose logging
     #[arg(long = "verbose", short = 'v')]
     verbose: bool,
@@ -55,29 +353,190 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .without_time()
         .init();
 
-    let addr = format!("{}:{}", args.host, args.port.unwrap());
+    // IPv6 literals get brackets
+    let addr = Endpoint::new(args.host.clone(), args.port.unwrap()).to_string();
 
     tracing::info!("Starting server on ws://{}/", addr);
     tracing::info!("Connect to: ws://{}/ws/<edi-host>/<edi-port>", addr);
 
-    let ws_listener = TcpListener::bind(addr).await?;
+    let ws_listener = bind_listener(&addr, args.backlog).await?;
     let ws_clients: SharedReceivers = Arc::new(DashMap::new());
 
     tokio::spawn(edi_extractor_cleanup_task(ws_clients.clone()));
 
-    while let Ok((stream, _)) = ws_listener.accept().await {
-        let receivers = ws_clients.clone();
-        tokio::spawn(handle_ws_connection(stream, receivers));
+    // installed before signalling readiness, a supervisor may stop us right away
+    let shutdown = shutdown_signal()?;
+    tokio::pin!(shutdown);
+
+    let ready_file = args
+        .ready_file
+        .as_deref()
+        .map(ReadyFile::create)
+        .transpose()?;
+    #[cfg(feature = "systemd")]
+    sd_notify("READY=1");
+
+    let idle_timeout = Duration::from_secs(args.upstream_idle_timeout);
+    let compression = Compression::new(args.compression_level);
+    let ping_interval = Duration::from_secs(args.ping_interval);
+
+    loop {
+        tokio::select! {
+            accepted = ws_listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let receivers = ws_clients.clone();
+                    tokio::spawn(handle_ws_connection(
+                        stream,
+                        receivers,
+                        args.max_lag,
+                        idle_timeout,
+                        compression,
+                        ping_interval,
+                        args.max_missed_pongs,
+                    ));
+                }
+                Err(e) => {
+                    tracing::error!("Accept failed: {}", e);
+                    break;
+                }
+            },
+            reason = &mut shutdown => {
+                tracing::info!("{}", reason);
+                break;
+            }
+        }
     }
 
+    #[cfg(feature = "systemd")]
+    sd_notify("STOPPING=1");
+    drop(ready_file);
+
     Ok(())
 }
 
-async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
+// completes on Ctrl-C or SIGTERM (systemctl stop, docker stop)
+fn shutdown_signal() -> io::Result<impl std::future::Future<Output = &'static str>> {
+    #[cfg(unix)]
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+    Ok(async move {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => "Interrupted",
+                _ = sigterm.recv() => "Terminated",
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            "Interrupted"
+        }
+    })
+}
+
+// --ready-file, contains the PID while listening. removed on drop
+struct ReadyFile(PathBuf);
+
+impl ReadyFile {
+    fn create(path: &Path) -> io::Result<Self> {
+        std::fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for ReadyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// systemd notify protocol, a no-op when not started by systemd
+#[cfg(feature = "systemd")]
+fn sd_notify(state: &str) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::net::UnixDatagram;
+
+        let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+            return;
+        };
+
+        // abstract socket names start with "@"
+        let result = UnixDatagram::unbound().and_then(|socket| {
+            #[cfg(target_os = "linux")]
+            if let Some(name) = path.to_str().and_then(|p| p.strip_prefix('@')) {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                return socket.send_to_addr(state.as_bytes(), &addr);
+            }
+            socket.send_to(state.as_bytes(), &path)
+        });
+
+        if let Err(e) = result {
+            tracing::warn!("sd_notify {} failed: {}", state, e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+// SO_REUSEADDR allows rebinding while old connections are in TIME_WAIT.
+// "::" listens on both IPv6 and IPv4.
+async fn bind_listener(addr: &str, backlog: i32) -> io::Result<TcpListener> {
+    let addr = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("Unable to resolve {}", addr),
+        )
+    })?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(backlog)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+async fn handle_ws_connection(
+    stream: TcpStream,
+    ws_clients: SharedReceivers,
+    max_lag: u32,
+    idle_timeout: Duration,
+    compression: Compression,
+    ping_interval: Duration,
+    max_missed_pongs: u32,
+) {
     let mut uri_holder = None;
+    let mut deflate = false;
 
-    let ws_stream = match accept_hdr_async(stream, |req: &Request, resp: Response| {
+    let ws_stream = match accept_hdr_async(stream, |req: &Request, mut resp: Response| {
         uri_holder = Some(req.uri().clone());
+
+        // compress only if the client asks for it
+        deflate = compression.level() > 0
+            && req
+                .headers()
+                .get_all(SEC_WEBSOCKET_PROTOCOL)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .any(|p| p.trim() == DEFLATE_PROTOCOL);
+
+        if deflate {
+            resp.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static(DEFLATE_PROTOCOL),
+            );
+        }
+
         Ok(resp)
     })
     .await
@@ -103,23 +562,45 @@ async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
         return;
     }
 
-    let host = parts[1].to_string();
-    let port = parts[2].to_string();
-    let key = format!("{}:{}", host, port);
+    let endpoint = match parts[2].parse::<u16>() {
+        // IPv6 addresses with or without brackets
+        Ok(port) if port > 0 && !parts[1].is_empty() => {
+            Endpoint::new(parts[1].trim_start_matches('[').trim_end_matches(']'), port)
+        }
+        _ => {
+            tracing::error!("Invalid EDI host / port in ws path: {}", uri);
+            return;
+        }
+    };
+    let key = endpoint.to_string();
 
-    tracing::debug!("New ws client for: {}", key);
+    let forward = match ControlMessage::from_query(uri.query().unwrap_or_default()) {
+        Ok(forward) => forward,
+        Err(e) => {
+            tracing::error!("Invalid ws query: {} - {}", uri, e);
+            return;
+        }
+    };
+
+    tracing::debug!("New ws client for: {} (deflate: {})", key, deflate);
 
     let (mut ws_stream, mut rx, conn_signal) = {
         let entry = ws_clients.entry(key.clone()).or_insert_with(|| {
-            let (tx, _) = broadcast::channel(100);
+            let (tx, _) = broadcast::channel(CLIENT_QUEUE_LEN);
             let (conn_status_tx, conn_status_rx) = oneshot::channel();
 
-            let task_handle = tokio::spawn(start_edi_extractor(
-                host.clone(),
-                port.clone(),
-                tx.clone(),
-                conn_status_tx,
-            ));
+            let task_handle = tokio::spawn({
+                let endpoint = endpoint.clone();
+                let tx = tx.clone();
+                let ws_clients = ws_clients.clone();
+                let key = key.clone();
+                async move {
+                    start_edi_extractor(endpoint, tx.clone(), conn_status_tx, idle_timeout).await;
+                    // upstream gone: dropping the last sender closes the clients, the next
+                    // client for this source connects again
+                    ws_clients.remove_if(&key, |_, entry| entry.0.same_channel(&tx));
+                }
+            });
             (tx, task_handle, Arc::new(Mutex::new(Some(conn_status_rx))))
         });
 
@@ -154,6 +635,37 @@ async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
         }
     }
 
+    // set by the URL query or a control message: forward AAC frames instead of raw EDI
+    let mut aac_forwarder: Option<AacForwarder> = match forward {
+        Some(ControlMessage {
+            scid: Some(scid),
+            format,
+        }) => {
+            tracing::debug!(
+                "ws client for {}: forward {:?} of SubCh {}",
+                key,
+                format,
+                scid
+            );
+            Some(AacForwarder::new(scid, format))
+        }
+        _ => None,
+    };
+
+    let mut lag = ClientLag::default();
+
+    // EDI bytes in / bytes sent, to judge the compression ratio
+    let mut bytes_in: u64 = 0;
+    let mut bytes_out: u64 = 0;
+
+    // keep-alive through NAT / proxies and detection of half-open clients.
+    // the timer is never polled if pings are disabled (interval must not be 0)
+    let ping_enabled = !ping_interval.is_zero();
+    let ping_period = ping_interval.max(Duration::from_secs(1));
+    let mut ping_timer = interval_at(Instant::now() + ping_period, ping_period);
+    ping_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut missed_pongs = 0;
+
     loop {
         tokio::select! {
             // handle disconnect or incoming client message
@@ -163,8 +675,26 @@ async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
                         tracing::debug!("Client sent close frame: {:?}", frame);
                         break;
                     }
+                    Some(Ok(WsMessage::Text(text))) => {
+                        match serde_json::from_str::<ControlMessage>(text.as_str()) {
+                            Ok(ControlMessage { scid: Some(scid), format }) => {
+                                tracing::debug!("ws client for {}: forward {:?} of SubCh {}", key, format, scid);
+                                aac_forwarder = Some(AacForwarder::new(scid, format));
+                            }
+                            Ok(ControlMessage { scid: None, .. }) => {
+                                tracing::debug!("ws client for {}: forward EDI", key);
+                                aac_forwarder = None;
+                            }
+                            Err(e) => {
+                                tracing::warn!("Invalid control message: {}", e);
+                            }
+                        }
+                    }
+                    Some(Ok(WsMessage::Pong(_))) => {
+                        missed_pongs = 0;
+                    }
                     Some(Ok(_)) => {
-                        // eventually handle client messages (ping/keepalive) here
+                        // client pings are answered by tungstenite
                         continue;
                     }
                     Some(Err(e)) => {
@@ -186,17 +716,71 @@ async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
                 }
             }
 
+            // keep-alive ping, a client missing max_missed_pongs in a row is gone
+            _ = ping_timer.tick(), if ping_enabled => {
+                if missed_pongs >= max_missed_pongs {
+                    tracing::info!("ws client for {} missed {} pings - disconnecting", key, missed_pongs);
+                    let close_frame = CloseFrame {
+                        code: CloseCode::Away,
+                        reason: "Ping timeout".into(),
+                    };
+                    let _ = ws_stream.close(Some(close_frame)).await;
+                    break;
+                }
+
+                if let Err(e) = ws_stream.send(WsMessage::Ping(Bytes::new())).await {
+                    tracing::warn!("WebSocket ping error: {}", e);
+                    break;
+                }
+                missed_pongs += 1;
+            }
+
             // broadcast data from the TCP source
             broadcast_msg = rx.recv() => {
                 match broadcast_msg {
-                    Ok(data) => {
-                        if let Err(e) = ws_stream.send(WsMessage::Binary(Bytes::from(data))).await {
+                    Ok(frame) => {
+                        let result = match aac_forwarder {
+                            Some(ref mut forwarder) => forwarder.feed(&frame.data, &mut ws_stream).await,
+                            None => {
+                                bytes_in += frame.data.len() as u64;
+                                let data = if deflate {
+                                    frame.deflated(compression)
+                                } else {
+                                    frame.data.clone()
+                                };
+                                bytes_out += data.len() as u64;
+                                ws_stream.send(WsMessage::Binary(data)).await
+                            }
+                        };
+
+                        if let Err(e) = result {
                             tracing::warn!("WebSocket send error: {}", e);
                             break;
                         }
+
+                        lag.sent();
                     }
-                    Err(_) => {
-                        // sender dropped or channel closed
+                    Err(RecvError::Lagged(n)) => {
+                        // skip ahead, the receiver continues with the oldest buffered frame
+                        tracing::debug!("ws client for {} lagging: {} frames dropped", key, n);
+
+                        if lag.lagged(max_lag) {
+                            tracing::warn!("ws client for {} too slow - disconnecting", key);
+                            let close_frame = CloseFrame {
+                                code: CloseCode::Again,
+                                reason: "Client too slow".into(),
+                            };
+                            let _ = ws_stream.close(Some(close_frame)).await;
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => {
+                        // sender dropped: EDI upstream closed, failed or idle
+                        let close_frame = CloseFrame {
+                            code: CloseCode::Away,
+                            reason: "EDI source closed".into(),
+                        };
+                        let _ = ws_stream.close(Some(close_frame)).await;
                         break;
                     }
                 }
@@ -204,20 +788,62 @@ async fn handle_ws_connection(stream: TcpStream, ws_clients: SharedReceivers) {
         }
     }
 
+    if deflate && bytes_in > 0 {
+        tracing::debug!(
+            "ws client for {}: {} bytes compressed to {} ({:.0}%)",
+            key,
+            bytes_in,
+            bytes_out,
+            bytes_out as f64 * 100.0 / bytes_in as f64
+        );
+    }
+
     tracing::debug!("Disconnected ws client for: {}", key);
     drop(rx);
 }
 
+// lag events of a client since it last caught up, see --max-lag
+#[derive(Debug, Default)]
+struct ClientLag {
+    count: u32,
+    // frames sent since the last lag
+    sent: usize,
+}
+
+impl ClientLag {
+    // a full queue delivered without lagging: caught up
+    fn sent(&mut self) {
+        self.sent += 1;
+        if self.sent >= CLIENT_QUEUE_LEN {
+            self.count = 0;
+        }
+    }
+
+    // true once the client lagged more than max_lag times without catching up
+    fn lagged(&mut self, max_lag: u32) -> bool {
+        self.count += 1;
+        self.sent = 0;
+        self.count > max_lag
+    }
+}
+
+// raw deflate (RFC 1951), e.g. DecompressionStream("deflate-raw") in the browser
+fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len()), compression);
+    // writing to a Vec does not fail
+    encoder.write_all(data).expect("deflate to Vec");
+    encoder.finish().expect("deflate to Vec")
+}
+
 async fn start_edi_extractor(
-    host: String,
-    port: String,
-    tx: broadcast::Sender<Vec<u8>>,
+    endpoint: Endpoint,
+    tx: broadcast::Sender<Arc<EdiFrame>>,
     conn_status_tx: oneshot::Sender<Result<(), String>>,
+    idle_timeout: Duration,
 ) {
-    let endpoint = format!("{}:{}", host, port);
     tracing::debug!("Starting TCP receiver for: {}", endpoint);
 
-    match TcpStream::connect(&endpoint).await {
+    match TcpStream::connect(endpoint.to_string()).await {
         Ok(stream) => {
             // Notify successful connection
             let _ = conn_status_tx.send(Ok(()));
@@ -226,12 +852,20 @@ async fn start_edi_extractor(
             let mut filled = 0;
 
             loop {
-                let ready = match stream.ready(Interest::READABLE).await {
-                    Ok(ready) => ready,
-                    Err(e) => {
+                let ready = match timeout(idle_timeout, stream.ready(Interest::READABLE)).await {
+                    Ok(Ok(ready)) => ready,
+                    Ok(Err(e)) => {
                         tracing::error!("Error on {}: {}", endpoint, e);
                         break;
                     }
+                    Err(_) => {
+                        tracing::warn!(
+                            "No data from {} for {:?} - dropping",
+                            endpoint,
+                            idle_timeout
+                        );
+                        break;
+                    }
                 };
 
                 if ready.is_readable() {
@@ -256,11 +890,23 @@ async fn start_edi_extractor(
                                     continue;
                                 }
 
-                                if extractor.frame.check_completed() {
-                                    let _ = tx.send(extractor.frame.data.clone());
-                                    extractor.frame.reset();
-                                    filled = 0;
+                                match extractor.frame.check_completed() {
+                                    Ok(true) => {
+                                        let _ =
+                                            tx.send(EdiFrame::new(extractor.frame.data.clone()));
+                                        extractor.frame.reset();
+                                        filled = 0;
+                                    }
+                                    Ok(false) => {}
+                                    Err(e) => {
+                                        // corrupt header: resync on the next "AF"
+                                        tracing::warn!("{}: {}", endpoint, e);
+                                        filled = extractor.resync(filled);
+                                    }
                                 }
+                            } else {
+                                // no sync in the buffer, keep a trailing 'A'
+                                filled = extractor.resync(filled);
                             }
                         }
                         Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
@@ -303,3 +949,541 @@ async fn edi_extractor_cleanup_task(ws_clients: SharedReceivers) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::utils::{calc_crc16_ccitt, calc_crc_fire_code};
+    use std::io::Read;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::MaybeTlsStream;
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    // AF packet (revision 1.0, PT "T") with the given tags and CRC
+    fn af_packet(tags: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for (name, value) in tags {
+            payload.extend_from_slice(*name);
+            payload.extend_from_slice(&((value.len() * 8) as u32).to_be_bytes());
+            payload.extend_from_slice(value);
+        }
+
+        let mut data = b"AF".to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x90, b'T']);
+        data.extend_from_slice(&payload);
+        let crc = calc_crc16_ccitt(&data);
+        data.extend_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    // DETI value with FIC (mode I, 3 FIBs), the first FIB carries the given FIGs
+    fn deti(figs: &[u8]) -> Vec<u8> {
+        let mut deti = vec![0x40, 0x00, 0xFF, 0x40, 0x00, 0x00];
+        for n in 0..3 {
+            let mut fib = if n == 0 { figs.to_vec() } else { Vec::new() };
+            fib.resize(30, 0xFF);
+            let crc = calc_crc16_ccitt(&fib);
+            deti.extend_from_slice(&fib);
+            deti.extend_from_slice(&crc.to_be_bytes());
+        }
+        deti
+    }
+
+    // FIG 0/1 and 0/2: SId 0xC221, DAB+ in SubCh 1 at CU 0, EEP 3-A 24 CUs (32 kbps)
+    const FIGS: [u8; 13] = [
+        0x05, 0x01, 0x04, 0x00, 0x88, 0x18, 0x06, 0x02, 0xC2, 0x21, 0x01, 0x3F, 0x06,
+    ];
+
+    // AAC-LC raw_data_block: a single channel element without spectral data, then ID_END
+    const SILENT_AU: [u8; 4] = [0x00, 0xC8, 0x00, 0x07];
+
+    // DAB+ superframe of 32 kbps (480 bytes, RS parity not set): 48 kHz AAC-LC mono, 6 AUs
+    // starting with au
+    fn superframe(au: &[u8]) -> Vec<u8> {
+        let mut sf = vec![0u8; 480];
+        sf[2] = 0x40;
+        // AU 1 - 5 start at 82, 153, 224, 295, 366
+        sf[3..11].copy_from_slice(&[0x05, 0x20, 0x99, 0x0E, 0x01, 0x27, 0x16, 0xE0]);
+
+        let starts = [11, 82, 153, 224, 295, 366, 440];
+        for bounds in starts.windows(2) {
+            let (start, end) = (bounds[0], bounds[1]);
+            sf[start..start + au.len()].copy_from_slice(au);
+            let crc = calc_crc16_ccitt(&sf[start..end - 2]);
+            sf[end - 2..end].copy_from_slice(&crc.to_be_bytes());
+        }
+
+        let fire_code = calc_crc_fire_code(&sf[2..11]);
+        sf[..2].copy_from_slice(&fire_code.to_be_bytes());
+        sf
+    }
+
+    // the superframe of SubCh 1 in 5 AF packets
+    fn edi_frames() -> Vec<Vec<u8>> {
+        let deti = deti(&FIGS);
+        superframe(&SILENT_AU)
+            .chunks(96)
+            .map(|chunk| {
+                // SSTC: SCID 1, SAD 0, TPL 0
+                let mut est = vec![0x04, 0x00, 0x00];
+                est.extend_from_slice(chunk);
+                af_packet(&[(b"deti", &deti), (b"est\x01", &est)])
+            })
+            .collect()
+    }
+
+    // EDI upstream on an ephemeral port: frames in a loop every 5ms, none at all if empty
+    async fn upstream(frames: Vec<Vec<u8>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let frames = frames.clone();
+                tokio::spawn(async move {
+                    if frames.is_empty() {
+                        std::future::pending::<()>().await;
+                    }
+                    for frame in frames.iter().cycle() {
+                        if stream.write_all(frame).await.is_err() {
+                            break;
+                        }
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                });
+            }
+        });
+
+        port
+    }
+
+    #[derive(Clone, Copy)]
+    struct Options {
+        idle_timeout: Duration,
+        ping_interval: Duration,
+        max_missed_pongs: u32,
+    }
+
+    impl Default for Options {
+        fn default() -> Self {
+            Self {
+                idle_timeout: Duration::from_secs(10),
+                ping_interval: Duration::ZERO,
+                max_missed_pongs: 3,
+            }
+        }
+    }
+
+    // the accept loop of main on an ephemeral port
+    async fn serve(options: Options) -> (SocketAddr, SharedReceivers) {
+        let listener = bind_listener("127.0.0.1:0", 16).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ws_clients: SharedReceivers = Arc::new(DashMap::new());
+
+        tokio::spawn({
+            let ws_clients = ws_clients.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_ws_connection(
+                        stream,
+                        ws_clients.clone(),
+                        10,
+                        options.idle_timeout,
+                        Compression::new(0),
+                        options.ping_interval,
+                        options.max_missed_pongs,
+                    ));
+                }
+            }
+        });
+
+        (addr, ws_clients)
+    }
+
+    // ws client for the upstream on port, query e.g. "?scid=1"
+    async fn client(addr: SocketAddr, port: u16, query: &str) -> Client {
+        let url = format!("ws://{}/ws/127.0.0.1/{}{}", addr, port, query);
+        tokio_tungstenite::connect_async(url).await.unwrap().0
+    }
+
+    // next text / binary / close message
+    async fn next(ws: &mut Client) -> WsMessage {
+        loop {
+            let msg = timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("no message")
+                .expect("stream ended")
+                .unwrap();
+            if !matches!(msg, WsMessage::Ping(_) | WsMessage::Pong(_)) {
+                return msg;
+            }
+        }
+    }
+
+    // skips everything else
+    async fn next_text(ws: &mut Client) -> serde_json::Value {
+        loop {
+            if let WsMessage::Text(text) = next(ws).await {
+                return serde_json::from_str(text.as_str()).unwrap();
+            }
+        }
+    }
+
+    fn is_edi(msg: &WsMessage) -> bool {
+        matches!(msg, WsMessage::Binary(data) if data.starts_with(b"AF"))
+    }
+
+    #[tokio::test]
+    async fn control_message() {
+        let port = upstream(edi_frames()).await;
+        let (addr, _) = serve(Options::default()).await;
+
+        let mut edi = client(addr, port, "").await;
+        let mut aac = client(addr, port, "").await;
+        assert!(is_edi(&next(&mut aac).await));
+
+        // AAC frames of SubCh 1 after the audio format, the other client keeps getting EDI
+        aac.send(WsMessage::Text(r#"{"scid": 1}"#.into()))
+            .await
+            .unwrap();
+        let header = next_text(&mut aac).await;
+        assert_eq!(header["scid"], 1);
+        assert_eq!(header["audio_format"]["codec"], "AAC-LC");
+        assert_eq!(header["audio_format"]["samplerate"], 48);
+
+        for _ in 0..6 {
+            match next(&mut aac).await {
+                WsMessage::Binary(frame) => assert!(frame.starts_with(&SILENT_AU)),
+                msg => panic!("unexpected {:?}", msg),
+            }
+            assert!(is_edi(&next(&mut edi).await));
+        }
+
+        // back to EDI
+        aac.send(WsMessage::Text(r#"{"scid": null}"#.into()))
+            .await
+            .unwrap();
+        while !is_edi(&next(&mut aac).await) {}
+    }
+
+    #[tokio::test]
+    async fn control_query() {
+        let port = upstream(edi_frames()).await;
+        let (addr, _) = serve(Options::default()).await;
+
+        let mut aac = client(addr, port, "?scid=1").await;
+        assert_eq!(next_text(&mut aac).await["scid"], 1);
+        assert!(
+            matches!(next(&mut aac).await, WsMessage::Binary(frame) if frame.starts_with(&SILENT_AU))
+        );
+    }
+
+    #[cfg(feature = "pcm")]
+    #[tokio::test]
+    async fn pcm_samples() {
+        let port = upstream(edi_frames()).await;
+        let (addr, _) = serve(Options::default()).await;
+
+        // decoder output as header, then interleaved f32 samples
+        let mut pcm = client(addr, port, "?scid=1&format=pcm").await;
+        let header = next_text(&mut pcm).await;
+        assert_eq!(header["scid"], 1);
+        assert_eq!(header["format"], "pcm");
+        assert_eq!(header["sample_rate"], 48000);
+        let channels = header["channels"].as_u64().unwrap() as usize;
+        assert!(channels > 0);
+
+        match next(&mut pcm).await {
+            WsMessage::Binary(samples) => {
+                assert!(!samples.is_empty());
+                assert_eq!(samples.len() % (4 * channels), 0);
+            }
+            msg => panic!("unexpected {:?}", msg),
+        }
+    }
+
+    // code and reason of the close frame, skips everything else
+    async fn closed(ws: &mut Client) -> (CloseCode, String) {
+        loop {
+            if let WsMessage::Close(frame) = next(ws).await {
+                let frame = frame.expect("close frame");
+                return (frame.code, frame.reason.as_str().to_string());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn ping_answered() {
+        let port = upstream(edi_frames()).await;
+        let (addr, _) = serve(Options {
+            ping_interval: Duration::from_secs(1),
+            max_missed_pongs: 1,
+            ..Default::default()
+        })
+        .await;
+
+        // a reading client answers the pings and stays connected
+        let mut ws = client(addr, port, "").await;
+        let mut pings = 0;
+        while pings < 3 {
+            let msg = timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("no message")
+                .expect("stream ended")
+                .unwrap();
+            match msg {
+                WsMessage::Ping(_) => pings += 1,
+                msg => assert!(is_edi(&msg), "unexpected {:?}", msg),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn ping_timeout() {
+        let port = upstream(Vec::new()).await;
+        let (addr, _) = serve(Options {
+            ping_interval: Duration::from_secs(1),
+            max_missed_pongs: 1,
+            ..Default::default()
+        })
+        .await;
+
+        // no reads, no pongs: closed on the second tick
+        let mut ws = client(addr, port, "").await;
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(
+            closed(&mut ws).await,
+            (CloseCode::Away, "Ping timeout".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn idle_upstream() {
+        let port = upstream(Vec::new()).await;
+        let (addr, ws_clients) = serve(Options {
+            idle_timeout: Duration::from_millis(200),
+            ..Default::default()
+        })
+        .await;
+
+        // connected but silent: dropped after the idle timeout, the clients are closed
+        let mut ws = client(addr, port, "").await;
+        assert_eq!(
+            closed(&mut ws).await,
+            (CloseCode::Away, "EDI source closed".to_string())
+        );
+        assert!(!ws_clients.contains_key(&format!("127.0.0.1:{}", port)));
+    }
+
+    #[tokio::test]
+    async fn client_close() {
+        let port = upstream(edi_frames()).await;
+        let (addr, ws_clients) = serve(Options::default()).await;
+        let key = format!("127.0.0.1:{}", port);
+        let receivers = || {
+            ws_clients
+                .get(&key)
+                .map_or(0, |entry| entry.value().0.receiver_count())
+        };
+
+        let mut first = client(addr, port, "").await;
+        let mut second = client(addr, port, "").await;
+        assert!(is_edi(&next(&mut first).await));
+        assert!(is_edi(&next(&mut second).await));
+        assert_eq!(receivers(), 2);
+
+        // the connection ends and unsubscribes, the other client keeps going
+        first.close(None).await.unwrap();
+        timeout(Duration::from_secs(5), async {
+            while let Some(Ok(_)) = first.next().await {}
+        })
+        .await
+        .unwrap();
+        timeout(Duration::from_secs(5), async {
+            while receivers() != 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(is_edi(&next(&mut second).await));
+    }
+
+    #[test]
+    fn forward_query() {
+        let forward = ControlMessage::from_query("scid=6").unwrap().unwrap();
+        assert_eq!(forward.scid, Some(6));
+        assert_eq!(forward.format, ForwardFormat::Aac);
+
+        let forward = ControlMessage::from_query("format=aac&scid=12&x=1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(forward.scid, Some(12));
+        assert_eq!(forward.format, ForwardFormat::Aac);
+    }
+
+    #[cfg(feature = "pcm")]
+    #[test]
+    fn forward_query_pcm() {
+        let forward = ControlMessage::from_query("format=pcm&scid=12")
+            .unwrap()
+            .unwrap();
+        assert_eq!(forward.format, ForwardFormat::Pcm);
+
+        let forward: ControlMessage =
+            serde_json::from_str(r#"{"scid": 6, "format": "pcm"}"#).unwrap();
+        assert_eq!(forward.format, ForwardFormat::Pcm);
+    }
+
+    #[cfg(not(feature = "pcm"))]
+    #[test]
+    fn forward_query_pcm_disabled() {
+        assert!(ControlMessage::from_query("format=pcm&scid=12").is_err());
+        assert!(serde_json::from_str::<ControlMessage>(r#"{"scid": 6, "format": "pcm"}"#).is_err());
+    }
+
+    #[test]
+    fn forward_query_edi() {
+        assert!(ControlMessage::from_query("").unwrap().is_none());
+        assert!(ControlMessage::from_query("format=aac").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rebind_time_wait() {
+        let listener = bind_listener("127.0.0.1:0", 16).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // closed by the server first: its end stays in TIME_WAIT on the listening port
+        let (connected, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        drop(accepted.unwrap());
+        let mut rest = Vec::new();
+        connected.unwrap().read_to_end(&mut rest).await.unwrap();
+        drop(listener);
+
+        // e.g. a restart right after the old process exited
+        let listener = bind_listener(&addr.to_string(), 16).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn ready_file_after_bind() {
+        let path = std::env::temp_dir().join(format!("forwarder-{}.ready", std::process::id()));
+
+        let listener = bind_listener("127.0.0.1:0", 16).await.unwrap();
+        let ready_file = ReadyFile::create(&path).unwrap();
+
+        // listening once the file is there
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        let addr = listener.local_addr().unwrap();
+        let (connected, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        connected.unwrap();
+        accepted.unwrap();
+
+        drop(ready_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn deflate_once() {
+        let frame = EdiFrame::new(b"AF".repeat(100));
+        let compression = Compression::new(6);
+
+        let a = frame.deflated(compression);
+        let b = frame.deflated(compression);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert!(a.len() < frame.data.len());
+
+        let mut inflated = Vec::new();
+        flate2::read::DeflateDecoder::new(&a[..])
+            .read_to_end(&mut inflated)
+            .unwrap();
+        assert_eq!(inflated, frame.data);
+    }
+
+    // CPU time vs bandwidth per compression level, over the replay fixture:
+    // cargo test -p edinburgh-frame-forwarder --release -- --ignored --nocapture compression_cost
+    #[test]
+    #[ignore]
+    fn compression_cost() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../shared/tests/data/replay.edi"
+        );
+        let data = std::fs::read(path).unwrap();
+        let frames = shared::dab::replay::split_packets(&data);
+
+        for level in [1, 3, 6, 9] {
+            let start = std::time::Instant::now();
+            let out: usize = frames
+                .iter()
+                .map(|f| compress(f, Compression::new(level)).len())
+                .sum();
+            let elapsed = start.elapsed();
+
+            println!(
+                "level {}: {:.0}% of {} bytes, {:.1} us per frame",
+                level,
+                out as f64 * 100.0 / data.len() as f64,
+                data.len(),
+                elapsed.as_secs_f64() * 1e6 / frames.len() as f64
+            );
+        }
+    }
+
+    // frames 0..n, numbered
+    fn broadcast_frames(tx: &broadcast::Sender<Arc<EdiFrame>>, n: std::ops::Range<u32>) {
+        for i in n {
+            tx.send(EdiFrame::new(i.to_be_bytes().to_vec())).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn lagging_subscriber() {
+        let (tx, mut rx) = broadcast::channel(CLIENT_QUEUE_LEN);
+        let mut lag = ClientLag::default();
+        let len = CLIENT_QUEUE_LEN as u32;
+
+        // 5 frames more than the queue holds: skipped, the client is kept and continues with
+        // the oldest buffered frame
+        broadcast_frames(&tx, 0..len + 5);
+        assert!(matches!(rx.recv().await, Err(RecvError::Lagged(5))));
+        assert!(!lag.lagged(2));
+        assert_eq!(rx.recv().await.unwrap().data[..], 5u32.to_be_bytes());
+        lag.sent();
+
+        // lagging again before a full queue was delivered
+        broadcast_frames(&tx, 0..len + 1);
+        assert!(matches!(rx.recv().await, Err(RecvError::Lagged(_))));
+        assert!(!lag.lagged(2));
+
+        broadcast_frames(&tx, 0..len + 1);
+        assert!(matches!(rx.recv().await, Err(RecvError::Lagged(_))));
+        assert!(lag.lagged(2));
+    }
+
+    #[test]
+    fn lagging_caught_up() {
+        let mut lag = ClientLag::default();
+        assert!(!lag.lagged(1));
+
+        // a full queue delivered in time resets the count
+        for _ in 0..CLIENT_QUEUE_LEN {
+            lag.sent();
+        }
+        assert!(!lag.lagged(1));
+        assert!(lag.lagged(1));
+    }
+
+    #[test]
+    fn forward_query_invalid() {
+        assert!(ControlMessage::from_query("scid=x").is_err());
+        assert!(ControlMessage::from_query("scid=300").is_err());
+        assert!(ControlMessage::from_query("scid=6&format=mp3").is_err());
+    }
+}
@@ -1,11 +1,11 @@
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
-use shared::dab::bus::{init_event_bus, DabEvent};
+use pyo3::types::{PyBytes, PyDict};
+use shared::dab::bus::{init_event_bus, shutdown_event_bus, DabEvent};
 use shared::dab::DabSource;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::runtime::{Builder, Runtime};
-use tokio::sync::mpsc::{Sender, UnboundedReceiver};
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::Mutex;
 
 type PyCallback = PyObject;
@@ -13,9 +13,8 @@ type PyCallback = PyObject;
 #[pyclass]
 #[allow(clippy::upper_case_acronyms)]
 struct EDI {
-    _inner: Arc<Mutex<DabSource>>,
+    source: DabSource,
     _callbacks: Arc<Mutex<HashMap<String, Vec<PyCallback>>>>,
-    tx: Sender<Vec<u8>>,
     _rt: Arc<Runtime>,
 }
 
@@ -30,55 +29,57 @@ impl EDI {
                 .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?,
         );
 
-        let source = Arc::new(Mutex::new(DabSource::new(None, None, None)));
         let callbacks = Arc::new(Mutex::new(HashMap::new()));
 
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(64);
-
-        // spawn feed loop
-        {
-            let handle = rt.handle().clone();
-            handle.spawn(async move {
-                let mut edisource = DabSource::new(None, None, None);
-                while let Some(data) = rx.recv().await {
-                    let _ = edisource.feed(&data).await;
-                }
-            });
-        }
-
-        // init the bus and spawn the event handler
-        let edi_rx = init_event_bus();
-        let event_handler = DabEventHandler::new(edi_rx, callbacks.clone());
-
-        {
-            let handle = rt.handle().clone();
-            handle.spawn(async move {
-                event_handler.run().await;
-            });
-        }
+        Self::spawn_event_handler(&rt, &callbacks);
 
         Ok(EDI {
-            _inner: source,
+            source: DabSource::new(None, None, None),
             _callbacks: callbacks,
-            tx,
             _rt: rt,
         })
     }
 
-    fn feed(&self, _py: Python<'_>, data: Bound<'_, PyBytes>) -> PyResult<()> {
-        match self.tx.try_send(data.as_bytes().to_vec()) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-                "Channel error: {e}"
-            ))),
-        }
+    // decodes on the calling thread, events are dispatched by the event handler task
+    fn feed(&mut self, py: Python<'_>, data: Bound<'_, PyBytes>) -> PyResult<()> {
+        let data = data.as_bytes().to_vec();
+        let source = &mut self.source;
+        py.allow_threads(|| source.feed_sync(&data));
+        Ok(())
     }
 
-    fn reset(&self) -> PyResult<()> {
+    // counters snapshot: frames, crc_errors, synced_subchannels, ensemble_complete
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let metrics = self.source.metrics();
+        let dict = PyDict::new(py);
+        dict.set_item("frames", metrics.frames)?;
+        dict.set_item("crc_errors", metrics.crc_errors)?;
+        dict.set_item("synced_subchannels", metrics.synced_subchannels)?;
+        dict.set_item("ensemble_complete", metrics.ensemble_complete)?;
+        Ok(dict)
+    }
+
+    // stop the event handler and start fresh
+    fn reset(&mut self) -> PyResult<()> {
+        shutdown_event_bus();
+        self.source.reset();
+        Self::spawn_event_handler(&self._rt, &self._callbacks);
         Ok(())
     }
 }
 
+impl EDI {
+    fn spawn_event_handler(rt: &Runtime, callbacks: &Arc<Mutex<HashMap<String, Vec<PyCallback>>>>) {
+        // init the bus and spawn the event handler, ends when the bus is shut down
+        let edi_rx = init_event_bus();
+        let event_handler = DabEventHandler::new(edi_rx, callbacks.clone());
+
+        rt.spawn(async move {
+            event_handler.run().await;
+        });
+    }
+}
+
 struct DabEventHandler {
     edi_rx: UnboundedReceiver<DabEvent>,
     #[allow(dead_code)]
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shared::dab::FicDecoder;
+
+fuzz_target!(|data: &[u8]| {
+    // lenient mode, random FIBs would never pass the CRC check
+    let _ = FicDecoder::new(false).decode(data);
+
+    // pad to whole FIBs so the size check does not reject most inputs
+    let mut fibs = data.to_vec();
+    fibs.resize(data.len().div_ceil(32) * 32, 0xFF);
+    let _ = FicDecoder::new(false).decode(&fibs);
+});
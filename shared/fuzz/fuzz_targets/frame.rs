@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shared::dab::{Frame, FrameParser};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Frame::from_bytes(data);
+
+    // same bytes through the streaming parser, in small chunks to hit the partial tag paths
+    let mut parser = FrameParser::new();
+    for chunk in data.chunks(7) {
+        let _ = parser.feed(chunk);
+    }
+});
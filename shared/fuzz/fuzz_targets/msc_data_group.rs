@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shared::dab::pad::MscDataGroup;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = MscDataGroup::from_bytes(data);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use shared::dab::pad::PadDecoder;
+
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = PadDecoder::new(0);
+
+    // consecutive PADs: X-PAD length (1), X-PAD, F-PAD (2)
+    let mut rest = data;
+    while rest.len() >= 3 {
+        let xpad_len = (rest[0] as usize).min(rest.len() - 3);
+        let xpad = &rest[1..1 + xpad_len];
+        let fpad = &rest[1 + xpad_len..3 + xpad_len];
+        decoder.feed(fpad, xpad);
+        rest = &rest[3 + xpad_len..];
+    }
+});
@@ -0,0 +1,91 @@
+#!/usr/bin/env python3
+"""
+writes small, valid seed inputs to corpus/<target>/seed-*, so the fuzzer starts
+past the sync / size / CRC checks. the frame seeds are taken from the replay
+fixture (shared/tests/data/replay.edi).
+
+usage: python3 make_seeds.py
+"""
+
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+FIXTURE = os.path.join(HERE, "..", "tests", "data", "replay.edi")
+
+
+def crc16_ccitt(data):
+    crc = 0xFFFF
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x1021) if crc & 0x8000 else crc << 1
+            crc &= 0xFFFF
+    return crc ^ 0xFFFF
+
+
+def with_crc(data):
+    return data + struct.pack(">H", crc16_ccitt(data))
+
+
+def af_packets(data):
+    packets = []
+    offset = 0
+    while offset + 10 <= len(data):
+        if data[offset : offset + 2] != b"AF":
+            offset += 1
+            continue
+        end = offset + 10 + struct.unpack(">I", data[offset + 2 : offset + 6])[0] + 2
+        if end > len(data):
+            offset += 1
+            continue
+        packets.append(data[offset:end])
+        offset = end
+    return packets
+
+
+def write(target, name, data):
+    path = os.path.join(HERE, "corpus", target)
+    os.makedirs(path, exist_ok=True)
+    with open(os.path.join(path, "seed-" + name), "wb") as f:
+        f.write(data)
+
+
+def main():
+    with open(FIXTURE, "rb") as f:
+        packets = af_packets(f.read())
+
+    # frame: a single packet (deti + est) and a short stream
+    write("frame", "packet", packets[0])
+    write("frame", "stream", b"".join(packets[:3]))
+
+    # fic: the FIC of the first DETI tag (3 FIBs)
+    deti = packets[0].index(b"deti") + 8
+    write("fic", "fibs", packets[0][deti + 6 : deti + 6 + 96])
+
+    # msc_data_group: MOT header (type 3) and body (type 4) segments, transport id 0x1234
+    # ContentName (PLI 3, id 0x0C): charset, "a.jpg"
+    content_name = bytes([0xCC, 0x06, 0x00]) + b"a.jpg"
+    # core: BodySize (28), HeaderSize (13), ContentType (6) image, ContentSubType (9) JPEG
+    core = 5 << 28 | (7 + len(content_name)) << 15 | 2 << 9 | 1
+    header = core.to_bytes(7, "big") + content_name
+    write(
+        "msc_data_group",
+        "mot-header",
+        with_crc(bytes([0x73, 0x00, 0x80, 0x00, 0x12, 0x12, 0x34]) + struct.pack(">H", len(header)) + header),
+    )
+    write(
+        "msc_data_group",
+        "mot-body",
+        with_crc(bytes([0x74, 0x00, 0x80, 0x00, 0x12, 0x12, 0x34]) + struct.pack(">H", 5) + b"\xff\xd8\xff\xd9\x00"),
+    )
+
+    # pad: X-PAD length, X-PAD (reversed, as carried in the audio frame), F-PAD.
+    # variable X-PAD with one DL CI (12 bytes): single segment "Hello"
+    dl = with_crc(bytes([0x64, 0x00]) + b"Hello")
+    xpad = bytes([0x62, 0x00]) + dl.ljust(12, b"\x00")
+    write("pad", "dl", bytes([len(xpad)]) + xpad[::-1] + bytes([0x20, 0x02]))
+
+
+if __name__ == "__main__":
+    main()
@@ -1,21 +1,36 @@
 pub mod bus;
 mod ensemble;
+pub mod epg;
 mod fic;
 mod frame;
 pub mod msc;
 pub mod pad;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replay;
 mod tables;
 mod utils;
 
 use derive_more::Debug;
-pub use ensemble::{Ensemble, Subchannel};
-use frame::Frame;
-use frame::Tag;
+pub use ensemble::{
+    DataComponent, Ensemble, EnsembleChange, EnsembleDelta, LinkageSet, Service, ServiceComponent,
+    Subchannel,
+};
+pub use fic::{DateTimeUTC, FicDecoder, LinkedId};
+pub use frame::Tag;
+use frame::{EstTag, FrameDecodeError};
+pub use frame::{Frame, FrameOptions, FrameParser};
 use log;
-use msc::{AacpExctractor, FeedResult};
+#[cfg(feature = "audio")]
+use msc::{AacpExctractor, FeedResult, Mp2Extractor};
+use msc::{AudioFormat, RawAu};
+use pad::mot::{MotStats, MOT_MAX_OBJECT_SIZE};
 use serde::Serialize;
+#[cfg(feature = "audio")]
+use tables::AudioServiceType;
 
-use bus::{emit_event, DabEvent};
+use bus::{
+    acquire_event_bus, emit_event, release_event_bus, set_event_sink, shutdown_event_bus, DabEvent,
+};
 
 #[derive(Debug, Serialize)]
 pub struct AacpFrame {
@@ -38,14 +53,89 @@ impl Drop for AacpFrame {
 #[derive(Debug)]
 pub struct DabSubchannel {
     scid: u8,
+    #[cfg(feature = "audio")]
     audio_extractor: AacpExctractor,
+    // set once FIG 0/2 signals a DAB (MP2) subchannel, used instead of audio_extractor
+    #[cfg(feature = "audio")]
+    mp2_extractor: Option<Mp2Extractor>,
 }
 
 impl DabSubchannel {
     pub fn new(scid: u8) -> Self {
         DabSubchannel {
             scid,
+            #[cfg(feature = "audio")]
             audio_extractor: AacpExctractor::new(scid),
+            #[cfg(feature = "audio")]
+            mp2_extractor: None,
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn audio_format(&self) -> Option<&AudioFormat> {
+        match self.mp2_extractor {
+            Some(ref mp2) => mp2.audio_format(),
+            None => self.audio_extractor.audio_format(),
+        }
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn audio_format(&self) -> Option<&AudioFormat> {
+        None
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn is_synced(&self) -> bool {
+        match self.mp2_extractor {
+            Some(ref mp2) => mp2.is_synced(),
+            None => self.audio_extractor.is_synced(),
+        }
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn is_synced(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn mot_stats(&self) -> Option<&MotStats> {
+        Some(self.audio_extractor.mot_stats())
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn mot_stats(&self) -> Option<&MotStats> {
+        None
+    }
+}
+
+// subchannel as seen by DabSource: EST data received, FIC info and audio format if known
+#[derive(Debug, Clone, Serialize)]
+pub struct SubchannelInfo {
+    pub scid: u8,
+    pub bitrate: Option<usize>,
+    pub pl: Option<String>,
+    pub audio_format: Option<AudioFormat>,
+    pub mot_stats: Option<MotStats>,
+}
+
+// about one second of DAB frames
+const STATS_INTERVAL_FRAMES: u64 = 42;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DecodeErrors {
+    pub fib_crc_errors: u64,
+    pub au_crc_errors: u64,
+    pub superframe_sync_losses: u64,
+}
+
+impl DecodeErrors {
+    fn since(&self, base: &DecodeErrors) -> DecodeErrors {
+        DecodeErrors {
+            fib_crc_errors: self.fib_crc_errors.saturating_sub(base.fib_crc_errors),
+            au_crc_errors: self.au_crc_errors.saturating_sub(base.au_crc_errors),
+            superframe_sync_losses: self
+                .superframe_sync_losses
+                .saturating_sub(base.superframe_sync_losses),
         }
     }
 }
@@ -55,6 +145,13 @@ pub struct DabStats {
     pub rx_rate: usize,
     pub rx_bytes: u64,
     pub rx_frames: u64,
+    // AF packets dropped due to CRC mismatch (with verify_af_crc)
+    pub crc_errors: u64,
+    // FIC / audio decode errors, cumulative and in the last interval (STATS_INTERVAL_FRAMES)
+    pub errors: DecodeErrors,
+    pub errors_interval: DecodeErrors,
+    #[serde(skip)]
+    errors_interval_base: DecodeErrors,
 }
 
 impl Default for DabStats {
@@ -69,6 +166,10 @@ impl DabStats {
             rx_rate: 0,
             rx_bytes: 0,
             rx_frames: 0,
+            crc_errors: 0,
+            errors: DecodeErrors::default(),
+            errors_interval: DecodeErrors::default(),
+            errors_interval_base: DecodeErrors::default(),
         }
     }
     pub fn feed(&mut self, data: &[u8]) {
@@ -77,24 +178,137 @@ impl DabStats {
         self.rx_bytes += bytes as u64;
         self.rx_frames += 1;
 
+        if self.rx_frames % STATS_INTERVAL_FRAMES == 0 {
+            self.errors_interval = self.errors.since(&self.errors_interval_base);
+            self.errors_interval_base = self.errors;
+        }
+
         emit_event(DabEvent::DabStatsUpdated(self.clone()));
     }
 }
 
+// pull-based counterpart to DabStatsUpdated, see DabSource::metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DabMetrics {
+    pub frames: u64,
+    // AF packet, FIB and AU CRC errors
+    pub crc_errors: u64,
+    // subchannels with superframe (DAB+) / frame header (DAB) sync
+    pub synced_subchannels: usize,
+    pub ensemble_complete: bool,
+}
+
+// DAB frame duration, one AF packet per frame
+#[cfg(not(target_arch = "wasm32"))]
+const FRAME_DURATION: std::time::Duration = std::time::Duration::from_millis(24);
+// max. drift (in frames) that is caught up by feeding without delay
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_CATCH_UP_FRAMES: u32 = 10;
+// bounds for the pacing speed factor, see DabSource::set_pace_speed
+pub const PACE_SPEED_MIN: f32 = 0.1;
+pub const PACE_SPEED_MAX: f32 = 16.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PaceMode {
+    // decode as fast as fed
+    #[default]
+    Off,
+    // sleep to the 24ms frame cadence, late frames are caught up
+    Realtime,
+    // sleep to the frame cadence, late frames resync the schedule
+    RealtimeNoCatchUp,
+}
+
+// subchannels to decode PAD (DL / SLS) from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PadSelection {
+    #[default]
+    All,
+    // the subchannel set with scid() / set_scid()
+    Selected,
+    Scids(Vec<u8>),
+}
+
+impl PadSelection {
+    fn contains(&self, scid: u8, selected: u8) -> bool {
+        match self {
+            PadSelection::All => true,
+            PadSelection::Selected => scid == selected,
+            PadSelection::Scids(scids) => scids.contains(&scid),
+        }
+    }
+}
+
 pub type EnsembleUpdateCallback = Box<dyn FnMut(&Ensemble) + Send>;
 
 pub type AacpSegmentCallback = Box<dyn FnMut(&AacpFrame) + Send>;
 
+// AUs before CRC stripping, including the ones failing the CRC check
+pub type AuRawCallback = Box<dyn FnMut(&RawAu) + Send>;
+
+// events of a single source, instead of the global bus
+pub type EventCallback = Box<dyn FnMut(DabEvent) + Send>;
+
 #[derive(Debug)]
 pub struct DabSource {
     ensemble: Ensemble,
     subchannels: Vec<DabSubchannel>,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
     scid: u8,
+    // no-audio watchdog for the selected subchannel: timeout (0: off), armed once a
+    // subchannel is selected, frame of the last decoded audio
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    no_audio_timeout_frames: u64,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    scid_selected: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    last_audio_frame: u64,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    no_audio_emitted: bool,
     #[debug(skip)]
     on_ensemble_update: Option<EnsembleUpdateCallback>,
     #[debug(skip)]
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
     on_aac_segment: Option<AacpSegmentCallback>,
+    #[debug(skip)]
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    on_au_raw: Option<AuRawCallback>,
+    #[debug(skip)]
+    on_event: Option<EventCallback>,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    mot_force_emit: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    mot_max_object_size: usize,
+    // subchannels with EST SAD != FIG 0/1 start
+    sad_mismatch: Vec<u8>,
+    // EST stream index > SSTC SCID, as first seen
+    est_streams: Vec<(u8, u8)>,
+    // AF revisions already warned about
+    revision_warned: Vec<(u8, u8)>,
+    // last "info" TAG text, EncoderInfo is only emitted on change
+    encoder_info: Option<String>,
+    verify_af_crc: bool,
+    fib_strict_crc: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    enable_pad: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pad_selection: PadSelection,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    enable_audio: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    audio_frames: bool,
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    decode_all: bool,
+    emit_fic: bool,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pace: PaceMode,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pace_speed: f32,
+    #[cfg(not(target_arch = "wasm32"))]
+    pace_next: Option<tokio::time::Instant>,
     stats: DabStats,
+    // counted in EVENT_BUS_USERS until shutdown / drop
+    bus_released: bool,
 }
 
 impl DabSource {
@@ -103,26 +317,91 @@ impl DabSource {
         on_ensemble_update: Option<EnsembleUpdateCallback>,
         on_aac_segment: Option<AacpSegmentCallback>,
     ) -> Self {
-        let stats = DabStats::new();
-        DabSource {
-            ensemble: Ensemble::new(),
-            subchannels: Vec::new(),
-            scid: scid.unwrap_or(0),
-            on_ensemble_update,
-            on_aac_segment,
-            stats,
-        }
+        let mut builder = DabSourceBuilder::new();
+        builder.scid = scid;
+        builder.on_ensemble_update = on_ensemble_update;
+        builder.on_aac_segment = on_aac_segment;
+        builder.build()
+    }
+
+    pub fn builder() -> DabSourceBuilder {
+        DabSourceBuilder::new()
     }
 
     pub async fn feed(&mut self, data: &[u8]) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.pace != PaceMode::Off {
+            self.pace().await;
+        }
+
+        self.decode(data);
+    }
+
+    // same as feed() for callers without an async runtime, pacing is not applied.
+    // events go to the bus (e.g. use blocking_recv() on the receiver) unless a sink is set,
+    // see DabSourceBuilder::event_sink
+    pub fn feed_sync(&mut self, data: &[u8]) {
+        self.decode(data);
+    }
+
+    // route the events emitted while decoding to the sink of this source, if any
+    fn decode(&mut self, data: &[u8]) {
+        match self.on_event.take() {
+            Some(sink) => {
+                let previous = set_event_sink(Some(sink));
+                self.decode_frame(data);
+                self.on_event = set_event_sink(previous);
+            }
+            None => self.decode_frame(data),
+        }
+    }
+
+    // log records from the decode path are attached to these spans (tracing-log)
+    #[tracing::instrument(
+        level = "debug",
+        name = "feed",
+        skip_all,
+        fields(len = data.len(), frame = self.stats.rx_frames)
+    )]
+    fn decode_frame(&mut self, data: &[u8]) {
         self.stats.feed(data);
 
-        match Frame::from_bytes(data) {
+        log::trace!("frame: {}", Frame::summary(data));
+
+        let options = FrameOptions {
+            verify_af_crc: self.verify_af_crc,
+            fib_strict_crc: self.fib_strict_crc,
+            keep_fic: self.emit_fic,
+        };
+
+        match Frame::decode_with(data, options) {
             Ok(frame) => {
+                if !frame.is_known_revision() {
+                    let (maj, min) = frame.revision;
+                    self.warn_revision(maj, min, "decoding anyway");
+                }
+
+                // the extractors are only fed from est tags, audio-less packets leave
+                // the superframe state (frame count, sync) untouched
+                if !frame.has_audio() {
+                    log::trace!("frame: no audio");
+                }
+
+                #[cfg(feature = "audio")]
+                self.check_audio_presence();
+
                 for tag in &frame.tags {
                     match tag {
                         Tag::Deti(tag) => {
-                            if self.ensemble.feed(tag).await {
+                            self.stats.errors.fib_crc_errors += tag.fib_crc_errors as u64;
+
+                            if self.emit_fic && !tag.fic.is_empty() {
+                                emit_event(DabEvent::FicReceived {
+                                    fibs: tag.fic.clone(),
+                                });
+                            }
+
+                            if self.ensemble.feed(tag) {
                                 if let Some(ref mut callback) = self.on_ensemble_update {
                                     callback(&self.ensemble);
                                 }
@@ -130,58 +409,15 @@ impl DabSource {
                         }
 
                         // AAC-segments
-                        Tag::Est(tag) => {
-                            let scid = tag.value[0] >> 2;
-
-                            let slice_data = &tag.value[3..];
-                            let slice_len = (tag.len / 8).saturating_sub(3);
-
-                            if scid == 0 {
-                                let dbg = &slice_data[..slice_len.min(slice_data.len())];
-                                let head = &dbg[..dbg.len().min(8)];
-                                let tail = &dbg[dbg.len().saturating_sub(8)..];
-
-                                // until here dablin & edinburgh behave IDENTICA!
-                                println!(
-                                    "SLICE: scid={} len={} head={:02X?} tail={:02X?}",
-                                    scid, slice_len, head, tail
-                                );
-                            }
+                        Tag::Est(tag) => self.est_received(tag),
 
-                            let sc = match self.subchannels.iter_mut().find(|x| x.scid == scid) {
-                                Some(sc) => sc,
-                                None => {
-                                    let mut sc = DabSubchannel::new(scid);
-                                    sc.audio_extractor.extract_pad = self.scid == scid;
-                                    self.subchannels.push(sc);
-                                    self.subchannels.last_mut().unwrap()
-                                }
-                            };
-
-                            match sc
-                                .audio_extractor
-                                // .feed(&slice_data, slice_len)
-                                .feed(&slice_data[..slice_len], slice_len)
-                                .await
-                            {
-                                Ok(FeedResult::Complete(r)) => {
-                                    // "inject" audio format into ensemble
-                                    self.ensemble.update_audio_format(r.scid, r.audio_format);
-
-                                    // audio frames
-                                    for frame in r.frames {
-                                        let aac_frame = AacpFrame::from_bytes(scid, frame);
-                                        if let Some(ref mut callback) = self.on_aac_segment {
-                                            callback(&aac_frame);
-                                        }
-                                    }
-                                }
-                                Ok(FeedResult::Buffering) => {
-                                    continue;
-                                }
-                                Err(err) => {
-                                    log::warn!("Error feeding frame: {}", err);
-                                }
+                        Tag::Info(tag) => {
+                            if self.encoder_info.as_deref() != Some(tag.text.as_str()) {
+                                log::debug!("EDI info: {}", tag.text);
+                                self.encoder_info = Some(tag.text.clone());
+                                emit_event(DabEvent::EncoderInfo {
+                                    text: tag.text.clone(),
+                                });
                             }
                         }
 
@@ -201,19 +437,1331 @@ impl DabSource {
                     }
                 }
             }
+            Err(FrameDecodeError::CrcMismatch { stored, calculated }) => {
+                self.stats.crc_errors += 1;
+                log::debug!(
+                    "AF CRC mismatch: 0x{:04X} != 0x{:04X} - {} dropped",
+                    stored,
+                    calculated,
+                    self.stats.crc_errors
+                );
+            }
+            Err(FrameDecodeError::UnsupportedRevision { maj, min }) => {
+                self.warn_revision(maj, min, "skipped");
+            }
             Err(err) => {
                 log::warn!("Error decoding frame: {:?}", err);
             }
         }
     }
 
+    // checks on the SSTC and the length, then the payload goes to the subchannel
+    fn est_received(&mut self, tag: &EstTag) {
+        let scid = tag.scid;
+
+        // SAD is the start address in CUs, 0..=863
+        if tag.sad > 863 {
+            log::warn!("EST SubCh {}: invalid SAD {} - skipped", scid, tag.sad);
+            return;
+        }
+
+        if !self.check_est_stream(tag.stream, scid) {
+            return;
+        }
+
+        self.check_sad(scid, tag.sad);
+
+        let slice_data = &tag.value[3..];
+        let announced_len = (tag.len / 8).saturating_sub(3);
+
+        // malformed EST, the tag length announces more than was received
+        if announced_len > slice_data.len() {
+            log::warn!(
+                "EST SubCh {}: length {} exceeds {} bytes of data - truncated",
+                scid,
+                announced_len,
+                slice_data.len()
+            );
+        }
+
+        let slice_len = announced_len.min(slice_data.len());
+
+        // truncated EST, nothing but the header
+        if slice_len == 0 {
+            log::trace!("EST SubCh {}: no payload - skipped", scid);
+            return;
+        }
+
+        self.feed_est(scid, &slice_data[..slice_len]);
+    }
+
+    #[cfg(feature = "audio")]
+    #[tracing::instrument(
+        level = "debug",
+        name = "est",
+        skip(self, data),
+        fields(len = data.len())
+    )]
+    fn feed_est(&mut self, scid: u8, data: &[u8]) {
+        if !self.enable_audio {
+            if !self.subchannels.iter().any(|x| x.scid == scid) {
+                self.subchannels.push(DabSubchannel::new(scid));
+            }
+            return;
+        }
+
+        let sc = match self.subchannels.iter_mut().find(|x| x.scid == scid) {
+            Some(sc) => sc,
+            None => {
+                let mut sc = DabSubchannel::new(scid);
+                sc.audio_extractor.extract_pad = self.extracts_pad(scid);
+                sc.audio_extractor.emit_frames = self.audio_frames;
+                sc.audio_extractor.raw_au = self.on_au_raw.is_some();
+                sc.audio_extractor.set_mot_force_emit(self.mot_force_emit);
+                sc.audio_extractor
+                    .set_mot_max_object_size(self.mot_max_object_size);
+                self.subchannels.push(sc);
+                self.subchannels.last_mut().unwrap()
+            }
+        };
+
+        // only tracked, see set_decode_all
+        if !self.decode_all && scid != self.scid {
+            return;
+        }
+
+        if self.ensemble.audio_service_type(scid) == Some(AudioServiceType::Dab) {
+            let mp2 = sc.mp2_extractor.get_or_insert_with(|| {
+                log::debug!("SubCh {}: DAB (MP2) audio", scid);
+                let mut mp2 = Mp2Extractor::new(scid);
+                mp2.emit_frames = self.audio_frames;
+                mp2
+            });
+
+            let result = mp2.feed(data);
+            // MP2 frame sync losses are counted along with the superframe ones
+            self.stats.errors.superframe_sync_losses += mp2.take_sync_losses();
+
+            if let FeedResult::Complete(r) = result {
+                self.ensemble.update_audio_format(r.scid, r.audio_format);
+                self.audio_received(scid);
+            }
+            return;
+        }
+
+        // bytes per 24ms frame from the FIG 0/1 bitrate (n x 8 kbps: n x 24 bytes), a shorter
+        // slice is rejected by the extractor. the slice length until FIG 0/1 is known
+        let f_len = self
+            .ensemble
+            .subchannels
+            .iter()
+            .find(|s| s.id == scid)
+            .and_then(|s| s.bitrate)
+            .map_or(data.len(), |bitrate| bitrate * 3);
+
+        let result = sc.audio_extractor.feed(data, f_len);
+
+        if let Some(ref mut callback) = self.on_au_raw {
+            for au in sc.audio_extractor.take_raw_aus() {
+                callback(&au);
+            }
+        }
+
+        let (au_crc_errors, sync_losses) = sc.audio_extractor.take_error_counts();
+        self.stats.errors.au_crc_errors += au_crc_errors;
+        self.stats.errors.superframe_sync_losses += sync_losses;
+
+        match result {
+            Ok(FeedResult::Complete(r)) => {
+                // "inject" audio format into ensemble
+                self.ensemble.update_audio_format(r.scid, r.audio_format);
+                self.audio_received(scid);
+
+                // audio frames
+                for frame in r.frames {
+                    let aac_frame = AacpFrame::from_bytes(scid, frame);
+                    if let Some(ref mut callback) = self.on_aac_segment {
+                        callback(&aac_frame);
+                    }
+                }
+            }
+            Ok(FeedResult::Buffering) => {}
+            Err(err) => {
+                log::warn!("Error feeding frame: {}", err);
+            }
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    fn audio_received(&mut self, scid: u8) {
+        if scid == self.scid {
+            self.last_audio_frame = self.stats.rx_frames;
+            self.no_audio_emitted = false;
+        }
+    }
+
+    // emit NoAudio once the selected subchannel did not deliver audio for the timeout
+    #[cfg(feature = "audio")]
+    fn check_audio_presence(&mut self) {
+        if self.no_audio_timeout_frames == 0
+            || !self.enable_audio
+            || !self.scid_selected
+            || self.no_audio_emitted
+        {
+            return;
+        }
+
+        if self.stats.rx_frames - self.last_audio_frame > self.no_audio_timeout_frames {
+            log::warn!("DabSource: no audio from SubCh {}", self.scid);
+            emit_event(DabEvent::NoAudio { scid: self.scid });
+            self.no_audio_emitted = true;
+        }
+    }
+
+    // built without audio: only keep track of the subchannels seen
+    #[cfg(not(feature = "audio"))]
+    fn feed_est(&mut self, scid: u8, _data: &[u8]) {
+        if !self.subchannels.iter().any(|x| x.scid == scid) {
+            self.subchannels.push(DabSubchannel::new(scid));
+        }
+    }
+
+    pub fn metrics(&self) -> DabMetrics {
+        DabMetrics {
+            frames: self.stats.rx_frames,
+            crc_errors: self.stats.crc_errors
+                + self.stats.errors.fib_crc_errors
+                + self.stats.errors.au_crc_errors,
+            synced_subchannels: self.subchannels.iter().filter(|sc| sc.is_synced()).count(),
+            ensemble_complete: self.ensemble.complete,
+        }
+    }
+
+    pub fn subchannels(&self) -> Vec<SubchannelInfo> {
+        let mut subchannels: Vec<SubchannelInfo> = self
+            .subchannels
+            .iter()
+            .map(|dsc| {
+                let sc = self.ensemble.subchannels.iter().find(|s| s.id == dsc.scid);
+                SubchannelInfo {
+                    scid: dsc.scid,
+                    bitrate: sc.and_then(|s| s.bitrate),
+                    pl: sc.and_then(|s| s.pl.clone()),
+                    audio_format: dsc.audio_format().cloned(),
+                    mot_stats: dsc.mot_stats().cloned(),
+                }
+            })
+            .collect();
+
+        subchannels.sort_by_key(|s| s.scid);
+        subchannels
+    }
+
+    // drop AF packets with CRC mismatch (counted in DabStats::crc_errors)
+    pub fn set_verify_af_crc(&mut self, verify: bool) {
+        self.verify_af_crc = verify;
+    }
+
+    // true (default): discard FIBs with CRC mismatch (counted in DecodeErrors::fib_crc_errors).
+    // false: decode them anyway, e.g. for weak signals where a partly broken FIB is better than none
+    pub fn set_fib_strict_crc(&mut self, strict: bool) {
+        self.fib_strict_crc = strict;
+    }
+
+    // true (default): extract audio (and PAD) of every subchannel, AacpFramesExtracted is
+    // emitted per SCID. that is a superframe sync per subchannel, a full ensemble costs
+    // noticeably more CPU than a single one. false: only the selected subchannel
+    pub fn set_decode_all(&mut self, decode_all: bool) {
+        self.decode_all = decode_all;
+    }
+
+    // emit the raw FIC of every frame as FicReceived, e.g. to compare against other decoders
+    pub fn set_emit_fic(&mut self, emit_fic: bool) {
+        self.emit_fic = emit_fic;
+    }
+
+    // emit MotImageReceived for every carousel retransmission, not only on change
+    pub fn set_mot_force_emit(&mut self, force_emit: bool) {
+        self.mot_force_emit = force_emit;
+        #[cfg(feature = "audio")]
+        for sc in &mut self.subchannels {
+            sc.audio_extractor.set_mot_force_emit(force_emit);
+        }
+    }
+
+    // MOT objects (header + body) above this size are dropped, see MotStats::oversized
+    pub fn set_mot_max_object_size(&mut self, max_object_size: usize) {
+        self.mot_max_object_size = max_object_size;
+        #[cfg(feature = "audio")]
+        for sc in &mut self.subchannels {
+            sc.audio_extractor.set_mot_max_object_size(max_object_size);
+        }
+    }
+
+    pub fn set_pace(&mut self, pace: PaceMode) {
+        self.pace = pace;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.pace_next = None;
+        }
+    }
+
+    // scale the frame cadence, e.g. 0.5: slow motion, 2.0: fast forward. 0 disables pacing
+    pub fn set_pace_speed(&mut self, speed: f32) {
+        if speed.is_nan() || speed <= 0.0 {
+            self.set_pace(PaceMode::Off);
+            return;
+        }
+        self.pace_speed = clamp_pace_speed(speed);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.pace_next = None;
+        }
+    }
+
+    pub fn pace_speed(&self) -> f32 {
+        self.pace_speed
+    }
+
+    pub fn scid(&self) -> u8 {
+        self.scid
+    }
+
+    // PadSelection::Selected follows the new subchannel, extractors are updated in place.
+    // (re)starts the no-audio timeout
     pub fn set_scid(&mut self, scid: u8) {
+        if self.scid == scid && self.scid_selected {
+            return;
+        }
+        log::debug!("DabSource: SubCh {} selected", scid);
         self.scid = scid;
+        self.scid_selected = true;
+        self.last_audio_frame = self.stats.rx_frames;
+        self.no_audio_emitted = false;
+        self.update_pad_selection();
+    }
+
+    // emit NoAudio when the selected subchannel delivers no audio for this long, 0 disables it
+    pub fn set_no_audio_timeout(&mut self, timeout: std::time::Duration) {
+        self.no_audio_timeout_frames = no_audio_timeout_frames(timeout);
+        self.last_audio_frame = self.stats.rx_frames;
+        self.no_audio_emitted = false;
+    }
+
+    pub fn set_pad_selection(&mut self, pad_selection: PadSelection) {
+        self.pad_selection = pad_selection;
+        self.update_pad_selection();
+    }
+
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    fn extracts_pad(&self, scid: u8) -> bool {
+        self.enable_pad && self.pad_selection.contains(scid, self.scid)
+    }
+
+    fn update_pad_selection(&mut self) {
+        #[cfg(feature = "audio")]
+        for i in 0..self.subchannels.len() {
+            let extract_pad = self.extracts_pad(self.subchannels[i].scid);
+            self.subchannels[i].audio_extractor.extract_pad = extract_pad;
+        }
     }
 
     pub fn reset(&mut self) {
         log::info!("DabSource: reset");
         self.ensemble.reset();
         self.subchannels.clear();
+        self.sad_mismatch.clear();
+        self.est_streams.clear();
+        self.encoder_info = None;
+        self.last_audio_frame = self.stats.rx_frames;
+        self.no_audio_emitted = false;
+    }
+
+    // reset and close the event bus: handlers waiting on it finish, a new bus can be initialized.
+    // the bus is shared, it stays open while other sources are alive
+    pub fn shutdown(&mut self) {
+        log::info!("DabSource: shutdown");
+        self.reset();
+
+        if self.bus_released {
+            return;
+        }
+        self.bus_released = true;
+
+        if release_event_bus() {
+            shutdown_event_bus();
+        } else {
+            log::debug!(
+                "DabSource: event bus still used by {} sources",
+                bus::event_bus_users()
+            );
+        }
+    }
+
+    // wait for the next frame slot
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn pace(&mut self) {
+        let frame_duration = self.frame_duration();
+        let now = tokio::time::Instant::now();
+        let mut next = self.pace_next.unwrap_or(now);
+
+        if next > now {
+            tokio::time::sleep_until(next).await;
+        } else if now - next > frame_duration {
+            let drift = now - next;
+
+            if self.pace == PaceMode::RealtimeNoCatchUp
+                || drift > frame_duration * MAX_CATCH_UP_FRAMES
+            {
+                log::info!("DabSource: {} ms behind - resync", drift.as_millis());
+                next = now;
+            }
+        }
+
+        self.pace_next = Some(next + frame_duration);
+    }
+
+    // FRAME_DURATION scaled by the speed factor
+    #[cfg(not(target_arch = "wasm32"))]
+    fn frame_duration(&self) -> std::time::Duration {
+        FRAME_DURATION.div_f32(self.pace_speed)
+    }
+
+    // log an unknown AF revision once instead of on every packet
+    fn warn_revision(&mut self, maj: u8, min: u8, action: &str) {
+        if !self.revision_warned.contains(&(maj, min)) {
+            log::warn!("AF revision {}.{} unknown - {}", maj, min, action);
+            self.revision_warned.push((maj, min));
+        }
+    }
+
+    // the SCID is taken from the SSTC. the stream index of the tag name is not tied to it (muxes
+    // usually count subchannels from 1), but a stream changing its SCID points to a parsing offset.
+    // returns false for an invalid stream index, the EST is skipped
+    fn check_est_stream(&mut self, stream: u8, scid: u8) -> bool {
+        if !(1..=64).contains(&stream) {
+            log::warn!(
+                "EST SubCh {}: invalid stream index {} - skipped",
+                scid,
+                stream
+            );
+            return false;
+        }
+
+        match self.est_streams.iter_mut().find(|(s, _)| *s == stream) {
+            Some((_, known)) if *known != scid => {
+                log::warn!(
+                    "est{}: SSTC SubCh {} does not match SubCh {} seen before",
+                    stream,
+                    scid,
+                    known
+                );
+                *known = scid;
+            }
+            Some(_) => {}
+            None => {
+                if stream != scid {
+                    log::debug!("est{}: SSTC SubCh {}", stream, scid);
+                }
+                self.est_streams.push((stream, scid));
+            }
+        }
+
+        true
+    }
+
+    // cross-check the EST start address against FIG 0/1, logged once per subchannel
+    fn check_sad(&mut self, scid: u8, sad: u16) {
+        let start = self
+            .ensemble
+            .subchannels
+            .iter()
+            .find(|s| s.id == scid)
+            .and_then(|s| s.start);
+
+        if let Some(start) = start {
+            if start != sad as usize && !self.sad_mismatch.contains(&scid) {
+                log::warn!(
+                    "EST SubCh {}: SAD {} does not match FIG 0/1 start {}",
+                    scid,
+                    sad,
+                    start
+                );
+                self.sad_mismatch.push(scid);
+            }
+        }
+    }
+}
+
+impl Drop for DabSource {
+    // dropped without shutdown: leave the bus to the remaining sources
+    fn drop(&mut self) {
+        if !self.bus_released {
+            self.bus_released = true;
+            release_event_bus();
+        }
+    }
+}
+
+/// Explicit configuration for [`DabSource`].
+///
+/// ```
+/// use shared::dab::DabSource;
+///
+/// let mut source = DabSource::builder()
+///     .scid(4)
+///     .verify_af_crc(true)
+///     .on_ensemble(|e| println!("ensemble: {:?}", e.label))
+///     .build();
+///
+/// // no AF packet: counted as received, nothing decoded
+/// source.feed_sync(b"not an AF packet");
+///
+/// assert_eq!(source.scid(), 4);
+/// assert_eq!(source.metrics().frames, 1);
+/// assert!(!source.metrics().ensemble_complete);
+/// ```
+pub struct DabSourceBuilder {
+    scid: Option<u8>,
+    verify_af_crc: bool,
+    fib_strict_crc: bool,
+    enable_pad: bool,
+    pad_selection: PadSelection,
+    enable_audio: bool,
+    audio_frames: bool,
+    decode_all: bool,
+    emit_fic: bool,
+    no_audio_timeout: std::time::Duration,
+    pace: PaceMode,
+    pace_speed: f32,
+    on_ensemble_update: Option<EnsembleUpdateCallback>,
+    on_aac_segment: Option<AacpSegmentCallback>,
+    on_au_raw: Option<AuRawCallback>,
+    on_event: Option<EventCallback>,
+}
+
+impl Default for DabSourceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DabSourceBuilder {
+    pub fn new() -> Self {
+        DabSourceBuilder {
+            scid: None,
+            verify_af_crc: false,
+            fib_strict_crc: true,
+            enable_pad: true,
+            pad_selection: PadSelection::All,
+            enable_audio: true,
+            audio_frames: true,
+            decode_all: true,
+            emit_fic: false,
+            no_audio_timeout: std::time::Duration::ZERO,
+            pace: PaceMode::Off,
+            pace_speed: 1.0,
+            on_ensemble_update: None,
+            on_aac_segment: None,
+            on_au_raw: None,
+            on_event: None,
+        }
+    }
+
+    // selected subchannel, see PadSelection::Selected
+    pub fn scid(mut self, scid: u8) -> Self {
+        self.scid = Some(scid);
+        self
+    }
+
+    // drop AF packets with CRC mismatch, see DabSource::set_verify_af_crc
+    pub fn verify_af_crc(mut self, verify: bool) -> Self {
+        self.verify_af_crc = verify;
+        self
+    }
+
+    // see DabSource::set_fib_strict_crc
+    pub fn fib_strict_crc(mut self, strict: bool) -> Self {
+        self.fib_strict_crc = strict;
+        self
+    }
+
+    pub fn enable_pad(mut self, enable_pad: bool) -> Self {
+        self.enable_pad = enable_pad;
+        self
+    }
+
+    pub fn pad_selection(mut self, pad_selection: PadSelection) -> Self {
+        self.pad_selection = pad_selection;
+        self
+    }
+
+    // without audio only FIC / ensemble information is decoded
+    pub fn enable_audio(mut self, enable_audio: bool) -> Self {
+        self.enable_audio = enable_audio;
+        self
+    }
+
+    // false: superframes are still synced for PAD, but no AU frames are emitted (metadata only)
+    pub fn audio_frames(mut self, audio_frames: bool) -> Self {
+        self.audio_frames = audio_frames;
+        self
+    }
+
+    // see DabSource::set_decode_all
+    pub fn decode_all(mut self, decode_all: bool) -> Self {
+        self.decode_all = decode_all;
+        self
+    }
+
+    // see DabSource::set_emit_fic
+    pub fn emit_fic(mut self, emit_fic: bool) -> Self {
+        self.emit_fic = emit_fic;
+        self
+    }
+
+    // see DabSource::set_no_audio_timeout, armed with scid() or DabSource::set_scid
+    pub fn no_audio_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.no_audio_timeout = timeout;
+        self
+    }
+
+    // pace decoding to the DAB frame rate, e.g. for file replay. not available on wasm
+    pub fn pace(mut self, pace: PaceMode) -> Self {
+        self.pace = pace;
+        self
+    }
+
+    // see DabSource::set_pace_speed
+    pub fn pace_speed(mut self, speed: f32) -> Self {
+        if speed.is_nan() || speed <= 0.0 {
+            self.pace = PaceMode::Off;
+        } else {
+            self.pace_speed = clamp_pace_speed(speed);
+        }
+        self
+    }
+
+    pub fn on_ensemble<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Ensemble) + Send + 'static,
+    {
+        self.on_ensemble_update = Some(Box::new(callback));
+        self
+    }
+
+    pub fn on_aac<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&AacpFrame) + Send + 'static,
+    {
+        self.on_aac_segment = Some(Box::new(callback));
+        self
+    }
+
+    // raw AUs incl. CRC, see AuRawCallback. AUs are only copied if set
+    pub fn on_au_raw<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&RawAu) + Send + 'static,
+    {
+        self.on_au_raw = Some(Box::new(callback));
+        self
+    }
+
+    // all events of this source, called on the decoding thread instead of sending to the
+    // global bus. no async runtime needed, e.g. for feed_sync
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(DabEvent) + Send + 'static,
+    {
+        self.on_event = Some(Box::new(callback));
+        self
+    }
+
+    // same as on_event, into a std channel
+    pub fn event_sink(self, tx: std::sync::mpsc::Sender<DabEvent>) -> Self {
+        self.on_event(move |event| {
+            let _ = tx.send(event);
+        })
+    }
+
+    pub fn build(self) -> DabSource {
+        acquire_event_bus();
+
+        DabSource {
+            ensemble: Ensemble::new(),
+            subchannels: Vec::new(),
+            scid: self.scid.unwrap_or(0),
+            no_audio_timeout_frames: no_audio_timeout_frames(self.no_audio_timeout),
+            scid_selected: self.scid.is_some(),
+            last_audio_frame: 0,
+            no_audio_emitted: false,
+            on_ensemble_update: self.on_ensemble_update,
+            on_aac_segment: self.on_aac_segment,
+            on_au_raw: self.on_au_raw,
+            on_event: self.on_event,
+            mot_force_emit: false,
+            mot_max_object_size: MOT_MAX_OBJECT_SIZE,
+            sad_mismatch: Vec::new(),
+            est_streams: Vec::new(),
+            revision_warned: Vec::new(),
+            encoder_info: None,
+            verify_af_crc: self.verify_af_crc,
+            fib_strict_crc: self.fib_strict_crc,
+            enable_pad: self.enable_pad,
+            pad_selection: self.pad_selection,
+            enable_audio: self.enable_audio,
+            audio_frames: self.audio_frames,
+            decode_all: self.decode_all,
+            emit_fic: self.emit_fic,
+            pace: self.pace,
+            pace_speed: self.pace_speed,
+            #[cfg(not(target_arch = "wasm32"))]
+            pace_next: None,
+            stats: DabStats::new(),
+            bus_released: false,
+        }
+    }
+}
+
+fn clamp_pace_speed(speed: f32) -> f32 {
+    let clamped = speed.clamp(PACE_SPEED_MIN, PACE_SPEED_MAX);
+    if clamped != speed {
+        log::warn!(
+            "DabSource: pace speed {} out of range - using {}",
+            speed,
+            clamped
+        );
+    }
+    clamped
+}
+
+// 24ms DAB frames
+fn no_audio_timeout_frames(timeout: std::time::Duration) -> u64 {
+    (timeout.as_millis() / 24) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // see tests/data/make_replay_fixture.py
+    fn fixture() -> Vec<u8> {
+        std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/replay.edi"
+        ))
+        .unwrap()
+    }
+
+    // AF packet (revision 1.0, PT "T") with the given tags and CRC
+    fn af_packet(tags: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for (name, value) in tags {
+            payload.extend_from_slice(*name);
+            payload.extend_from_slice(&((value.len() * 8) as u32).to_be_bytes());
+            payload.extend_from_slice(value);
+        }
+
+        let mut data = b"AF".to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x90, b'T']);
+        data.extend_from_slice(&payload);
+        let crc = crate::utils::calc_crc16_ccitt(&data);
+        data.extend_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    // DETI value with FIC (mode I, 3 FIBs), the first FIB carries the given FIGs
+    fn deti(figs: &[u8]) -> Vec<u8> {
+        let mut deti = vec![0x40, 0x00, 0xFF, 0x40, 0x00, 0x00];
+        for n in 0..3 {
+            let mut fib = if n == 0 { figs.to_vec() } else { Vec::new() };
+            fib.resize(30, 0xFF);
+            let crc = crate::utils::calc_crc16_ccitt(&fib);
+            deti.extend_from_slice(&fib);
+            deti.extend_from_slice(&crc.to_be_bytes());
+        }
+        deti
+    }
+
+    // EST value: SSTC (SCID, SAD, TPL 0) and the MSC data
+    fn est(scid: u8, sad: u16, data: &[u8]) -> Vec<u8> {
+        let mut est = vec![scid << 2 | (sad >> 8) as u8, sad as u8, 0x00];
+        est.extend_from_slice(data);
+        est
+    }
+
+    // deti with empty FIBs, no est
+    fn management_packet() -> Vec<u8> {
+        af_packet(&[(b"deti", &deti(&[]))])
+    }
+
+    // FIG 0/1 and 0/2: SId 0xC221, DAB+ in SubCh 1 at CU 0, EEP 3-A 84 CUs (112 kbps)
+    const FIGS: [u8; 13] = [
+        0x05, 0x01, 0x04, 0x00, 0x88, 0x54, 0x06, 0x02, 0xC2, 0x21, 0x01, 0x3F, 0x06,
+    ];
+
+    // DAB+ frame of the FIGS subchannel with the given EST payload length
+    fn dab_plus_packet(len: usize) -> Vec<u8> {
+        af_packet(&[
+            (b"deti", &deti(&FIGS)),
+            (b"est\x01", &est(1, 0, &vec![0x00; len])),
+        ])
+    }
+
+    // FIG 0/1 and 0/2: SIds 0xC221 and 0xC222, DAB+ in SubCh 1 at CU 0 and SubCh 2 at CU 24,
+    // EEP 3-A 24 CUs each (32 kbps)
+    #[cfg(feature = "audio")]
+    const FIGS_32: [u8; 22] = [
+        0x09, 0x01, 0x04, 0x00, 0x88, 0x18, 0x08, 0x18, 0x88, 0x18, 0x0B, 0x02, 0xC2, 0x21, 0x01,
+        0x3F, 0x06, 0xC2, 0x22, 0x01, 0x3F, 0x0A,
+    ];
+
+    // the superframe in 5 frames, one EST per given FIGS_32 subchannel
+    #[cfg(feature = "audio")]
+    fn feed_superframe(source: &mut DabSource, scids: &[u8], sf: &[u8]) -> Vec<DabEvent> {
+        let deti = deti(&FIGS_32);
+        bus::capture_events(|| {
+            for chunk in sf.chunks(96) {
+                let names: Vec<[u8; 4]> = (1..=scids.len() as u8)
+                    .map(|n| [b'e', b's', b't', n])
+                    .collect();
+                let ests: Vec<Vec<u8>> = scids
+                    .iter()
+                    .map(|&scid| est(scid, (scid as u16 - 1) * 24, chunk))
+                    .collect();
+                let mut tags: Vec<(&[u8; 4], &[u8])> = vec![(b"deti", &deti)];
+                tags.extend(names.iter().zip(&ests).map(|(n, e)| (n, e.as_slice())));
+                source.feed_sync(&af_packet(&tags));
+            }
+        })
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn sync_across_management_packets() {
+        let management = management_packet();
+        assert!(!Frame::decode(&management, true).unwrap().has_audio());
+
+        let mut source = DabSource::builder().scid(1).verify_af_crc(true).build();
+        let data = fixture();
+        let mut synced = false;
+
+        for packet in replay::split_packets(&data) {
+            source.feed_sync(packet);
+            source.feed_sync(&management);
+            source.feed_sync(&management);
+
+            // once synced, the packets in between must not drop it
+            let now = source.metrics().synced_subchannels == 1;
+            assert!(now || !synced, "sync lost");
+            synced = now;
+        }
+
+        assert!(synced);
+        assert_eq!(source.metrics().crc_errors, 0);
+    }
+
+    // no runtime: events arrive through the sink, the bus is not involved
+    #[test]
+    fn event_sink() {
+        assert!(tokio::runtime::Handle::try_current().is_err());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut source = DabSource::builder().scid(1).event_sink(tx).build();
+
+        let data = fixture();
+        for packet in replay::split_packets(&data) {
+            source.feed_sync(packet);
+        }
+
+        let events: Vec<DabEvent> = rx.try_iter().collect();
+        let stats = events
+            .iter()
+            .filter(|e| matches!(e, DabEvent::DabStatsUpdated(_)))
+            .count();
+        assert_eq!(stats, 84);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DabEvent::EnsembleUpdated(ensemble) if ensemble.complete)));
+
+        // only installed while decoding
+        emit_event(DabEvent::NoAudio { scid: 1 });
+        assert!(rx.try_recv().is_err());
+    }
+
+    // 112 kbps: 336 bytes per frame, a shorter slice does not establish another frame length
+    #[cfg(feature = "audio")]
+    #[test]
+    fn est_frame_length() {
+        let mut source = DabSource::builder().scid(1).build();
+
+        let logs = crate::utils::capture_logs(|| {
+            bus::capture_events(|| {
+                source.feed_sync(&dab_plus_packet(336));
+                source.feed_sync(&dab_plus_packet(300));
+                source.feed_sync(&dab_plus_packet(336));
+            });
+        });
+        let errors: Vec<&str> = logs
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Warn)
+            .map(|(_, msg)| msg.as_str())
+            .collect();
+
+        assert_eq!(errors, ["Error feeding frame: Slice too short: 300 < 336"]);
+    }
+
+    // EST tag of the given tag length (bytes), whatever data follows
+    fn est_tag(len: usize, value: &[u8]) -> EstTag {
+        let mut data = b"est\x01".to_vec();
+        data.extend_from_slice(&((len * 8) as u32).to_be_bytes());
+        data.extend_from_slice(value);
+        EstTag::from_bytes(&data).unwrap()
+    }
+
+    #[test]
+    fn est_length_exceeds_data() {
+        let mut source = DabSource::builder().scid(1).build();
+        // announces 400 bytes of payload, carries 120
+        let tag = est_tag(3 + 400, &est(1, 0, &[0x00; 120]));
+
+        let logs = crate::utils::capture_logs(|| {
+            bus::capture_events(|| source.est_received(&tag));
+        });
+
+        assert_eq!(
+            logs,
+            [(
+                log::Level::Warn,
+                "EST SubCh 1: length 400 exceeds 120 bytes of data - truncated".to_string()
+            )]
+        );
+        // what is there is passed on
+        assert_eq!(source.subchannels().len(), 1);
+    }
+
+    // EST cut off after the SSTC, all zero: SubCh 0 without payload
+    #[test]
+    fn est_truncated_quiet() {
+        let mut source = DabSource::builder().build();
+        let packet = af_packet(&[(b"deti", &deti(&[])), (b"est\x01", &[0x00; 3])]);
+
+        let logs = crate::utils::capture_logs(|| {
+            bus::capture_events(|| source.feed_sync(&packet));
+        });
+
+        assert!(logs.contains(&(
+            log::Level::Trace,
+            "EST SubCh 0: no payload - skipped".to_string()
+        )));
+        assert!(logs.iter().all(|(level, _)| *level >= log::Level::Debug));
+        assert!(source.subchannels().is_empty());
+    }
+
+    #[test]
+    fn est_sad() {
+        let mut source = DabSource::builder().build();
+        let warnings = |source: &mut DabSource, sad: u16| {
+            // a full 112 kbps frame
+            let tag = est_tag(3 + 336, &est(1, sad, &[0x00; 336]));
+            crate::utils::capture_logs(|| {
+                bus::capture_events(|| source.est_received(&tag));
+            })
+            .into_iter()
+            .filter(|(level, _)| *level == log::Level::Warn)
+            .map(|(_, msg)| msg)
+            .collect::<Vec<_>>()
+        };
+
+        // beyond the last CU
+        assert_eq!(
+            warnings(&mut source, 864),
+            ["EST SubCh 1: invalid SAD 864 - skipped"]
+        );
+        assert!(source.subchannels().is_empty());
+
+        // FIG 0/1 not known yet: nothing to compare against
+        assert!(warnings(&mut source, 863).is_empty());
+        assert_eq!(source.subchannels().len(), 1);
+
+        // SubCh 1 starts at CU 0
+        bus::capture_events(|| source.feed_sync(&af_packet(&[(b"deti", &deti(&FIGS))])));
+        assert!(warnings(&mut source, 0).is_empty());
+        assert_eq!(
+            warnings(&mut source, 84),
+            ["EST SubCh 1: SAD 84 does not match FIG 0/1 start 0"]
+        );
+        // logged once
+        assert!(warnings(&mut source, 84).is_empty());
+        assert_eq!(source.sad_mismatch, [1]);
+    }
+
+    #[test]
+    fn encoder_info() {
+        let mut source = DabSource::builder().build();
+        let info = |text: &[u8]| af_packet(&[(b"deti", &deti(&[])), (b"info", text)]);
+
+        let events = bus::capture_events(|| {
+            source.feed_sync(&info(b"ODR-DabMux v4.4.1\0"));
+            source.feed_sync(&info(b"ODR-DabMux v4.4.1\0"));
+            source.feed_sync(&info(b"ODR-DabMux v5.0.0"));
+        });
+        let texts: Vec<&str> = events
+            .iter()
+            .filter_map(|e| match e {
+                DabEvent::EncoderInfo { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        // repeated in every frame, emitted on change only
+        assert_eq!(texts, ["ODR-DabMux v4.4.1", "ODR-DabMux v5.0.0"]);
+    }
+
+    #[test]
+    fn stats_fib_crc_errors() {
+        let mut source = DabSource::builder().build();
+        let good = af_packet(&[(b"deti", &deti(&FIGS))]);
+        let mut deti_corrupt = deti(&FIGS);
+        deti_corrupt[6 + 31] ^= 0x01;
+        let corrupt = af_packet(&[(b"deti", &deti_corrupt)]);
+
+        let mut feed = |packets: &[&[u8]]| {
+            let events = bus::capture_events(|| {
+                for packet in packets {
+                    source.feed_sync(packet);
+                }
+            });
+            match events.last() {
+                Some(DabEvent::DabStatsUpdated(stats)) => stats.clone(),
+                other => panic!("{:?}", other),
+            }
+        };
+
+        // 10 of the first interval
+        let mut packets: Vec<&[u8]> = vec![&corrupt; 10];
+        packets.resize(STATS_INTERVAL_FRAMES as usize - 1, &good);
+        let stats = feed(&packets);
+        assert_eq!(stats.errors.fib_crc_errors, 10);
+        // updated at the end of the interval
+        assert_eq!(stats.errors_interval, DecodeErrors::default());
+
+        let stats = feed(&[&good]);
+        assert_eq!(stats.rx_frames, STATS_INTERVAL_FRAMES);
+        assert_eq!(stats.errors_interval.fib_crc_errors, 10);
+
+        // a clean interval
+        let stats = feed(&vec![&good[..]; STATS_INTERVAL_FRAMES as usize]);
+        assert_eq!(stats.errors.fib_crc_errors, 10);
+        assert_eq!(stats.errors_interval.fib_crc_errors, 0);
+        assert_eq!(stats.crc_errors, 0);
+    }
+
+    #[test]
+    fn check_est_stream() {
+        let mut source = DabSource::builder().build();
+
+        assert!(!source.check_est_stream(0, 1));
+        assert!(!source.check_est_stream(65, 1));
+        assert!(source.est_streams.is_empty());
+
+        // the index does not have to match the SCID
+        assert!(source.check_est_stream(1, 4));
+        assert!(source.check_est_stream(2, 7));
+        assert_eq!(source.est_streams, [(1, 4), (2, 7)]);
+
+        // SCID changed for a known stream: still routed, the new one is kept
+        assert!(source.check_est_stream(1, 5));
+        assert_eq!(source.est_streams, [(1, 5), (2, 7)]);
+
+        source.reset();
+        assert!(source.est_streams.is_empty());
+    }
+
+    #[test]
+    fn metrics() {
+        let mut source = DabSource::builder().scid(1).verify_af_crc(true).build();
+        assert_eq!(source.metrics(), DabMetrics::default());
+
+        let data = fixture();
+        let packets = replay::split_packets(&data);
+        for packet in &packets {
+            source.feed_sync(packet);
+        }
+
+        let metrics = source.metrics();
+        assert_eq!(metrics.frames, 84);
+        assert_eq!(metrics.crc_errors, 0);
+        assert!(metrics.ensemble_complete);
+        // MP2 in SubCh 1, SubCh 2 has no EST
+        assert_eq!(metrics.synced_subchannels, cfg!(feature = "audio") as usize);
+
+        let mut corrupted = packets[0].to_vec();
+        corrupted[12] ^= 0x01;
+        source.feed_sync(&corrupted);
+
+        let metrics = source.metrics();
+        assert_eq!(metrics.frames, 85);
+        assert_eq!(metrics.crc_errors, 1);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn decode_all() {
+        let scids = |decode_all: bool| {
+            let mut source = DabSource::builder().scid(1).decode_all(decode_all).build();
+            let sf = msc::tests::superframe(None);
+            (0..3)
+                .flat_map(|_| feed_superframe(&mut source, &[1, 2], &sf))
+                .filter_map(|e| match e {
+                    DabEvent::AacpFramesExtracted(r) => Some(r.scid),
+                    _ => None,
+                })
+                .collect::<Vec<u8>>()
+        };
+
+        // one result per superframe and subchannel, both in the same frames
+        assert_eq!(scids(true), [1, 2, 1, 2, 1, 2]);
+        assert_eq!(scids(false), [1, 1, 1]);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn subchannels() {
+        let mut source = DabSource::builder().scid(1).build();
+        assert!(source.subchannels().is_empty());
+
+        // SubCh 2 seen first
+        let sf = msc::tests::superframe(None);
+        feed_superframe(&mut source, &[2], &sf);
+        feed_superframe(&mut source, &[1, 2], &sf);
+
+        let subchannels = source.subchannels();
+        let scids: Vec<u8> = subchannels.iter().map(|s| s.scid).collect();
+        assert_eq!(scids, [1, 2]);
+        for sc in &subchannels {
+            assert_eq!(sc.bitrate, Some(32));
+            assert_eq!(sc.pl.as_deref(), Some("EEP 3-A"));
+            assert_eq!(
+                sc.audio_format.as_ref().map(|f| f.summary()).as_deref(),
+                Some("HE-AAC, 48 kHz Mono, 32 kbps")
+            );
+            assert!(sc.mot_stats.is_some());
+        }
+
+        source.reset();
+        assert!(source.subchannels().is_empty());
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn no_audio() {
+        let sf = msc::tests::superframe(None);
+        let no_audio = |events: Vec<DabEvent>| {
+            events
+                .into_iter()
+                .filter_map(|e| match e {
+                    DabEvent::NoAudio { scid } => Some(scid),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // 4 superframes: 20 frames
+        let feed = |source: &mut DabSource, scids: &[u8]| {
+            let events = (0..4)
+                .flat_map(|_| feed_superframe(source, scids, &sf))
+                .collect();
+            no_audio(events)
+        };
+
+        // 240 ms: 10 frames
+        let timeout = std::time::Duration::from_millis(240);
+        let mut source = DabSource::builder()
+            .scid(1)
+            .no_audio_timeout(timeout)
+            .build();
+        assert!(feed(&mut source, &[1, 2]).is_empty());
+
+        // no such subchannel, emitted once
+        source.set_scid(5);
+        assert_eq!(feed(&mut source, &[1, 2]), [5]);
+        assert!(feed(&mut source, &[1, 2]).is_empty());
+
+        source.set_scid(2);
+        assert!(feed(&mut source, &[1, 2]).is_empty());
+
+        // audio stops
+        assert_eq!(feed(&mut source, &[1]), [2]);
+        assert!(feed(&mut source, &[1]).is_empty());
+
+        // nothing selected
+        let mut source = DabSource::builder().no_audio_timeout(timeout).build();
+        assert!(feed(&mut source, &[]).is_empty());
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn set_scid_pad_selection() {
+        let mut source = DabSource::builder()
+            .scid(1)
+            .pad_selection(PadSelection::Selected)
+            .build();
+        feed_superframe(&mut source, &[1, 2], &msc::tests::superframe(None));
+        let extracts_pad = |source: &DabSource| {
+            source
+                .subchannels
+                .iter()
+                .map(|sc| (sc.scid, sc.audio_extractor.extract_pad))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(extracts_pad(&source), [(1, true), (2, false)]);
+
+        // existing extractors follow
+        source.set_scid(2);
+        assert_eq!(extracts_pad(&source), [(1, false), (2, true)]);
+
+        source.set_pad_selection(PadSelection::All);
+        assert_eq!(extracts_pad(&source), [(1, true), (2, true)]);
+    }
+
+    // names and fields of the spans created, e.g. "fic fibs=3"
+    #[derive(Clone, Default)]
+    struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    struct SpanFields(String);
+
+    impl tracing::field::Visit for SpanFields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = SpanFields(span.metadata().name().to_string());
+            span.record(&mut fields);
+
+            let mut spans = self.0.lock().unwrap();
+            spans.push(fields.0);
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, _: &tracing::Event<'_>) {}
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn decode_spans() {
+        let recorder = SpanRecorder::default();
+        let mut source = DabSource::builder().scid(1).build();
+
+        let data = fixture();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            bus::capture_events(|| {
+                for packet in replay::split_packets(&data) {
+                    source.feed_sync(packet);
+                }
+            });
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        let named = |name: &str| {
+            spans
+                .iter()
+                .filter(|s| s.split(' ').next() == Some(name))
+                .collect::<Vec<_>>()
+        };
+
+        // one per packet, with its length and number
+        let feed = named("feed");
+        assert_eq!(feed.len(), 84);
+        assert!(feed[0].contains(" len=517") && feed[0].contains(" frame=0"));
+        assert!(feed[83].contains(" frame=83"));
+
+        assert_eq!(named("fic").len(), 84);
+        assert!(named("fic").iter().all(|s| s.contains(" fibs=3")));
+
+        // per subchannel, filterable by scid
+        #[cfg(feature = "audio")]
+        {
+            let est = named("est");
+            assert_eq!(est.len(), 84);
+            assert!(est
+                .iter()
+                .all(|s| s.contains(" scid=1") && s.contains(" len=384")));
+        }
+    }
+
+    // frames fed back to back, time spent pacing them
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn feed_paced(source: &mut DabSource, frames: usize) -> std::time::Duration {
+        let packet = management_packet();
+        let start = tokio::time::Instant::now();
+        for _ in 0..frames {
+            source.feed(&packet).await;
+        }
+        start.elapsed()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test(start_paused = true)]
+    async fn pace_cadence() {
+        let mut source = DabSource::builder().pace(PaceMode::Realtime).build();
+        // the first frame right away
+        assert_eq!(feed_paced(&mut source, 10).await, FRAME_DURATION * 9);
+
+        // 5 frames late: fed without delay until back on schedule
+        tokio::time::advance(FRAME_DURATION * 5).await;
+        assert_eq!(feed_paced(&mut source, 5).await, std::time::Duration::ZERO);
+        assert_eq!(feed_paced(&mut source, 1).await, FRAME_DURATION);
+
+        // too far behind to catch up
+        tokio::time::advance(FRAME_DURATION * (MAX_CATCH_UP_FRAMES + 5)).await;
+        assert_eq!(feed_paced(&mut source, 5).await, FRAME_DURATION * 4);
+
+        // late frames resync right away
+        let mut source = DabSource::builder()
+            .pace(PaceMode::RealtimeNoCatchUp)
+            .build();
+        feed_paced(&mut source, 1).await;
+        tokio::time::advance(FRAME_DURATION * 5).await;
+        assert_eq!(feed_paced(&mut source, 5).await, FRAME_DURATION * 4);
+
+        let mut source = DabSource::builder().build();
+        assert_eq!(feed_paced(&mut source, 10).await, std::time::Duration::ZERO);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test(start_paused = true)]
+    async fn pace_speed() {
+        let mut source = DabSource::builder()
+            .pace(PaceMode::Realtime)
+            .pace_speed(2.0)
+            .build();
+        let frame_duration = source.frame_duration();
+        assert!((frame_duration.as_secs_f64() - 0.012).abs() < 1e-6);
+        assert_eq!(feed_paced(&mut source, 5).await, frame_duration * 4);
+
+        // the schedule restarts at the new interval
+        source.set_pace_speed(0.5);
+        let frame_duration = source.frame_duration();
+        assert!((frame_duration.as_secs_f64() - 0.048).abs() < 1e-6);
+        assert_eq!(feed_paced(&mut source, 5).await, frame_duration * 4);
+
+        source.set_pace_speed(100.0);
+        assert_eq!(source.pace_speed(), PACE_SPEED_MAX);
+
+        // 0 disables pacing
+        source.set_pace_speed(0.0);
+        assert_eq!(source.pace, PaceMode::Off);
+        assert_eq!(feed_paced(&mut source, 5).await, std::time::Duration::ZERO);
     }
 }
@@ -1,48 +1,112 @@
 use serde::Serialize;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use super::ensemble::Ensemble;
-use super::msc::AacpResult;
+use super::ensemble::{Ensemble, EnsembleDelta, Subchannel};
+use super::epg::ProgrammeSchedule;
+use super::fic::DateTimeUTC;
+use super::msc::{AacpResult, AudioFormat};
 use super::pad::dl::DlObject;
 use super::pad::mot::MotImage;
-use super::DabStats;
+use super::tables::AnnouncementType;
+use super::{DabStats, EventCallback};
 
+// bumped on incompatible changes of the serialized event shape, see DabEvent::to_json
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+// serialized as {"type": "<variant in snake_case>", ...payload fields}.
+// internally tagged: newtype variants must carry structs, not plain values
 #[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum DabEvent {
     //
     EnsembleUpdated(Ensemble),
+    // emitted right before EnsembleUpdated
+    EnsembleChanged(EnsembleDelta),
+    // FIG 0/1 subchannel appeared / expired, emitted right before EnsembleChanged
+    SubchannelAdded(Subchannel),
+    SubchannelRemoved {
+        id: u8,
+    },
     AacpFramesExtracted(AacpResult),
+    // MPEG Layer II frames of a DAB (not DAB+) subchannel, audio_format.codec is "MP2"
+    Mp2FramesExtracted(AacpResult),
+    AudioFormatDetected {
+        scid: u8,
+        format: AudioFormat,
+    },
+    // no audio decoded from the selected subchannel within the no-audio timeout,
+    // e.g. a data subchannel or a wrong SCID. emitted again if audio stops later
+    NoAudio {
+        scid: u8,
+    },
     //
     MotImageReceived(MotImage),
     DlObjectReceived(DlObject),
+    EpgScheduleReceived(ProgrammeSchedule),
+    //
+    AnnouncementActive {
+        cluster: u8,
+        announcement_type: AnnouncementType,
+        subchid: u8,
+    },
+    // FIG 0/10, lto (half hours) from FIG 0/9 if seen yet
+    DateTimeUpdated {
+        utc: DateTimeUTC,
+        lto: Option<i8>,
+    },
+    // "info" TAG text, emitted when it changes
+    EncoderInfo {
+        text: String,
+    },
     //
     DabStatsUpdated(DabStats),
+    // raw FIC of every frame, before FIG parsing. only with DabSource::set_emit_fic
+    FicReceived {
+        fibs: Vec<u8>,
+    },
+}
+
+impl DabEvent {
+    // self-describing JSON object: type, version and the payload fields
+    pub fn to_json(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("version".to_string(), EVENT_SCHEMA_VERSION.into());
+        }
+        Ok(value)
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
 mod platform {
     use super::*;
     use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
-    use once_cell::unsync::OnceCell;
     use std::cell::RefCell;
-    use std::rc::Rc;
 
     thread_local! {
-        static EVENT_TX: OnceCell<Rc<RefCell<UnboundedSender<DabEvent>>>> = OnceCell::new();
+        static EVENT_TX: RefCell<Option<UnboundedSender<DabEvent>>> = const { RefCell::new(None) };
     }
 
+    // a previous bus is closed, its receiver ends
     pub fn init_event_bus() -> UnboundedReceiver<DabEvent> {
         let (tx, rx) = unbounded::<DabEvent>();
         EVENT_TX.with(|cell| {
-            cell.set(Rc::new(RefCell::new(tx)))
-                .expect("Already initialized");
+            cell.replace(Some(tx));
         });
         rx
     }
 
-    pub fn emit_event(event: DabEvent) {
+    pub fn shutdown_event_bus() {
         EVENT_TX.with(|cell| {
-            if let Some(tx) = cell.get() {
-                let _ = tx.borrow_mut().unbounded_send(event);
+            cell.take();
+        });
+    }
+
+    pub fn send_event(event: DabEvent) {
+        EVENT_TX.with(|cell| {
+            if let Some(tx) = cell.borrow().as_ref() {
+                let _ = tx.unbounded_send(event);
             }
         });
     }
@@ -51,26 +115,218 @@ mod platform {
 #[cfg(not(target_arch = "wasm32"))]
 mod platform {
     use super::*;
-    use once_cell::sync::OnceCell;
     use std::sync::Mutex;
     use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-    static EVENT_TX: OnceCell<Mutex<UnboundedSender<DabEvent>>> = OnceCell::new();
+    static EVENT_TX: Mutex<Option<UnboundedSender<DabEvent>>> = Mutex::new(None);
 
+    // a previous bus is closed, its receiver ends
     pub fn init_event_bus() -> UnboundedReceiver<DabEvent> {
         let (tx, rx) = unbounded_channel::<DabEvent>();
-        EVENT_TX
-            .set(Mutex::new(tx))
-            .expect("Event bus already initialized");
+        EVENT_TX.lock().unwrap().replace(tx);
         rx
     }
 
-    pub fn emit_event(event: DabEvent) {
-        if let Some(tx) = EVENT_TX.get() {
-            let _ = tx.lock().unwrap().send(event);
+    // drop the sender: receivers drain the pending events and then end
+    pub fn shutdown_event_bus() {
+        EVENT_TX.lock().unwrap().take();
+    }
+
+    pub fn send_event(event: DabEvent) {
+        if let Some(tx) = EVENT_TX.lock().unwrap().as_ref() {
+            let _ = tx.send(event);
         }
     }
 }
 
 // re-export unified interface from the platform module
-pub use platform::{emit_event, init_event_bus};
+pub use platform::{init_event_bus, shutdown_event_bus};
+
+thread_local! {
+    // sink of the DabSource decoding on this thread, see DabSourceBuilder::on_event
+    static EVENT_SINK: RefCell<Option<EventCallback>> = const { RefCell::new(None) };
+}
+
+// to the sink of the decoding source if it has one, else to the bus
+pub fn emit_event(event: DabEvent) {
+    let event = EVENT_SINK.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(sink) => {
+            sink(event);
+            None
+        }
+        None => Some(event),
+    });
+
+    if let Some(event) = event {
+        platform::send_event(event);
+    }
+}
+
+// install the sink for this thread, returns the previous one
+pub(crate) fn set_event_sink(sink: Option<EventCallback>) -> Option<EventCallback> {
+    EVENT_SINK.with(|cell| cell.replace(sink))
+}
+
+// events emitted on this thread while running f
+#[cfg(test)]
+pub(crate) fn capture_events(f: impl FnOnce()) -> Vec<DabEvent> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let previous = set_event_sink(Some(Box::new(move |event| {
+        let _ = tx.send(event);
+    })));
+    f();
+    set_event_sink(previous);
+    rx.try_iter().collect()
+}
+
+// DabSources sharing the bus, see DabSource::shutdown
+static EVENT_BUS_USERS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn event_bus_users() -> usize {
+    EVENT_BUS_USERS.load(Ordering::SeqCst)
+}
+
+pub(crate) fn acquire_event_bus() {
+    EVENT_BUS_USERS.fetch_add(1, Ordering::SeqCst);
+}
+
+// true for the last user
+pub(crate) fn release_event_bus() -> bool {
+    EVENT_BUS_USERS.fetch_sub(1, Ordering::SeqCst) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio_format() -> AudioFormat {
+        AudioFormat {
+            sbr: true,
+            ps: false,
+            codec: "HE-AAC".to_string(),
+            samplerate: 48,
+            bitrate: 72,
+            au_count: 3,
+            channels: 2,
+            asc: vec![0x13, 0x14],
+        }
+    }
+
+    // one of each variant
+    fn events() -> Vec<DabEvent> {
+        let subchannel = Subchannel {
+            id: 1,
+            start: Some(0),
+            size: Some(54),
+            pl: Some("EEP 3-A".to_string()),
+            bitrate: Some(72),
+            bitrate_mismatch: false,
+            service_sids: vec![0xC221],
+        };
+
+        vec![
+            DabEvent::EnsembleUpdated(Ensemble::new()),
+            DabEvent::EnsembleChanged(EnsembleDelta {
+                initial: true,
+                complete: false,
+                changes: Vec::new(),
+            }),
+            DabEvent::SubchannelAdded(subchannel),
+            DabEvent::SubchannelRemoved { id: 1 },
+            DabEvent::AacpFramesExtracted(AacpResult::new(
+                1,
+                Some(audio_format()),
+                vec![vec![0x00; 4]],
+            )),
+            DabEvent::Mp2FramesExtracted(AacpResult::new(1, None, Vec::new())),
+            DabEvent::AudioFormatDetected {
+                scid: 1,
+                format: audio_format(),
+            },
+            DabEvent::NoAudio { scid: 1 },
+            DabEvent::MotImageReceived(MotImage::new(1, 1, vec![0xFF, 0xD8], None, None)),
+            DabEvent::DlObjectReceived(DlObject::new(1, 0, 0)),
+            DabEvent::EpgScheduleReceived(ProgrammeSchedule {
+                service: None,
+                entries: Vec::new(),
+            }),
+            DabEvent::AnnouncementActive {
+                cluster: 1,
+                announcement_type: AnnouncementType::RoadTraffic,
+                subchid: 1,
+            },
+            DabEvent::DateTimeUpdated {
+                utc: DateTimeUTC::Short {
+                    year: 2024,
+                    month: 3,
+                    day: 15,
+                    hours: 13,
+                    minutes: 45,
+                },
+                lto: Some(2),
+            },
+            DabEvent::EncoderInfo {
+                text: "ODR-DabMux".to_string(),
+            },
+            DabEvent::DabStatsUpdated(DabStats::new()),
+            DabEvent::FicReceived {
+                fibs: vec![0x00; 96],
+            },
+        ]
+    }
+
+    #[test]
+    fn to_json_type_and_version() {
+        let types: Vec<String> = events()
+            .iter()
+            .map(|event| {
+                let value = event.to_json().unwrap();
+                assert_eq!(value["version"], EVENT_SCHEMA_VERSION, "{:?}", event);
+                value["type"].as_str().unwrap().to_string()
+            })
+            .collect();
+
+        assert_eq!(
+            types,
+            [
+                "ensemble_updated",
+                "ensemble_changed",
+                "subchannel_added",
+                "subchannel_removed",
+                "aacp_frames_extracted",
+                "mp2_frames_extracted",
+                "audio_format_detected",
+                "no_audio",
+                "mot_image_received",
+                "dl_object_received",
+                "epg_schedule_received",
+                "announcement_active",
+                "date_time_updated",
+                "encoder_info",
+                "dab_stats_updated",
+                "fic_received",
+            ]
+        );
+    }
+
+    #[test]
+    fn to_json_payload() {
+        let value = DabEvent::AudioFormatDetected {
+            scid: 6,
+            format: audio_format(),
+        }
+        .to_json()
+        .unwrap();
+        assert_eq!(value["scid"], 6);
+        assert_eq!(value["format"]["codec"], "HE-AAC");
+
+        // newtype variants carry the fields of the inner struct
+        let value = DabEvent::SubchannelRemoved { id: 3 }.to_json().unwrap();
+        assert_eq!(value["id"], 3);
+        let value = DabEvent::DlObjectReceived(DlObject::new(2, 0, 0))
+            .to_json()
+            .unwrap();
+        assert_eq!(value["scid"], 2);
+        assert_eq!(value["label"], "");
+    }
+}
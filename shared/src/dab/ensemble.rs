@@ -1,8 +1,9 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt;
 
 use super::bus::{emit_event, DabEvent};
-use super::fic::Fig;
+use super::fic::{DateTimeUTC, Fig, LinkedId};
 use super::frame::DetiTag;
 use super::msc::AudioFormat;
 use super::tables;
@@ -13,25 +14,110 @@ pub struct Subchannel {
     pub start: Option<usize>,
     pub size: Option<usize>,
     pub pl: Option<String>,
+    // FIC (FIG 0/1) bitrate, preferred for display
     pub bitrate: Option<usize>,
+    // FIC and superframe derived bitrates differ by more than BITRATE_TOLERANCE
+    pub bitrate_mismatch: bool,
+    // services with a component carried in this subchannel (FIG 0/2, FIG 0/3), usually one
+    pub service_sids: Vec<u16>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+// kbps
+const BITRATE_TOLERANCE: usize = 8;
+
+// a subchannel missing from FIG 0/1 for this many frames (~10s) is removed
+const SUBCHANNEL_EXPIRY_FRAMES: u64 = 416;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ServiceComponent {
     pub scid: u8,
     pub language: Option<tables::Language>,
     pub subchannel_id: Option<u8>,
     pub user_apps: Vec<tables::UserApplication>,
+    pub primary: bool,
+    // FIG 0/2, DAB (MP2) or DAB+
+    pub ascty: Option<tables::AudioServiceType>,
     // is this a good idea?
     pub audio_format: Option<AudioFormat>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+// packet mode data component (FIG 0/2 TMId 3, details from FIG 0/3)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DataComponent {
+    // 12 bit service component id
+    pub scid: u16,
+    pub subchannel_id: Option<u8>,
+    pub packet_address: Option<u16>,
+    // data service component type, e.g. 5: TDC, 60: MOT
+    pub dscty: Option<u8>,
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Service {
     pub sid: u16,
     pub label: Option<String>,
     pub short_label: Option<String>,
     pub components: Vec<ServiceComponent>,
+    pub data_components: Vec<DataComponent>,
+    // FIG 0/18
+    pub announcement_support: Vec<tables::AnnouncementType>,
+    pub announcement_clusters: Vec<u8>,
+}
+
+// FIG 0/6 linkage set, keyed by LSN / hard / international
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkageSet {
+    pub lsn: u16,
+    pub active: bool,
+    pub hard: bool,
+    pub international: bool,
+    pub ids: Vec<LinkedId>,
+}
+
+impl Service {
+    // FIG 0/2 P/S flag, falls back to the first component (e.g. before FIG 0/2 is complete)
+    pub fn primary_component(&self) -> Option<&ServiceComponent> {
+        self.components
+            .iter()
+            .find(|c| c.primary)
+            .or_else(|| self.components.first())
+    }
+
+    // FIG 0/2 TMId 0 (audio stream mode) components
+    pub fn audio_components(&self) -> impl Iterator<Item = &ServiceComponent> {
+        self.components.iter().filter(|c| c.ascty.is_some())
+    }
+
+    // stream mode data components (FIG 0/2 TMId 1 / 2), packet mode ones are in data_components
+    pub fn stream_data_components(&self) -> impl Iterator<Item = &ServiceComponent> {
+        self.components.iter().filter(|c| c.ascty.is_none())
+    }
+
+    // audio format of the primary component
+    pub fn primary_audio(&self) -> Option<&AudioFormat> {
+        self.primary_component()
+            .and_then(|c| c.audio_format.as_ref())
+    }
+
+    // "MP2" / "AAC", from the audio format if detected, else from the FIG 0/2 ASCTy
+    pub fn codec(&self) -> Option<&str> {
+        let component = self.primary_component()?;
+
+        match (&component.audio_format, component.ascty) {
+            (Some(af), _) => Some(af.codec()),
+            (None, Some(ascty)) => Some(ascty.codec()),
+            (None, None) => None,
+        }
+    }
+}
+
+// FIG 0/19 announcement currently active in a cluster
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Announcement {
+    pub cluster: u8,
+    pub announcement_type: tables::AnnouncementType,
+    pub subchid: u8,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,9 +126,65 @@ pub struct Ensemble {
     pub al_flag: Option<bool>,
     pub label: Option<String>,
     pub short_label: Option<String>,
+    // FIG 0/9: extended country code, local time offset (half hours), international table
+    pub ecc: Option<u8>,
+    pub lto: Option<i8>,
+    pub international_table_id: Option<u8>,
+    // sid -> ecc for services with an ECC other than the ensemble one
+    pub service_ecc: BTreeMap<u16, u8>,
     pub services: Vec<Service>,
     pub subchannels: Vec<Subchannel>,
+    pub linkage_sets: Vec<LinkageSet>,
+    pub announcements: Vec<Announcement>,
+    pub complete: bool,
+    // snapshot of the last update, the next delta is computed against it
+    #[serde(skip)]
+    emitted: Option<Box<Ensemble>>,
+    // last FIG 0/10 date / time, goes out as DateTimeUpdated
+    #[serde(skip)]
+    datetime: Option<DateTimeUTC>,
+    // DETI tags fed, and the frame each subchannel was last seen in FIG 0/1
+    #[serde(skip)]
+    frames: u64,
+    #[serde(skip)]
+    subchannels_seen: BTreeMap<u8, u64>,
+}
+
+// what changed between two ensemble updates
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum EnsembleChange {
+    Identity {
+        eid: Option<u16>,
+        al_flag: Option<bool>,
+        label: Option<String>,
+        short_label: Option<String>,
+    },
+    ServiceAdded(Service),
+    ServiceUpdated(Service),
+    ServiceRemoved {
+        sid: u16,
+    },
+    SubchannelAdded(Subchannel),
+    SubchannelUpdated(Subchannel),
+    SubchannelRemoved {
+        id: u8,
+    },
+    LinkageSetsUpdated(Vec<LinkageSet>),
+    AnnouncementsUpdated(Vec<Announcement>),
+    Country {
+        ecc: Option<u8>,
+        lto: Option<i8>,
+        international_table_id: Option<u8>,
+        service_ecc: BTreeMap<u16, u8>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleDelta {
+    // computed against an empty ensemble (first update / after reset), previous state is stale
+    pub initial: bool,
     pub complete: bool,
+    pub changes: Vec<EnsembleChange>,
 }
 
 impl Default for Ensemble {
@@ -70,15 +212,27 @@ impl Ensemble {
             al_flag: None,
             label: None,
             short_label: None,
+            ecc: None,
+            lto: None,
+            international_table_id: None,
+            service_ecc: BTreeMap::new(),
             services: Vec::new(),
             subchannels: Vec::new(),
+            linkage_sets: Vec::new(),
+            announcements: Vec::new(),
             complete: false,
+            emitted: None,
+            datetime: None,
+            frames: 0,
+            subchannels_seen: BTreeMap::new(),
         }
     }
 
-    pub async fn feed(&mut self, tag: &DetiTag) -> bool {
+    pub fn feed(&mut self, tag: &DetiTag) -> bool {
         let mut updated = false;
 
+        self.frames += 1;
+
         for fig in &tag.figs {
             match fig {
                 Fig::F0_0(fig) => {
@@ -87,6 +241,8 @@ impl Ensemble {
                 }
                 Fig::F0_1(fig) => {
                     for sc in &fig.subchannels {
+                        self.subchannels_seen.insert(sc.id, self.frames);
+
                         let existing_sc = self.subchannels.iter_mut().find(|s| s.id == sc.id);
 
                         match existing_sc {
@@ -109,6 +265,8 @@ impl Ensemble {
                                     size: sc.size,
                                     pl: sc.pl.clone(),
                                     bitrate: sc.bitrate,
+                                    bitrate_mismatch: false,
+                                    service_sids: Vec::new(),
                                 });
                                 updated = true;
                             }
@@ -117,23 +275,50 @@ impl Ensemble {
                 }
                 Fig::F0_2(fig) => {
                     for entry in &fig.services {
+                        if let Some(packet_scid) = entry.packet_scid {
+                            updated |=
+                                self.add_data_component(entry.sid, packet_scid, entry.primary);
+                            continue;
+                        }
+
+                        // TMId 0: audio stream mode
+                        let ascty =
+                            (entry.tmid == 0).then(|| tables::AudioServiceType::from(entry.ascty));
+
                         let service = self.services.iter_mut().find(|s| s.sid == entry.sid);
 
                         match service {
                             Some(existing_service) => {
-                                if !existing_service
+                                match existing_service
                                     .components
-                                    .iter()
-                                    .any(|c| c.scid == entry.scid)
+                                    .iter_mut()
+                                    .find(|c| c.scid == entry.scid)
                                 {
-                                    existing_service.components.push(ServiceComponent {
-                                        scid: entry.scid,
-                                        language: None,
-                                        subchannel_id: Some(entry.scid),
-                                        user_apps: Vec::new(),
-                                        audio_format: None,
-                                    });
-                                    updated = true;
+                                    Some(component) => {
+                                        if component.ascty != ascty {
+                                            component.ascty = ascty;
+                                            updated = true;
+                                        }
+                                        // P/S flag moved to another component
+                                        if component.primary != entry.primary {
+                                            component.primary = entry.primary;
+                                            updated = true;
+                                        }
+                                        updated |= component.subchannel_id.replace(entry.scid)
+                                            != Some(entry.scid);
+                                    }
+                                    None => {
+                                        existing_service.components.push(ServiceComponent {
+                                            scid: entry.scid,
+                                            language: None,
+                                            subchannel_id: Some(entry.scid),
+                                            user_apps: Vec::new(),
+                                            primary: entry.primary,
+                                            ascty,
+                                            audio_format: None,
+                                        });
+                                        updated = true;
+                                    }
                                 }
                             }
                             None => {
@@ -146,14 +331,36 @@ impl Ensemble {
                                         language: None,
                                         subchannel_id: Some(entry.scid),
                                         user_apps: Vec::new(),
+                                        primary: entry.primary,
+                                        ascty,
                                         audio_format: None,
                                     }],
+                                    data_components: Vec::new(),
+                                    announcement_support: Vec::new(),
+                                    announcement_clusters: Vec::new(),
                                 });
                                 updated = true;
                             }
                         }
                     }
                 }
+                Fig::F0_3(fig) => {
+                    for pc in &fig.components {
+                        let component = self
+                            .services
+                            .iter_mut()
+                            .flat_map(|s| s.data_components.iter_mut())
+                            .find(|c| c.scid == pc.scid);
+
+                        if let Some(component) = component {
+                            updated |=
+                                component.subchannel_id.replace(pc.subchid) != Some(pc.subchid);
+                            updated |= component.packet_address.replace(pc.packet_address)
+                                != Some(pc.packet_address);
+                            updated |= component.dscty.replace(pc.dscty) != Some(pc.dscty);
+                        }
+                    }
+                }
                 Fig::F0_5(fig) => {
                     for lang in &fig.services {
                         for service in &mut self.services {
@@ -166,6 +373,64 @@ impl Ensemble {
                         }
                     }
                 }
+                Fig::F0_6(fig) => {
+                    for ls in &fig.linkage_sets {
+                        let existing = self.linkage_sets.iter_mut().find(|l| {
+                            l.lsn == ls.lsn
+                                && l.hard == ls.hard
+                                && l.international == ls.international
+                        });
+
+                        let linkage_set = match existing {
+                            Some(l) => l,
+                            None => {
+                                self.linkage_sets.push(LinkageSet {
+                                    lsn: ls.lsn,
+                                    active: ls.active,
+                                    hard: ls.hard,
+                                    international: ls.international,
+                                    ids: Vec::new(),
+                                });
+                                updated = true;
+                                self.linkage_sets.last_mut().unwrap()
+                            }
+                        };
+
+                        if linkage_set.active != ls.active {
+                            linkage_set.active = ls.active;
+                            updated = true;
+                        }
+
+                        // CEI carries no Id list
+                        if let Some(ids) = &ls.ids {
+                            for id in ids {
+                                if !linkage_set.ids.contains(id) {
+                                    linkage_set.ids.push(id.clone());
+                                    updated = true;
+                                }
+                            }
+                        }
+                    }
+                }
+                Fig::F0_9(fig) => {
+                    updated |= self.ecc.replace(fig.ecc) != Some(fig.ecc);
+                    updated |= self.lto.replace(fig.lto) != Some(fig.lto);
+                    updated |= self.international_table_id.replace(fig.int_table_id)
+                        != Some(fig.int_table_id);
+                    for &(sid, ecc) in &fig.service_ecc {
+                        updated |= self.service_ecc.insert(sid, ecc) != Some(ecc);
+                    }
+                }
+                Fig::F0_10(fig) => {
+                    // repeated FIGs carry the same (short form) time, only emit changes
+                    if self.datetime.as_ref() != Some(&fig.utc) {
+                        self.datetime = Some(fig.utc.clone());
+                        emit_event(DabEvent::DateTimeUpdated {
+                            utc: fig.utc.clone(),
+                            lto: self.lto,
+                        });
+                    }
+                }
                 Fig::F0_13(fig) => {
                     for entry in &fig.services {
                         if let Some(service) = self.services.iter_mut().find(|s| s.sid == entry.sid)
@@ -195,6 +460,53 @@ impl Ensemble {
                         }
                     }
                 }
+                Fig::F0_18(fig) => {
+                    for entry in &fig.services {
+                        if let Some(service) = self.services.iter_mut().find(|s| s.sid == entry.sid)
+                        {
+                            let support = tables::AnnouncementType::from_flags(entry.asu);
+                            if service.announcement_support != support {
+                                service.announcement_support = support;
+                                updated = true;
+                            }
+                            if service.announcement_clusters != entry.clusters {
+                                service.announcement_clusters = entry.clusters.clone();
+                                updated = true;
+                            }
+                        }
+                    }
+                }
+                Fig::F0_19(fig) => {
+                    for entry in &fig.announcements {
+                        let active: Vec<Announcement> =
+                            tables::AnnouncementType::from_flags(entry.asw)
+                                .into_iter()
+                                .map(|announcement_type| Announcement {
+                                    cluster: entry.cluster,
+                                    announcement_type,
+                                    subchid: entry.subchid,
+                                })
+                                .collect();
+
+                        // announcements that ended in this cluster
+                        let before = self.announcements.len();
+                        self.announcements
+                            .retain(|a| a.cluster != entry.cluster || active.contains(a));
+                        updated |= self.announcements.len() != before;
+
+                        for a in active {
+                            if !self.announcements.contains(&a) {
+                                emit_event(DabEvent::AnnouncementActive {
+                                    cluster: a.cluster,
+                                    announcement_type: a.announcement_type,
+                                    subchid: a.subchid,
+                                });
+                                self.announcements.push(a);
+                                updated = true;
+                            }
+                        }
+                    }
+                }
                 Fig::F1_0(fig) => {
                     updated |= self.label.replace(fig.label.clone()) != Some(fig.label.clone());
                     updated |= self.short_label.replace(fig.short_label.clone())
@@ -212,6 +524,13 @@ impl Ensemble {
             }
         }
 
+        updated |= self.expire_subchannels();
+
+        if updated {
+            self.link_services();
+            self.reconcile_bitrates();
+        }
+
         if updated {
             // "completeness" means for the moment:
             // - EID and label present
@@ -226,12 +545,181 @@ impl Ensemble {
         }
 
         if updated {
-            emit_event(DabEvent::EnsembleUpdated(self.clone()));
+            self.emit_update();
         }
 
         updated
     }
 
+    // drop subchannels no longer signalled in FIG 0/1 (mux reconfiguration)
+    fn expire_subchannels(&mut self) -> bool {
+        let frames = self.frames;
+        let seen = &mut self.subchannels_seen;
+        let before = self.subchannels.len();
+
+        self.subchannels.retain(|sc| {
+            let expired = seen
+                .get(&sc.id)
+                .is_some_and(|last| frames - last > SUBCHANNEL_EXPIRY_FRAMES);
+            if expired {
+                log::info!("SubCh {}: no longer in FIG 0/1 - removed", sc.id);
+                seen.remove(&sc.id);
+            }
+            !expired
+        });
+
+        self.subchannels.len() != before
+    }
+
+    // emit the delta against the previous update, followed by the full snapshot for late subscribers
+    fn emit_update(&mut self) {
+        let previous = self.emitted.take();
+        let snapshot = self.clone();
+
+        let delta = EnsembleDelta::between(previous.as_deref(), &snapshot);
+        if !delta.changes.is_empty() {
+            // subchannel presence on its own, e.g. for recorders
+            for change in &delta.changes {
+                match change {
+                    EnsembleChange::SubchannelAdded(sc) => {
+                        emit_event(DabEvent::SubchannelAdded(sc.clone()));
+                    }
+                    EnsembleChange::SubchannelRemoved { id } => {
+                        emit_event(DabEvent::SubchannelRemoved { id: *id });
+                    }
+                    _ => {}
+                }
+            }
+            emit_event(DabEvent::EnsembleChanged(delta));
+        }
+
+        emit_event(DabEvent::EnsembleUpdated(snapshot.clone()));
+        self.emitted = Some(Box::new(snapshot));
+    }
+
+    // bring a snapshot up to date, e.g. on the consumer side of EnsembleChanged
+    pub fn apply(&mut self, delta: &EnsembleDelta) {
+        if delta.initial {
+            self.reset();
+        }
+
+        for change in &delta.changes {
+            match change {
+                EnsembleChange::Identity {
+                    eid,
+                    al_flag,
+                    label,
+                    short_label,
+                } => {
+                    self.eid = *eid;
+                    self.al_flag = *al_flag;
+                    self.label = label.clone();
+                    self.short_label = short_label.clone();
+                }
+                EnsembleChange::ServiceAdded(service) | EnsembleChange::ServiceUpdated(service) => {
+                    match self.services.iter_mut().find(|s| s.sid == service.sid) {
+                        Some(existing) => *existing = service.clone(),
+                        None => self.services.push(service.clone()),
+                    }
+                }
+                EnsembleChange::ServiceRemoved { sid } => {
+                    self.services.retain(|s| s.sid != *sid);
+                }
+                EnsembleChange::SubchannelAdded(sc) | EnsembleChange::SubchannelUpdated(sc) => {
+                    match self.subchannels.iter_mut().find(|s| s.id == sc.id) {
+                        Some(existing) => *existing = sc.clone(),
+                        None => self.subchannels.push(sc.clone()),
+                    }
+                }
+                EnsembleChange::SubchannelRemoved { id } => {
+                    self.subchannels.retain(|s| s.id != *id);
+                }
+                EnsembleChange::LinkageSetsUpdated(linkage_sets) => {
+                    self.linkage_sets = linkage_sets.clone();
+                }
+                EnsembleChange::AnnouncementsUpdated(announcements) => {
+                    self.announcements = announcements.clone();
+                }
+                EnsembleChange::Country {
+                    ecc,
+                    lto,
+                    international_table_id,
+                    service_ecc,
+                } => {
+                    self.ecc = *ecc;
+                    self.lto = *lto;
+                    self.international_table_id = *international_table_id;
+                    self.service_ecc = service_ecc.clone();
+                }
+            }
+        }
+
+        self.complete = delta.complete;
+    }
+
+    // M3U playlist, one entry per audio service: <base_url>?scid=<subchannel of the primary component>
+    pub fn to_m3u(&self, base_url: &str) -> String {
+        let separator = if base_url.contains('?') { '&' } else { '?' };
+
+        let mut m3u = String::from("#EXTM3U\n");
+
+        for service in &self.services {
+            let component = service.primary_component();
+
+            let Some(subchannel_id) = component.and_then(|c| c.subchannel_id) else {
+                continue;
+            };
+
+            let label = service.label.as_deref().map(str::trim).unwrap_or_default();
+
+            m3u.push_str(&format!(
+                "#EXTINF:-1,{}\n{}{}scid={}\n",
+                label, base_url, separator, subchannel_id
+            ));
+        }
+
+        m3u
+    }
+
+    // country from the EId country id and the FIG 0/9 ECC, None until both are known
+    pub fn country(&self) -> Option<&'static str> {
+        let country_id = (self.eid? >> 12) as u8;
+        tables::country_name(self.ecc?, country_id)
+    }
+
+    // returns true if the component was added
+    fn add_data_component(&mut self, sid: u16, scid: u16, primary: bool) -> bool {
+        let service = match self.services.iter_mut().find(|s| s.sid == sid) {
+            Some(s) => s,
+            None => {
+                self.services.push(Service {
+                    sid,
+                    label: None,
+                    short_label: None,
+                    components: Vec::new(),
+                    data_components: Vec::new(),
+                    announcement_support: Vec::new(),
+                    announcement_clusters: Vec::new(),
+                });
+                self.services.last_mut().unwrap()
+            }
+        };
+
+        if service.data_components.iter().any(|c| c.scid == scid) {
+            return false;
+        }
+
+        service.data_components.push(DataComponent {
+            scid,
+            subchannel_id: None,
+            packet_address: None,
+            dscty: None,
+            primary,
+        });
+
+        true
+    }
+
     pub fn update_audio_format(&mut self, scid: u8, audio_format: Option<AudioFormat>) -> bool {
         let mut updated = false;
 
@@ -247,18 +735,857 @@ impl Ensemble {
         }
 
         if updated {
-            emit_event(DabEvent::EnsembleUpdated(self.clone()));
+            self.reconcile_bitrates();
+            self.emit_update();
         }
 
         updated
     }
 
+    // FIG 0/2 ASCTy of the audio component carried in the subchannel
+    pub fn audio_service_type(&self, subchannel_id: u8) -> Option<tables::AudioServiceType> {
+        self.services
+            .iter()
+            .flat_map(|s| &s.components)
+            .find(|c| c.subchannel_id == Some(subchannel_id))
+            .and_then(|c| c.ascty)
+    }
+
+    // resolve the subchannel -> service back-references from the service components
+    fn link_services(&mut self) {
+        for sc in &mut self.subchannels {
+            let mut sids: Vec<u16> = self
+                .services
+                .iter()
+                .filter(|s| {
+                    s.components.iter().any(|c| c.subchannel_id == Some(sc.id))
+                        || s.data_components
+                            .iter()
+                            .any(|c| c.subchannel_id == Some(sc.id))
+                })
+                .map(|s| s.sid)
+                .collect();
+
+            sids.sort_unstable();
+            sc.service_sids = sids;
+        }
+    }
+
+    // services carried in the given subchannel
+    pub fn services_in_subchannel(&self, subchannel_id: u8) -> impl Iterator<Item = &Service> {
+        let sids = self
+            .subchannels
+            .iter()
+            .find(|sc| sc.id == subchannel_id)
+            .map(|sc| sc.service_sids.as_slice())
+            .unwrap_or_default();
+
+        self.services.iter().filter(|s| sids.contains(&s.sid))
+    }
+
+    // flag subchannels where the FIG 0/1 bitrate does not match the one from the superframe
+    fn reconcile_bitrates(&mut self) {
+        for sc in &mut self.subchannels {
+            let audio_bitrate = self
+                .services
+                .iter()
+                .flat_map(|s| &s.components)
+                .find(|c| c.subchannel_id == Some(sc.id))
+                .and_then(|c| c.audio_format.as_ref())
+                .map(|af| af.bitrate_kbps());
+
+            sc.bitrate_mismatch = match (sc.bitrate, audio_bitrate) {
+                (Some(fic), Some(audio)) => fic.abs_diff(audio) > BITRATE_TOLERANCE,
+                _ => false,
+            };
+
+            if sc.bitrate_mismatch {
+                log::debug!(
+                    "SubCh {}: bitrate mismatch FIC {:?} / audio {:?} kbps",
+                    sc.id,
+                    sc.bitrate,
+                    audio_bitrate
+                );
+            }
+        }
+    }
+
+    // back to the new() state, including completeness
     pub fn reset(&mut self) {
-        self.eid = None;
-        self.al_flag = None;
-        self.label = None;
-        self.short_label = None;
-        self.services.clear();
-        self.subchannels.clear();
+        *self = Self::new();
+    }
+}
+
+impl EnsembleDelta {
+    // the decoder only ever adds or updates services, subchannels also expire
+    // (SUBCHANNEL_EXPIRY_FRAMES). removal of everything happens on reset, signalled by initial
+    pub fn between(previous: Option<&Ensemble>, current: &Ensemble) -> Self {
+        let empty = Ensemble::new();
+        let initial = previous.is_none();
+        let previous = previous.unwrap_or(&empty);
+
+        let mut changes = Vec::new();
+
+        if previous.eid != current.eid
+            || previous.al_flag != current.al_flag
+            || previous.label != current.label
+            || previous.short_label != current.short_label
+        {
+            changes.push(EnsembleChange::Identity {
+                eid: current.eid,
+                al_flag: current.al_flag,
+                label: current.label.clone(),
+                short_label: current.short_label.clone(),
+            });
+        }
+
+        if previous.ecc != current.ecc
+            || previous.lto != current.lto
+            || previous.international_table_id != current.international_table_id
+            || previous.service_ecc != current.service_ecc
+        {
+            changes.push(EnsembleChange::Country {
+                ecc: current.ecc,
+                lto: current.lto,
+                international_table_id: current.international_table_id,
+                service_ecc: current.service_ecc.clone(),
+            });
+        }
+
+        for service in &current.services {
+            match previous.services.iter().find(|s| s.sid == service.sid) {
+                None => changes.push(EnsembleChange::ServiceAdded(service.clone())),
+                Some(s) if s != service => {
+                    changes.push(EnsembleChange::ServiceUpdated(service.clone()))
+                }
+                _ => {}
+            }
+        }
+
+        for service in &previous.services {
+            if !current.services.iter().any(|s| s.sid == service.sid) {
+                changes.push(EnsembleChange::ServiceRemoved { sid: service.sid });
+            }
+        }
+
+        for sc in &current.subchannels {
+            match previous.subchannels.iter().find(|s| s.id == sc.id) {
+                None => changes.push(EnsembleChange::SubchannelAdded(sc.clone())),
+                Some(s) if s != sc => changes.push(EnsembleChange::SubchannelUpdated(sc.clone())),
+                _ => {}
+            }
+        }
+
+        for sc in &previous.subchannels {
+            if !current.subchannels.iter().any(|s| s.id == sc.id) {
+                changes.push(EnsembleChange::SubchannelRemoved { id: sc.id });
+            }
+        }
+
+        if previous.linkage_sets != current.linkage_sets {
+            changes.push(EnsembleChange::LinkageSetsUpdated(
+                current.linkage_sets.clone(),
+            ));
+        }
+
+        if previous.announcements != current.announcements {
+            changes.push(EnsembleChange::AnnouncementsUpdated(
+                current.announcements.clone(),
+            ));
+        }
+
+        Self {
+            initial,
+            complete: current.complete,
+            changes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dab::bus::capture_events;
+    use crate::dab::FicDecoder;
+    use tables::AudioServiceType;
+
+    fn component(scid: u8, primary: bool, ascty: Option<AudioServiceType>) -> ServiceComponent {
+        ServiceComponent {
+            scid,
+            language: None,
+            subchannel_id: Some(scid),
+            user_apps: Vec::new(),
+            primary,
+            ascty,
+            audio_format: None,
+        }
+    }
+
+    fn service(components: Vec<ServiceComponent>) -> Service {
+        Service {
+            sid: 0xC221,
+            label: None,
+            short_label: None,
+            components,
+            data_components: Vec::new(),
+            announcement_support: Vec::new(),
+            announcement_clusters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn primary_component() {
+        assert!(service(Vec::new()).primary_component().is_none());
+
+        // listed second
+        let svc = service(vec![
+            component(2, false, Some(AudioServiceType::Dab)),
+            component(1, true, Some(AudioServiceType::DabPlus)),
+        ]);
+        assert_eq!(svc.primary_component().map(|c| c.scid), Some(1));
+        assert_eq!(svc.codec(), Some("AAC"));
+
+        // no P/S flag yet
+        let svc = service(vec![
+            component(2, false, Some(AudioServiceType::Dab)),
+            component(1, false, Some(AudioServiceType::DabPlus)),
+        ]);
+        assert_eq!(svc.primary_component().map(|c| c.scid), Some(2));
+        assert_eq!(svc.codec(), Some("MP2"));
+    }
+
+    #[test]
+    fn primary_audio() {
+        let mut primary = component(1, true, Some(AudioServiceType::DabPlus));
+        let mut secondary = component(2, false, Some(AudioServiceType::Dab));
+        secondary.audio_format =
+            AudioFormat::from_mp2_header(&[0xFF, 0xFC, 0x84, 0x04]).map(|(af, _)| af);
+
+        // not detected yet
+        let svc = service(vec![secondary.clone(), primary.clone()]);
+        assert!(svc.primary_audio().is_none());
+
+        primary.audio_format = Some(audio_format(112));
+        let svc = service(vec![secondary, primary]);
+        assert_eq!(
+            svc.primary_audio().map(|af| af.summary()).as_deref(),
+            Some("AAC-LC, 48 kHz Stereo, 112 kbps")
+        );
+    }
+
+    fn subchannel(id: u8, bitrate: usize) -> Subchannel {
+        Subchannel {
+            id,
+            start: Some(id as usize * 84),
+            size: Some(84),
+            pl: Some("EEP 3-A".to_string()),
+            bitrate: Some(bitrate),
+            bitrate_mismatch: false,
+            service_sids: Vec::new(),
+        }
+    }
+
+    fn ensemble() -> Ensemble {
+        let mut second = service(vec![component(2, true, Some(AudioServiceType::Dab))]);
+        second.sid = 0xC222;
+
+        let mut ensemble = Ensemble::new();
+        ensemble.eid = Some(0xCEDB);
+        ensemble.label = Some("Edinburgh".to_string());
+        ensemble.services = vec![
+            service(vec![component(1, true, Some(AudioServiceType::DabPlus))]),
+            second,
+        ];
+        ensemble.subchannels = vec![subchannel(1, 96), subchannel(2, 128)];
+        ensemble.complete = true;
+        ensemble
+    }
+
+    // public state, what a consumer of the deltas sees
+    fn assert_same(a: &Ensemble, b: &Ensemble) {
+        assert_eq!(
+            (a.eid, a.al_flag, &a.label, &a.short_label),
+            (b.eid, b.al_flag, &b.label, &b.short_label)
+        );
+        assert_eq!(
+            (a.ecc, a.lto, a.international_table_id, &a.service_ecc),
+            (b.ecc, b.lto, b.international_table_id, &b.service_ecc)
+        );
+        assert_eq!(a.services, b.services);
+        assert_eq!(a.subchannels, b.subchannels);
+        assert_eq!(a.linkage_sets, b.linkage_sets);
+        assert_eq!(a.announcements, b.announcements);
+        assert_eq!(a.complete, b.complete);
+    }
+
+    fn apply_between(a: &Ensemble, b: &Ensemble) -> EnsembleDelta {
+        let delta = EnsembleDelta::between(Some(a), b);
+        let mut applied = a.clone();
+        applied.apply(&delta);
+        assert_same(&applied, b);
+        delta
+    }
+
+    #[test]
+    fn delta_service_added() {
+        let a = ensemble();
+        let mut added = service(vec![component(3, true, Some(AudioServiceType::DabPlus))]);
+        added.sid = 0xC223;
+
+        let mut b = a.clone();
+        b.services.push(added.clone());
+
+        let delta = apply_between(&a, &b);
+        assert!(!delta.initial);
+        assert_eq!(delta.changes, [EnsembleChange::ServiceAdded(added)]);
+
+        // nothing changed
+        assert!(apply_between(&b, &b).changes.is_empty());
+    }
+
+    #[test]
+    fn delta_services_and_components() {
+        let a = ensemble();
+
+        // service removed
+        let mut b = a.clone();
+        b.services.retain(|s| s.sid != 0xC222);
+        let delta = apply_between(&a, &b);
+        assert_eq!(
+            delta.changes,
+            [EnsembleChange::ServiceRemoved { sid: 0xC222 }]
+        );
+
+        // component added, component changed, label
+        let mut b = a.clone();
+        b.services[0].components.push(component(4, false, None));
+        b.services[1].components[0].ascty = Some(AudioServiceType::DabPlus);
+        b.services[1].label = Some("Two".to_string());
+        let delta = apply_between(&a, &b);
+        assert_eq!(delta.changes.len(), 2);
+        assert!(delta
+            .changes
+            .iter()
+            .all(|c| matches!(c, EnsembleChange::ServiceUpdated(_))));
+
+        // component removed
+        let mut c = b.clone();
+        c.services[0].components.truncate(1);
+        apply_between(&b, &c);
+    }
+
+    #[test]
+    fn delta_subchannels() {
+        let a = ensemble();
+
+        let mut b = a.clone();
+        b.subchannels.retain(|sc| sc.id != 2);
+        b.subchannels[0].bitrate = Some(88);
+        b.subchannels[0].bitrate_mismatch = true;
+        b.subchannels.push(subchannel(5, 64));
+
+        let delta = apply_between(&a, &b);
+        assert_eq!(
+            delta.changes,
+            [
+                EnsembleChange::SubchannelUpdated(b.subchannels[0].clone()),
+                EnsembleChange::SubchannelAdded(subchannel(5, 64)),
+                EnsembleChange::SubchannelRemoved { id: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn delta_identity_and_country() {
+        let a = ensemble();
+
+        let mut b = a.clone();
+        b.label = Some("Edinburgh Test".to_string());
+        b.ecc = Some(0xE1);
+        b.lto = Some(2);
+        b.service_ecc.insert(0xC222, 0xE0);
+        b.linkage_sets.push(LinkageSet {
+            lsn: 0x123,
+            active: true,
+            hard: false,
+            international: false,
+            ids: vec![LinkedId {
+                ecc: None,
+                id: 0xC221,
+            }],
+        });
+        b.announcements.push(Announcement {
+            cluster: 1,
+            announcement_type: tables::AnnouncementType::RoadTraffic,
+            subchid: 2,
+        });
+        b.complete = false;
+
+        let delta = apply_between(&a, &b);
+        assert_eq!(delta.changes.len(), 4);
+        assert!(!delta.complete);
+    }
+
+    #[test]
+    fn delta_initial() {
+        // first update / after reset: whatever the consumer had before is dropped
+        let b = ensemble();
+        let delta = EnsembleDelta::between(None, &b);
+        assert!(delta.initial);
+
+        let mut stale = ensemble();
+        stale.services[0].sid = 0xD000;
+        stale.subchannels.push(subchannel(9, 32));
+        stale.apply(&delta);
+        assert_same(&stale, &b);
+    }
+
+    #[test]
+    fn component_accessors() {
+        let svc = service(vec![
+            component(1, true, Some(AudioServiceType::DabPlus)),
+            component(3, false, None),
+            component(2, false, Some(AudioServiceType::Dab)),
+        ]);
+
+        let scids = |c: Vec<&ServiceComponent>| c.iter().map(|c| c.scid).collect::<Vec<_>>();
+        assert_eq!(scids(svc.audio_components().collect()), [1, 2]);
+        assert_eq!(scids(svc.stream_data_components().collect()), [3]);
+    }
+
+    // DETI with a single FIB carrying the given FIGs
+    fn tag(figs: &[u8]) -> DetiTag {
+        let mut fib = figs.to_vec();
+        fib.resize(30, 0xFF);
+        fib.extend_from_slice(&crate::utils::calc_crc16_ccitt(&fib).to_be_bytes());
+
+        DetiTag {
+            atstf: Vec::new(),
+            fic: Vec::new(),
+            figs: FicDecoder::from_bytes(&fib).unwrap(),
+            rfudf: Vec::new(),
+            fib_crc_errors: 0,
+        }
+    }
+
+    // FIB with FIG 0/2 for SId 0xC221: DAB+ audio in SubCh 1 (primary), stream data in SubCh 3
+    // and packet mode SCId 0x123, and FIG 0/3 for the latter: SubCh 5, address 1000, MOT
+    fn deti() -> DetiTag {
+        tag(&[
+            0x0A, 0x02, 0xC2, 0x21, 0x03, 0x3F, 0x06, 0x45, 0x0C, 0xC4, 0x8C, // FIG 0/2
+            0x06, 0x03, 0x12, 0x30, 0x3C, 0x17, 0xE8, // FIG 0/3
+        ])
+    }
+
+    // FIG 0 with the given extension and data
+    fn fig0(ext: u8, data: &[u8]) -> Vec<u8> {
+        let mut fig = vec![data.len() as u8 + 1, ext];
+        fig.extend_from_slice(data);
+        fig
+    }
+
+    // FIG 0/1, long form EEP 3-A subchannels of 84 CUs (112 kbps), back to back
+    fn fig0_1(ids: &[u8]) -> Vec<u8> {
+        let data: Vec<u8> = ids
+            .iter()
+            .flat_map(|&id| {
+                let start = id as u16 * 84;
+                [id << 2 | (start >> 8) as u8, start as u8, 0x88, 0x54]
+            })
+            .collect();
+        fig0(1, &data)
+    }
+
+    // FIG 1/0 (ensemble) or 1/1 (programme service) label, no short label
+    fn fig1(ext: u8, id: u16, label: &str) -> Vec<u8> {
+        let mut fig = vec![0x35, ext];
+        fig.extend_from_slice(&id.to_be_bytes());
+        let mut chars = label.as_bytes().to_vec();
+        chars.resize(16, b' ');
+        fig.extend(chars);
+        fig.extend_from_slice(&[0x00, 0x00]);
+        fig
+    }
+
+    // FIG 0/6 linkage set LSN 0x123 (soft, national, active) with SIds 0xC221 and 0xC321
+    const FIG0_6: [u8; 7] = [0xC1, 0x23, 0x02, 0xC2, 0x21, 0xC3, 0x21];
+
+    // FIG 0/18: SId 0xC221 supports road traffic announcements in cluster 1
+    const FIG0_18: [u8; 6] = [0xC2, 0x21, 0x00, 0x02, 0x01, 0x01];
+
+    // FIG 0/19: road traffic announcement in cluster 1 on SubCh 1, or none (ASw 0)
+    fn fig0_19(active: bool) -> Vec<u8> {
+        fig0(19, &[0x01, 0x00, if active { 0x02 } else { 0x00 }, 0x81])
+    }
+
+    // FIG 0/2 for SId 0xC221, DAB+ audio in SubCh 1 and 2, the P/S flag on the first or second
+    fn fig0_2_primary(first: bool) -> DetiTag {
+        let (p1, p2) = if first { (0x02, 0x00) } else { (0x00, 0x02) };
+        tag(&[
+            0x08,
+            0x02,
+            0xC2,
+            0x21,
+            0x02,
+            0x3F,
+            0x04 | p1,
+            0x3F,
+            0x08 | p2,
+        ])
+    }
+
+    #[test]
+    fn primary_flag_change() {
+        let mut ensemble = Ensemble::new();
+        assert!(ensemble.feed(&fig0_2_primary(true)));
+        assert_eq!(
+            ensemble.services[0].primary_component().map(|c| c.scid),
+            Some(1)
+        );
+
+        assert!(ensemble.feed(&fig0_2_primary(false)));
+        let svc = &ensemble.services[0];
+        assert_eq!(svc.primary_component().map(|c| c.scid), Some(2));
+        assert_eq!(svc.components.iter().filter(|c| c.primary).count(), 1);
+
+        assert!(!ensemble.feed(&fig0_2_primary(false)));
+    }
+
+    #[test]
+    fn reset() {
+        let mut ensemble = Ensemble::new();
+        // FIG 0/10 data: MJD 60384, 13:45 UTC
+        let time = (60384u32 << 14 | 13 << 6 | 45).to_be_bytes();
+
+        capture_events(|| {
+            ensemble.feed(&tag(&[
+                fig0(0, &[0xCE, 0xDB, 0x00, 0x00]),
+                fig0_1(&[1, 5]),
+                // ECC 0xE0, SId 0xC222 in ECC 0xE1
+                fig0(9, &[0x82, 0xE0, 0x00, 0x40, 0xE1, 0xC2, 0x22]),
+            ]
+            .concat()));
+            ensemble.feed(&deti());
+            ensemble.feed(&tag(&fig1(0, 0xCEDB, "Edinburgh")));
+            ensemble.feed(&tag(&fig1(1, 0xC221, "One")));
+            ensemble.feed(&tag(&[
+                fig0(6, &FIG0_6),
+                fig0(18, &FIG0_18),
+                fig0_19(true),
+                fig0(10, &time),
+            ]
+            .concat()));
+        });
+
+        assert!(ensemble.complete);
+        assert_eq!(ensemble.label.as_deref(), Some("Edinburgh"));
+        assert_eq!(ensemble.lto, Some(2));
+        assert_eq!(ensemble.service_ecc.get(&0xC222), Some(&0xE1));
+        assert_eq!(ensemble.subchannels.len(), 2);
+        assert_eq!(ensemble.services[0].data_components.len(), 1);
+        assert_eq!(ensemble.services[0].announcement_clusters, [1]);
+        assert_eq!(ensemble.linkage_sets.len(), 1);
+        assert_eq!(ensemble.announcements.len(), 1);
+        assert!(ensemble.emitted.is_some());
+        assert!(ensemble.datetime.is_some());
+        assert!(!ensemble.subchannels_seen.is_empty());
+
+        ensemble.reset();
+
+        assert_same(&ensemble, &Ensemble::new());
+        assert!(!ensemble.complete);
+        assert!(ensemble.emitted.is_none());
+        assert!(ensemble.datetime.is_none());
+        assert_eq!(ensemble.frames, 0);
+        assert!(ensemble.subchannels_seen.is_empty());
+
+        // consumers start over with the next update
+        let events = capture_events(|| {
+            ensemble.feed(&deti());
+        });
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DabEvent::EnsembleChanged(delta) if delta.initial)));
+    }
+
+    fn linked(ids: &[u32]) -> Vec<LinkedId> {
+        ids.iter().map(|&id| LinkedId { ecc: None, id }).collect()
+    }
+
+    #[test]
+    fn linkage_sets_merged() {
+        let mut ensemble = Ensemble::new();
+        let mut feed = |data: &[u8]| {
+            let mut updated = false;
+            capture_events(|| updated = ensemble.feed(&tag(&fig0(6, data))));
+            updated
+        };
+
+        assert!(feed(&FIG0_6));
+        // same set, one more SId
+        assert!(feed(&[0xC1, 0x23, 0x01, 0xC4, 0x21]));
+        // same LSN, but hard: a set of its own
+        assert!(feed(&[0xE1, 0x23, 0x01, 0xC5, 0x21]));
+        // repeated
+        assert!(!feed(&FIG0_6));
+        // CEI: deactivated, the Ids stay
+        assert!(feed(&[0x01, 0x23]));
+
+        assert_eq!(
+            ensemble.linkage_sets,
+            [
+                LinkageSet {
+                    lsn: 0x123,
+                    active: false,
+                    hard: false,
+                    international: false,
+                    ids: linked(&[0xC221, 0xC321, 0xC421]),
+                },
+                LinkageSet {
+                    lsn: 0x123,
+                    active: true,
+                    hard: true,
+                    international: false,
+                    ids: linked(&[0xC521]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn announcements() {
+        let mut ensemble = Ensemble::new();
+        capture_events(|| {
+            ensemble.feed(&deti());
+            ensemble.feed(&tag(&fig0(18, &FIG0_18)));
+        });
+
+        let svc = &ensemble.services[0];
+        assert_eq!(
+            svc.announcement_support,
+            [tables::AnnouncementType::RoadTraffic]
+        );
+        assert_eq!(svc.announcement_clusters, [1]);
+
+        let mut feed = |active: bool| {
+            let mut updated = false;
+            let events = capture_events(|| updated = ensemble.feed(&tag(&fig0_19(active))));
+            let started = events
+                .iter()
+                .filter(|e| matches!(e, DabEvent::AnnouncementActive { .. }))
+                .count();
+            (updated, started)
+        };
+
+        // started, still on, ended
+        assert_eq!(feed(true), (true, 1));
+        assert_eq!(feed(true), (false, 0));
+        assert_eq!(feed(false), (true, 0));
+        assert_eq!(feed(false), (false, 0));
+        // and again
+        assert_eq!(feed(true), (true, 1));
+
+        assert_eq!(
+            ensemble.announcements,
+            [Announcement {
+                cluster: 1,
+                announcement_type: tables::AnnouncementType::RoadTraffic,
+                subchid: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn service_ecc_from_fig0_9() {
+        let mut ensemble = Ensemble::new();
+        let mut feed = |data: &[u8]| {
+            let mut updated = false;
+            capture_events(|| updated = ensemble.feed(&tag(&fig0(9, data))));
+            updated
+        };
+
+        assert!(feed(&[
+            0x82, 0xE0, 0x00, 0x80, 0xE1, 0xD2, 0x21, 0xD2, 0x22
+        ]));
+        assert!(!feed(&[
+            0x82, 0xE0, 0x00, 0x80, 0xE1, 0xD2, 0x21, 0xD2, 0x22
+        ]));
+        // one of them moved to another ECC
+        assert!(feed(&[0x82, 0xE0, 0x00, 0x40, 0xE2, 0xD2, 0x22]));
+
+        assert_eq!((ensemble.ecc, ensemble.lto), (Some(0xE0), Some(2)));
+        assert_eq!(
+            ensemble.service_ecc,
+            BTreeMap::from([(0xD221, 0xE1), (0xD222, 0xE2)])
+        );
+    }
+
+    #[test]
+    fn subchannel_events() {
+        let mut ensemble = Ensemble::new();
+
+        let events = capture_events(|| {
+            ensemble.feed(&tag(&fig0_1(&[1, 2])));
+        });
+        let added: Vec<u8> = events
+            .iter()
+            .filter_map(|e| match e {
+                DabEvent::SubchannelAdded(sc) => Some(sc.id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(added, [1, 2]);
+        assert_eq!(ensemble.subchannels[1].bitrate, Some(112));
+
+        // SubCh 2 no longer signalled: kept for SUBCHANNEL_EXPIRY_FRAMES
+        let events = capture_events(|| {
+            for _ in 0..SUBCHANNEL_EXPIRY_FRAMES {
+                assert!(!ensemble.feed(&tag(&fig0_1(&[1]))));
+            }
+        });
+        assert!(events.is_empty());
+        assert_eq!(ensemble.subchannels.len(), 2);
+
+        let events = capture_events(|| {
+            assert!(ensemble.feed(&tag(&fig0_1(&[1]))));
+        });
+        assert!(matches!(
+            events.as_slice(),
+            [
+                DabEvent::SubchannelRemoved { id: 2 },
+                DabEvent::EnsembleChanged(_),
+                DabEvent::EnsembleUpdated(_)
+            ]
+        ));
+        assert_eq!(ensemble.subchannels.len(), 1);
+
+        // back again
+        let events = capture_events(|| {
+            ensemble.feed(&tag(&fig0_1(&[1, 2])));
+        });
+        assert!(matches!(
+            events.first(),
+            Some(DabEvent::SubchannelAdded(sc)) if sc.id == 2
+        ));
+    }
+
+    #[test]
+    fn shared_subchannel() {
+        let mut ensemble = Ensemble::new();
+        capture_events(|| {
+            // SId 0xC222: DAB+ audio in SubCh 1 as well
+            ensemble.feed(&tag(&[
+                fig0_1(&[1, 5]),
+                fig0(2, &[0xC2, 0x22, 0x01, 0x3F, 0x06]),
+            ]
+            .concat()));
+            ensemble.feed(&deti());
+        });
+
+        assert_eq!(ensemble.subchannels[0].service_sids, [0xC221, 0xC222]);
+        // packet mode data component of 0xC221
+        assert_eq!(ensemble.subchannels[1].service_sids, [0xC221]);
+
+        let sids = |id: u8| {
+            ensemble
+                .services_in_subchannel(id)
+                .map(|s| s.sid)
+                .collect::<Vec<_>>()
+        };
+        // in the order of the services, 0xC222 came first
+        assert_eq!(sids(1), [0xC222, 0xC221]);
+        assert_eq!(sids(5), [0xC221]);
+        // SubCh 3 (stream data) is not in FIG 0/1, SubCh 9 unknown
+        assert!(sids(3).is_empty());
+        assert!(sids(9).is_empty());
+    }
+
+    fn audio_format(bitrate: usize) -> AudioFormat {
+        AudioFormat {
+            sbr: false,
+            ps: false,
+            codec: "AAC-LC".to_string(),
+            samplerate: 48,
+            bitrate,
+            au_count: 6,
+            channels: 2,
+            asc: vec![0x11, 0x90],
+        }
+    }
+
+    #[test]
+    fn bitrate_mismatch() {
+        let mut ensemble = Ensemble::new();
+        capture_events(|| {
+            ensemble.feed(&tag(&fig0_1(&[1])));
+            ensemble.feed(&deti());
+        });
+
+        let mut mismatch = |bitrate: Option<usize>| {
+            capture_events(|| {
+                ensemble.update_audio_format(1, bitrate.map(audio_format));
+            });
+            let sc = &ensemble.subchannels[0];
+            // the FIC bitrate is kept
+            assert_eq!(sc.bitrate, Some(112));
+            sc.bitrate_mismatch
+        };
+
+        assert!(!mismatch(Some(112)));
+        // within BITRATE_TOLERANCE
+        assert!(!mismatch(Some(120)));
+        assert!(!mismatch(Some(104)));
+        // beyond
+        assert!(mismatch(Some(121)));
+        assert!(!mismatch(None));
+        assert!(mismatch(Some(103)));
+    }
+
+    #[test]
+    fn components_from_figs() {
+        let mut ensemble = Ensemble::new();
+        assert!(ensemble.feed(&deti()));
+
+        let svc = &ensemble.services[0];
+        assert_eq!(svc.sid, 0xC221);
+        assert_eq!(svc.primary_component().map(|c| c.scid), Some(1));
+
+        let audio: Vec<_> = svc.audio_components().collect();
+        assert_eq!(audio.len(), 1);
+        assert_eq!(audio[0].ascty, Some(AudioServiceType::DabPlus));
+
+        let data: Vec<_> = svc.stream_data_components().collect();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].subchannel_id, Some(3));
+
+        assert_eq!(
+            svc.data_components,
+            [DataComponent {
+                scid: 0x123,
+                subchannel_id: Some(5),
+                packet_address: Some(1000),
+                dscty: Some(60),
+                primary: false,
+            }]
+        );
+
+        // repeated: nothing changes
+        assert!(!ensemble.feed(&deti()));
+    }
+
+    #[test]
+    fn country() {
+        let mut ensemble = Ensemble::new();
+        assert_eq!(ensemble.country(), None);
+
+        // EId country id 0xD: Germany in ECC 0xE0
+        ensemble.eid = Some(0xD210);
+        assert_eq!(ensemble.country(), None);
+
+        ensemble.ecc = Some(0xE0);
+        assert_eq!(ensemble.country(), Some("Germany"));
+
+        ensemble.ecc = Some(0xE1);
+        assert_eq!(ensemble.country(), Some("Libya"));
     }
 }
@@ -0,0 +1,431 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use super::utils::mjd_to_date;
+
+// binary encoded SPI / EPG (TS 102 371)
+// only the schedule container and programme elements are decoded for now.
+// token tables (string compression) are not supported.
+
+const TAG_CDATA: u8 = 0x01;
+const TAG_EPG: u8 = 0x02;
+const TAG_SHORT_NAME: u8 = 0x10;
+const TAG_MEDIUM_NAME: u8 = 0x11;
+const TAG_LONG_NAME: u8 = 0x12;
+const TAG_LOCATION: u8 = 0x19;
+const TAG_PROGRAMME: u8 = 0x1C;
+const TAG_SCHEDULE: u8 = 0x21;
+const TAG_SCOPE: u8 = 0x24;
+const TAG_SERVICE_SCOPE: u8 = 0x25;
+const TAG_TIME: u8 = 0x2C;
+
+// attributes, the meaning depends on the element
+const ATTR_SERVICE_SCOPE_ID: u8 = 0x80;
+const ATTR_TIME_TIME: u8 = 0x80;
+const ATTR_TIME_DURATION: u8 = 0x81;
+
+#[derive(Debug, Error)]
+pub enum EpgError {
+    #[error("Invalid element size: {l}")]
+    InvalidSize { l: usize },
+
+    #[error("Unexpected top-level element: 0x{tag:02X}")]
+    UnexpectedElement { tag: u8 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EpgTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    // local time offset in half hours
+    pub lto: Option<i8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Programme {
+    pub start: Option<EpgTime>,
+    // seconds
+    pub duration: Option<u32>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgrammeSchedule {
+    // serviceScope id, e.g. "e1.c181.c221.0"
+    pub service: Option<String>,
+    pub entries: Vec<Programme>,
+}
+
+impl ProgrammeSchedule {
+    // SId from the service scope: "<gcc>.<eid>.<sid>.<scids>", hex, optionally with "dab:"
+    pub fn sid(&self) -> Option<u16> {
+        let scope = self.service.as_deref()?;
+        let scope = scope.strip_prefix("dab:").unwrap_or(scope);
+        let sid = scope.split('.').nth(2)?;
+        u16::from_str_radix(sid, 16).ok()
+    }
+}
+
+struct Element<'a> {
+    tag: u8,
+    data: &'a [u8],
+}
+
+// split data into elements / attributes: tag (8), length (8, 0xFE: 16 bit follows, 0xFF: 24 bit follows)
+fn elements(data: &[u8]) -> Result<Vec<Element<'_>>, EpgError> {
+    let mut out = Vec::new();
+    let mut offset = 0;
+
+    while offset + 2 <= data.len() {
+        let tag = data[offset];
+
+        let (len, header_len) = match data[offset + 1] {
+            0xFE => {
+                if offset + 4 > data.len() {
+                    return Err(EpgError::InvalidSize { l: data.len() });
+                }
+                (
+                    u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize,
+                    4,
+                )
+            }
+            0xFF => {
+                if offset + 5 > data.len() {
+                    return Err(EpgError::InvalidSize { l: data.len() });
+                }
+                (
+                    (data[offset + 2] as usize) << 16
+                        | (data[offset + 3] as usize) << 8
+                        | data[offset + 4] as usize,
+                    5,
+                )
+            }
+            l => (l as usize, 2),
+        };
+
+        let start = offset + header_len;
+
+        if start + len > data.len() {
+            return Err(EpgError::InvalidSize { l: data.len() });
+        }
+
+        out.push(Element {
+            tag,
+            data: &data[start..start + len],
+        });
+
+        offset = start + len;
+    }
+
+    Ok(out)
+}
+
+fn decode_text(data: &[u8]) -> Result<Option<String>, EpgError> {
+    Ok(elements(data)?
+        .iter()
+        .find(|e| e.tag == TAG_CDATA)
+        .map(|e| String::from_utf8_lossy(e.data).to_string()))
+}
+
+// same layout as FIG 0/10: rfu(1) MJD(17) rfu(1) LTO flag(1) UTC flag(1) UTC(11 / 27) [LTO(8)]
+fn decode_time(data: &[u8]) -> Option<EpgTime> {
+    if data.len() < 4 {
+        return None;
+    }
+
+    let mjd = (((data[0] & 0x7F) as u32) << 10) | ((data[1] as u32) << 2) | ((data[2] as u32) >> 6);
+    let lto_flag = (data[2] & 0x10) != 0;
+    let utc_flag = (data[2] & 0x08) != 0;
+    let hours = ((data[2] & 0x07) << 2) | (data[3] >> 6);
+    let minutes = data[3] & 0x3F;
+
+    let (seconds, lto_offset) = if utc_flag {
+        (data.get(4).map(|b| b >> 2).unwrap_or(0), 6)
+    } else {
+        (0, 4)
+    };
+
+    let lto = if lto_flag {
+        data.get(lto_offset).map(|b| {
+            let v = (b & 0x1F) as i8;
+            if b & 0x20 != 0 {
+                -v
+            } else {
+                v
+            }
+        })
+    } else {
+        None
+    };
+
+    let (year, month, day) = mjd_to_date(mjd);
+
+    Some(EpgTime {
+        year,
+        month,
+        day,
+        hours,
+        minutes,
+        seconds,
+        lto,
+    })
+}
+
+fn decode_programme(data: &[u8]) -> Result<Programme, EpgError> {
+    let mut programme = Programme {
+        start: None,
+        duration: None,
+        name: None,
+    };
+
+    let mut short_name = None;
+    let mut long_name = None;
+
+    for e in elements(data)? {
+        match e.tag {
+            TAG_SHORT_NAME => short_name = decode_text(e.data)?,
+            TAG_MEDIUM_NAME => programme.name = decode_text(e.data)?,
+            TAG_LONG_NAME => long_name = decode_text(e.data)?,
+            TAG_LOCATION => {
+                for loc in elements(e.data)? {
+                    if loc.tag != TAG_TIME || programme.start.is_some() {
+                        continue;
+                    }
+                    for attr in elements(loc.data)? {
+                        match attr.tag {
+                            ATTR_TIME_TIME => programme.start = decode_time(attr.data),
+                            ATTR_TIME_DURATION if attr.data.len() >= 2 => {
+                                programme.duration =
+                                    Some(u16::from_be_bytes([attr.data[0], attr.data[1]]) as u32);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // prefer medium name, fall back to long / short
+    if programme.name.is_none() {
+        programme.name = long_name.or(short_name);
+    }
+
+    Ok(programme)
+}
+
+fn decode_service_scope(data: &[u8]) -> Result<Option<String>, EpgError> {
+    for e in elements(data)? {
+        if e.tag != TAG_SERVICE_SCOPE {
+            continue;
+        }
+        for attr in elements(e.data)? {
+            if attr.tag == ATTR_SERVICE_SCOPE_ID {
+                return Ok(Some(String::from_utf8_lossy(attr.data).to_string()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// decode a binary PI (programme information) object: epg > schedule > programme
+pub fn decode_schedule(data: &[u8]) -> Result<ProgrammeSchedule, EpgError> {
+    let mut schedule = ProgrammeSchedule {
+        service: None,
+        entries: Vec::new(),
+    };
+
+    for top in elements(data)? {
+        if top.tag != TAG_EPG {
+            return Err(EpgError::UnexpectedElement { tag: top.tag });
+        }
+
+        for e in elements(top.data)? {
+            if e.tag != TAG_SCHEDULE {
+                continue;
+            }
+
+            for child in elements(e.data)? {
+                match child.tag {
+                    TAG_SCOPE => {
+                        if schedule.service.is_none() {
+                            schedule.service = decode_service_scope(child.data)?;
+                        }
+                    }
+                    TAG_PROGRAMME => schedule.entries.push(decode_programme(child.data)?),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn el(tag: u8, children: &[Vec<u8>]) -> Vec<u8> {
+        let data = children.concat();
+        let mut out = vec![tag, data.len() as u8];
+        out.extend(data);
+        out
+    }
+
+    fn cdata(text: &str) -> Vec<u8> {
+        el(TAG_CDATA, &[text.as_bytes().to_vec()])
+    }
+
+    // MJD, hours, minutes, LTO flag / UTC flag clear: short form, optional LTO byte
+    fn time(mjd: u32, hours: u8, minutes: u8, lto: Option<u8>) -> Vec<u8> {
+        let mut out = vec![
+            (mjd >> 10) as u8 & 0x7F,
+            (mjd >> 2) as u8,
+            ((mjd & 0x03) as u8) << 6 | (lto.is_some() as u8) << 4 | hours >> 2,
+            (hours & 0x03) << 6 | minutes,
+        ];
+        out.extend(lto);
+        out
+    }
+
+    fn programme(name_tag: u8, name: &str, start: Vec<u8>, duration: u16) -> Vec<u8> {
+        el(
+            TAG_PROGRAMME,
+            &[
+                el(name_tag, &[cdata(name)]),
+                el(
+                    TAG_LOCATION,
+                    &[el(
+                        TAG_TIME,
+                        &[
+                            el(ATTR_TIME_TIME, &[start]),
+                            el(ATTR_TIME_DURATION, &[duration.to_be_bytes().to_vec()]),
+                        ],
+                    )],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn schedule() {
+        let data = el(
+            TAG_EPG,
+            &[el(
+                TAG_SCHEDULE,
+                &[
+                    el(
+                        TAG_SCOPE,
+                        &[el(
+                            TAG_SERVICE_SCOPE,
+                            &[el(ATTR_SERVICE_SCOPE_ID, &[b"e1.c181.c221.0".to_vec()])],
+                        )],
+                    ),
+                    programme(
+                        TAG_MEDIUM_NAME,
+                        "Morning Show",
+                        time(60384, 6, 30, Some(0x02)),
+                        3600,
+                    ),
+                    programme(TAG_SHORT_NAME, "News", time(60384, 9, 0, None), 300),
+                ],
+            )],
+        );
+
+        let schedule = decode_schedule(&data).unwrap();
+        assert_eq!(schedule.service.as_deref(), Some("e1.c181.c221.0"));
+        assert_eq!(schedule.sid(), Some(0xC221));
+        assert_eq!(schedule.entries.len(), 2);
+
+        let morning = &schedule.entries[0];
+        assert_eq!(morning.name.as_deref(), Some("Morning Show"));
+        assert_eq!(morning.duration, Some(3600));
+        let start = morning.start.as_ref().unwrap();
+        assert_eq!(
+            (
+                start.year,
+                start.month,
+                start.day,
+                start.hours,
+                start.minutes
+            ),
+            (2024, 3, 15, 6, 30)
+        );
+        assert_eq!(start.lto, Some(2));
+
+        // short name as fallback
+        let news = &schedule.entries[1];
+        assert_eq!(news.name.as_deref(), Some("News"));
+        assert_eq!(news.duration, Some(300));
+        assert_eq!(news.start.as_ref().unwrap().hours, 9);
+        assert_eq!(news.start.as_ref().unwrap().lto, None);
+    }
+
+    // 16 bit length
+    fn cdata_long(text: &str) -> Vec<u8> {
+        let mut out = vec![TAG_CDATA, 0xFE];
+        out.extend((text.len() as u16).to_be_bytes());
+        out.extend(text.as_bytes());
+        out
+    }
+
+    #[test]
+    fn long_element_length() {
+        let name = "x".repeat(300);
+        let cdata = cdata_long(&name);
+        let mut name_el = vec![TAG_LONG_NAME, 0xFE];
+        name_el.extend((cdata.len() as u16).to_be_bytes());
+        name_el.extend(cdata);
+
+        let elements = elements(&name_el).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(decode_text(elements[0].data).unwrap(), Some(name));
+    }
+
+    #[test]
+    fn invalid() {
+        // length beyond the data
+        assert!(matches!(
+            decode_schedule(&[TAG_EPG, 0x10, 0x21]),
+            Err(EpgError::InvalidSize { .. })
+        ));
+        assert!(matches!(
+            decode_schedule(&[TAG_EPG, 0xFE, 0x01]),
+            Err(EpgError::InvalidSize { .. })
+        ));
+        assert!(matches!(
+            decode_schedule(&el(TAG_SCHEDULE, &[])),
+            Err(EpgError::UnexpectedElement { tag: TAG_SCHEDULE })
+        ));
+
+        // empty schedule, no scope
+        let schedule = decode_schedule(&el(TAG_EPG, &[el(TAG_SCHEDULE, &[])])).unwrap();
+        assert!(schedule.entries.is_empty());
+        assert_eq!(schedule.sid(), None);
+    }
+
+    #[test]
+    fn schedule_sid() {
+        let sid = |service: &str| {
+            ProgrammeSchedule {
+                service: Some(service.to_string()),
+                entries: Vec::new(),
+            }
+            .sid()
+        };
+
+        assert_eq!(sid("e1.c181.c221.0"), Some(0xC221));
+        assert_eq!(sid("dab:de0.10bc.d210.0"), Some(0xD210));
+        // 32 bit data service ids, malformed
+        assert_eq!(sid("e1.c181.e1c22100.0"), None);
+        assert_eq!(sid("e1.c181"), None);
+        assert_eq!(sid(""), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::dab::utils::decode_chars;
+use crate::dab::utils::{date_to_mjd, decode_chars, mjd_to_date};
 use crate::utils;
 use serde::Serialize;
 use thiserror::Error;
@@ -77,6 +77,10 @@ impl Fig0_1 {
             let start = ((data[offset] & 0x03) as usize) << 8 | data[offset + 1] as usize;
             offset += 2;
 
+            if offset >= data.len() {
+                return Err(FigError::InvalidSize { l: data.len() });
+            }
+
             let mut size = None;
             let mut pl = None;
             let mut bitrate = None;
@@ -105,18 +109,24 @@ impl Fig0_1 {
                         pl = Some(format!("EEP {}-B", pl_index + 1));
                         bitrate = Some(subch_size / EEP_B_SIZE_FACTORS[pl_index as usize] * 32);
                     }
-                    _ => {}
+                    _ => {
+                        // options 2..7 are reserved, the size in CUs is valid anyway
+                        size = Some(subch_size);
+                        log::debug!("FIG0/1: SubCh {} - reserved EEP option {}", id, option);
+                    }
                 }
             } else {
                 // short form
                 let table_switch = data.get(offset).map(|&b| b & 0x40 != 0).unwrap_or(false);
+                let table_index = (data[offset] & 0x3F) as usize;
                 if !table_switch {
-                    let table_index = (data[offset] & 0x3F) as usize;
-                    if table_index < UEP_SIZES.len() {
-                        size = Some(UEP_SIZES[table_index]);
-                        pl = Some(format!("UEP {}", UEP_PLS[table_index]));
-                        bitrate = Some(UEP_BITRATES[table_index]);
-                    }
+                    // the UEP table covers all 64 indices
+                    size = Some(UEP_SIZES[table_index]);
+                    pl = Some(format!("UEP {}", UEP_PLS[table_index]));
+                    bitrate = Some(UEP_BITRATES[table_index]);
+                } else {
+                    // table switch 1 is reserved, there is no table to look up the index in
+                    log::debug!("FIG0/1: SubCh {} - reserved UEP table {}", id, table_index);
                 }
                 offset += 1;
             }
@@ -151,7 +161,11 @@ pub struct Fig0_2 {
 pub struct ServiceComponent {
     pub sid: u16,
     pub tmid: u8,
+    // TMId 0 (audio stream): ASCTy, 0 = DAB (MP2), 63 = DAB+
+    pub ascty: u8,
     pub scid: u8,
+    // TMId 3 (packet mode): 12 bit service component id, see FIG 0/3
+    pub packet_scid: Option<u16>,
     pub primary: bool,
     pub ca: bool,
 }
@@ -162,10 +176,13 @@ impl Fig0_2 {
         let mut offset = 0;
         let mut services = Vec::new();
 
-        while offset + 2 <= data.len() {
-            // service ID (SID) - first two bytes
+        // P/D flag: 32 bit SId for data services
+        let sid_len = if base.pd { 4 } else { 2 };
+
+        while offset + sid_len <= data.len() {
+            // service ID (SID) - first two bytes. 32 bit SIds are not supported (yet), skipped below.
             let sid = u16::from_be_bytes([data[offset], data[offset + 1]]);
-            offset += 2;
+            offset += sid_len;
 
             // check remaining bytes
             if offset >= data.len() {
@@ -181,22 +198,23 @@ impl Fig0_2 {
                 }
 
                 let tmid = (data[offset] & 0xC0) >> 6; // transport Mechanism ID
-                let _ascty = data[offset] & 0x3F; // audio Service Type (ignored)
+                let ascty = data[offset] & 0x3F; // audio service component type
                 let scid = data[offset + 1] >> 2; // subchannel ID
+                let packet_scid = (tmid == 3).then(|| {
+                    (((data[offset] & 0x3F) as u16) << 6) | ((data[offset + 1] >> 2) as u16)
+                });
                 let primary = (data[offset + 1] & 0x02) != 0; // primary component flag
                 let ca = (data[offset + 1] & 0x01) != 0; // conditional Access flag
                 offset += 2;
 
-                // astci  0: DAB
-                // ascti 63: DAB+
-                // log::debug!("ASCTI: {}", ascty);
-
                 // ignore CA (Conditional Access) components
-                if !ca {
+                if !ca && !base.pd {
                     services.push(ServiceComponent {
                         sid,
                         tmid,
+                        ascty,
                         scid,
+                        packet_scid,
                         primary,
                         ca,
                     });
@@ -214,6 +232,11 @@ impl Fig0_2 {
 #[derive(Debug, Serialize)]
 pub struct Fig0_3 {
     base: Fig0,
+    pub components: Vec<PacketComponent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PacketComponent {
     pub scid: u16,           // 12 bits
     pub rfa: u8,             // 3 bits
     pub scca_flag: bool,     // 1 bit
@@ -226,7 +249,23 @@ pub struct Fig0_3 {
 }
 
 impl Fig0_3 {
+    // FIG 0/3 - Service component in packet mode
     pub fn from_bytes(base: Fig0, data: &[u8]) -> Result<Self, FigError> {
+        let mut offset = 0;
+        let mut components = Vec::new();
+
+        while offset < data.len() {
+            let component = PacketComponent::from_bytes(&data[offset..])?;
+            offset += if component.scca_flag { 7 } else { 5 };
+            components.push(component);
+        }
+
+        Ok(Self { base, components })
+    }
+}
+
+impl PacketComponent {
+    fn from_bytes(data: &[u8]) -> Result<Self, FigError> {
         if data.len() < 5 {
             return Err(FigError::InvalidSize { l: data.len() });
         }
@@ -258,7 +297,6 @@ impl Fig0_3 {
         };
 
         Ok(Self {
-            base,
             scid,
             rfa,
             scca_flag,
@@ -325,12 +363,119 @@ impl Fig0_5 {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct Fig0_6 {
+    base: Fig0,
+    pub linkage_sets: Vec<LinkageSet>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkageSet {
+    pub lsn: u16,
+    pub active: bool,
+    pub hard: bool,
+    pub international: bool,
+    // Id list qualifier, see EN 300 401 table 21
+    pub idlq: Option<u8>,
+    // None: CEI (change event indication) without Id list
+    pub ids: Option<Vec<LinkedId>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkedId {
+    pub ecc: Option<u8>,
+    pub id: u32,
+}
+
+impl Fig0_6 {
+    // FIG 0/6 - Service linking information (SI)
+    pub fn from_bytes(base: Fig0, data: &[u8]) -> Result<Self, FigError> {
+        let mut linkage_sets = Vec::new();
+        let mut offset = 0;
+
+        while offset + 2 <= data.len() {
+            let id_list_flag = (data[offset] & 0x80) != 0;
+            let active = (data[offset] & 0x40) != 0;
+            let hard = (data[offset] & 0x20) != 0;
+            let international = (data[offset] & 0x10) != 0;
+            let lsn = ((data[offset] & 0x0F) as u16) << 8 | data[offset + 1] as u16;
+            offset += 2;
+
+            if !id_list_flag {
+                linkage_sets.push(LinkageSet {
+                    lsn,
+                    active,
+                    hard,
+                    international,
+                    idlq: None,
+                    ids: None,
+                });
+                continue;
+            }
+
+            if offset >= data.len() {
+                return Err(FigError::InvalidSize { l: data.len() });
+            }
+
+            let idlq = (data[offset] & 0x60) >> 5;
+            let num_ids = (data[offset] & 0x0F) as usize;
+            offset += 1;
+
+            // P/D = 1: 32-bit SIds, ILS = 1: ECC + 16-bit Id, else 16-bit Ids
+            let id_len = match (base.pd, international) {
+                (true, _) => 4,
+                (false, true) => 3,
+                (false, false) => 2,
+            };
+
+            if offset + num_ids * id_len > data.len() {
+                return Err(FigError::InvalidSize { l: data.len() });
+            }
+
+            let mut ids = Vec::with_capacity(num_ids);
+
+            for chunk in data[offset..offset + num_ids * id_len].chunks(id_len) {
+                ids.push(match id_len {
+                    4 => LinkedId {
+                        ecc: None,
+                        id: u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                    },
+                    3 => LinkedId {
+                        ecc: Some(chunk[0]),
+                        id: u16::from_be_bytes([chunk[1], chunk[2]]) as u32,
+                    },
+                    _ => LinkedId {
+                        ecc: None,
+                        id: u16::from_be_bytes([chunk[0], chunk[1]]) as u32,
+                    },
+                });
+            }
+
+            offset += num_ids * id_len;
+
+            linkage_sets.push(LinkageSet {
+                lsn,
+                active,
+                hard,
+                international,
+                idlq: Some(idlq),
+                ids: Some(ids),
+            });
+        }
+
+        Ok(Self { base, linkage_sets })
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Fig0_9 {
     base: Fig0,
-    lto: i32,
-    ecc: u8,
-    int_table_id: u8,
+    // local time offset in half hours
+    pub lto: i8,
+    pub ecc: u8,
+    pub int_table_id: u8,
+    // (sid, ecc) for services whose ECC differs from the ensemble ECC
+    pub service_ecc: Vec<(u16, u8)>,
 }
 
 impl Fig0_9 {
@@ -348,7 +493,7 @@ impl Fig0_9 {
 
         let lto_half_hours = lto_raw & 0x1F;
 
-        let lto = lto_sign * (lto_half_hours as i32) / 2;
+        let lto = lto_sign * lto_half_hours as i8;
         let ecc = data[1];
         let int_table_id = data[2];
 
@@ -358,32 +503,29 @@ impl Fig0_9 {
         Extended field: this n × 8-bit field shall contain one or more sub-fields, which define those services for which their
         ECC differs from that of the ensemble.
         */
+        let mut service_ecc = Vec::new();
+
         if ext_flag {
             let mut idx = 3;
             while idx + 3 <= data.len() {
                 let byte = data[idx];
                 let num_services = byte >> 6;
-                let _ecc = data
+                let service_ecc_value = data
                     .get(idx + 1)
                     .copied()
                     .ok_or(FigError::InvalidSize { l: data.len() })?;
 
-                let mut sids = Vec::new();
                 for i in 0..num_services {
                     let sid_offset = idx + 2 + (i as usize) * 2;
                     if sid_offset + 1 >= data.len() {
                         return Err(FigError::InvalidSize { l: data.len() });
                     }
                     let sid = u16::from_be_bytes([data[sid_offset], data[sid_offset + 1]]);
-                    sids.push(sid);
+                    service_ecc.push((sid, service_ecc_value));
                 }
 
                 let size = 2 + (num_services as usize) * 2;
                 idx += size;
-
-                // log::debug!("FIG0/9: Extended subfield: ecc: {} sids: {:?}", ecc, sids);
-
-                // TODO: we somehow want to store this ;)
             }
         }
 
@@ -394,6 +536,7 @@ impl Fig0_9 {
             lto,
             ecc,
             int_table_id,
+            service_ecc,
         })
     }
 }
@@ -407,7 +550,7 @@ pub struct Fig0_10 {
     pub utc: DateTimeUTC,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum DateTimeUTC {
     Short {
         year: i32,
@@ -427,6 +570,50 @@ pub enum DateTimeUTC {
     },
 }
 
+impl DateTimeUTC {
+    // (year, month, day, hours, minutes, seconds), seconds are 0 in the short form
+    pub fn fields(&self) -> (i32, u8, u8, u8, u8, u8) {
+        match *self {
+            DateTimeUTC::Short {
+                year,
+                month,
+                day,
+                hours,
+                minutes,
+            } => (year, month, day, hours, minutes, 0),
+            DateTimeUTC::Long {
+                year,
+                month,
+                day,
+                hours,
+                minutes,
+                seconds,
+                ..
+            } => (year, month, day, hours, minutes, seconds),
+        }
+    }
+
+    // same fields shifted by the FIG 0/9 local time offset (half hours), the date rolls over
+    pub fn to_local(&self, lto: i8) -> (i32, u8, u8, u8, u8, u8) {
+        let (year, month, day, hours, minutes, seconds) = self.fields();
+
+        let total = hours as i32 * 60 + minutes as i32 + lto as i32 * 30;
+        let mjd = date_to_mjd(year, month, day) as i32 + total.div_euclid(24 * 60);
+        let total = total.rem_euclid(24 * 60);
+
+        let (year, month, day) = mjd_to_date(mjd as u32);
+
+        (
+            year,
+            month,
+            day,
+            (total / 60) as u8,
+            (total % 60) as u8,
+            seconds,
+        )
+    }
+}
+
 impl Fig0_10 {
     // FIG 0/10 - Date & time (SI)
     pub fn from_bytes(base: Fig0, data: &[u8]) -> Result<Self, FigError> {
@@ -440,15 +627,7 @@ impl Fig0_10 {
         let mjd =
             (((data[0] & 0x7F) as u32) << 10) | ((data[1] as u32) << 2) | ((data[2] as u32) >> 6);
 
-        // inline MJD > Gregorian date conversion. consider moving this to a another place..
-        let mjd_f = mjd as f64;
-        let y0 = ((mjd_f - 15078.2) / 365.25).floor();
-        let m0 = ((mjd_f - 14956.1 - (y0 * 365.25).floor()) / 30.6001).floor();
-        let d = (mjd_f - 14956.0 - (y0 * 365.25).floor() - (m0 * 30.6001).floor()) as u8;
-        let k = if m0 == 14.0 || m0 == 15.0 { 1.0 } else { 0.0 };
-        let year = (y0 + k) as i32 + 1900;
-        let month = (m0 - 1.0 - k * 12.0) as u8;
-        let day = d;
+        let (year, month, day) = mjd_to_date(mjd);
 
         let lsi = ((data[2] >> 5) & 0x01) != 0;
         let utc_flag = ((data[2] >> 3) & 0x01) != 0;
@@ -477,19 +656,9 @@ impl Fig0_10 {
                 milliseconds: millisecond,
             }
         } else {
-            if data.len() < 6 {
-                log::warn!(
-                    "FIG0/10: Invalid size for short form UTC: {} bytes",
-                    data.len()
-                );
-                return Err(FigError::InvalidSize { l: data.len() });
-            }
-
-            let b4 = data[4];
-            let b5 = data[5];
-
-            let hour = (b4 >> 3) & 0x1F;
-            let minute = ((b4 & 0x07) << 3) | (b5 >> 5);
+            // short form: hours (5) and minutes (6) only, fits the 4 bytes
+            let hour = ((data[2] & 0x07) << 2) | (data[3] >> 6);
+            let minute = data[3] & 0x3F;
 
             DateTimeUTC::Short {
                 year,
@@ -579,6 +748,102 @@ impl Fig0_13 {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct Fig0_18 {
+    base: Fig0,
+    pub services: Vec<AnnouncementSupport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnouncementSupport {
+    pub sid: u16,
+    pub asu: u16,
+    pub clusters: Vec<u8>,
+}
+
+impl Fig0_18 {
+    // FIG 0/18 - Announcement support (SI)
+    pub fn from_bytes(base: Fig0, data: &[u8]) -> Result<Self, FigError> {
+        let mut services = Vec::new();
+        let mut offset = 0;
+
+        while offset + 5 <= data.len() {
+            let sid = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let asu = u16::from_be_bytes([data[offset + 2], data[offset + 3]]);
+            let num_clusters = (data[offset + 4] & 0x1F) as usize;
+            offset += 5;
+
+            if offset + num_clusters > data.len() {
+                return Err(FigError::InvalidSize { l: data.len() });
+            }
+
+            let clusters = data[offset..offset + num_clusters].to_vec();
+            offset += num_clusters;
+
+            services.push(AnnouncementSupport { sid, asu, clusters });
+        }
+
+        Ok(Self { base, services })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Fig0_19 {
+    base: Fig0,
+    pub announcements: Vec<AnnouncementSwitching>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnouncementSwitching {
+    pub cluster: u8,
+    // 0: no announcement active in this cluster
+    pub asw: u16,
+    pub new_flag: bool,
+    pub subchid: u8,
+    pub region: Option<u8>,
+}
+
+impl Fig0_19 {
+    // FIG 0/19 - Announcement switching (SI)
+    pub fn from_bytes(base: Fig0, data: &[u8]) -> Result<Self, FigError> {
+        let mut announcements = Vec::new();
+        let mut offset = 0;
+
+        while offset + 4 <= data.len() {
+            let cluster = data[offset];
+            let asw = u16::from_be_bytes([data[offset + 1], data[offset + 2]]);
+            let new_flag = (data[offset + 3] & 0x80) != 0;
+            let region_flag = (data[offset + 3] & 0x40) != 0;
+            let subchid = data[offset + 3] & 0x3F;
+            offset += 4;
+
+            let region = if region_flag {
+                if offset >= data.len() {
+                    return Err(FigError::InvalidSize { l: data.len() });
+                }
+                let region = data[offset] & 0x3F;
+                offset += 1;
+                Some(region)
+            } else {
+                None
+            };
+
+            announcements.push(AnnouncementSwitching {
+                cluster,
+                asw,
+                new_flag,
+                subchid,
+                region,
+            });
+        }
+
+        Ok(Self {
+            base,
+            announcements,
+        })
+    }
+}
+
 // FIG 1s
 #[derive(Debug, Serialize)]
 pub struct Fig1_0 {
@@ -589,7 +854,7 @@ pub struct Fig1_0 {
 }
 impl Fig1_0 {
     pub fn from_bytes(base: Fig1, data: &[u8]) -> Result<Self, FigError> {
-        if data.len() < 18 {
+        if data.len() < 20 {
             return Err(FigError::InvalidSize { l: data.len() });
         }
 
@@ -631,7 +896,7 @@ pub struct Fig1_1 {
 
 impl Fig1_1 {
     pub fn from_bytes(base: Fig1, data: &[u8]) -> Result<Self, FigError> {
-        if data.len() < 18 {
+        if data.len() < 20 {
             return Err(FigError::InvalidSize { l: data.len() });
         }
 
@@ -682,9 +947,12 @@ pub enum Fig {
     F0_2(Fig0_2),
     F0_3(Fig0_3),
     F0_5(Fig0_5),
+    F0_6(Fig0_6),
     F0_9(Fig0_9),
     F0_10(Fig0_10),
     F0_13(Fig0_13),
+    F0_18(Fig0_18),
+    F0_19(Fig0_19),
     //
     F1_0(Fig1_0),
     F1_1(Fig1_1),
@@ -716,29 +984,67 @@ pub enum FicError {
 pub struct FicDecoder {
     #[allow(dead_code)]
     eid: Option<String>,
+    // skip FIBs with CRC mismatch. lenient mode parses them anyway
+    pub strict_crc: bool,
+}
+
+impl Default for FicDecoder {
+    fn default() -> Self {
+        Self::new(true)
+    }
 }
 
 impl FicDecoder {
+    pub fn new(strict_crc: bool) -> Self {
+        Self {
+            eid: None,
+            strict_crc,
+        }
+    }
+
     pub fn from_bytes(data: &[u8]) -> Result<Vec<Fig>, FicError> {
+        Self::default().decode(data)
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<Fig>, FicError> {
+        self.decode_counted(data).map(|(figs, _)| figs)
+    }
+
+    // same as decode(), also returns the number of FIBs with CRC mismatch
+    #[tracing::instrument(level = "trace", name = "fic", skip_all, fields(fibs = data.len() / 32))]
+    pub fn decode_counted(&self, data: &[u8]) -> Result<(Vec<Fig>, usize), FicError> {
         if (data.len() % 32) != 0 {
             return Err(FicError::SizeInvalid { l: data.len() });
         }
 
         let mut figs: Vec<Fig> = Vec::new();
+        let mut crc_errors = 0;
 
         for chunk in data.chunks(32) {
-            figs.extend(Self::decode_fib(chunk)?);
+            let crc_ok = Self::fib_crc_ok(chunk);
+            if !crc_ok {
+                crc_errors += 1;
+            }
+            figs.extend(self.decode_fib(chunk, crc_ok)?);
         }
 
-        Ok(figs)
+        Ok((figs, crc_errors))
     }
 
-    fn decode_fib(data: &[u8]) -> Result<Vec<Fig>, FicError> {
+    fn fib_crc_ok(data: &[u8]) -> bool {
         let crc_stored = u16::from_be_bytes([data[30], data[31]]);
         let crc_calculated = utils::calc_crc16_ccitt(&data[..30]);
 
-        if crc_stored != crc_calculated {
-            log::warn!("FicDecoder: Discarding FIB due to CRC mismatch");
+        crc_stored == crc_calculated
+    }
+
+    fn decode_fib(&self, data: &[u8], crc_ok: bool) -> Result<Vec<Fig>, FicError> {
+        if !crc_ok {
+            if self.strict_crc {
+                log::warn!("FicDecoder: Discarding FIB due to CRC mismatch");
+                return Ok(Vec::new());
+            }
+            log::warn!("FicDecoder: FIB CRC mismatch, decoding anyway");
         }
 
         let mut figs: Vec<Fig> = Vec::new();
@@ -751,6 +1057,11 @@ impl FicDecoder {
 
             offset += 1;
 
+            if offset + fig_length > 30 {
+                log::warn!("FicDecoder: FIG length {} exceeds FIB", fig_length);
+                break;
+            }
+
             // primary type: 0 / 1
             match fig_type {
                 0 => {
@@ -800,9 +1111,12 @@ impl FicDecoder {
             2 => Ok(Fig::F0_2(Fig0_2::from_bytes(base, &data[1..])?)),
             3 => Ok(Fig::F0_3(Fig0_3::from_bytes(base, &data[1..])?)),
             5 => Ok(Fig::F0_5(Fig0_5::from_bytes(base, &data[1..])?)),
+            6 => Ok(Fig::F0_6(Fig0_6::from_bytes(base, &data[1..])?)),
             9 => Ok(Fig::F0_9(Fig0_9::from_bytes(base, &data[1..])?)),
             10 => Ok(Fig::F0_10(Fig0_10::from_bytes(base, &data[1..])?)),
             13 => Ok(Fig::F0_13(Fig0_13::from_bytes(base, &data[1..])?)),
+            18 => Ok(Fig::F0_18(Fig0_18::from_bytes(base, &data[1..])?)),
+            19 => Ok(Fig::F0_19(Fig0_19::from_bytes(base, &data[1..])?)),
             _ => Err(FigError::Unsupported { kind: ext }),
         }
     }
@@ -851,3 +1165,424 @@ const UEP_BITRATES: [usize; 64] = [
 
 const EEP_A_SIZE_FACTORS: [usize; 4] = [12, 8, 6, 4];
 const EEP_B_SIZE_FACTORS: [usize; 4] = [27, 21, 18, 15];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FIB with the given FIGs, padded with 0xFF, incl. CRC
+    fn fib(figs: &[u8]) -> Vec<u8> {
+        let mut fib = figs.to_vec();
+        fib.resize(30, 0xFF);
+        let crc = utils::calc_crc16_ccitt(&fib);
+        fib.extend_from_slice(&crc.to_be_bytes());
+        fib
+    }
+
+    // FIG 0/0, EId 0x4FFF
+    const FIG0_0: [u8; 6] = [0x05, 0x00, 0x4F, 0xFF, 0x00, 0x00];
+
+    fn corrupted_fib() -> Vec<u8> {
+        let mut data = fib(&FIG0_0);
+        data[31] ^= 0x01;
+        data
+    }
+
+    fn fig0(ext: u8) -> Fig0 {
+        Fig0 {
+            cn: false,
+            oe: false,
+            pd: false,
+            ext,
+        }
+    }
+
+    // FIG 0/10 data: Rfu (1), MJD (17), LSI, ConfInd, UTC flag, hours (5), minutes (6)
+    // and with the UTC flag seconds (6), milliseconds (10)
+    fn fig0_10(mjd: u32, hours: u8, minutes: u8, long: Option<(u8, u16)>) -> Vec<u8> {
+        let utc_flag = long.is_some() as u32;
+        let bits = mjd << 14 | utc_flag << 11 | (hours as u32) << 6 | minutes as u32;
+
+        let mut data = bits.to_be_bytes().to_vec();
+        if let Some((seconds, milliseconds)) = long {
+            data.push(seconds << 2 | (milliseconds >> 8) as u8);
+            data.push(milliseconds as u8);
+        }
+        data
+    }
+
+    #[test]
+    fn fig0_10_short_form() {
+        let fig = Fig0_10::from_bytes(fig0(10), &fig0_10(60384, 13, 45, None)).unwrap();
+
+        assert_eq!(fig.mjd, 60384);
+        assert!(!fig.utc_flag);
+        assert_eq!(
+            fig.utc,
+            DateTimeUTC::Short {
+                year: 2024,
+                month: 3,
+                day: 15,
+                hours: 13,
+                minutes: 45,
+            }
+        );
+    }
+
+    #[test]
+    fn fig0_10_long_form() {
+        let data = fig0_10(60384, 23, 59, Some((58, 999)));
+        let fig = Fig0_10::from_bytes(fig0(10), &data).unwrap();
+
+        assert!(fig.utc_flag);
+        assert_eq!(
+            fig.utc,
+            DateTimeUTC::Long {
+                year: 2024,
+                month: 3,
+                day: 15,
+                hours: 23,
+                minutes: 59,
+                seconds: 58,
+                milliseconds: 999,
+            }
+        );
+
+        // UTC flag set, but only the short form bytes
+        assert!(Fig0_10::from_bytes(fig0(10), &data[..4]).is_err());
+    }
+
+    #[test]
+    fn fig0_9_lto_sign() {
+        let lto = |data: &[u8]| Fig0_9::from_bytes(fig0(9), data).unwrap().lto;
+
+        assert_eq!(lto(&[0x02, 0xE0, 0x00]), 2);
+        assert_eq!(lto(&[0x2B, 0xE0, 0x00]), -11);
+    }
+
+    #[test]
+    fn fig0_9_extended_field() {
+        // ensemble ECC 0xE0, SIds 0xD221 and 0xD222 in ECC 0xE1, SId 0xC223 in ECC 0xE2
+        let data = [
+            0x82, 0xE0, 0x00, 0x80, 0xE1, 0xD2, 0x21, 0xD2, 0x22, 0x40, 0xE2, 0xC2, 0x23,
+        ];
+        let fig = Fig0_9::from_bytes(fig0(9), &data).unwrap();
+
+        assert_eq!((fig.lto, fig.ecc, fig.int_table_id), (2, 0xE0, 0x00));
+        assert_eq!(
+            fig.service_ecc,
+            [(0xD221, 0xE1), (0xD222, 0xE1), (0xC223, 0xE2)]
+        );
+
+        // second SId cut off
+        assert!(Fig0_9::from_bytes(fig0(9), &data[..8]).is_err());
+
+        // without the extended flag the sub-fields are ignored
+        let mut data = data;
+        data[0] &= 0x7F;
+        assert!(Fig0_9::from_bytes(fig0(9), &data)
+            .unwrap()
+            .service_ecc
+            .is_empty());
+    }
+
+    #[test]
+    fn to_local_lto() {
+        let utc = DateTimeUTC::Short {
+            year: 2024,
+            month: 12,
+            day: 31,
+            hours: 23,
+            minutes: 30,
+        };
+        assert_eq!(utc.to_local(0), (2024, 12, 31, 23, 30, 0));
+        // +1h: into the next year
+        assert_eq!(utc.to_local(2), (2025, 1, 1, 0, 30, 0));
+
+        let utc = DateTimeUTC::Long {
+            year: 2024,
+            month: 3,
+            day: 1,
+            hours: 2,
+            minutes: 15,
+            seconds: 7,
+            milliseconds: 0,
+        };
+        // -5:30h: back to the leap day
+        assert_eq!(utc.to_local(-11), (2024, 2, 29, 20, 45, 7));
+    }
+
+    // FIG 0/2 for SId 0xC221: DAB+ audio in SubCh 1 (primary) and packet mode SCId 0x123
+    const FIG0_2: [u8; 7] = [0xC2, 0x21, 0x02, 0x3F, 0x06, 0xC4, 0x8C];
+
+    #[test]
+    fn fig0_2_components() {
+        let fig = Fig0_2::from_bytes(fig0(2), &FIG0_2).unwrap();
+
+        assert_eq!(fig.services.len(), 2);
+
+        let audio = &fig.services[0];
+        assert_eq!(audio.sid, 0xC221);
+        assert_eq!((audio.tmid, audio.ascty, audio.scid), (0, 63, 1));
+        assert_eq!(audio.packet_scid, None);
+        assert!(audio.primary);
+
+        let data = &fig.services[1];
+        assert_eq!(data.sid, 0xC221);
+        assert_eq!(data.tmid, 3);
+        assert_eq!(data.packet_scid, Some(0x123));
+        assert!(!data.primary);
+    }
+
+    #[test]
+    fn fig0_2_skipped() {
+        // conditional access
+        let mut data = FIG0_2;
+        data[6] |= 0x01;
+        let fig = Fig0_2::from_bytes(fig0(2), &data).unwrap();
+        assert_eq!(fig.services.len(), 1);
+
+        // 32 bit SId: consumed, not decoded
+        let mut base = fig0(2);
+        base.pd = true;
+        let data = [0xE1, 0xC2, 0x21, 0x00, 0x01, 0xC4, 0x8C];
+        let fig = Fig0_2::from_bytes(base, &data).unwrap();
+        assert!(fig.services.is_empty());
+
+        // component cut off
+        assert!(Fig0_2::from_bytes(fig0(2), &FIG0_2[..6]).is_err());
+    }
+
+    #[test]
+    fn fig0_3_components() {
+        let data = [
+            // SCId 0x123, DSCTy 60 (MOT), SubCh 5, packet address 1000
+            0x12, 0x30, 0x3C, 0x17, 0xE8,
+            // SCId 0x124 with SCCA 0xABCD, DG flag, DSCTy 5 (TDC), SubCh 6, packet address 1
+            0x12, 0x41, 0x85, 0x18, 0x01, 0xAB, 0xCD,
+        ];
+        let fig = Fig0_3::from_bytes(fig0(3), &data).unwrap();
+
+        assert_eq!(fig.components.len(), 2);
+
+        let mot = &fig.components[0];
+        assert_eq!(mot.scid, 0x123);
+        assert!(!mot.scca_flag && !mot.dg_flag);
+        assert_eq!((mot.dscty, mot.subchid, mot.packet_address), (60, 5, 1000));
+        assert_eq!(mot.scca, None);
+
+        let tdc = &fig.components[1];
+        assert_eq!(tdc.scid, 0x124);
+        assert!(tdc.scca_flag && tdc.dg_flag);
+        assert_eq!((tdc.dscty, tdc.subchid, tdc.packet_address), (5, 6, 1));
+        assert_eq!(tdc.scca, Some(0xABCD));
+
+        // SCCA flag set, but no SCCA
+        assert!(Fig0_3::from_bytes(fig0(3), &data[..10]).is_err());
+        assert!(Fig0_3::from_bytes(fig0(3), &data[..4]).is_err());
+    }
+
+    // FIG 0/1 long form, SubCh 1 at CU 0, 84 CUs
+    fn fig0_1_long(option: u8, pl_index: u8) -> [u8; 4] {
+        [0x04, 0x00, 0x80 | option << 4 | pl_index << 2, 0x54]
+    }
+
+    #[test]
+    fn fig0_1_eep() {
+        let subchannel = |data: &[u8]| {
+            let mut fig = Fig0_1::from_bytes(fig0(1), data).unwrap();
+            assert_eq!(fig.subchannels.len(), 1);
+            let sc = fig.subchannels.remove(0);
+            (sc.size, sc.pl, sc.bitrate)
+        };
+
+        assert_eq!(
+            subchannel(&fig0_1_long(0, 2)),
+            (Some(84), Some("EEP 3-A".to_string()), Some(112))
+        );
+        assert_eq!(
+            subchannel(&fig0_1_long(1, 3)),
+            (Some(84), Some("EEP 4-B".to_string()), Some(160))
+        );
+
+        // reserved options: only the size
+        for option in 2..=7 {
+            assert_eq!(
+                subchannel(&fig0_1_long(option, 2)),
+                (Some(84), None, None),
+                "option {}",
+                option
+            );
+        }
+    }
+
+    #[test]
+    fn fig0_1_short_form() {
+        let fig = Fig0_1::from_bytes(fig0(1), &[0x08, 0x54, 0x05, 0x0C, 0xA8, 0x45]).unwrap();
+
+        // UEP table index 5
+        let sc = &fig.subchannels[0];
+        assert_eq!((sc.id, sc.start), (2, 84));
+        assert_eq!(
+            (sc.size, sc.pl.as_deref(), sc.bitrate),
+            (Some(24), Some("UEP 5"), Some(48))
+        );
+
+        // reserved table switch: no table to look up the index in
+        let sc = &fig.subchannels[1];
+        assert_eq!((sc.id, sc.start), (3, 168));
+        assert_eq!((sc.size, sc.pl.as_deref(), sc.bitrate), (None, None, None));
+    }
+
+    #[test]
+    fn fig0_6_linkage_sets() {
+        // LSN 0x123, active, soft, national: SIds 0xC221, 0xC321
+        let national = [0xC1, 0x23, 0x02, 0xC2, 0x21, 0xC3, 0x21];
+        // LSN 0x456, hard: CEI, no Id list
+        let cei = [0x64, 0x56];
+        // LSN 0x789, international: 0xD210 in ECC 0xE1
+        let international = [0x97, 0x89, 0x01, 0xE1, 0xD2, 0x10];
+        let data = [&national[..], &cei, &international].concat();
+        let fig = Fig0_6::from_bytes(fig0(6), &data).unwrap();
+
+        assert_eq!(fig.linkage_sets.len(), 3);
+
+        let national = &fig.linkage_sets[0];
+        assert_eq!(national.lsn, 0x123);
+        assert!(national.active && !national.hard && !national.international);
+        assert_eq!(national.idlq, Some(0));
+        assert_eq!(
+            national.ids,
+            Some(vec![
+                LinkedId {
+                    ecc: None,
+                    id: 0xC221
+                },
+                LinkedId {
+                    ecc: None,
+                    id: 0xC321
+                },
+            ])
+        );
+
+        let cei = &fig.linkage_sets[1];
+        assert_eq!(cei.lsn, 0x456);
+        assert!(cei.active && cei.hard);
+        assert_eq!((cei.idlq, cei.ids.as_ref()), (None, None));
+
+        let international = &fig.linkage_sets[2];
+        assert_eq!(international.lsn, 0x789);
+        assert!(!international.active && international.international);
+        assert_eq!(
+            international.ids,
+            Some(vec![LinkedId {
+                ecc: Some(0xE1),
+                id: 0xD210
+            }])
+        );
+
+        // Id list cut off, Id list qualifier missing
+        assert!(Fig0_6::from_bytes(fig0(6), &data[..6]).is_err());
+        assert!(Fig0_6::from_bytes(fig0(6), &data[..2]).is_err());
+    }
+
+    #[test]
+    fn fig0_6_32_bit_ids() {
+        let mut base = fig0(6);
+        base.pd = true;
+        let data = [0xC0, 0x01, 0x21, 0xE1, 0xC2, 0x21, 0x00];
+        let fig = Fig0_6::from_bytes(base, &data).unwrap();
+
+        assert_eq!(fig.linkage_sets[0].idlq, Some(1));
+        assert_eq!(
+            fig.linkage_sets[0].ids,
+            Some(vec![LinkedId {
+                ecc: None,
+                id: 0xE1C2_2100
+            }])
+        );
+    }
+
+    #[test]
+    fn fig0_6_from_fib() {
+        let data = fib(&[0x08, 0x06, 0xC1, 0x23, 0x02, 0xC2, 0x21, 0xC3, 0x21]);
+        let figs = FicDecoder::from_bytes(&data).unwrap();
+
+        assert!(matches!(
+            figs.as_slice(),
+            [Fig::F0_6(f)] if f.linkage_sets.len() == 1 && f.linkage_sets[0].lsn == 0x123
+        ));
+    }
+
+    #[test]
+    fn fig0_18_announcement_support() {
+        // SId 0xC221: alarm and road traffic in clusters 1 and 2, SId 0xC222: none
+        let data = [
+            0xC2, 0x21, 0x00, 0x03, 0x02, 0x01, 0x02, 0xC2, 0x22, 0x00, 0x00, 0x00,
+        ];
+        let fig = Fig0_18::from_bytes(fig0(18), &data).unwrap();
+
+        assert_eq!(fig.services.len(), 2);
+        assert_eq!(fig.services[0].sid, 0xC221);
+        assert_eq!(fig.services[0].asu, 0x0003);
+        assert_eq!(fig.services[0].clusters, [1, 2]);
+        assert_eq!(fig.services[1].sid, 0xC222);
+        assert!(fig.services[1].clusters.is_empty());
+
+        // clusters cut off
+        assert!(Fig0_18::from_bytes(fig0(18), &data[..6]).is_err());
+    }
+
+    #[test]
+    fn fig0_19_announcement_switching() {
+        // cluster 1: road traffic on SubCh 3, new. cluster 2: ended, with region 0x05
+        let data = [0x01, 0x00, 0x02, 0x83, 0x02, 0x00, 0x00, 0x44, 0x05];
+        let fig = Fig0_19::from_bytes(fig0(19), &data).unwrap();
+
+        assert_eq!(fig.announcements.len(), 2);
+
+        let traffic = &fig.announcements[0];
+        assert_eq!(
+            (traffic.cluster, traffic.asw, traffic.subchid),
+            (1, 0x0002, 3)
+        );
+        assert!(traffic.new_flag);
+        assert_eq!(traffic.region, None);
+
+        let ended = &fig.announcements[1];
+        assert_eq!((ended.cluster, ended.asw, ended.subchid), (2, 0, 4));
+        assert!(!ended.new_flag);
+        assert_eq!(ended.region, Some(0x05));
+
+        // region flag set, but no region
+        assert!(Fig0_19::from_bytes(fig0(19), &data[..8]).is_err());
+    }
+
+    #[test]
+    fn fib_crc_ok() {
+        let (figs, crc_errors) = FicDecoder::new(true).decode_counted(&fib(&FIG0_0)).unwrap();
+
+        assert_eq!(crc_errors, 0);
+        assert!(matches!(figs.as_slice(), [Fig::F0_0(f)] if f.eid == 0x4FFF));
+    }
+
+    #[test]
+    fn fib_crc_mismatch_strict() {
+        let (figs, crc_errors) = FicDecoder::new(true)
+            .decode_counted(&corrupted_fib())
+            .unwrap();
+
+        assert_eq!(crc_errors, 1);
+        assert!(figs.is_empty());
+    }
+
+    #[test]
+    fn fib_crc_mismatch_lenient() {
+        let (figs, crc_errors) = FicDecoder::new(false)
+            .decode_counted(&corrupted_fib())
+            .unwrap();
+
+        assert_eq!(crc_errors, 1);
+        assert!(matches!(figs.as_slice(), [Fig::F0_0(f)] if f.eid == 0x4FFF));
+    }
+}
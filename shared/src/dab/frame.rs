@@ -4,6 +4,9 @@ use serde::Serialize;
 use thiserror::Error;
 
 use super::fic::{FicDecoder, Fig};
+use super::utils::decode_text;
+use crate::edi_frame_extractor::DEFAULT_MAX_FRAME_SIZE;
+use crate::utils;
 
 #[derive(Debug, Error)]
 pub enum FrameDecodeError {
@@ -12,16 +15,55 @@ pub enum FrameDecodeError {
 
     #[error("Unknown frame: {kind}")]
     UnknownKind { kind: String },
+
+    #[error("CRC mismatch: stored 0x{stored:04X} != calculated 0x{calculated:04X}")]
+    CrcMismatch { stored: u16, calculated: u16 },
+
+    #[error("Unsupported AF revision: {maj}.{min}")]
+    UnsupportedRevision { maj: u8, min: u8 },
 }
 
 #[derive(Debug, Serialize)]
 pub struct FrameDecodeResult {
     pub tags: Vec<Tag>,
+    // AF protocol revision (MAJ, MIN)
+    pub revision: (u8, u8),
 }
 
 impl FrameDecodeResult {
-    pub fn new(tags: Vec<Tag>) -> Self {
-        Self { tags }
+    pub fn new(tags: Vec<Tag>, revision: (u8, u8)) -> Self {
+        Self { tags, revision }
+    }
+
+    // false if the packet uses a minor revision that is not known to be compatible
+    pub fn is_known_revision(&self) -> bool {
+        AF_ACCEPTED_REVISIONS.contains(&self.revision)
+    }
+
+    // false for management-only packets (deti, no est)
+    pub fn has_audio(&self) -> bool {
+        self.tags.iter().any(|tag| matches!(tag, Tag::Est(_)))
+    }
+}
+
+// decode options passed down to the tag parsers
+#[derive(Debug, Clone, Copy)]
+pub struct FrameOptions {
+    // drop the packet on AF CRC mismatch, otherwise the CRC is only traced
+    pub verify_af_crc: bool,
+    // discard FIBs with CRC mismatch, see FicDecoder::strict_crc
+    pub fib_strict_crc: bool,
+    // keep a copy of the raw FIC in DetiTag::fic
+    pub keep_fic: bool,
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        Self {
+            verify_af_crc: false,
+            fib_strict_crc: true,
+            keep_fic: false,
+        }
     }
 }
 
@@ -31,12 +73,33 @@ pub struct Frame {
 }
 
 impl Frame {
+    // convenience wrapper over FrameParser for a fully buffered AF packet
     pub fn from_bytes(data: &[u8]) -> Result<FrameDecodeResult, FrameDecodeError> {
+        Self::decode(data, false)
+    }
+
+    // verify_crc: drop the packet on AF CRC mismatch, otherwise the CRC is only traced
+    pub fn decode(data: &[u8], verify_crc: bool) -> Result<FrameDecodeResult, FrameDecodeError> {
+        Self::decode_with(
+            data,
+            FrameOptions {
+                verify_af_crc: verify_crc,
+                ..FrameOptions::default()
+            },
+        )
+    }
+
+    pub fn decode_with(
+        data: &[u8],
+        options: FrameOptions,
+    ) -> Result<FrameDecodeResult, FrameDecodeError> {
+        let verify_crc = options.verify_af_crc;
+
         if data.len() < 12 {
             return Err(FrameDecodeError::FrameTooShort { l: data.len() });
         }
 
-        let kind = std::str::from_utf8(&data[..2]).unwrap();
+        let kind = std::str::from_utf8(&data[..2]).unwrap_or("");
 
         if kind != "AF" {
             return Err(FrameDecodeError::UnknownKind {
@@ -44,54 +107,84 @@ impl Frame {
             });
         }
 
-        // LEN: combine bytes 2-5 into a length value.
-        let len = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        // CF (1), MAJ (3), MIN (4)
+        let maj = (data[8] >> 4) & 0x07;
+        let min = data[8] & 0x0F;
 
-        let mut tags: Vec<Tag> = Vec::new();
+        // minor revisions are backwards compatible, an unknown major is not
+        if !AF_ACCEPTED_REVISIONS.iter().any(|(m, _)| *m == maj) {
+            return Err(FrameDecodeError::UnsupportedRevision { maj, min });
+        }
 
-        let mut i = 0usize;
+        let len = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        let crc_offset = AF_HEADER_LEN + len;
 
-        // println!("Frame: kind: {}, len: {}", kind, len);
+        if data.len() >= crc_offset + AF_CRC_LEN {
+            let stored = u16::from_be_bytes([data[crc_offset], data[crc_offset + 1]]);
+            let calculated = utils::calc_crc16_ccitt(&data[..crc_offset]);
 
-        while i < len.saturating_sub(8) {
-            let start = 10 + i;
+            log::trace!(
+                "AF CRC: stored 0x{:04X} - calculated 0x{:04X}",
+                stored,
+                calculated
+            );
 
-            // avoid overflow
-            if start + 8 > data.len() {
-                break;
+            if verify_crc && stored != calculated {
+                return Err(FrameDecodeError::CrcMismatch { stored, calculated });
             }
+        } else if verify_crc {
+            return Err(FrameDecodeError::FrameTooShort { l: data.len() });
+        }
 
-            let tag_item = &data[start..];
+        let mut parser = FrameParser::with_options(options);
+        let tags = parser.feed(data);
 
-            let tag_len =
-                u32::from_be_bytes([tag_item[4], tag_item[5], tag_item[6], tag_item[7]]) as usize;
+        Ok(FrameDecodeResult::new(tags, (maj, min)))
+    }
 
-            match Self::parse_tag(tag_item) {
-                Ok(tag) => {
-                    tags.push(tag);
-                }
-                Err(_e) => {
-                    // log::debug!("Error parsing tag: {:?}", _e);
-                }
-            }
+    // tag names and value sizes in bytes of an AF packet, e.g. "deti(106) est3(1152) *ptr(8)".
+    // works on the raw packet, nothing is decoded
+    pub fn summary(data: &[u8]) -> String {
+        if data.len() < AF_HEADER_LEN || &data[..2] != b"AF" {
+            return "(no AF packet)".to_string();
+        }
+
+        let payload_len = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        let payload_end = (AF_HEADER_LEN + payload_len).min(data.len());
+
+        let mut items = Vec::new();
+        let mut pos = AF_HEADER_LEN;
+
+        while pos + 8 <= payload_end {
+            let item = &data[pos..];
+            let tag_len = u32::from_be_bytes([item[4], item[5], item[6], item[7]]) as usize;
 
-            // i += 4 + 4 + (tag_len + 7) / 8;
-            i += 4 + 4 + tag_len.div_ceil(8);
+            // the 4th byte of an EST tag name is the stream index, not a character
+            let name = if item.starts_with(b"est") {
+                format!("est{}", item[3])
+            } else {
+                String::from_utf8_lossy(&item[..4]).to_string()
+            };
+
+            items.push(format!("{}({})", name, tag_len.div_ceil(8)));
+            pos += 8 + tag_len.div_ceil(8);
         }
 
-        let result = FrameDecodeResult::new(tags);
+        if pos > payload_end {
+            items.push("(truncated)".to_string());
+        }
 
-        Ok(result)
+        items.join(" ")
     }
 
-    fn parse_tag(data: &[u8]) -> Result<Tag, TagError> {
+    fn parse_tag(data: &[u8], options: &FrameOptions) -> Result<Tag, TagError> {
         let name = std::str::from_utf8(data.get(..4).unwrap_or(&[])).unwrap_or("");
         let kind = if name.starts_with("est") { "est" } else { name };
         // let value = data[8..].to_vec();
 
         match kind {
             // tags we actually care
-            "deti" => match DetiTag::from_bytes(data) {
+            "deti" => match DetiTag::decode(data, options) {
                 Ok(tag) => Ok(Tag::Deti(tag)),
                 Err(e) => Err(e),
             },
@@ -99,6 +192,10 @@ impl Frame {
                 Ok(tag) => Ok(Tag::Est(tag)),
                 Err(e) => Err(e),
             },
+            "info" => match InfoTag::from_bytes(data) {
+                Ok(tag) => Ok(Tag::Info(tag)),
+                Err(e) => Err(e),
+            },
             // tags i guess we don't care
             "*ptr" => Ok(Tag::Ptr(PtrTag())),
             "*dmy" => Ok(Tag::Dmy(DmyTag())),
@@ -113,6 +210,140 @@ impl Frame {
     }
 }
 
+// AF header: sync (2), LEN (4), SEQ (2), AR / PT (2)
+pub(super) const AF_HEADER_LEN: usize = 10;
+// AF CRC after the payload
+pub(super) const AF_CRC_LEN: usize = 2;
+// AF revisions (MAJ, MIN) known to decode correctly
+const AF_ACCEPTED_REVISIONS: &[(u8, u8)] = &[(1, 0)];
+
+// streaming AF packet parser: accepts arbitrary chunks and yields tags as soon as they are complete,
+// so e.g. EST data can be processed before the whole AF packet is buffered.
+#[derive(Debug)]
+pub struct FrameParser {
+    options: FrameOptions,
+    // upper bound for the AF packet size announced in the header, see set_max_frame_size
+    max_frame_size: usize,
+    buf: Vec<u8>,
+    // payload length of the current AF packet, None while searching for sync
+    payload_len: Option<usize>,
+    // offset of the next tag in buf
+    pos: usize,
+}
+
+impl Default for FrameParser {
+    fn default() -> Self {
+        Self::with_options(FrameOptions::default())
+    }
+}
+
+impl FrameParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: FrameOptions) -> Self {
+        Self {
+            options,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            buf: Vec::new(),
+            payload_len: None,
+            pos: 0,
+        }
+    }
+
+    // larger packets are treated as a corrupt header: the sync is skipped and the parser resyncs
+    // on the next "AF" instead of buffering whatever LEN announces
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Tag> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut tags = Vec::new();
+
+        loop {
+            let payload_len = match self.payload_len {
+                Some(l) => l,
+                None => {
+                    // sync on "AF"
+                    match self.buf.windows(2).position(|w| w == b"AF") {
+                        Some(offset) => {
+                            self.buf.drain(..offset);
+                        }
+                        None => {
+                            // keep a trailing 'A', it could be the start of the next sync
+                            let keep = usize::from(self.buf.last() == Some(&b'A'));
+                            self.buf.drain(..self.buf.len() - keep);
+                            break;
+                        }
+                    }
+
+                    if self.buf.len() < AF_HEADER_LEN {
+                        break;
+                    }
+
+                    let l = u32::from_be_bytes([self.buf[2], self.buf[3], self.buf[4], self.buf[5]])
+                        as usize;
+
+                    let size = AF_HEADER_LEN + l + AF_CRC_LEN;
+                    if size > self.max_frame_size {
+                        log::debug!(
+                            "FrameParser: AF packet of {} bytes exceeds {} bytes - resync",
+                            size,
+                            self.max_frame_size
+                        );
+                        self.buf.drain(..1);
+                        continue;
+                    }
+
+                    self.payload_len = Some(l);
+                    self.pos = AF_HEADER_LEN;
+                    l
+                }
+            };
+
+            let payload_end = AF_HEADER_LEN + payload_len;
+
+            // tags: name (4), length in bits (4), value
+            while self.pos + 8 <= payload_end && self.pos + 8 <= self.buf.len() {
+                let item = &self.buf[self.pos..];
+                let tag_len = u32::from_be_bytes([item[4], item[5], item[6], item[7]]) as usize;
+                let tag_size = 8 + tag_len.div_ceil(8);
+
+                if self.pos + tag_size > self.buf.len() {
+                    break;
+                }
+
+                match Frame::parse_tag(&item[..tag_size], &self.options) {
+                    Ok(tag) => {
+                        tags.push(tag);
+                    }
+                    Err(_e) => {
+                        // log::debug!("Error parsing tag: {:?}", _e);
+                    }
+                }
+
+                self.pos += tag_size;
+            }
+
+            // wait for the remaining tags / CRC
+            let frame_end = payload_end + AF_CRC_LEN;
+
+            if self.pos + 8 <= payload_end || self.buf.len() < frame_end {
+                break;
+            }
+
+            self.buf.drain(..frame_end);
+            self.payload_len = None;
+            self.pos = 0;
+        }
+
+        tags
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum TagError {
     #[error("Unsupported tag: {name}")]
@@ -126,6 +357,7 @@ pub enum TagError {
 pub enum Tag {
     Deti(DetiTag),
     Est(EstTag),
+    Info(InfoTag),
     Ptr(PtrTag),
     Dmy(DmyTag),
     Fsst(FsstTag),
@@ -145,12 +377,22 @@ pub struct DmyTag();
 pub struct DetiTag {
     // DAB ETI(LI) Management
     pub atstf: Vec<u8>,
+    // raw FIC (FIBs incl. CRC), 96 bytes - 128 in mode III. empty without FICF or
+    // FrameOptions::keep_fic
+    #[debug(skip)]
+    pub fic: Vec<u8>,
     pub figs: Vec<Fig>,
     pub rfudf: Vec<u8>,
+    // FIBs with CRC mismatch
+    pub fib_crc_errors: usize,
 }
 
 impl DetiTag {
     pub fn from_bytes(data: &[u8]) -> Result<Self, TagError> {
+        Self::decode(data, &FrameOptions::default())
+    }
+
+    pub fn decode(data: &[u8], options: &FrameOptions) -> Result<Self, TagError> {
         if data.len() < 8 {
             return Err(TagError::InvalidSize { l: data.len() });
         }
@@ -159,6 +401,10 @@ impl DetiTag {
         let _header = data[0..8].to_vec();
         let value = data[8..].to_vec();
 
+        if value.len() < 4 {
+            return Err(TagError::InvalidSize { l: data.len() });
+        }
+
         let has_atstf = (value[0] & 0x80) != 0;
         let has_ficf = (value[0] & 0x40) != 0;
         let has_rfudf = (value[0] & 0x20) != 0;
@@ -177,7 +423,7 @@ impl DetiTag {
 
         let len_calc = 2 + 4 + len_atstf + fic_len + len_rfudf;
 
-        if len_calc * 8 != len {
+        if len_calc * 8 != len || value.len() < len_calc {
             return Err(TagError::InvalidSize { l: len });
         }
 
@@ -190,16 +436,22 @@ impl DetiTag {
 
         // just dummy values for now
         let atstf = vec![];
+        let mut fic = vec![];
         let mut figs = vec![];
         let rfudf = vec![];
+        let mut fib_crc_errors = 0;
 
         if has_ficf {
             let fic_start = 2 + 4 + if has_atstf { 8 } else { 0 };
             let fic_data = &value[fic_start..fic_start + fic_len];
+            if options.keep_fic {
+                fic = fic_data.to_vec();
+            }
 
-            match FicDecoder::from_bytes(fic_data) {
-                Ok(_figs) => {
+            match FicDecoder::new(options.fib_strict_crc).decode_counted(fic_data) {
+                Ok((_figs, crc_errors)) => {
                     figs.extend(_figs);
+                    fib_crc_errors = crc_errors;
                 }
                 Err(e) => {
                     log::error!("Error decoding FIC: {:?}", e);
@@ -207,13 +459,25 @@ impl DetiTag {
             }
         }
 
-        Ok(Self { atstf, figs, rfudf })
+        Ok(Self {
+            atstf,
+            fic,
+            figs,
+            rfudf,
+            fib_crc_errors,
+        })
     }
 }
 
 #[derive(Debug, Serialize)]
 pub struct EstTag {
     pub len: usize,
+    // stream index n of the "est<n>" tag name, 1..=64
+    pub stream: u8,
+    // SSTC: SCID (6), SAD (10), TPL (6)
+    pub scid: u8,
+    pub sad: u16,
+    pub tpl: u8,
     pub header: Vec<u8>,
     #[debug(skip)]
     pub value: Vec<u8>,
@@ -226,12 +490,57 @@ impl EstTag {
         }
 
         let len = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let stream = data[3];
         let header = data[0..8].to_vec();
         let value = data[8..].to_vec();
 
-        // TODO: maybe add some checks?
+        if value.len() < 3 {
+            return Err(TagError::InvalidSize { l: data.len() });
+        }
 
-        Ok(Self { len, header, value })
+        let scid = value[0] >> 2;
+        let sad = ((value[0] & 0x03) as u16) << 8 | value[1] as u16;
+        let tpl = value[2] >> 2;
+
+        Ok(Self {
+            len,
+            stream,
+            scid,
+            sad,
+            tpl,
+            header,
+            value,
+        })
+    }
+}
+
+// free text, e.g. the mux software and version (ODR-DabMux)
+#[derive(Debug, Serialize)]
+pub struct InfoTag {
+    pub text: String,
+}
+
+impl InfoTag {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, TagError> {
+        if data.len() < 8 {
+            return Err(TagError::InvalidSize { l: data.len() });
+        }
+
+        // length in bits
+        let len = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize / 8;
+        let value = &data[8..];
+
+        if value.len() < len {
+            return Err(TagError::InvalidSize { l: data.len() });
+        }
+
+        // no charset indicator, usually ASCII
+        let text = decode_text(&value[..len])
+            .trim_end_matches('\0')
+            .trim()
+            .to_string();
+
+        Ok(Self { text })
     }
 }
 
@@ -244,3 +553,244 @@ pub struct FpttTag {}
 
 #[derive(Debug, Serialize)]
 pub struct FsidTag {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // AF packet (revision 1.0, PT "T") with the given tags and CRC
+    fn af_packet(tags: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for (name, value) in tags {
+            payload.extend_from_slice(*name);
+            payload.extend_from_slice(&((value.len() * 8) as u32).to_be_bytes());
+            payload.extend_from_slice(value);
+        }
+
+        let mut data = b"AF".to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x01, 0x90, b'T']);
+        data.extend_from_slice(&payload);
+        let crc = utils::calc_crc16_ccitt(&data);
+        data.extend_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    // DETI value with FIC (mode I, 3 FIBs): the first FIB carries FIG 0/0 (EId 0x4FFF)
+    fn deti_value(corrupt_fib: bool) -> Vec<u8> {
+        let mut value = vec![0x40, 0x00, 0xFF, 0x40, 0x00, 0x00];
+        for n in 0..3 {
+            let mut fib = if n == 0 {
+                vec![0x05, 0x00, 0x4F, 0xFF, 0x00, 0x00]
+            } else {
+                vec![]
+            };
+            fib.resize(30, 0xFF);
+            let crc = utils::calc_crc16_ccitt(&fib);
+            fib.extend_from_slice(&crc.to_be_bytes());
+            if n == 0 && corrupt_fib {
+                fib[31] ^= 0x01;
+            }
+            value.extend_from_slice(&fib);
+        }
+        value
+    }
+
+    fn deti(result: &FrameDecodeResult) -> &DetiTag {
+        result
+            .tags
+            .iter()
+            .find_map(|tag| match tag {
+                Tag::Deti(deti) => Some(deti),
+                _ => None,
+            })
+            .expect("deti tag")
+    }
+
+    #[test]
+    fn summary() {
+        let est = [0u8; 12];
+        let data = af_packet(&[
+            (b"*ptr", b"DETI\x00\x00\x00\x00"),
+            (b"deti", &deti_value(false)),
+            (b"est\x03", &est),
+        ]);
+        assert_eq!(Frame::summary(&data), "*ptr(8) deti(102) est3(12)");
+
+        // last tag value cut off
+        assert_eq!(
+            Frame::summary(&data[..data.len() - 6]),
+            "*ptr(8) deti(102) est3(12) (truncated)"
+        );
+
+        assert_eq!(Frame::summary(&af_packet(&[])), "");
+        assert_eq!(Frame::summary(b"PF\x00\x00\x00\x00"), "(no AF packet)");
+    }
+
+    #[test]
+    fn has_audio() {
+        let data = af_packet(&[(b"deti", &deti_value(false))]);
+        assert!(!Frame::decode(&data, true).unwrap().has_audio());
+
+        let est = [0x04, 0x00, 0x00, 0xFF, 0xFC];
+        let data = af_packet(&[(b"deti", &deti_value(false)), (b"est\x01", &est)]);
+        assert!(Frame::decode(&data, true).unwrap().has_audio());
+    }
+
+    #[test]
+    fn parser_resyncs_after_oversized_header() {
+        let packet = af_packet(&[(b"info", b"edinburgh")]);
+
+        // LEN announces 1 MiB
+        let mut data = b"AF\x00\x10\x00\x00\x00\x00\x90T".to_vec();
+        data.extend_from_slice(&packet);
+
+        let tags = FrameParser::new().feed(&data);
+        assert!(matches!(tags.as_slice(), [Tag::Info(tag)] if tag.text == "edinburgh"));
+
+        // same in small chunks
+        let mut parser = FrameParser::new();
+        let tags: Vec<Tag> = data.chunks(7).flat_map(|c| parser.feed(c)).collect();
+        assert!(matches!(tags.as_slice(), [Tag::Info(tag)] if tag.text == "edinburgh"));
+    }
+
+    // Debug skips the EST value, it is compared as well
+    fn describe(tags: &[Tag]) -> Vec<String> {
+        tags.iter()
+            .map(|tag| match tag {
+                Tag::Est(est) => format!("{:?} {:?}", est, est.value),
+                tag => format!("{:?}", tag),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parser_split_chunks() {
+        let est: Vec<u8> = (0..=255).collect();
+        let packet = af_packet(&[
+            (b"*ptr", b"DETI\x00\x00\x00\x00"),
+            (b"deti", &deti_value(false)),
+            (b"est\x01", &est),
+            (b"info", b"edinburgh"),
+        ]);
+        let expected = describe(&Frame::decode(&packet, true).unwrap().tags);
+        assert_eq!(expected.len(), 4);
+        let expected = [expected.clone(), expected].concat();
+
+        // two packets back to back, split anywhere
+        let data = [packet.clone(), packet].concat();
+        for split in 0..=data.len() {
+            let mut parser = FrameParser::new();
+            let mut tags = parser.feed(&data[..split]);
+            tags.extend(parser.feed(&data[split..]));
+            assert_eq!(describe(&tags), expected, "split at {}", split);
+        }
+
+        let mut parser = FrameParser::new();
+        let tags: Vec<Tag> = data.iter().flat_map(|b| parser.feed(&[*b])).collect();
+        assert_eq!(describe(&tags), expected);
+    }
+
+    #[test]
+    fn parser_max_frame_size() {
+        let packet = af_packet(&[(b"info", b"edinburgh")]);
+
+        let mut parser = FrameParser::new();
+        parser.set_max_frame_size(packet.len());
+        assert_eq!(parser.feed(&packet).len(), 1);
+
+        let mut parser = FrameParser::new();
+        parser.set_max_frame_size(packet.len() - 1);
+        assert!(parser.feed(&packet).is_empty());
+    }
+
+    // the packet with another AR byte (CF, MAJ, MIN) and its CRC updated
+    fn with_revision(mut data: Vec<u8>, ar: u8) -> Vec<u8> {
+        data[8] = ar;
+        let crc_offset = data.len() - AF_CRC_LEN;
+        let crc = utils::calc_crc16_ccitt(&data[..crc_offset]);
+        data[crc_offset..].copy_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn af_revision() {
+        let data = af_packet(&[(b"info", b"edinburgh")]);
+        let result = Frame::decode(&data, true).unwrap();
+        assert_eq!(result.revision, (1, 0));
+        assert!(result.is_known_revision());
+
+        // unknown minor: decoded, flagged
+        let result = Frame::decode(&with_revision(data.clone(), 0x91), true).unwrap();
+        assert_eq!(result.revision, (1, 1));
+        assert!(!result.is_known_revision());
+        assert!(matches!(result.tags.as_slice(), [Tag::Info(tag)] if tag.text == "edinburgh"));
+
+        let result = Frame::decode(&with_revision(data, 0xA0), true);
+        assert!(matches!(
+            result,
+            Err(FrameDecodeError::UnsupportedRevision { maj: 2, min: 0 })
+        ));
+    }
+
+    #[test]
+    fn af_crc() {
+        let mut data = af_packet(&[(b"info", b"edinburgh")]);
+        let last = data.len() - 1;
+        data[last] ^= 0x01;
+
+        let result = Frame::decode(&data, true);
+        assert!(matches!(
+            result,
+            Err(FrameDecodeError::CrcMismatch { stored, calculated }) if stored == calculated ^ 0x0001
+        ));
+
+        // only traced
+        let result = Frame::decode(&data, false).unwrap();
+        assert!(matches!(result.tags.as_slice(), [Tag::Info(tag)] if tag.text == "edinburgh"));
+
+        // no CRC to verify
+        let result = Frame::decode(&data[..last - 1], true);
+        assert!(matches!(
+            result,
+            Err(FrameDecodeError::FrameTooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn deti_keep_fic() {
+        let data = af_packet(&[(b"deti", &deti_value(false))]);
+
+        let result = Frame::decode_with(&data, FrameOptions::default()).unwrap();
+        assert!(deti(&result).fic.is_empty());
+
+        let options = FrameOptions {
+            keep_fic: true,
+            ..FrameOptions::default()
+        };
+        let result = Frame::decode_with(&data, options).unwrap();
+        assert_eq!(deti(&result).fic, deti_value(false)[6..]);
+    }
+
+    #[test]
+    fn deti_fib_crc_strict() {
+        let data = af_packet(&[(b"deti", &deti_value(true))]);
+        let result = Frame::decode_with(&data, FrameOptions::default()).unwrap();
+
+        assert_eq!(deti(&result).fib_crc_errors, 1);
+        assert!(deti(&result).figs.is_empty());
+    }
+
+    #[test]
+    fn deti_fib_crc_lenient() {
+        let options = FrameOptions {
+            fib_strict_crc: false,
+            ..FrameOptions::default()
+        };
+        let data = af_packet(&[(b"deti", &deti_value(true))]);
+        let result = Frame::decode_with(&data, options).unwrap();
+
+        assert_eq!(deti(&result).fib_crc_errors, 1);
+        assert!(matches!(deti(&result).figs.as_slice(), [Fig::F0_0(f)] if f.eid == 0x4FFF));
+    }
+}
@@ -1,5 +1,10 @@
+#[cfg(feature = "audio")]
 use super::bus::{emit_event, DabEvent};
+#[cfg(feature = "audio")]
+use super::pad::mot::MotStats;
+#[cfg(feature = "audio")]
 use super::pad::PadDecoder;
+#[cfg(feature = "audio")]
 use crate::utils;
 use derive_more::Debug;
 use log;
@@ -7,8 +12,16 @@ use serde::Serialize;
 use std::fmt;
 use thiserror::Error;
 
+#[cfg(feature = "audio")]
 const FPAD_LEN: usize = 2;
 
+// MPEG Layer II bitrates (kbps) by bitrate index, MPEG-1 (48 kHz) and MPEG-2 LSF (24 kHz)
+const MP2_BITRATES: [usize; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+const MP2_LSF_BITRATES: [usize; 15] =
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
 #[derive(Debug, Error)]
 pub enum FormatError {
     #[error("AU start values are zero")]
@@ -85,23 +98,105 @@ impl AudioFormat {
     }
 }
 
-impl fmt::Display for AudioFormat {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // channels: show "Stereo" or "Mono"
-        let channels_str = match self.channels {
-            2 => "S",
-            1 => "M",
+impl AudioFormat {
+    // MPEG-1 / MPEG-2 Layer II frame header (DAB), returns the format and the frame length in bytes.
+    // DAB does not use padding, free format is not supported
+    pub fn from_mp2_header(h: &[u8]) -> Option<(Self, usize)> {
+        if h.len() < 4 || h[0] != 0xFF {
+            return None;
+        }
+
+        // sync (12) ID (1) layer (2) protection (1): ID 1 = MPEG-1, 0 = MPEG-2 LSF, layer 0b10 = II
+        let lsf = match h[1] & 0xFE {
+            0xFC => false,
+            0xF4 => true,
+            _ => return None,
+        };
+
+        let bitrate_index = (h[2] >> 4) as usize;
+        let samplerate: u32 = match ((h[2] >> 2) & 0x03, lsf) {
+            (0, false) => 44100,
+            (1, false) => 48000,
+            (2, false) => 32000,
+            (0, true) => 22050,
+            (1, true) => 24000,
+            (2, true) => 16000,
+            _ => return None,
+        };
+
+        let bitrate = match bitrate_index {
+            1..=14 if lsf => MP2_LSF_BITRATES[bitrate_index],
+            1..=14 => MP2_BITRATES[bitrate_index],
+            _ => return None,
+        };
+
+        // mode 0b11: single channel
+        let channels = if (h[3] >> 6) == 0x03 { 1 } else { 2 };
+
+        // 1152 samples per frame
+        let f_len = 144 * bitrate * 1000 / samplerate as usize;
+
+        Some((
+            Self {
+                sbr: false,
+                ps: false,
+                codec: "MP2".to_string(),
+                samplerate: (samplerate / 1000) as u8,
+                bitrate,
+                au_count: 1,
+                channels,
+                asc: Vec::new(),
+            },
+            f_len,
+        ))
+    }
+
+    pub fn codec(&self) -> &str {
+        &self.codec
+    }
+
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.samplerate as u32 * 1000
+    }
+
+    pub fn bitrate_kbps(&self) -> usize {
+        self.bitrate
+    }
+
+    // 2 for PS or stereo channel mode, 1 for mono
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    // SBR is signalled for HE-AAC and HE-AAC v2
+    pub fn is_he_aac(&self) -> bool {
+        self.sbr
+    }
+
+    // e.g. "HE-AAC-v2, 48 kHz Stereo, 64 kbps"
+    pub fn summary(&self) -> String {
+        let channels_str = match self.channels() {
+            2 => "Stereo",
+            1 => "Mono",
             _ => "-",
         };
 
-        write!(
-            f,
-            "{:<10} {} kHz @ {} kbit/s {}",
-            self.codec, self.samplerate, self.bitrate, channels_str
+        format!(
+            "{}, {} kHz {}, {} kbps",
+            self.codec(),
+            self.sample_rate_hz() / 1000,
+            channels_str,
+            self.bitrate_kbps()
         )
     }
 }
 
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary())
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct AacpResult {
     pub scid: u8,
@@ -120,6 +215,16 @@ impl AacpResult {
     }
 }
 
+// access unit as found in the superframe, including the trailing CRC
+#[derive(Debug, Clone, Serialize)]
+pub struct RawAu {
+    pub scid: u8,
+    pub au_index: usize,
+    #[debug("{} bytes", data.len())]
+    pub data: Vec<u8>,
+    pub crc_ok: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct PadResult {
     pub fpad: Vec<u8>,
@@ -140,6 +245,9 @@ pub enum FeedError {
 
     #[error("Frame length invalid: {l}")]
     FrameLengtInvalid { l: usize },
+
+    #[error("Slice too short: {l} < {f_len}")]
+    SliceTooShort { l: usize, f_len: usize },
 }
 
 #[derive(Debug)]
@@ -148,12 +256,18 @@ pub enum FeedResult {
     Buffering,
 }
 
+#[cfg(feature = "audio")]
 #[derive(Debug)]
 pub struct AacpExctractor {
     scid: u8,
     f_len: usize,
     f_count: usize,
     f_sync: usize,
+    // superframe sync found at least once since the last loss
+    sf_synced: bool,
+    // since the last take_error_counts()
+    au_crc_errors: u64,
+    sync_losses: u64,
     sf_len: usize,
     sf_raw: Vec<u8>,
     sf_buff: Vec<u8>,
@@ -163,8 +277,14 @@ pub struct AacpExctractor {
     au_frames: Vec<Vec<u8>>,
     pad_decoder: PadDecoder,
     pub extract_pad: bool,
+    // false: AUs are still extracted for PAD, but not emitted (metadata only)
+    pub emit_frames: bool,
+    // collect raw AUs (with CRC) for take_raw_aus()
+    pub raw_au: bool,
+    raw_aus: Vec<RawAu>,
 }
 
+#[cfg(feature = "audio")]
 impl AacpExctractor {
     pub fn new(scid: u8) -> Self {
         Self {
@@ -172,6 +292,9 @@ impl AacpExctractor {
             f_len: 0,
             f_count: 0,
             f_sync: 0,
+            sf_synced: false,
+            au_crc_errors: 0,
+            sync_losses: 0,
             sf_len: 0,
             sf_raw: Vec::new(),
             sf_buff: Vec::new(),
@@ -181,11 +304,67 @@ impl AacpExctractor {
             au_frames: Vec::new(),
             pad_decoder: PadDecoder::new(scid),
             extract_pad: false,
+            emit_frames: true,
+            raw_au: false,
+            raw_aus: Vec::new(),
         }
     }
-    pub async fn feed(&mut self, data: &[u8], f_len: usize) -> Result<FeedResult, FeedError> {
+
+    pub fn audio_format(&self) -> Option<&AudioFormat> {
+        self.audio_format.as_ref()
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.sf_synced
+    }
+
+    pub fn set_mot_force_emit(&mut self, force_emit: bool) {
+        self.pad_decoder.set_mot_force_emit(force_emit);
+    }
+
+    pub fn set_mot_max_object_size(&mut self, max_object_size: usize) {
+        self.pad_decoder.set_mot_max_object_size(max_object_size);
+    }
+
+    pub fn mot_stats(&self) -> &MotStats {
+        self.pad_decoder.mot_stats()
+    }
+
+    // (AU CRC errors, superframe sync losses) since the last call
+    pub fn take_error_counts(&mut self) -> (u64, u64) {
+        let counts = (self.au_crc_errors, self.sync_losses);
+        self.au_crc_errors = 0;
+        self.sync_losses = 0;
+        counts
+    }
+
+    // raw AUs collected since the last call, empty unless raw_au is set
+    pub fn take_raw_aus(&mut self) -> Vec<RawAu> {
+        std::mem::take(&mut self.raw_aus)
+    }
+
+    // drop superframe sync state and the detected audio format, keeps extract_pad / emit_frames / raw_au
+    pub fn reset(&mut self) {
+        *self = Self {
+            extract_pad: self.extract_pad,
+            emit_frames: self.emit_frames,
+            raw_au: self.raw_au,
+            ..Self::new(self.scid)
+        };
+    }
+
+    pub fn feed(&mut self, data: &[u8], f_len: usize) -> Result<FeedResult, FeedError> {
         self.au_frames.clear();
 
+        // truncated EST slice. the frame is skipped, the superframe window slides on and
+        // resyncs (firecode) once the gap is out of it - same as a lost frame
+        if data.len() < f_len {
+            return Err(FeedError::SliceTooShort {
+                l: data.len(),
+                f_len,
+            });
+        }
+
         if self.f_len != 0 {
             if self.f_len != f_len {
                 return Err(FeedError::FrameLengtMismatch {
@@ -235,21 +414,36 @@ impl AacpExctractor {
             }
             self.f_sync += 1;
 
+            if self.sf_synced {
+                self.sf_synced = false;
+                self.sync_losses += 1;
+            }
+
             return Ok(FeedResult::Buffering);
         }
 
+        self.sf_synced = true;
+
         if self.f_sync > 0 {
             self.f_sync = 0;
         }
 
-        if self.audio_format.is_none() && self.sf_buff.len() >= 11 {
+        // check the format on every superframe, emit on first detection and on change
+        if self.sf_buff.len() >= 11 {
             match AudioFormat::from_bytes(&self.sf_buff, self.sf_len) {
                 Ok(af) => {
-                    self.audio_format = Some(af);
+                    if self.audio_format.as_ref() != Some(&af) {
+                        emit_event(DabEvent::AudioFormatDetected {
+                            scid: self.scid,
+                            format: af.clone(),
+                        });
+                        self.audio_format = Some(af);
+                    }
                 }
-                Err(err) => {
+                Err(err) if self.audio_format.is_none() => {
                     log::warn!("Format error - SCID: {} - {:?}", self.scid, err);
                 }
+                Err(_) => {}
             }
         }
 
@@ -262,31 +456,45 @@ impl AacpExctractor {
                 continue;
             }
 
-            // check if this is correct
             let au_data = &self.sf_buff[self.au_start[i]..self.au_start[i + 1]];
             let au_len = self.au_start[i + 1] - self.au_start[i];
 
             let au_crc_stored = ((au_data[au_len - 2] as u16) << 8) | au_data[au_len - 1] as u16;
             let au_crc_calced = utils::calc_crc16_ccitt(&au_data[0..au_len - 2]);
 
+            if self.raw_au {
+                self.raw_aus.push(RawAu {
+                    scid: self.scid,
+                    au_index: i,
+                    data: au_data.to_vec(),
+                    crc_ok: au_crc_stored == au_crc_calced,
+                });
+            }
+
             if au_crc_stored != au_crc_calced {
                 log::warn!("AD: AU CRC mismatch!");
+                self.au_crc_errors += 1;
                 continue;
             }
 
             // copy AU frames to buffer. do not forget to remove last two bytes (CRC)
-            self.au_frames.push(au_data[..au_len - 2].to_vec());
+            if self.emit_frames {
+                self.au_frames.push(au_data[..au_len - 2].to_vec());
+            }
 
-            let pad = Self::extract_pad(&au_data[..au_len - 2]);
-            if let Some(pad) = pad {
-                self.pad_decoder.feed(&pad.fpad, &pad.xpad);
+            if self.extract_pad {
+                if let Some(pad) = Self::extract_pad(&au_data[..au_len - 2]) {
+                    self.pad_decoder.feed(&pad.fpad, &pad.xpad);
+                }
             }
         }
 
         let result: AacpResult =
             AacpResult::new(self.scid, self.audio_format.clone(), self.au_frames.clone());
 
-        emit_event(DabEvent::AacpFramesExtracted(result.clone()));
+        if self.emit_frames {
+            emit_event(DabEvent::AacpFramesExtracted(result.clone()));
+        }
 
         self.f_count = 0;
 
@@ -386,3 +594,405 @@ impl AacpExctractor {
         Some(pad)
     }
 }
+
+// MPEG Layer II (DAB) frames, synced on two consecutive frame headers. a frame covers one (48 kHz)
+// or two (24 kHz) logical DAB frames. PAD is not extracted for now.
+#[cfg(feature = "audio")]
+#[derive(Debug)]
+pub struct Mp2Extractor {
+    scid: u8,
+    buf: Vec<u8>,
+    synced: bool,
+    // since the last take_sync_losses()
+    sync_losses: u64,
+    audio_format: Option<AudioFormat>,
+    pub emit_frames: bool,
+}
+
+#[cfg(feature = "audio")]
+impl Mp2Extractor {
+    pub fn new(scid: u8) -> Self {
+        Self {
+            scid,
+            buf: Vec::new(),
+            synced: false,
+            sync_losses: 0,
+            audio_format: None,
+            emit_frames: true,
+        }
+    }
+
+    pub fn audio_format(&self) -> Option<&AudioFormat> {
+        self.audio_format.as_ref()
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    pub fn take_sync_losses(&mut self) -> u64 {
+        std::mem::take(&mut self.sync_losses)
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> FeedResult {
+        self.buf.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+
+        while self.buf.len() >= 4 {
+            let Some((af, f_len)) = AudioFormat::from_mp2_header(&self.buf[..4]) else {
+                if self.synced {
+                    self.synced = false;
+                    self.sync_losses += 1;
+                }
+
+                // skip to the next sync candidate
+                let skip = self.buf[1..]
+                    .iter()
+                    .position(|b| *b == 0xFF)
+                    .map_or(self.buf.len(), |p| p + 1);
+                self.buf.drain(..skip);
+                continue;
+            };
+
+            if self.buf.len() < f_len {
+                break;
+            }
+
+            // a single 0xFFF pattern is easily found in audio data, (re)sync only if the
+            // next header follows at the expected offset
+            if !self.synced {
+                if self.buf.len() < f_len + 4 {
+                    break;
+                }
+
+                let next = AudioFormat::from_mp2_header(&self.buf[f_len..f_len + 4]);
+                if next.is_none_or(|(_, next_len)| next_len != f_len) {
+                    self.buf.drain(..1);
+                    continue;
+                }
+            }
+
+            self.synced = true;
+
+            if self.audio_format.as_ref() != Some(&af) {
+                emit_event(DabEvent::AudioFormatDetected {
+                    scid: self.scid,
+                    format: af.clone(),
+                });
+                self.audio_format = Some(af);
+            }
+
+            let frame: Vec<u8> = self.buf.drain(..f_len).collect();
+
+            if self.emit_frames {
+                frames.push(frame);
+            }
+        }
+
+        if frames.is_empty() {
+            return FeedResult::Buffering;
+        }
+
+        let result = AacpResult::new(self.scid, self.audio_format.clone(), frames);
+
+        emit_event(DabEvent::Mp2FramesExtracted(result.clone()));
+
+        FeedResult::Complete(result)
+    }
+}
+
+#[cfg(all(test, feature = "audio"))]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dab::bus::capture_events;
+
+    // MPEG-1 layer II, 128 kbps, 48 kHz, stereo
+    const MP2_HEADER: [u8; 4] = [0xFF, 0xFC, 0x84, 0x04];
+    const MP2_FRAME_LEN: usize = 384;
+
+    fn mp2_frame(fill: u8) -> Vec<u8> {
+        let mut frame = vec![fill; MP2_FRAME_LEN];
+        frame[..4].copy_from_slice(&MP2_HEADER);
+        frame
+    }
+
+    fn frames(result: FeedResult) -> Vec<Vec<u8>> {
+        match result {
+            FeedResult::Complete(result) => result.frames,
+            FeedResult::Buffering => Vec::new(),
+        }
+    }
+
+    // DAB+ superframe, 32 kbps (5 * 96 bytes), 48 kHz HE-AAC: 3 AUs from 6, 150, 300 to 440.
+    // the header passes the fire code, the AU CRC of `bad_au` is broken. no RS parity
+    pub(crate) fn superframe(bad_au: Option<usize>) -> Vec<u8> {
+        superframe_with(0x60, bad_au)
+    }
+
+    // same with another header byte 2, only the channel mode (0x10) and PS (0x08) bits keep
+    // the AU layout
+    pub(crate) fn superframe_with(h: u8, bad_au: Option<usize>) -> Vec<u8> {
+        let mut sf = vec![0u8; 480];
+        sf[2] = h;
+        sf[3..6].copy_from_slice(&[0x09, 0x61, 0x2C]);
+
+        for (i, (start, end)) in [(6, 150), (150, 300), (300, 440)].into_iter().enumerate() {
+            sf[start..end - 2].fill(i as u8 + 1);
+            let mut crc = utils::calc_crc16_ccitt(&sf[start..end - 2]);
+            if bad_au == Some(i) {
+                crc ^= 0x0001;
+            }
+            sf[end - 2..end].copy_from_slice(&crc.to_be_bytes());
+        }
+
+        // covers the start of the first AU
+        let fire_code = utils::calc_crc_fire_code(&sf[2..11]);
+        sf[..2].copy_from_slice(&fire_code.to_be_bytes());
+        sf
+    }
+
+    fn feed_superframe(extractor: &mut AacpExctractor, sf: &[u8]) -> Option<AacpResult> {
+        let mut result = None;
+        for frame in sf.chunks(96) {
+            if let Ok(FeedResult::Complete(r)) = extractor.feed(frame, 96) {
+                result = Some(r);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn aacp_raw_au() {
+        let mut extractor = AacpExctractor::new(1);
+        extractor.raw_au = true;
+
+        // the format is detected on the first superframe, AUs follow from the next one
+        let result = feed_superframe(&mut extractor, &superframe(None)).unwrap();
+        assert!(result.frames.is_empty());
+        assert_eq!(extractor.audio_format().unwrap().au_count, 3);
+        assert!(extractor.take_raw_aus().is_empty());
+
+        let result = feed_superframe(&mut extractor, &superframe(Some(1))).unwrap();
+
+        // CRC stripped, the broken AU dropped
+        let lens: Vec<usize> = result.frames.iter().map(|f| f.len()).collect();
+        assert_eq!(lens, [142, 138]);
+
+        // CRC kept, the broken AU included
+        let raw_aus = extractor.take_raw_aus();
+        let raw: Vec<(usize, usize, bool)> = raw_aus
+            .iter()
+            .map(|au| (au.au_index, au.data.len(), au.crc_ok))
+            .collect();
+        assert_eq!(raw, [(0, 144, true), (1, 150, false), (2, 140, true)]);
+        assert_eq!(raw_aus[0].data[..142], result.frames[0][..]);
+
+        assert!(extractor.take_raw_aus().is_empty());
+        assert_eq!(extractor.take_error_counts(), (1, 0));
+    }
+
+    #[test]
+    fn audio_format_detected() {
+        let mut extractor = AacpExctractor::new(1);
+        let mut detected = |sf: &[u8]| {
+            capture_events(|| {
+                feed_superframe(&mut extractor, sf);
+            })
+            .into_iter()
+            .filter_map(|e| match e {
+                DabEvent::AudioFormatDetected { scid: 1, format } => Some(format),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+        };
+
+        let formats = detected(&superframe(None));
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].summary(), "HE-AAC, 48 kHz Mono, 32 kbps");
+
+        // checked on every superframe, unchanged
+        assert!(detected(&superframe(None)).is_empty());
+        assert!(detected(&superframe(Some(0))).is_empty());
+
+        // channel mode: stereo
+        let formats = detected(&superframe_with(0x70, None));
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].channels(), 2);
+        assert!(detected(&superframe_with(0x70, None)).is_empty());
+    }
+
+    #[test]
+    fn aacp_raw_au_off() {
+        let mut extractor = AacpExctractor::new(1);
+        feed_superframe(&mut extractor, &superframe(None));
+        feed_superframe(&mut extractor, &superframe(None));
+
+        assert!(extractor.take_raw_aus().is_empty());
+    }
+
+    #[test]
+    fn aacp_slice_too_short() {
+        let mut extractor = AacpExctractor::new(1);
+
+        assert!(matches!(
+            extractor.feed(&[0; 95], 96),
+            Err(FeedError::SliceTooShort { l: 95, f_len: 96 })
+        ));
+        // skipped, nothing buffered
+        assert_eq!(extractor.f_count, 0);
+        assert_eq!(extractor.f_len, 0);
+
+        assert!(matches!(
+            extractor.feed(&[0; 100], 96),
+            Ok(FeedResult::Buffering)
+        ));
+        assert_eq!(extractor.f_count, 1);
+
+        // frames after the short one go on filling the superframe
+        assert!(extractor.feed(&[0; 95], 96).is_err());
+        assert!(extractor.feed(&[0; 96], 96).is_ok());
+        assert_eq!(extractor.f_count, 2);
+    }
+
+    // superframe header byte 2: DAC 48 kHz (0x40), SBR (0x20), channel mode (0x10), PS (0x08)
+    fn audio_format(h: u8, sf_len: usize) -> AudioFormat {
+        AudioFormat::from_bytes(&[0x00, 0x00, h, 0x09, 0x61], sf_len).unwrap()
+    }
+
+    #[test]
+    fn audio_format_getters() {
+        let af = audio_format(0x60, 480);
+        assert_eq!(af.codec(), "HE-AAC");
+        assert_eq!(af.sample_rate_hz(), 48000);
+        assert_eq!(af.bitrate_kbps(), 32);
+        assert_eq!(af.channels(), 1);
+        assert!(af.is_he_aac());
+
+        // stereo channel mode
+        assert_eq!(audio_format(0x70, 480).channels(), 2);
+
+        // PS: stereo whatever the channel mode says
+        let af = audio_format(0x68, 480);
+        assert_eq!(af.codec(), "HE-AAC-v2");
+        assert_eq!(af.channels(), 2);
+        assert!(af.is_he_aac());
+
+        let af = audio_format(0x10, 1440);
+        assert_eq!(af.codec(), "AAC-LC");
+        assert_eq!(af.sample_rate_hz(), 32000);
+        assert_eq!(af.bitrate_kbps(), 96);
+        assert_eq!(af.channels(), 2);
+        assert!(!af.is_he_aac());
+
+        assert!(matches!(
+            AudioFormat::from_bytes(&[0x00, 0x00, 0x60, 0x00, 0x00], 480),
+            Err(FormatError::StartValuesZero)
+        ));
+    }
+
+    #[test]
+    fn audio_format_summary() {
+        let summaries: Vec<String> = [0x00, 0x10, 0x20, 0x28, 0x40, 0x50, 0x60, 0x70, 0x68]
+            .into_iter()
+            .map(|h| audio_format(h, 480).summary())
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                "AAC-LC, 32 kHz Mono, 32 kbps",
+                "AAC-LC, 32 kHz Stereo, 32 kbps",
+                "HE-AAC, 32 kHz Mono, 32 kbps",
+                "HE-AAC-v2, 32 kHz Stereo, 32 kbps",
+                "AAC-LC, 48 kHz Mono, 32 kbps",
+                "AAC-LC, 48 kHz Stereo, 32 kbps",
+                "HE-AAC, 48 kHz Mono, 32 kbps",
+                "HE-AAC, 48 kHz Stereo, 32 kbps",
+                "HE-AAC-v2, 48 kHz Stereo, 32 kbps",
+            ]
+        );
+
+        let (af, _) = AudioFormat::from_mp2_header(&MP2_HEADER).unwrap();
+        assert_eq!(af.summary(), "MP2, 48 kHz Stereo, 128 kbps");
+        assert_eq!(af.to_string(), af.summary());
+    }
+
+    #[test]
+    fn mp2_header() {
+        let (af, f_len) = AudioFormat::from_mp2_header(&MP2_HEADER).unwrap();
+        assert_eq!(f_len, MP2_FRAME_LEN);
+        assert_eq!(af.codec(), "MP2");
+        assert_eq!(af.bitrate_kbps(), 128);
+        assert_eq!(af.sample_rate_hz(), 48000);
+        assert_eq!(af.channels(), 2);
+
+        // mono, 24 kHz LSF: 64 kbps gives 144 * 64000 / 24000
+        let (af, f_len) = AudioFormat::from_mp2_header(&[0xFF, 0xF4, 0x84, 0xC4]).unwrap();
+        assert_eq!(f_len, 384);
+        assert_eq!(af.bitrate_kbps(), 64);
+        assert_eq!(af.sample_rate_hz(), 24000);
+        assert_eq!(af.channels(), 1);
+
+        // layer III, free format, reserved sample rate
+        assert!(AudioFormat::from_mp2_header(&[0xFF, 0xFA, 0x84, 0x04]).is_none());
+        assert!(AudioFormat::from_mp2_header(&[0xFF, 0xFC, 0x04, 0x04]).is_none());
+        assert!(AudioFormat::from_mp2_header(&[0xFF, 0xFC, 0x8C, 0x04]).is_none());
+    }
+
+    #[test]
+    fn mp2_sync_needs_two_headers() {
+        let mut extractor = Mp2Extractor::new(1);
+
+        // a lone header is not enough to sync
+        assert!(frames(extractor.feed(&mp2_frame(0x00))).is_empty());
+        assert!(!extractor.is_synced());
+
+        let frames = frames(extractor.feed(&mp2_frame(0x11)));
+        assert_eq!(frames, [mp2_frame(0x00), mp2_frame(0x11)]);
+        assert!(extractor.is_synced());
+        assert_eq!(extractor.audio_format().unwrap().bitrate_kbps(), 128);
+    }
+
+    #[test]
+    fn mp2_ignores_false_sync() {
+        let mut extractor = Mp2Extractor::new(1);
+
+        // garbage containing a valid looking header without a successor
+        let mut data = vec![0x00; 100];
+        data[10..14].copy_from_slice(&MP2_HEADER);
+        data.resize(10 + MP2_FRAME_LEN + 20, 0x00);
+        data.extend(mp2_frame(0x22));
+        data.extend(mp2_frame(0x33));
+        data.extend(mp2_frame(0x44));
+
+        let frames = frames(extractor.feed(&data));
+        assert_eq!(frames, [mp2_frame(0x22), mp2_frame(0x33), mp2_frame(0x44)]);
+        assert!(extractor.is_synced());
+        assert_eq!(extractor.take_sync_losses(), 0);
+    }
+
+    #[test]
+    fn mp2_sync_loss() {
+        let mut extractor = Mp2Extractor::new(1);
+
+        let mut data = mp2_frame(0x00);
+        data.extend(mp2_frame(0x11));
+        data.extend(vec![0x00; 50]);
+        data.extend(mp2_frame(0x22));
+        data.extend(mp2_frame(0x33));
+        data.extend(mp2_frame(0x44));
+
+        let frames = frames(extractor.feed(&data));
+        assert_eq!(
+            frames,
+            [
+                mp2_frame(0x00),
+                mp2_frame(0x11),
+                mp2_frame(0x22),
+                mp2_frame(0x33),
+                mp2_frame(0x44)
+            ]
+        );
+        assert_eq!(extractor.take_sync_losses(), 1);
+    }
+}
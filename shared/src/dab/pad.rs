@@ -4,37 +4,45 @@ pub mod mot;
 use derive_more::Debug;
 use log;
 
+use crate::utils;
+
 use dl::DlDecoder;
-use mot::MotDecoder;
+use mot::{MotDecoder, MotStats};
 
+// EN 300 401 7.4.2: there is no extended X-PAD, variable size X-PAD carries up to
+// four contents indicators, terminated by an end marker (kind 0) if less than four
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum XPadIndicator {
+    None,
     Short,
     Variable,
-    Extended,
     Reserved,
 }
 
+// F-PAD byte L-1: type (2), X-PAD indicator (2), byte L: CI flag (bit 1)
 #[derive(Debug, Clone, Copy)]
 pub struct FPad {
-    pub ci_flag: bool,
+    pub fpad_type: u8,
     pub xpad_indicator: XPadIndicator,
+    pub ci_flag: bool,
 }
 
-impl From<u8> for FPad {
-    fn from(byte: u8) -> Self {
-        let ci_flag = byte & 0b1000_0000 != 0;
-        let indicator_bits = (byte >> 5) & 0b11;
-        let xpad_indicator = match indicator_bits {
-            0b00 => XPadIndicator::Short,
-            0b01 => XPadIndicator::Variable,
-            0b10 => XPadIndicator::Extended,
+impl FPad {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 2 {
+            return None;
+        }
+        let xpad_indicator = match (data[0] & 0x30) >> 4 {
+            0b00 => XPadIndicator::None,
+            0b01 => XPadIndicator::Short,
+            0b10 => XPadIndicator::Variable,
             _ => XPadIndicator::Reserved,
         };
-        FPad {
-            ci_flag,
+        Some(FPad {
+            fpad_type: data[0] >> 6,
             xpad_indicator,
-        }
+            ci_flag: data[1] & 0x02 != 0,
+        })
     }
 }
 
@@ -84,6 +92,9 @@ pub struct MscDataGroup {
     pub length_indicator: u8,
     pub transport_id: Option<u16>,
     pub end_user_addr_field: Vec<u8>,
+    // segmentation header at the start of the data field: repetition count (3), segment size (13)
+    pub segment_repetition: u8,
+    pub segment_size: Option<u16>,
     #[debug("{} bytes", data_field.len())]
     pub data_field: Vec<u8>,
 }
@@ -105,6 +116,8 @@ impl MscDataGroup {
             length_indicator: 0,
             transport_id: None,
             end_user_addr_field: Vec::new(),
+            segment_repetition: 0,
+            segment_size: None,
             data_field: Vec::new(),
         };
 
@@ -149,6 +162,9 @@ impl MscDataGroup {
         }
 
         if dg.user_access_flag {
+            if data.len() < idx + 1 {
+                return dg;
+            }
             let byte = data[idx];
             idx += 1;
 
@@ -177,17 +193,57 @@ impl MscDataGroup {
         if data.len() >= idx + crc_len {
             let data_field_len = data.len() - idx - crc_len;
 
-            // should we remove first 2 bytes of data first?
-            //       they contain segmentation metadata.
-
             dg.data_field = data[idx..idx + data_field_len].to_vec();
+
+            if dg.segment_flag && dg.data_field.len() >= 2 {
+                dg.segment_repetition = dg.data_field[0] >> 5;
+                dg.segment_size =
+                    Some(((dg.data_field[0] as u16 & 0x1F) << 8) | dg.data_field[1] as u16);
+            }
         } else {
             log::warn!("MscDataGroup: Not enough data for data field");
+            return dg;
+        }
+
+        // CRC over header and data field
+        if crc_flag {
+            let crc_offset = data.len() - 2;
+            let crc_stored = u16::from_be_bytes([data[crc_offset], data[crc_offset + 1]]);
+            let crc_calculated = utils::calc_crc16_ccitt(&data[..crc_offset]);
+
+            if crc_stored != crc_calculated {
+                log::debug!(
+                    "MscDataGroup: CRC mismatch: 0x{:04X} != 0x{:04X}",
+                    crc_stored,
+                    crc_calculated
+                );
+                return dg;
+            }
         }
 
-        dg.is_valid = true; // this should be checked ;)
+        dg.is_valid = true;
         dg
     }
+
+    // segment number, 0 for unsegmented data groups
+    pub fn segment_index(&self) -> u16 {
+        self.segment_num.unwrap_or(0)
+    }
+
+    pub fn last_segment(&self) -> bool {
+        !self.segment_flag || self.last_flag
+    }
+
+    // data field without the segmentation header, limited to the signalled segment size
+    pub fn payload(&self) -> &[u8] {
+        match self.segment_size {
+            Some(size) => {
+                let end = (2 + size as usize).min(self.data_field.len());
+                &self.data_field[2..end]
+            }
+            None => &self.data_field,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -206,6 +262,10 @@ impl DlDataGroup {
     pub fn feed(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
         self.data.extend_from_slice(payload);
 
+        if self.data.is_empty() {
+            return None;
+        }
+
         let field_len = (self.data[0] & 0x0F) + 1;
         self.size_needed = 2 + field_len as usize + 2;
 
@@ -276,29 +336,41 @@ impl PadDecoder {
             mot_decoder: MotDecoder::new(scid),
         }
     }
+
+    pub fn set_mot_force_emit(&mut self, force_emit: bool) {
+        self.mot_decoder.force_emit = force_emit;
+    }
+
+    pub fn set_mot_max_object_size(&mut self, max_object_size: usize) {
+        self.mot_decoder.max_object_size = max_object_size;
+    }
+
+    pub fn mot_stats(&self) -> &MotStats {
+        &self.mot_decoder.stats
+    }
+
     pub fn feed(&mut self, fpad_bytes: &[u8], xpad_bytes: &[u8]) {
-        if fpad_bytes.len() < 2 {
+        let Some(fpad) = FPad::from_bytes(fpad_bytes) else {
             log::warn!("PadDecoder: Missing FPAD bytes");
             return;
-        }
+        };
 
         let used_xpad_len = xpad_bytes.len().min(64);
         let xpad: Vec<u8> = xpad_bytes[..used_xpad_len].iter().rev().copied().collect();
 
-        let fpad_type = fpad_bytes[0] >> 6;
-        let xpad_ind = (fpad_bytes[0] & 0x30) >> 4;
-        let ci_flag = fpad_bytes[1] & 0x02 != 0;
-
         let prev_xpad_ci = self.last_xpad_ci.clone();
         self.last_xpad_ci = None;
 
-        if fpad_type != 0b00 {
+        if fpad.fpad_type != 0b00 {
             return;
         }
 
-        let (ci_list, ci_header_len) = if ci_flag {
-            Self::build_ci_list(&xpad, fpad_bytes)
-        } else if xpad_ind == 0b01 || xpad_ind == 0b10 {
+        let (ci_list, ci_header_len) = if fpad.ci_flag {
+            Self::build_ci_list(&xpad, &fpad)
+        } else if matches!(
+            fpad.xpad_indicator,
+            XPadIndicator::Short | XPadIndicator::Variable
+        ) {
             if let Some(prev_ci) = &prev_xpad_ci {
                 if prev_ci.is_valid() {
                     (vec![prev_ci.clone()], 0)
@@ -361,24 +433,16 @@ impl PadDecoder {
         }
     }
 
-    fn build_ci_list(xpad: &[u8], fpad: &[u8]) -> (Vec<XPadCI>, usize) {
+    fn build_ci_list(xpad: &[u8], fpad: &FPad) -> (Vec<XPadCI>, usize) {
         let mut ci_list = Vec::new();
         let mut ci_header_len = 0;
 
-        if fpad.len() < 2 {
+        if fpad.fpad_type != 0b00 || !fpad.ci_flag {
             return (ci_list, ci_header_len);
         }
 
-        let fpad_type = fpad[0] >> 6;
-        let xpad_ind = (fpad[0] & 0x30) >> 4;
-        let ci_flag = fpad[1] & 0x02 != 0;
-
-        if fpad_type != 0b00 || !ci_flag {
-            return (ci_list, ci_header_len);
-        }
-
-        match xpad_ind {
-            0b01 => {
+        match fpad.xpad_indicator {
+            XPadIndicator::Short => {
                 // short format: 1 byte
                 if !xpad.is_empty() {
                     let kind = xpad[0] & 0x1F;
@@ -388,8 +452,8 @@ impl PadDecoder {
                     }
                 }
             }
-            0b10 => {
-                // long format: multiple CIs
+            XPadIndicator::Variable => {
+                // variable format: up to 4 CIs, the end marker counts to the header
                 for &raw in xpad.iter().take(4) {
                     let kind = raw & 0x1F;
                     ci_header_len += 1;
@@ -409,6 +473,9 @@ impl PadDecoder {
         match ci.kind {
             1 => {
                 // DGLI - Data Group Length Indicator
+                if payload.len() < 2 {
+                    return;
+                }
                 let dg_size = ((payload[0] & 0x3F) as u16) << 8 | payload[1] as u16;
                 self.next_dg_size = dg_size as usize;
             }
@@ -456,3 +523,147 @@ impl PadDecoder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fpad(data: [u8; 2]) -> FPad {
+        FPad::from_bytes(&data).unwrap()
+    }
+
+    // MOT data group: CRC, segment number and last flag, transport id 0x1234, segmentation header
+    fn data_group(seg_type: u8, segment: u16, last: bool, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x70 | seg_type, 0x00];
+        data.extend((segment | u16::from(last) << 15).to_be_bytes());
+        data.extend([0x12, 0x12, 0x34]);
+        data.extend((payload.len() as u16).to_be_bytes());
+        data.extend(payload);
+        data.extend(utils::calc_crc16_ccitt(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn data_group_crc() {
+        let data = data_group(4, 0, true, b"edinburgh");
+        let dg = MscDataGroup::from_bytes(&data);
+        assert!(dg.is_valid);
+        assert_eq!(dg.transport_id, Some(0x1234));
+        assert_eq!(dg.payload(), b"edinburgh");
+
+        // payload, CRC
+        for offset in [10, data.len() - 1] {
+            let mut corrupt = data.clone();
+            corrupt[offset] ^= 0x01;
+            assert!(!MscDataGroup::from_bytes(&corrupt).is_valid);
+        }
+
+        // no CRC flag: nothing to check
+        let mut data = data[..data.len() - 2].to_vec();
+        data[0] &= !0x40;
+        data[10] ^= 0x01;
+        assert!(MscDataGroup::from_bytes(&data).is_valid);
+    }
+
+    #[test]
+    fn data_group_segments() {
+        let body: Vec<u8> = (0..18).collect();
+        let segments: Vec<MscDataGroup> = body
+            .chunks(8)
+            .enumerate()
+            .map(|(n, chunk)| {
+                let last = n == 2;
+                MscDataGroup::from_bytes(&data_group(4, n as u16, last, chunk))
+            })
+            .collect();
+
+        let headers: Vec<(u16, bool, Option<u16>)> = segments
+            .iter()
+            .map(|dg| (dg.segment_index(), dg.last_segment(), dg.segment_size))
+            .collect();
+        assert_eq!(
+            headers,
+            [(0, false, Some(8)), (1, false, Some(8)), (2, true, Some(2))]
+        );
+
+        // the payload follows the segmentation header
+        assert_eq!(segments[1].data_field[..2], [0x00, 0x08]);
+        assert_eq!(segments[1].payload(), &body[8..16]);
+        let payload: Vec<u8> = segments
+            .iter()
+            .flat_map(|dg| dg.payload().to_vec())
+            .collect();
+        assert_eq!(payload, body);
+
+        // 15 bit segment number below the last flag
+        let dg = MscDataGroup::from_bytes(&data_group(4, 0x7FFF, false, b"x"));
+        assert_eq!(dg.segment_num, Some(0x7FFF));
+        assert!(!dg.last_flag);
+
+        // limited to the segment size
+        let mut data = data_group(4, 0, true, b"edinburgh");
+        data[8] = 4;
+        let crc_offset = data.len() - 2;
+        let crc = utils::calc_crc16_ccitt(&data[..crc_offset]);
+        data[crc_offset..].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(MscDataGroup::from_bytes(&data).payload(), b"edin");
+    }
+
+    #[test]
+    fn fpad_from_bytes() {
+        let indicator = |byte: u8| fpad([byte, 0x00]).xpad_indicator;
+        assert_eq!(indicator(0x00), XPadIndicator::None);
+        assert_eq!(indicator(0x10), XPadIndicator::Short);
+        assert_eq!(indicator(0x20), XPadIndicator::Variable);
+        assert_eq!(indicator(0x30), XPadIndicator::Reserved);
+
+        let f = fpad([0x60, 0x02]);
+        assert_eq!(f.fpad_type, 1);
+        assert_eq!(f.xpad_indicator, XPadIndicator::Variable);
+        assert!(f.ci_flag);
+        assert!(!fpad([0x20, 0xFD]).ci_flag);
+
+        assert!(FPad::from_bytes(&[0x20]).is_none());
+    }
+
+    #[test]
+    fn ci_list_short() {
+        let short = fpad([0x10, 0x02]);
+
+        let (ci_list, header_len) = PadDecoder::build_ci_list(&[0x02, 0xFF], &short);
+        assert_eq!(ci_list, [XPadCI::new(3, 2)]);
+        assert_eq!(header_len, 1);
+
+        // end marker only
+        let (ci_list, header_len) = PadDecoder::build_ci_list(&[0x00, 0xFF], &short);
+        assert!(ci_list.is_empty());
+        assert_eq!(header_len, 0);
+    }
+
+    #[test]
+    fn ci_list_variable() {
+        let variable = fpad([0x20, 0x02]);
+
+        // DL (4 bytes), MOT (12 bytes), end marker
+        let (ci_list, header_len) = PadDecoder::build_ci_list(&[0x02, 0x6C, 0x00, 0xFF], &variable);
+        assert_eq!(ci_list, [XPadCI::new(4, 2), XPadCI::new(12, 12)]);
+        assert_eq!(header_len, 3);
+
+        // at most four, no end marker then
+        let (ci_list, header_len) = PadDecoder::build_ci_list(&[0x02; 6], &variable);
+        assert_eq!(ci_list, vec![XPadCI::new(4, 2); 4]);
+        assert_eq!(header_len, 4);
+    }
+
+    #[test]
+    fn ci_list_none() {
+        let xpad = [0x02, 0x00];
+
+        // no CI flag, reserved indicator, F-PAD type other than 0
+        for data in [[0x20, 0x00], [0x30, 0x02], [0x60, 0x02]] {
+            let (ci_list, header_len) = PadDecoder::build_ci_list(&xpad, &fpad(data));
+            assert!(ci_list.is_empty());
+            assert_eq!(header_len, 0);
+        }
+    }
+}
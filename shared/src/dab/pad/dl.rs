@@ -27,6 +27,10 @@ impl DlObject {
             seg_count: 0,
         }
     }
+    // DL charset indicator: 0x0 EBU Latin, 0x4 ISO 8859-1, 0x6 UCS-2, 0xF UTF-8
+    pub fn charset(&self) -> u8 {
+        self.charset
+    }
     pub fn decode_label(&self) -> String {
         decode_chars(&self.chars, self.charset)
     }
@@ -105,7 +109,16 @@ impl Serialize for DlObject {
     }
 }
 
-#[derive(Debug, Serialize, Clone)]
+// same label text and DL+ tags, independent of toggle, charset and segmentation
+impl PartialEq for DlObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.scid == other.scid
+            && self.dl_plus_tags == other.dl_plus_tags
+            && self.decode_label() == other.decode_label()
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub struct DlPlusTag {
     pub kind: u8,
     pub start: u8,
@@ -333,11 +346,17 @@ impl fmt::Display for DlPlusContentType {
     }
 }
 
+// max. segments of a label: 128 bytes in 16 byte segments
+const DL_MAX_SEGMENTS: u8 = 8;
+
 #[derive(Debug)]
 pub struct DlDecoder {
     scid: u8,
+    // label being assembled, and the segment number expected next
     current: Option<DlObject>,
-    last_toggle: Option<u8>,
+    next_seg: u8,
+    // last complete label, DL+ tags are attached to it
+    last: Option<DlObject>,
 }
 
 impl DlDecoder {
@@ -345,7 +364,8 @@ impl DlDecoder {
         Self {
             scid,
             current: None,
-            last_toggle: None,
+            next_seg: 0,
+            last: None,
         }
     }
 
@@ -396,42 +416,56 @@ impl DlDecoder {
         }
 
         let nibble = (data[1] >> 4) & 0x0F;
-        let (_seg_no, charset) = if is_first {
+        let (seg_no, charset) = if is_first {
             (0, Some(nibble)) // charset = full 4 bits
         } else {
             (nibble & 0x07, None) // charset not in data
         };
 
-        // log::debug!("DL: seg = {}", seg_no);
-
-        if is_first {
-            self.flush();
-
-            self.current = Some(DlObject::new(self.scid, toggle, charset.unwrap_or(0)));
-        }
-
         let start = 2;
         let end = start + num_chars as usize;
-        if data.len() >= end {
-            // self.current.chars.extend_from_slice(&data[start..end]);
-            if let Some(current) = self.current.as_mut() {
-                current.chars.extend_from_slice(&data[start..end]);
-            }
-        } else {
+        if data.len() < end {
             log::warn!(
                 "[{:2}] DL: segment too short: expected {} bytes, got {}",
                 self.scid,
                 end,
                 data.len()
             );
+            self.current = None;
+            return None;
+        }
+
+        if is_first {
+            if self.current.is_some() {
+                log::debug!("[{:2}] DL: incomplete label dropped", self.scid);
+            }
+            self.current = Some(DlObject::new(self.scid, toggle, charset.unwrap_or(0)));
+            self.next_seg = 0;
+        }
+
+        let Some(current) = self.current.as_mut() else {
+            // joined in the middle of a label
+            return None;
+        };
+
+        // segments of one label share the toggle bit and arrive in order
+        if current.toggle != toggle || seg_no != self.next_seg || seg_no >= DL_MAX_SEGMENTS {
+            log::debug!(
+                "[{:2}] DL: unexpected segment {} (toggle {}) - label dropped",
+                self.scid,
+                seg_no,
+                toggle
+            );
+            self.current = None;
             return None;
         }
 
-        // log::debug!("DL current chars: {:?}", self.current.chars.len());
+        current.chars.extend_from_slice(&data[start..end]);
+        current.seg_count += 1;
+        self.next_seg += 1;
 
         if is_last {
-            // log::debug!("DL: {}", self.current.decode_label());
-            // self.reset();
+            self.complete();
         }
 
         None
@@ -471,46 +505,183 @@ impl DlDecoder {
             return;
         }
 
+        let mut tags = Vec::new();
+
         for i in 0..num_tags {
             let base = 1 + (i * 3) as usize;
             let content_type = data[base] & 0x7F;
             let start = data[base + 1] & 0x7F;
             let len = (data[base + 2] & 0x7F) + 1;
 
-            let tag = DlPlusTag::new(content_type, start, len);
-
-            // log::debug!(
-            //     "DL+ tag: {:?}", tag
-            // );
+            tags.push(DlPlusTag::new(content_type, start, len));
+        }
 
-            if let Some(current) = self.current.as_mut() {
-                current.dl_plus_tags.push(tag);
+        // DL+ refers to the last complete label, re-emit it when the tags change
+        if let Some(last) = self.last.as_mut() {
+            if last.dl_plus_tags != tags {
+                last.dl_plus_tags = tags;
+                emit_event(DabEvent::DlObjectReceived(last.clone()));
             }
         }
 
         // log::debug!("DL+ it_toggle={}, it_running={}", it_toggle, it_running);
     }
 
-    pub fn flush(&mut self) {
-        if let Some(current) = self.current.take() {
-            if !current.chars.is_empty() && self.last_toggle != Some(current.toggle) {
-                log::debug!(
-                    "[{:2}] DL: {} - {:?}",
-                    self.scid,
-                    current.decode_label(),
-                    current
-                );
+    // last segment received, emit unless it is a repetition (same toggle) of the last label
+    fn complete(&mut self) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
 
-                // log::debug!("[{:2}] DL{} {}", self.scid, if !current.dl_plus_tags.is_empty() {"+"} else {" "}, current.decode_label());
+        if current.chars.is_empty() {
+            return;
+        }
 
-                // log::debug!("{:?}", current.get_dl_plus());
+        if self
+            .last
+            .as_ref()
+            .is_some_and(|last| last.toggle == current.toggle)
+        {
+            return;
+        }
 
-                // let json = serde_json::to_string_pretty(&current).unwrap();
-                // println!("{}", json);
+        log::debug!(
+            "[{:2}] DL: {} - {:?}",
+            self.scid,
+            current.decode_label(),
+            current
+        );
 
-                emit_event(DabEvent::DlObjectReceived(current.clone()));
-                self.last_toggle = Some(current.toggle);
-            }
-        }
+        emit_event(DabEvent::DlObjectReceived(current.clone()));
+        self.last = Some(current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DL segment: prefix (toggle, first, last, length), charset (first) or segment number, chars
+    fn segment(toggle: u8, first: bool, last: bool, nibble: u8, chars: &[u8]) -> Vec<u8> {
+        let flags = toggle << 7 | (first as u8) << 6 | (last as u8) << 5 | (chars.len() - 1) as u8;
+        let mut data = vec![flags, nibble << 4];
+        data.extend_from_slice(chars);
+        data
+    }
+
+    fn last_label(decoder: &DlDecoder) -> Option<String> {
+        decoder.last.as_ref().map(|dl| dl.decode_label())
+    }
+
+    #[test]
+    fn single_segment() {
+        let mut decoder = DlDecoder::new(1);
+        decoder.feed(&segment(0, true, true, 0xF, b"Edinburgh"));
+
+        assert_eq!(last_label(&decoder).as_deref(), Some("Edinburgh"));
+        assert_eq!(decoder.last.as_ref().unwrap().seg_count, 1);
+        assert_eq!(decoder.last.as_ref().unwrap().charset(), 0xF);
+        assert!(decoder.current.is_none());
+    }
+
+    #[test]
+    fn multi_segment() {
+        let mut decoder = DlDecoder::new(1);
+
+        decoder.feed(&segment(1, true, false, 0x0, b"Now playing: "));
+        decoder.feed(&segment(1, false, false, 1, b"Jock "));
+        assert_eq!(last_label(&decoder), None);
+
+        // complete on the last segment, not on the next label
+        decoder.feed(&segment(1, false, true, 2, b"Tamson"));
+        assert_eq!(
+            last_label(&decoder).as_deref(),
+            Some("Now playing: Jock Tamson")
+        );
+        assert_eq!(decoder.last.as_ref().unwrap().seg_count, 3);
+    }
+
+    #[test]
+    fn repetition() {
+        let mut decoder = DlDecoder::new(1);
+        decoder.feed(&segment(0, true, true, 0x0, b"first"));
+
+        // same toggle: a repetition, not a new label
+        decoder.feed(&segment(0, true, true, 0x0, b"second"));
+        assert_eq!(last_label(&decoder).as_deref(), Some("first"));
+
+        decoder.feed(&segment(1, true, true, 0x0, b"second"));
+        assert_eq!(last_label(&decoder).as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn unexpected_segments() {
+        let mut decoder = DlDecoder::new(1);
+
+        // joined in the middle of a label
+        decoder.feed(&segment(0, false, true, 1, b"playing"));
+        assert_eq!(last_label(&decoder), None);
+
+        // segment 1 missing
+        decoder.feed(&segment(0, true, false, 0x0, b"Now "));
+        decoder.feed(&segment(0, false, true, 2, b"playing"));
+        assert_eq!(last_label(&decoder), None);
+        assert!(decoder.current.is_none());
+
+        // toggle changed within a label
+        decoder.feed(&segment(0, true, false, 0x0, b"Now "));
+        decoder.feed(&segment(1, false, true, 1, b"playing"));
+        assert_eq!(last_label(&decoder), None);
+
+        // too short for the announced length
+        let mut data = segment(0, true, true, 0x0, b"Now playing");
+        data.truncate(6);
+        decoder.feed(&data);
+        assert_eq!(last_label(&decoder), None);
+    }
+
+    #[test]
+    fn dl_plus_tags() {
+        let mut decoder = DlDecoder::new(1);
+
+        // no label yet: dropped
+        let command = [0x12, 0x00, 0x01, 0x04, 0x00, 0x05, 0x01, 0x09, 0x04];
+        decoder.feed(&command);
+        assert!(decoder.last.is_none());
+
+        decoder.feed(&segment(0, true, true, 0x0, b"Artist - Title"));
+        decoder.feed(&command);
+
+        let dl = decoder.last.as_ref().unwrap();
+        assert!(dl.is_dl_plus());
+        assert_eq!(
+            dl.dl_plus_tags,
+            [DlPlusTag::new(4, 0, 6), DlPlusTag::new(1, 9, 5)]
+        );
+        let values: Vec<String> = dl.get_dl_plus().into_iter().map(|t| t.value).collect();
+        assert_eq!(values, ["Artist", "Title"]);
+    }
+
+    fn dl_object(scid: u8, toggle: u8, charset: u8, chars: &[u8]) -> DlObject {
+        let mut dl = DlObject::new(scid, toggle, charset);
+        dl.chars = chars.to_vec();
+        dl
+    }
+
+    #[test]
+    fn dl_object_eq() {
+        let dl = dl_object(1, 0, 0xF, "Café".as_bytes());
+
+        // toggle, charset and encoding don't matter
+        assert_eq!(dl, dl_object(1, 1, 0xF, "Café".as_bytes()));
+        assert_eq!(dl, dl_object(1, 0, 0x0, b"Caf\x82"));
+        assert_eq!(dl, dl_object(1, 0, 0x4, b"Caf\xE9"));
+
+        assert_ne!(dl, dl_object(1, 0, 0xF, b"Cafe"));
+        assert_ne!(dl, dl_object(2, 0, 0xF, "Café".as_bytes()));
+
+        let mut tagged = dl.clone();
+        tagged.dl_plus_tags.push(DlPlusTag::new(1, 0, 4));
+        assert_ne!(dl, tagged);
     }
 }
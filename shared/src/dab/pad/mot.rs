@@ -1,9 +1,16 @@
 use super::MscDataGroup;
 use crate::dab::bus::{emit_event, DabEvent};
+use crate::dab::epg;
 use md5::compute;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
+// carousel cycles to wait for a missing segment before the object is dropped
+const MOT_MAX_GAP_REPETITIONS: u8 = 2;
+// default limit for header + body of an object in assembly, SLS images are usually < 50 kB
+pub const MOT_MAX_OBJECT_SIZE: usize = 1024 * 1024;
+
 #[derive(Debug, Serialize)]
 pub struct MotImage {
     pub scid: u8,
@@ -14,6 +21,12 @@ pub struct MotImage {
     pub data: Vec<u8>,
     pub click_through_url: Option<String>,
     pub alternative_location_url: Option<String>,
+    // categorized slideshow, None for plain SLS
+    pub category_id: Option<u8>,
+    pub slide_id: Option<u8>,
+    pub category_title: Option<String>,
+    // MOT ContentName, e.g. a file name
+    pub content_name: Option<String>,
 }
 
 impl MotImage {
@@ -44,6 +57,10 @@ impl MotImage {
             data,
             click_through_url,
             alternative_location_url,
+            category_id: None,
+            slide_id: None,
+            category_title: None,
+            content_name: None,
         }
     }
 
@@ -77,6 +94,9 @@ pub struct MotObject {
     pub body: Vec<u8>,
     pub header_complete: bool,
     pub body_complete: bool,
+    // segments by number, assembled into header / body once complete
+    header_segments: SegmentBuffer,
+    body_segments: SegmentBuffer,
 
     // available after parsing
     // primary MOT header
@@ -87,6 +107,9 @@ pub struct MotObject {
     pub content_name: Option<String>,
     pub click_through_url: Option<String>,
     pub alternative_location_url: Option<String>,
+    pub category_id: Option<u8>,
+    pub slide_id: Option<u8>,
+    pub category_title: Option<String>,
 }
 
 impl MotObject {
@@ -98,12 +121,17 @@ impl MotObject {
             body: Vec::new(),
             header_complete: false,
             body_complete: false,
+            header_segments: SegmentBuffer::default(),
+            body_segments: SegmentBuffer::default(),
             body_size: None,
             content_type: None,
             content_subtype: None,
             content_name: None,
             click_through_url: None,
             alternative_location_url: None,
+            category_id: None,
+            slide_id: None,
+            category_title: None,
         }
     }
 
@@ -111,6 +139,14 @@ impl MotObject {
         self.header_complete && self.body_complete
     }
 
+    // URL parameters (ClickThroughURL, AlternativeLocationURL): some encoders pad
+    // with NUL / spaces, an empty value is treated as absent
+    fn url_param(field_data: &[u8]) -> Option<String> {
+        let value = String::from_utf8_lossy(field_data);
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
     pub fn parse_header(&mut self) {
         // log::debug!("MOT parse header: {} bytes", self.header.len());
 
@@ -216,24 +252,45 @@ impl MotObject {
                 self.content_name = Some(value.clone());
             }
 
-            // ClickThroughURL (ParamID = 0x27)
-            if param_id == 0x27 && field_data.len() > 1 {
-                let value = String::from_utf8_lossy(field_data).to_string();
-                self.click_through_url = Some(value.clone());
+            // CategoryID/SlideID (ParamID = 0x25)
+            if param_id == 0x25 && field_data.len() >= 2 {
+                self.category_id = Some(field_data[0]);
+                self.slide_id = Some(field_data[1]);
 
-                log::trace!("[{:>2}] MOT header: ClickThroughURL: {} ", self.scid, value);
+                log::trace!(
+                    "[{:>2}] MOT header: CategoryID: {} SlideID: {}",
+                    self.scid,
+                    field_data[0],
+                    field_data[1]
+                );
             }
 
-            // AlternativeLocationURL (ParamID = 0x28)
-            if param_id == 0x28 && field_data.len() > 1 {
+            // CategoryTitle (ParamID = 0x26)
+            if param_id == 0x26 && !field_data.is_empty() {
                 let value = String::from_utf8_lossy(field_data).to_string();
-                self.alternative_location_url = Some(value.clone());
+                self.category_title = Some(value.clone());
 
-                log::trace!(
-                    "[{:>2}] MOT header: AlternativeLocationURL: {} ",
-                    self.scid,
-                    value
-                );
+                log::trace!("[{:>2}] MOT header: CategoryTitle: {} ", self.scid, value);
+            }
+
+            // ClickThroughURL (ParamID = 0x27)
+            if param_id == 0x27 {
+                if let Some(value) = Self::url_param(field_data) {
+                    log::trace!("[{:>2}] MOT header: ClickThroughURL: {} ", self.scid, value);
+                    self.click_through_url = Some(value);
+                }
+            }
+
+            // AlternativeLocationURL (ParamID = 0x28)
+            if param_id == 0x28 {
+                if let Some(value) = Self::url_param(field_data) {
+                    log::trace!(
+                        "[{:>2}] MOT header: AlternativeLocationURL: {} ",
+                        self.scid,
+                        value
+                    );
+                    self.alternative_location_url = Some(value);
+                }
             }
 
             // MOT parameter CAInfo > scrambled
@@ -261,7 +318,7 @@ impl MotObject {
         );
 
         match content_type {
-            2 => {}
+            2 | 7 => {}
             _ => {
                 log::warn!("MOT unknown content type: {}", content_type);
             }
@@ -269,10 +326,99 @@ impl MotObject {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum SegmentStatus {
+    Pending,
+    Complete,
+    // a segment is still missing after MOT_MAX_GAP_REPETITIONS carousel cycles
+    Expired,
+}
+
+#[derive(Debug, Default)]
+struct SegmentBuffer {
+    segments: BTreeMap<u16, Vec<u8>>,
+    // bytes held in segments
+    len: usize,
+    last: Option<u16>,
+    // a segment was missing at some point
+    gap: bool,
+    // carousel cycles (repeated segment 0) seen while a segment is missing
+    repetitions: u8,
+}
+
+impl SegmentBuffer {
+    fn insert(&mut self, index: u16, last: bool, data: &[u8]) -> SegmentStatus {
+        if last {
+            self.last = Some(index);
+        }
+
+        if self.segments.contains_key(&index) {
+            if index == 0 && self.has_gap() {
+                self.repetitions += 1;
+                if self.repetitions > MOT_MAX_GAP_REPETITIONS {
+                    return SegmentStatus::Expired;
+                }
+            }
+        } else {
+            self.segments.insert(index, data.to_vec());
+            self.len += data.len();
+        }
+
+        if self.has_gap() {
+            self.gap = true;
+        }
+
+        if self.is_complete() {
+            SegmentStatus::Complete
+        } else {
+            SegmentStatus::Pending
+        }
+    }
+
+    // a segment below the highest known one is missing
+    fn has_gap(&self) -> bool {
+        self.segments
+            .keys()
+            .next_back()
+            .is_some_and(|&max| self.segments.len() <= max as usize)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.last.is_some_and(|last| {
+            self.segments.len() == last as usize + 1
+                && self.segments.keys().next_back() == Some(&last)
+        })
+    }
+
+    fn assemble(&self) -> Vec<u8> {
+        self.segments.values().flatten().copied().collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MotStats {
+    // objects with a missing segment
+    pub incomplete: u64,
+    // ... completed by a carousel repetition
+    pub recovered: u64,
+    // ... given up on
+    pub dropped: u64,
+    // objects dropped for exceeding the size limit (also counted in dropped)
+    pub oversized: u64,
+}
+
 #[derive(Debug)]
 pub struct MotDecoder {
     scid: u8,
     pub current: Option<MotObject>,
+    // carousel retransmissions are suppressed unless force_emit is set
+    last_md5: Option<[u8; 16]>,
+    pub force_emit: bool,
+    // header + body bytes of the object in assembly, larger objects are dropped
+    pub max_object_size: usize,
+    // last object dropped as too large, its carousel repetitions are ignored
+    oversized_transport_id: Option<u16>,
+    pub stats: MotStats,
 }
 
 impl MotDecoder {
@@ -280,6 +426,11 @@ impl MotDecoder {
         Self {
             scid,
             current: None,
+            last_md5: None,
+            force_emit: false,
+            max_object_size: MOT_MAX_OBJECT_SIZE,
+            oversized_transport_id: None,
+            stats: MotStats::default(),
         }
     }
     pub fn feed(&mut self, dg: &MscDataGroup) {
@@ -296,101 +447,496 @@ impl MotDecoder {
 
         let seg_type = dg.seg_type;
         let transport_id = dg.transport_id.unwrap_or(0);
-        let data = &dg.data_field[2..];
+        let segment = dg.segment_index();
+        let data = dg.payload();
 
         // log::debug!("MOT DG: type = {} - id = {} - data = {} bytes", seg_type, transport_id, data.len());
 
+        // a header with another transport id starts the next object, the id may be reused later
+        if seg_type == 3
+            && self
+                .oversized_transport_id
+                .is_some_and(|id| id != transport_id)
+        {
+            self.oversized_transport_id = None;
+        }
+
+        if self.oversized_transport_id == Some(transport_id) {
+            return;
+        }
+
         match seg_type {
             3 => {
-                // start new MOT object on header
-                // log::debug!("MOT: header: {} bytes", data.len());
+                // start new MOT object on a header with a new transport id
+                if !self
+                    .current
+                    .as_ref()
+                    .is_some_and(|o| o.transport_id == transport_id)
+                {
+                    if let Some(obj) = self.current.take() {
+                        self.drop_object(&obj, "superseded");
+                    }
+                    self.current = Some(MotObject::new(self.scid, transport_id));
+                }
+            }
+            4 => match self.current {
+                Some(ref obj) if obj.transport_id != transport_id => {
+                    log::warn!(
+                        "MOT: transport_id mismatch (got {}, expected {})",
+                        transport_id,
+                        obj.transport_id
+                    );
+                    return;
+                }
+                Some(_) => {}
+                // if we start extracting in the middle of a transmission
+                None => return,
+            },
+            _ => {
+                log::debug!("MOT: skipping unsupported seg_type {}", seg_type);
+                return;
+            }
+        }
 
-                let mut obj = MotObject::new(self.scid, transport_id);
-                obj.header.extend_from_slice(data);
-                obj.header_complete = dg.last_flag;
+        let Some(mut obj) = self.current.take() else {
+            return;
+        };
 
-                if obj.header_complete {
-                    obj.parse_header();
+        let segments = if seg_type == 3 {
+            &mut obj.header_segments
+        } else {
+            &mut obj.body_segments
+        };
 
-                    log::trace!(
-                        "[{:>2}] MOT header complete: {} bytes - {:?}",
-                        self.scid,
-                        obj.header.len(),
-                        obj.content_name
-                    );
+        let had_gap = segments.gap;
+        let status = segments.insert(segment, dg.last_segment(), data);
+
+        if segments.gap && !had_gap {
+            self.stats.incomplete += 1;
+            log::debug!(
+                "[{:>2}] MOT {} segment missing - transport id {}",
+                self.scid,
+                if seg_type == 3 { "header" } else { "body" },
+                transport_id
+            );
+        }
+
+        // one object is assembled at a time, so this bounds the memory per subchannel
+        let size = obj.header_segments.len + obj.body_segments.len;
+        if size > self.max_object_size {
+            self.drop_oversized(&obj, size);
+            return;
+        }
+
+        match status {
+            SegmentStatus::Expired => {
+                self.drop_object(&obj, "segment missing");
+                return;
+            }
+            SegmentStatus::Complete if seg_type == 3 && !obj.header_complete => {
+                obj.header = obj.header_segments.assemble();
+                obj.header_complete = true;
+                obj.parse_header();
+
+                // reject on the announced size, before the body is received
+                if let Some(body_size) = obj.body_size.filter(|s| *s > self.max_object_size) {
+                    self.drop_oversized(&obj, body_size);
+                    return;
                 }
 
-                self.current = Some(obj);
+                log::trace!(
+                    "[{:>2}] MOT header complete: {} bytes - {:?}",
+                    self.scid,
+                    obj.header.len(),
+                    obj.content_name
+                );
             }
+            SegmentStatus::Complete if seg_type == 4 && !obj.body_complete => {
+                obj.body = obj.body_segments.assemble();
+                obj.body_complete = true;
+            }
+            _ => {}
+        }
 
-            4 => {
-                if let Some(ref mut obj) = self.current {
-                    if obj.transport_id != transport_id {
-                        log::warn!(
-                            "MOT: transport_id mismatch (got {}, expected {})",
-                            transport_id,
-                            obj.transport_id
-                        );
-                        return;
-                    }
+        if obj.is_complete() {
+            if obj.header_segments.gap || obj.body_segments.gap {
+                self.stats.recovered += 1;
+            }
+            self.complete(obj);
+        } else {
+            if seg_type == 4 {
+                log::trace!(
+                    "[{:>2}] MOT body segment: received {} segments",
+                    self.scid,
+                    obj.body_segments.segments.len()
+                );
+            }
+            self.current = Some(obj);
+        }
+    }
 
-                    // log::debug!("MOT: body: {} bytes", data.len());
-
-                    obj.body.extend_from_slice(data);
-                    obj.body_complete = dg.last_flag;
-
-                    if obj.is_complete() {
-                        log::debug!(
-                            "[{:>2}] MOT object complete: Header = {} bytes, Body = {} bytes",
-                            self.scid,
-                            obj.header.len(),
-                            obj.body.len()
-                        );
-
-                        // log::debug!(
-                        //     "[{:2}] MOT: Header = {} bytes, Body = {} bytes",
-                        //     self.scid,
-                        //     obj.header.len(),
-                        //     obj.body.len()
-                        // );
-
-                        match obj.content_type {
-                            Some(2) => {
-                                let mot_image = MotImage::new(
-                                    self.scid,
-                                    obj.content_subtype.unwrap_or(0),
-                                    obj.body.clone(),
-                                    obj.click_through_url.clone(),
-                                    obj.alternative_location_url.clone(),
-                                );
-                                emit_event(DabEvent::MotImageReceived(mot_image));
-                            }
-                            _ => {
-                                log::warn!(
-                                    "MOT unknown content type: {}",
-                                    obj.content_type.unwrap_or(0)
-                                );
-                            }
-                        }
+    fn drop_object(&mut self, obj: &MotObject, reason: &str) {
+        if obj.is_complete() {
+            return;
+        }
+        self.stats.dropped += 1;
+        log::debug!(
+            "[{:>2}] MOT object {} dropped: {}",
+            self.scid,
+            obj.transport_id,
+            reason
+        );
+    }
 
-                        self.current = None;
-                    } else {
-                        log::trace!(
-                            "[{:>2}] MOT body segment: received {} of total {} bytes",
-                            self.scid,
-                            obj.body.len(),
-                            obj.body_size.unwrap_or(0)
-                        );
-                    }
+    fn drop_oversized(&mut self, obj: &MotObject, size: usize) {
+        self.oversized_transport_id = Some(obj.transport_id);
+        self.stats.oversized += 1;
+        log::warn!(
+            "[{:>2}] MOT object {}: {} bytes exceed the limit of {} bytes",
+            self.scid,
+            obj.transport_id,
+            size,
+            self.max_object_size
+        );
+        self.drop_object(obj, "too large");
+    }
+
+    fn complete(&mut self, obj: MotObject) {
+        log::debug!(
+            "[{:>2}] MOT object complete: Header = {} bytes, Body = {} bytes",
+            self.scid,
+            obj.header.len(),
+            obj.body.len()
+        );
+
+        match obj.content_type {
+            Some(2) => {
+                let mut mot_image = MotImage::new(
+                    self.scid,
+                    obj.content_subtype.unwrap_or(0),
+                    obj.body,
+                    obj.click_through_url,
+                    obj.alternative_location_url,
+                );
+                mot_image.category_id = obj.category_id;
+                mot_image.slide_id = obj.slide_id;
+                mot_image.category_title = obj.category_title;
+                mot_image.content_name = obj.content_name;
+
+                if self.force_emit || self.last_md5 != Some(mot_image.md5) {
+                    self.last_md5 = Some(mot_image.md5);
+                    emit_event(DabEvent::MotImageReceived(mot_image));
                 } else {
-                    // if we start extracting in the middle of a transmission
-                    // log::debug!("MOT: body segment received without active header");
+                    log::trace!("[{:>2}] MOT image unchanged", self.scid);
                 }
             }
-
+            // EPG, subtype 1: programme information
+            Some(7) if obj.content_subtype == Some(1) => match epg::decode_schedule(&obj.body) {
+                Ok(schedule) => {
+                    emit_event(DabEvent::EpgScheduleReceived(schedule));
+                }
+                Err(e) => {
+                    log::warn!("[{:>2}] EPG decode error: {}", self.scid, e);
+                }
+            },
+            Some(7) => {
+                log::debug!(
+                    "[{:>2}] EPG subtype {} not supported",
+                    self.scid,
+                    obj.content_subtype.unwrap_or(0)
+                );
+            }
             _ => {
-                log::debug!("MOT: skipping unsupported seg_type {}", seg_type);
+                log::warn!(
+                    "MOT unknown content type: {}",
+                    obj.content_type.unwrap_or(0)
+                );
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dab::bus::capture_events;
+    use crate::utils::calc_crc16_ccitt;
+
+    // single (last) segment with transport id and CRC
+    fn data_group(seg_type: u8, transport_id: u16, payload: &[u8]) -> MscDataGroup {
+        segment(seg_type, transport_id, 0, true, payload)
+    }
+
+    fn segment(
+        seg_type: u8,
+        transport_id: u16,
+        segment: u16,
+        last: bool,
+        payload: &[u8],
+    ) -> MscDataGroup {
+        let mut data = vec![0x70 | seg_type, 0x00];
+        data.extend((segment | u16::from(last) << 15).to_be_bytes());
+        data.push(0x12);
+        data.extend(transport_id.to_be_bytes());
+        data.extend((payload.len() as u16).to_be_bytes());
+        data.extend(payload);
+        data.extend(calc_crc16_ccitt(&data).to_be_bytes());
+
+        let dg = MscDataGroup::from_bytes(&data);
+        assert!(dg.is_valid);
+        dg
+    }
+
+    // core header only: BodySize, HeaderSize 7, ContentType 2 (image), ContentSubType 1 (JPEG)
+    fn header(transport_id: u16, body_size: u64) -> MscDataGroup {
+        let core = body_size << 28 | 7 << 15 | 2 << 9 | 1;
+        data_group(3, transport_id, &core.to_be_bytes()[1..])
+    }
+
+    fn body(transport_id: u16, len: usize) -> MscDataGroup {
+        data_group(4, transport_id, &vec![0xAA; len])
+    }
+
+    #[test]
+    fn mot_complete() {
+        let mut decoder = MotDecoder::new(1);
+
+        decoder.feed(&header(1, 20));
+        assert!(decoder.current.as_ref().is_some_and(|o| o.header_complete));
+
+        decoder.feed(&body(1, 20));
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.dropped, 0);
+    }
+
+    // segment n of a 3 segment body, 8 bytes each
+    fn body_segment(transport_id: u16, n: u16) -> MscDataGroup {
+        segment(4, transport_id, n, n == 2, &[n as u8; 8])
+    }
+
+    fn images(events: Vec<DabEvent>) -> Vec<MotImage> {
+        events
+            .into_iter()
+            .filter_map(|e| match e {
+                DabEvent::MotImageReceived(image) => Some(image),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mot_segment_gap_recovered() {
+        let mut decoder = MotDecoder::new(1);
+
+        let events = capture_events(|| {
+            decoder.feed(&header(1, 24));
+            // segment 1 lost
+            decoder.feed(&body_segment(1, 0));
+            decoder.feed(&body_segment(1, 2));
+            assert_eq!(decoder.stats.incomplete, 1);
+            assert!(decoder.current.is_some());
+
+            // next carousel cycle
+            for n in 0..3 {
+                decoder.feed(&body_segment(1, n));
+            }
+        });
+
+        let images = images(events);
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].data, [[0; 8], [1; 8], [2; 8]].concat());
+        assert_eq!(decoder.stats.recovered, 1);
+        assert_eq!(decoder.stats.dropped, 0);
+    }
+
+    #[test]
+    fn mot_segment_gap_expired() {
+        let mut decoder = MotDecoder::new(1);
+
+        let events = capture_events(|| {
+            decoder.feed(&header(1, 24));
+            decoder.feed(&body_segment(1, 0));
+            decoder.feed(&body_segment(1, 2));
+
+            // segment 1 never repeated
+            for _ in 0..MOT_MAX_GAP_REPETITIONS {
+                decoder.feed(&body_segment(1, 0));
+                decoder.feed(&body_segment(1, 2));
+                assert!(decoder.current.is_some());
+            }
+            decoder.feed(&body_segment(1, 0));
+        });
+
+        assert!(images(events).is_empty());
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.incomplete, 1);
+        assert_eq!(decoder.stats.dropped, 1);
+    }
+
+    #[test]
+    fn mot_unchanged_suppressed() {
+        let mut decoder = MotDecoder::new(1);
+        let cycle = |decoder: &mut MotDecoder, fill: u8| {
+            let events = capture_events(|| {
+                decoder.feed(&header(1, 8));
+                decoder.feed(&data_group(4, 1, &[fill; 8]));
+            });
+            images(events).len()
+        };
+
+        assert_eq!(cycle(&mut decoder, 0xAA), 1);
+        // carousel repetition
+        assert_eq!(cycle(&mut decoder, 0xAA), 0);
+        assert_eq!(cycle(&mut decoder, 0xBB), 1);
+        assert_eq!(cycle(&mut decoder, 0xAA), 1);
+
+        decoder.force_emit = true;
+        assert_eq!(cycle(&mut decoder, 0xAA), 1);
+        assert_eq!(cycle(&mut decoder, 0xAA), 1);
+    }
+
+    #[test]
+    fn mot_oversized_announced() {
+        let mut decoder = MotDecoder::new(1);
+        decoder.max_object_size = 100;
+
+        // rejected on the header's body size
+        decoder.feed(&header(1, 1000));
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.oversized, 1);
+        assert_eq!(decoder.stats.dropped, 1);
+
+        // carousel repetitions are ignored
+        decoder.feed(&header(1, 1000));
+        decoder.feed(&body(1, 50));
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.oversized, 1);
+    }
+
+    #[test]
+    fn mot_oversized_segments() {
+        let mut decoder = MotDecoder::new(1);
+        decoder.max_object_size = 100;
+
+        // the header understates the size
+        decoder.feed(&header(1, 20));
+        decoder.feed(&body(1, 200));
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.oversized, 1);
+    }
+
+    #[test]
+    fn mot_oversized_transport_id_reused() {
+        let mut decoder = MotDecoder::new(1);
+        decoder.max_object_size = 100;
+
+        decoder.feed(&header(1, 1000));
+        assert_eq!(decoder.stats.oversized, 1);
+
+        decoder.feed(&header(2, 20));
+        decoder.feed(&body(2, 20));
+        assert!(decoder.current.is_none());
+
+        // a later object with the same transport id is accepted
+        decoder.feed(&header(1, 20));
+        assert!(decoder
+            .current
+            .as_ref()
+            .is_some_and(|o| o.transport_id == 1));
+        decoder.feed(&body(1, 20));
+        assert!(decoder.current.is_none());
+        assert_eq!(decoder.stats.oversized, 1);
+        assert_eq!(decoder.stats.dropped, 1);
+    }
+
+    #[test]
+    fn url_param() {
+        assert_eq!(
+            MotObject::url_param(b"http://example.com/\0\0").as_deref(),
+            Some("http://example.com/")
+        );
+        assert_eq!(
+            MotObject::url_param(b" http://example.com/ \r\n").as_deref(),
+            Some("http://example.com/")
+        );
+        assert_eq!(MotObject::url_param(b"x").as_deref(), Some("x"));
+        assert_eq!(MotObject::url_param(b"\0 \0"), None);
+        assert_eq!(MotObject::url_param(b""), None);
+    }
+
+    // header core plus extension parameters, all with PLI 3 and a short length
+    fn header_data(body_size: u64, params: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut extension = Vec::new();
+        for (param_id, value) in params {
+            extension.push(0xC0 | param_id);
+            extension.push(value.len() as u8);
+            extension.extend_from_slice(value);
+        }
+
+        let header_size = 7 + extension.len() as u64;
+        let core = body_size << 28 | header_size << 15 | 2 << 9 | 1;
+
+        let mut data = core.to_be_bytes()[1..].to_vec();
+        data.extend(extension);
+        data
+    }
+
+    fn parse_header(params: &[(u8, &[u8])]) -> MotObject {
+        let mut obj = MotObject::new(1, 1);
+        obj.header = header_data(1000, params);
+        obj.parse_header();
+        obj
+    }
+
+    #[test]
+    fn url_params_in_header() {
+        let obj = parse_header(&[(0x27, b"http://example.com/slide\0\0"), (0x28, b"  ")]);
+        assert_eq!(obj.body_size, Some(1000));
+        assert_eq!(
+            obj.click_through_url.as_deref(),
+            Some("http://example.com/slide")
+        );
+        assert_eq!(obj.alternative_location_url, None);
+
+        let obj = parse_header(&[(0x27, b""), (0x28, b"x")]);
+        assert_eq!(obj.click_through_url, None);
+        assert_eq!(obj.alternative_location_url.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn category_slide_params() {
+        let params: [(u8, &[u8]); 3] = [
+            (0x0C, b"\x00slide-7.jpg"),
+            (0x25, &[3, 7]),
+            (0x26, b"Weather"),
+        ];
+        let obj = parse_header(&params);
+        assert_eq!(obj.category_id, Some(3));
+        assert_eq!(obj.slide_id, Some(7));
+        assert_eq!(obj.category_title.as_deref(), Some("Weather"));
+        assert_eq!(obj.content_name.as_deref(), Some("slide-7.jpg"));
+
+        // plain SLS
+        let obj = parse_header(&[]);
+        assert_eq!((obj.category_id, obj.slide_id), (None, None));
+        assert_eq!(obj.category_title, None);
+
+        // CategoryID without SlideID
+        assert_eq!(parse_header(&[(0x25, &[3])]).category_id, None);
+
+        // passed on with the image
+        let mut decoder = MotDecoder::new(1);
+        let events = capture_events(|| {
+            decoder.feed(&data_group(3, 1, &header_data(8, &params)));
+            decoder.feed(&body(1, 8));
+        });
+        let images = images(events);
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            (images[0].category_id, images[0].slide_id),
+            (Some(3), Some(7))
+        );
+        assert_eq!(images[0].category_title.as_deref(), Some("Weather"));
+        assert_eq!(images[0].content_name.as_deref(), Some("slide-7.jpg"));
+    }
+}
@@ -0,0 +1,118 @@
+use std::io;
+use std::path::Path;
+
+use super::bus::{init_event_bus, DabEvent};
+use super::frame::{AF_CRC_LEN, AF_HEADER_LEN};
+use super::DabSource;
+
+// split a raw capture (complete AF packets, as written by `edinburgh --save-raw`) into packets.
+// garbage between packets is skipped, as is an "AF" whose LEN runs past the end of the data
+// (a corrupt header or a truncated last packet).
+pub fn split_packets(data: &[u8]) -> Vec<&[u8]> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+
+    while offset + AF_HEADER_LEN <= data.len() {
+        if &data[offset..offset + 2] != b"AF" {
+            offset += 1;
+            continue;
+        }
+
+        let len = u32::from_be_bytes([
+            data[offset + 2],
+            data[offset + 3],
+            data[offset + 4],
+            data[offset + 5],
+        ]) as usize;
+        let end = offset + AF_HEADER_LEN + len + AF_CRC_LEN;
+
+        if end > data.len() {
+            // resync on the next "AF"
+            offset += 1;
+            continue;
+        }
+
+        packets.push(&data[offset..end]);
+        offset = end;
+    }
+
+    packets
+}
+
+// decode a raw capture and collect all emitted events, e.g. for regression checks.
+// scid: selected subchannel, see DabSourceBuilder::scid.
+// (re-)initializes the global event bus, so don't run it next to a live source.
+pub async fn replay_file(path: impl AsRef<Path>, scid: Option<u8>) -> io::Result<Vec<DabEvent>> {
+    let data = std::fs::read(path)?;
+
+    let mut rx = init_event_bus();
+    let mut builder = DabSource::builder();
+    if let Some(scid) = scid {
+        builder = builder.scid(scid);
+    }
+    let mut source = builder.build();
+
+    for packet in split_packets(&data) {
+        source.feed(packet).await;
+    }
+
+    // closes the bus unless other sources still use it, events are emitted synchronously
+    // while feeding so draining what is queued is enough either way
+    source.shutdown();
+
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn af_packet(payload: &[u8]) -> Vec<u8> {
+        let mut data = b"AF".to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x90, b'T']);
+        data.extend_from_slice(payload);
+        data.extend_from_slice(&[0x00, 0x00]);
+        data
+    }
+
+    #[test]
+    fn split_skips_garbage() {
+        let a = af_packet(b"first");
+        let b = af_packet(b"second");
+
+        let mut data = b"xyz".to_vec();
+        data.extend_from_slice(&a);
+        data.extend_from_slice(b"AXFA");
+        data.extend_from_slice(&b);
+
+        assert_eq!(split_packets(&data), vec![a.as_slice(), b.as_slice()]);
+    }
+
+    #[test]
+    fn split_resyncs_after_oversized_len() {
+        let a = af_packet(b"first");
+
+        // LEN runs past the end of the data, the packet behind it must not be lost
+        let mut data = b"AF\x00\x10\x00\x00\x00\x00\x90T".to_vec();
+        data.extend_from_slice(&a);
+
+        assert_eq!(split_packets(&data), vec![a.as_slice()]);
+    }
+
+    #[test]
+    fn split_drops_truncated_last_packet() {
+        let a = af_packet(b"first");
+        let b = af_packet(b"second");
+
+        let mut data = a.clone();
+        data.extend_from_slice(&b[..b.len() - 1]);
+
+        assert_eq!(split_packets(&data), vec![a.as_slice()]);
+    }
+}
@@ -269,19 +269,50 @@ impl fmt::Display for Language {
     }
 }
 
+// user application types (TS 101 756 table 16)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum UserApplication {
     Reserved = 0x000,
     Sls = 0x002,
+    Bws = 0x003,
     Tpeg = 0x004,
+    Dgps = 0x005,
+    Tmc = 0x006,
     Spi = 0x007,
+    DabJava = 0x008,
     Dmb = 0x009,
+    Ipdc = 0x00A,
+    Voice = 0x00B,
+    Middleware = 0x00C,
     Filecasting = 0x00D,
     Fis = 0x00E,
     Journaline = 0x044A,
 
-    Unknown(u8), // fallback for unmapped (0..=255 only)
+    Unknown(u16), // fallback for unmapped types
+}
+
+impl UserApplication {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserApplication::Reserved => "Reserved",
+            UserApplication::Sls => "Slideshow",
+            UserApplication::Bws => "Broadcast Website",
+            UserApplication::Tpeg => "TPEG",
+            UserApplication::Dgps => "DGPS",
+            UserApplication::Tmc => "TMC",
+            UserApplication::Spi => "EPG / SPI",
+            UserApplication::DabJava => "DAB Java",
+            UserApplication::Dmb => "DMB",
+            UserApplication::Ipdc => "IPDC",
+            UserApplication::Voice => "Voice",
+            UserApplication::Middleware => "Middleware",
+            UserApplication::Filecasting => "Filecasting",
+            UserApplication::Fis => "FIS",
+            UserApplication::Journaline => "Journaline",
+            UserApplication::Unknown(_) => "Unknown",
+        }
+    }
 }
 
 impl From<u16> for UserApplication {
@@ -289,13 +320,20 @@ impl From<u16> for UserApplication {
         match value {
             0x000 => UserApplication::Reserved,
             0x002 => UserApplication::Sls,
+            0x003 => UserApplication::Bws,
             0x004 => UserApplication::Tpeg,
+            0x005 => UserApplication::Dgps,
+            0x006 => UserApplication::Tmc,
             0x007 => UserApplication::Spi,
+            0x008 => UserApplication::DabJava,
             0x009 => UserApplication::Dmb,
+            0x00A => UserApplication::Ipdc,
+            0x00B => UserApplication::Voice,
+            0x00C => UserApplication::Middleware,
             0x00D => UserApplication::Filecasting,
             0x00E => UserApplication::Fis,
             0x044A => UserApplication::Journaline,
-            val => UserApplication::Unknown((val & 0xFF) as u8),
+            val => UserApplication::Unknown(val),
         }
     }
 }
@@ -303,15 +341,8 @@ impl From<u16> for UserApplication {
 impl fmt::Display for UserApplication {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            UserApplication::Reserved => write!(f, "Reserved"),
-            UserApplication::Sls => write!(f, "SLS"),
-            UserApplication::Tpeg => write!(f, "TPEG"),
-            UserApplication::Spi => write!(f, "SPI"),
-            UserApplication::Dmb => write!(f, "DMB"),
-            UserApplication::Filecasting => write!(f, "Filecasting"),
-            UserApplication::Fis => write!(f, "FIS"),
-            UserApplication::Journaline => write!(f, "Journaline"),
-            UserApplication::Unknown(v) => write!(f, "Unknown(0x{:02X})", v),
+            UserApplication::Unknown(v) => write!(f, "Unknown(0x{:03X})", v),
+            ua => write!(f, "{}", ua.name()),
         }
     }
 }
@@ -325,6 +356,203 @@ impl Serialize for UserApplication {
     }
 }
 
+// audio service component type, ASCTy of FIG 0/2 (TS 101 756 table 2a)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioServiceType {
+    // MPEG-1 / MPEG-2 Layer II
+    Dab,
+    // HE-AAC v2 superframes
+    DabPlus,
+    Reserved(u8),
+}
+
+impl AudioServiceType {
+    pub fn codec(&self) -> &'static str {
+        match self {
+            AudioServiceType::Dab => "MP2",
+            AudioServiceType::DabPlus => "AAC",
+            AudioServiceType::Reserved(_) => "Unknown",
+        }
+    }
+}
+
+impl From<u8> for AudioServiceType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => AudioServiceType::Dab,
+            63 => AudioServiceType::DabPlus,
+            val => AudioServiceType::Reserved(val),
+        }
+    }
+}
+
+impl fmt::Display for AudioServiceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioServiceType::Dab => write!(f, "DAB"),
+            AudioServiceType::DabPlus => write!(f, "DAB+"),
+            AudioServiceType::Reserved(v) => write!(f, "Reserved({})", v),
+        }
+    }
+}
+
+impl Serialize for AudioServiceType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+// announcement types, bit position in the ASu / ASw flags (TS 101 756 table 14)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnouncementType {
+    Alarm,
+    RoadTraffic,
+    TransportFlash,
+    Warning,
+    NewsFlash,
+    AreaWeather,
+    Event,
+    SpecialEvent,
+    ProgrammeInformation,
+    Sport,
+    Financial,
+    Reserved(u8),
+}
+
+impl AnnouncementType {
+    // all types flagged in a 16-bit ASu / ASw field
+    pub fn from_flags(flags: u16) -> Vec<Self> {
+        (0..16)
+            .filter(|bit| flags & (1 << bit) != 0)
+            .map(Self::from)
+            .collect()
+    }
+}
+
+impl From<u8> for AnnouncementType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => AnnouncementType::Alarm,
+            1 => AnnouncementType::RoadTraffic,
+            2 => AnnouncementType::TransportFlash,
+            3 => AnnouncementType::Warning,
+            4 => AnnouncementType::NewsFlash,
+            5 => AnnouncementType::AreaWeather,
+            6 => AnnouncementType::Event,
+            7 => AnnouncementType::SpecialEvent,
+            8 => AnnouncementType::ProgrammeInformation,
+            9 => AnnouncementType::Sport,
+            10 => AnnouncementType::Financial,
+            val => AnnouncementType::Reserved(val),
+        }
+    }
+}
+
+impl fmt::Display for AnnouncementType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnouncementType::Alarm => write!(f, "Alarm"),
+            AnnouncementType::RoadTraffic => write!(f, "Road Traffic flash"),
+            AnnouncementType::TransportFlash => write!(f, "Transport flash"),
+            AnnouncementType::Warning => write!(f, "Warning/Service"),
+            AnnouncementType::NewsFlash => write!(f, "News flash"),
+            AnnouncementType::AreaWeather => write!(f, "Area weather flash"),
+            AnnouncementType::Event => write!(f, "Event announcement"),
+            AnnouncementType::SpecialEvent => write!(f, "Special event"),
+            AnnouncementType::ProgrammeInformation => write!(f, "Programme Information"),
+            AnnouncementType::Sport => write!(f, "Sport report"),
+            AnnouncementType::Financial => write!(f, "Financial report"),
+            AnnouncementType::Reserved(v) => write!(f, "Reserved({})", v),
+        }
+    }
+}
+
+impl Serialize for AnnouncementType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+// ECC + country id (first nibble of EId / SId) > country (TS 101 756 table 3, european broadcasting area)
+pub fn country_name(ecc: u8, country_id: u8) -> Option<&'static str> {
+    match (ecc, country_id) {
+        (0xE0, 0x1) => Some("Germany"),
+        (0xE0, 0x2) => Some("Algeria"),
+        (0xE0, 0x3) => Some("Andorra"),
+        (0xE0, 0x4) => Some("Israel"),
+        (0xE0, 0x5) => Some("Italy"),
+        (0xE0, 0x6) => Some("Belgium"),
+        (0xE0, 0x7) => Some("Russian Federation"),
+        (0xE0, 0x8) => Some("Palestine"),
+        (0xE0, 0x9) => Some("Albania"),
+        (0xE0, 0xA) => Some("Austria"),
+        (0xE0, 0xB) => Some("Hungary"),
+        (0xE0, 0xC) => Some("Malta"),
+        (0xE0, 0xD) => Some("Germany"),
+        (0xE0, 0xF) => Some("Egypt"),
+        (0xE1, 0x1) => Some("Greece"),
+        (0xE1, 0x2) => Some("Cyprus"),
+        (0xE1, 0x3) => Some("San Marino"),
+        (0xE1, 0x4) => Some("Switzerland"),
+        (0xE1, 0x5) => Some("Jordan"),
+        (0xE1, 0x6) => Some("Finland"),
+        (0xE1, 0x7) => Some("Luxembourg"),
+        (0xE1, 0x8) => Some("Bulgaria"),
+        (0xE1, 0x9) => Some("Denmark"),
+        (0xE1, 0xA) => Some("Gibraltar"),
+        (0xE1, 0xB) => Some("Iraq"),
+        (0xE1, 0xC) => Some("United Kingdom"),
+        (0xE1, 0xD) => Some("Libya"),
+        (0xE1, 0xE) => Some("Romania"),
+        (0xE1, 0xF) => Some("France"),
+        (0xE2, 0x1) => Some("Morocco"),
+        (0xE2, 0x2) => Some("Czech Republic"),
+        (0xE2, 0x3) => Some("Poland"),
+        (0xE2, 0x4) => Some("Vatican"),
+        (0xE2, 0x5) => Some("Slovakia"),
+        (0xE2, 0x6) => Some("Syria"),
+        (0xE2, 0x7) => Some("Tunisia"),
+        (0xE2, 0x9) => Some("Liechtenstein"),
+        (0xE2, 0xA) => Some("Iceland"),
+        (0xE2, 0xB) => Some("Monaco"),
+        (0xE2, 0xC) => Some("Lithuania"),
+        (0xE2, 0xD) => Some("Serbia"),
+        (0xE2, 0xE) => Some("Spain"),
+        (0xE2, 0xF) => Some("Norway"),
+        (0xE3, 0x1) => Some("Montenegro"),
+        (0xE3, 0x2) => Some("Ireland"),
+        (0xE3, 0x3) => Some("Turkey"),
+        (0xE3, 0x4) => Some("North Macedonia"),
+        (0xE3, 0x8) => Some("Netherlands"),
+        (0xE3, 0x9) => Some("Latvia"),
+        (0xE3, 0xA) => Some("Lebanon"),
+        (0xE3, 0xB) => Some("Azerbaijan"),
+        (0xE3, 0xC) => Some("Croatia"),
+        (0xE3, 0xD) => Some("Kazakhstan"),
+        (0xE3, 0xE) => Some("Sweden"),
+        (0xE3, 0xF) => Some("Belarus"),
+        (0xE4, 0x1) => Some("Moldova"),
+        (0xE4, 0x2) => Some("Estonia"),
+        (0xE4, 0x3) => Some("Kyrgyzstan"),
+        (0xE4, 0x6) => Some("Ukraine"),
+        (0xE4, 0x7) => Some("Kosovo"),
+        (0xE4, 0x8) => Some("Portugal"),
+        (0xE4, 0x9) => Some("Slovenia"),
+        (0xE4, 0xA) => Some("Armenia"),
+        (0xE4, 0xB) => Some("Uzbekistan"),
+        (0xE4, 0xC) => Some("Georgia"),
+        (0xE4, 0xE) => Some("Turkmenistan"),
+        (0xE4, 0xF) => Some("Bosnia and Herzegovina"),
+        _ => None,
+    }
+}
+
 pub static EBU_LATIN_TO_UNICODE: [u16; 256] = [
     0x0000, 0x0118, 0x012E, 0x0172, 0x0102, 0x0116, 0x010E, 0x0218, 0x021A, 0x010A, 0x000A, 0x000B,
     0x0120, 0x0139, 0x017B, 0x0143, 0x0105, 0x0119, 0x012F, 0x0173, 0x0103, 0x0117, 0x010F, 0x0219,
@@ -349,3 +577,43 @@ pub static EBU_LATIN_TO_UNICODE: [u16; 256] = [
     0x00E3, 0x00E5, 0x00E6, 0x0153, 0x0175, 0x00FD, 0x00F5, 0x00F8, 0x00FE, 0x014B, 0x0155, 0x0107,
     0x015B, 0x017A, 0x0165, 0x0127,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_application_name() {
+        let ua = UserApplication::from(0x002);
+        assert_eq!(ua, UserApplication::Sls);
+        assert_eq!(ua.name(), "Slideshow");
+        assert_eq!(ua.to_string(), "Slideshow");
+        assert_eq!(UserApplication::from(0x007).to_string(), "EPG / SPI");
+        assert_eq!(UserApplication::from(0x44A).to_string(), "Journaline");
+
+        // all 11 bits kept
+        let ua = UserApplication::from(0x7FF);
+        assert_eq!(ua, UserApplication::Unknown(0x7FF));
+        assert_eq!(ua.name(), "Unknown");
+        assert_eq!(ua.to_string(), "Unknown(0x7FF)");
+        assert_eq!(UserApplication::from(0x001).to_string(), "Unknown(0x001)");
+
+        assert_eq!(
+            serde_json::to_string(&UserApplication::Spi).unwrap(),
+            r#""EPG / SPI""#
+        );
+    }
+
+    #[test]
+    fn country_name_ecc() {
+        // the country id alone is ambiguous
+        assert_eq!(country_name(0xE0, 0xD), Some("Germany"));
+        assert_eq!(country_name(0xE1, 0xD), Some("Libya"));
+        assert_eq!(country_name(0xE1, 0xC), Some("United Kingdom"));
+        assert_eq!(country_name(0xE4, 0xF), Some("Bosnia and Herzegovina"));
+
+        // unassigned id, ECC outside the european table
+        assert_eq!(country_name(0xE0, 0xE), None);
+        assert_eq!(country_name(0xA0, 0x1), None);
+    }
+}
@@ -1,13 +1,146 @@
 use crate::dab::tables::EBU_LATIN_TO_UNICODE;
 
+// charset indicator of FIG 1 labels and DL (TS 101 756 table 19)
 pub fn decode_chars(chars: &[u8], charset: u8) -> String {
     match charset {
         0xF => String::from_utf8_lossy(chars).to_string(),
+        0x6 => decode_ucs2(chars),
         0x4 => chars.iter().map(|&b| b as char).collect(),
-        0x0 => chars
-            .iter()
-            .map(|&b| char::from_u32(EBU_LATIN_TO_UNICODE[b as usize] as u32).unwrap_or('?'))
-            .collect(),
-        _ => format!("[unsupported charset 0x{:X}]", charset),
+        0x0 => decode_ebu_latin(chars),
+        _ => {
+            log::debug!("Unsupported charset 0x{:X} - guessing", charset);
+            decode_text(chars)
+        }
+    }
+}
+
+// text without charset indicator: UTF-8 if valid, EBU Latin (the DAB default) otherwise
+pub fn decode_text(chars: &[u8]) -> String {
+    match std::str::from_utf8(chars) {
+        Ok(s) => s.to_string(),
+        Err(_) => decode_ebu_latin(chars),
+    }
+}
+
+fn decode_ebu_latin(chars: &[u8]) -> String {
+    chars
+        .iter()
+        .map(|&b| char::from_u32(EBU_LATIN_TO_UNICODE[b as usize] as u32).unwrap_or('?'))
+        .collect()
+}
+
+// UCS-2, big endian. an odd trailing byte is dropped
+fn decode_ucs2(chars: &[u8]) -> String {
+    let units: Vec<u16> = chars
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// MJD (days since 1858-11-17) > gregorian date (year, month, day)
+// integer civil-from-days conversion, exact for any MJD. the float formula from ETS 300 468 annex C
+// is only valid from 1900-03-01 to 2100-02-28 and is prone to rounding at month boundaries.
+pub fn mjd_to_date(mjd: u32) -> (i32, u8, u8) {
+    // days relative to 0000-03-01, years start in march so the leap day is the last day
+    let z = mjd as i64 + 678_881;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year as i32, month as u8, day as u8)
+}
+
+// gregorian date > MJD, inverse of mjd_to_date
+pub fn date_to_mjd(year: i32, month: u8, day: u8) -> u32 {
+    let y = year as i64 - if month <= 2 { 1 } else { 0 };
+    // month index with march = 0
+    let mp = (if month > 2 { month - 3 } else { month + 9 }) as i64;
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    (era * 146_097 + doe - 678_881) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_chars_ebu_latin() {
+        assert_eq!(decode_chars(b"Caf\x82 M\x99nchen", 0x0), "Café München");
+        // differs from ASCII / latin-1
+        assert_eq!(decode_chars(b"\x24\xA9", 0x0), "ł€");
+        assert_eq!(decode_chars(b"Caf\xE9", 0x4), "Café");
+    }
+
+    #[test]
+    fn decode_chars_ucs2() {
+        assert_eq!(
+            decode_chars(&[0x00, 0x5A, 0x00, 0xFC, 0x20, 0xAC], 0x6),
+            "Zü€"
+        );
+        // odd trailing byte dropped, unpaired surrogate replaced
+        assert_eq!(decode_chars(&[0x00, 0x41, 0x00], 0x6), "A");
+        assert_eq!(decode_chars(&[0xD8, 0x00, 0x00, 0x41], 0x6), "\u{FFFD}A");
+    }
+
+    #[test]
+    fn decode_chars_unknown_charset() {
+        // valid UTF-8 as is, EBU Latin otherwise
+        assert_eq!(decode_chars("Zürich".as_bytes(), 0x1), "Zürich");
+        assert_eq!(decode_chars(b"Z\x99rich", 0x1), "Zürich");
+        assert_eq!(decode_text(b"Z\x99rich"), "Zürich");
+        assert_eq!(decode_chars("Zürich".as_bytes(), 0xF), "Zürich");
+    }
+
+    #[test]
+    fn mjd_epoch() {
+        assert_eq!(mjd_to_date(0), (1858, 11, 17));
+        assert_eq!(date_to_mjd(1858, 11, 17), 0);
+    }
+
+    // ETS 300 468 annex C worked example
+    #[test]
+    fn mjd_annex_c() {
+        assert_eq!(mjd_to_date(45218), (1982, 9, 6));
+        assert_eq!(date_to_mjd(1982, 9, 6), 45218);
+    }
+
+    #[test]
+    fn mjd_leap_days() {
+        assert_eq!(mjd_to_date(51603), (2000, 2, 29));
+        assert_eq!(mjd_to_date(51604), (2000, 3, 1));
+        // 2100 is not a leap year
+        assert_eq!(mjd_to_date(88127), (2100, 2, 28));
+        assert_eq!(mjd_to_date(88128), (2100, 3, 1));
+        assert_eq!(date_to_mjd(2100, 3, 1) - date_to_mjd(2100, 2, 28), 1);
+    }
+
+    #[test]
+    fn mjd_round_trip() {
+        // 1858 - 2132, every day
+        for mjd in 0..100_000 {
+            let (year, month, day) = mjd_to_date(mjd);
+            assert_eq!(
+                date_to_mjd(year, month, day),
+                mjd,
+                "{}-{}-{}",
+                year,
+                month,
+                day
+            );
+        }
     }
 }
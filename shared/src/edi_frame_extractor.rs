@@ -1,4 +1,17 @@
 use std::fmt;
+use thiserror::Error;
+
+// sync (2), LEN (4), SEQ (2) - enough to know the size of the AF packet
+const AF_SYNC_HEADER_LEN: usize = 8;
+// AF packets carry 24ms of the ensemble, so a few kB in practice
+pub const DEFAULT_INITIAL_FRAME_SIZE: usize = 4096;
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FrameSizeError {
+    #[error("AF packet of {size} bytes exceeds max frame size of {max} bytes")]
+    TooLarge { size: usize, max: usize },
+}
 
 #[derive(Debug, Clone)]
 struct SyncMagic {
@@ -25,6 +38,8 @@ pub struct ApplicationFrame {
     pub data: Vec<u8>,
     pub initial_size: usize,
     pub expected_size: usize,
+    // upper bound for the size announced in the header, larger packets are rejected
+    pub max_size: usize,
     sync_magic: SyncMagic,
 }
 
@@ -36,10 +51,19 @@ impl Default for ApplicationFrame {
 
 impl ApplicationFrame {
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_INITIAL_FRAME_SIZE, DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    // initial_capacity is preallocated, so typical packets don't reallocate while growing
+    pub fn with_limits(initial_capacity: usize, max_size: usize) -> Self {
+        let mut data = Vec::with_capacity(initial_capacity.max(AF_SYNC_HEADER_LEN));
+        data.resize(AF_SYNC_HEADER_LEN, 0);
+
         ApplicationFrame {
-            data: vec![0; 8],
-            initial_size: 8,
+            data,
+            initial_size: AF_SYNC_HEADER_LEN,
             expected_size: 0,
+            max_size,
             sync_magic: SyncMagic::new(vec![b'A', b'F'], "AF"),
         }
     }
@@ -56,23 +80,35 @@ impl ApplicationFrame {
         None
     }
 
-    pub fn check_completed(&mut self) -> bool {
+    // on error the frame is reset, the caller should drop the sync bytes and search again
+    pub fn check_completed(&mut self) -> Result<bool, FrameSizeError> {
         let d = &self.data;
         if d.is_empty() {
-            return false;
+            return Ok(false);
         }
-        if d.len() == 8 {
+        if d.len() == AF_SYNC_HEADER_LEN {
             // header only > retrieve payload len and resize the buffer
             let len = (d[2] as usize) << 24
                 | (d[3] as usize) << 16
                 | (d[4] as usize) << 8
                 | (d[5] as usize);
 
-            self.expected_size = len + 10 + 2;
-            self.resize(10 + len + 2);
-            false
+            let size = 10 + len + 2;
+
+            // corrupt (or hostile) header, don't allocate whatever it announces
+            if size > self.max_size {
+                self.reset();
+                return Err(FrameSizeError::TooLarge {
+                    size,
+                    max: self.max_size,
+                });
+            }
+
+            self.expected_size = size;
+            self.resize(size);
+            Ok(false)
         } else {
-            true
+            Ok(true)
         }
     }
 
@@ -109,4 +145,110 @@ impl EdiFrameExtractor {
             frame: ApplicationFrame::new(),
         }
     }
+
+    pub fn with_limits(initial_capacity: usize, max_frame_size: usize) -> Self {
+        EdiFrameExtractor {
+            frame: ApplicationFrame::with_limits(initial_capacity, max_frame_size),
+        }
+    }
+
+    // no usable sync at the start of frame.data (none found, or check_completed() rejected the
+    // header): move the next "AF" candidate after it to the start, a trailing 'A' is kept.
+    // filled: bytes read into frame.data, returns the new count
+    pub fn resync(&mut self, filled: usize) -> usize {
+        let data = &self.frame.data[..filled];
+        let next = (1..filled)
+            .find(|&i| data[i] == b'A' && data.get(i + 1).is_none_or(|&b| b == b'F'))
+            .unwrap_or(filled);
+
+        self.frame.data.copy_within(next..filled, 0);
+        filled - next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn af_packet(payload: &[u8]) -> Vec<u8> {
+        let mut data = b"AF".to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x90, b'T']);
+        data.extend_from_slice(payload);
+        data.extend_from_slice(&[0x00, 0x00]);
+        data
+    }
+
+    // the read loop of the CLI / forwarder / directory, fed byte by byte
+    fn extract(extractor: &mut EdiFrameExtractor, stream: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        let mut filled = 0;
+
+        for &byte in stream {
+            extractor.frame.data[filled] = byte;
+            filled += 1;
+
+            if filled < extractor.frame.data.len() {
+                continue;
+            }
+            if let Some(offset) = extractor.frame.find_sync_magic() {
+                if offset > 0 {
+                    extractor.frame.data.copy_within(offset.., 0);
+                    filled -= offset;
+                    continue;
+                }
+                match extractor.frame.check_completed() {
+                    Ok(true) => {
+                        frames.push(extractor.frame.data.clone());
+                        extractor.frame.reset();
+                        filled = 0;
+                    }
+                    Ok(false) => {}
+                    Err(_) => filled = extractor.resync(filled),
+                }
+            } else {
+                filled = extractor.resync(filled);
+            }
+        }
+
+        frames
+    }
+
+    #[test]
+    fn complete_frames() {
+        let a = af_packet(b"first");
+        let b = af_packet(b"second");
+
+        let mut stream = b"xy".to_vec();
+        stream.extend_from_slice(&a);
+        stream.extend_from_slice(b"garbage without sync, A");
+        stream.extend_from_slice(&b);
+
+        let mut extractor = EdiFrameExtractor::new();
+        assert_eq!(extract(&mut extractor, &stream), vec![a, b]);
+    }
+
+    #[test]
+    fn oversized_header_resync() {
+        let a = af_packet(b"first");
+
+        // LEN announces 1 MiB, more than DEFAULT_MAX_FRAME_SIZE
+        let mut stream = b"AF\x00\x10\x00\x00\x00\x00".to_vec();
+        stream.extend_from_slice(&a);
+
+        let mut extractor = EdiFrameExtractor::new();
+        assert_eq!(extract(&mut extractor, &stream), vec![a]);
+        assert!(extractor.frame.data.capacity() < DEFAULT_MAX_FRAME_SIZE);
+    }
+
+    #[test]
+    fn max_frame_size() {
+        let a = af_packet(b"first");
+
+        let mut extractor = EdiFrameExtractor::with_limits(16, a.len());
+        assert_eq!(extract(&mut extractor, &a), vec![a.clone()]);
+
+        let mut extractor = EdiFrameExtractor::with_limits(16, a.len() - 1);
+        assert!(extract(&mut extractor, &a).is_empty());
+    }
 }
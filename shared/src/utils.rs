@@ -1,16 +1,16 @@
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
 #[allow(unexpected_cfgs)]
 pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
 
-// CRC-16 CCITT
-pub fn calc_crc16_ccitt(data: &[u8]) -> u16 {
-    let initial_invert = true;
-    let final_invert = true;
-    let gen_polynom: u16 = 0x1021;
-
-    let mut crc: u16 = if initial_invert { 0xFFFF } else { 0x0000 };
+// CRC-16, MSB first, no reflection
+pub fn calc_crc16(data: &[u8], gen_polynom: u16, init: u16, xorout: u16) -> u16 {
+    let mut crc = init;
 
     for &byte in data {
         crc ^= (byte as u16) << 8;
@@ -23,28 +23,340 @@ pub fn calc_crc16_ccitt(data: &[u8]) -> u16 {
         }
     }
 
-    if final_invert {
-        crc ^= 0xFFFF;
-    }
+    crc ^ xorout
+}
 
-    crc
+// CRC-16 CCITT, initial and final invert.
+// the same convention for FIB (EN 300 401 5.2.1), MSC data group (5.3.3.4),
+// AF packet (TS 102 821) and AU (TS 102 563) CRCs
+pub fn calc_crc16_ccitt(data: &[u8]) -> u16 {
+    calc_crc16(data, 0x1021, 0xFFFF, 0xFFFF)
 }
 
-// CRC-16 FIRE CODE
+// CRC-16 FIRE CODE (TS 102 563 superframe header)
 pub fn calc_crc_fire_code(data: &[u8]) -> u16 {
-    let gen_polynom: u16 = 0x782F;
-    let mut crc: u16 = 0;
+    calc_crc16(data, 0x782F, 0x0000, 0x0000)
+}
 
-    for &byte in data {
-        crc ^= (byte as u16) << 8;
-        for _ in 0..8 {
-            if crc & 0x8000 != 0 {
-                crc = (crc << 1) ^ gen_polynom;
-            } else {
-                crc <<= 1;
-            }
+#[derive(Debug, Error, PartialEq)]
+pub enum EndpointError {
+    #[error("Missing port in \"{s}\", expected host:port")]
+    MissingPort { s: String },
+
+    #[error("Invalid port in \"{s}\"")]
+    InvalidPort { s: String },
+
+    #[error("Missing host in \"{s}\"")]
+    MissingHost { s: String },
+
+    #[error("IPv6 address in \"{s}\" needs brackets, e.g. [::1]:9000")]
+    UnbracketedIpv6 { s: String },
+}
+
+// host:port, the host is a hostname, IPv4 or IPv6 address (stored without brackets)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    // a bracketed IPv6 host (e.g. --host [::]) is unwrapped
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        let host = host.into();
+        let host = match host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            Some(h) => h.to_string(),
+            None => host,
+        };
+
+        Self { host, port }
+    }
+}
+
+// usable as ToSocketAddrs / for TcpStream::connect, IPv6 addresses in brackets
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = EndpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_endpoint(s)
+    }
+}
+
+// "host:port", "1.2.3.4:9000", "[::1]:9000"
+pub fn parse_endpoint(s: &str) -> Result<Endpoint, EndpointError> {
+    let err_s = || s.to_string();
+
+    let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+        let (host, port) = rest
+            .split_once("]:")
+            .ok_or_else(|| EndpointError::MissingPort { s: err_s() })?;
+        (host, port)
+    } else {
+        let (host, port) = s
+            .rsplit_once(':')
+            .ok_or_else(|| EndpointError::MissingPort { s: err_s() })?;
+
+        if host.contains(':') {
+            return Err(EndpointError::UnbracketedIpv6 { s: err_s() });
         }
+        (host, port)
+    };
+
+    if host.is_empty() {
+        return Err(EndpointError::MissingHost { s: err_s() });
     }
 
-    crc
+    // port 0 can't be connected to
+    let port = match port.parse::<u16>() {
+        Ok(port) if port > 0 => port,
+        _ => return Err(EndpointError::InvalidPort { s: err_s() }),
+    };
+
+    Ok(Endpoint::new(host, port))
+}
+
+// connect to addr, optionally from a local address (multi-homed hosts). the first resolved
+// address of the same family as bind is used. bind port is 0 (ephemeral)
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn connect_tcp(
+    addr: &str,
+    bind: Option<std::net::IpAddr>,
+) -> std::io::Result<tokio::net::TcpStream> {
+    use std::io;
+    use std::net::SocketAddr;
+    use tokio::net::TcpSocket;
+
+    let Some(bind) = bind else {
+        return tokio::net::TcpStream::connect(addr).await;
+    };
+
+    let remote = tokio::net::lookup_host(addr)
+        .await?
+        .find(|a| a.is_ipv4() == bind.is_ipv4())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!(
+                    "Unable to resolve {} to an IPv{} address (bind {})",
+                    addr,
+                    if bind.is_ipv4() { 4 } else { 6 },
+                    bind
+                ),
+            )
+        })?;
+
+    let socket = if remote.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+
+    socket
+        .bind(SocketAddr::new(bind, 0))
+        .map_err(|e| io::Error::new(e.kind(), format!("Unable to bind to {}: {}", bind, e)))?;
+
+    socket.connect(remote).await
+}
+
+// log records of this thread while running f, for tests on warnings
+#[cfg(test)]
+pub(crate) fn capture_logs(f: impl FnOnce()) -> Vec<(log::Level, String)> {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static RECORDS: RefCell<Option<Vec<(log::Level, String)>>> = const { RefCell::new(None) };
+    }
+
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            RECORDS.with(|cell| {
+                if let Some(records) = cell.borrow_mut().as_mut() {
+                    records.push((record.level(), record.args().to_string()));
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        if log::set_logger(&Capture).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+    });
+
+    RECORDS.with(|cell| cell.replace(Some(Vec::new())));
+    f();
+    RECORDS.with(|cell| cell.take()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn connect_tcp_bind() {
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let local = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let (stream, accepted) = tokio::join!(connect_tcp(&addr, Some(local)), listener.accept());
+        let stream = stream.unwrap();
+        let (_, peer) = accepted.unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), local);
+        assert_eq!(peer, stream.local_addr().unwrap());
+
+        // without bind
+        let (stream, accepted) = tokio::join!(connect_tcp(&addr, None), listener.accept());
+        stream.unwrap();
+        accepted.unwrap();
+
+        // no IPv6 address for an IPv4 host
+        let err = connect_tcp(&addr, Some("::1".parse().unwrap()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrNotAvailable);
+
+        // not a local address
+        let err = connect_tcp(&addr, Some("192.0.2.1".parse().unwrap()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Unable to bind to 192.0.2.1"));
+    }
+
+    // check values of CRC-16/GENIBUS and (without xorout) CRC-16/CCITT-FALSE
+    #[test]
+    fn crc16_ccitt_check() {
+        assert_eq!(calc_crc16_ccitt(b"123456789"), 0xD64E);
+        assert_eq!(calc_crc16(b"123456789", 0x1021, 0xFFFF, 0x0000), 0x29B1);
+    }
+
+    #[test]
+    fn crc16_ccitt_fib() {
+        // FIG 0/0 (EId 0xCEDB), end marker, padding
+        let mut fib = vec![0x05, 0x00, 0xCE, 0xDB, 0x00, 0x00, 0xFF];
+        fib.resize(30, 0x00);
+        assert_eq!(calc_crc16_ccitt(&fib), 0xCCBC);
+    }
+
+    #[test]
+    fn crc16_ccitt_af() {
+        // AF header (LEN 4, SEQ 1, CF, MAJ 1, PT "T") and a tag name
+        assert_eq!(
+            calc_crc16_ccitt(b"AF\x00\x00\x00\x04\x00\x01\x90Tdeti"),
+            0x7F44
+        );
+    }
+
+    #[test]
+    fn crc16_ccitt_au() {
+        assert_eq!(calc_crc16_ccitt(&[0x01, 0x02, 0x03, 0x04, 0x05]), 0x6CFB);
+    }
+
+    #[test]
+    fn crc16_ccitt_msc_data_group() {
+        // MOT header segment, transport id 0x1234, 2 byte segment
+        let dg = [
+            0x73, 0x00, 0x80, 0x00, 0x12, 0x12, 0x34, 0x00, 0x02, 0xAB, 0xCD,
+        ];
+        assert_eq!(calc_crc16_ccitt(&dg), 0xB39B);
+    }
+
+    #[test]
+    fn crc_fire_code() {
+        assert_eq!(calc_crc_fire_code(b"123456789"), 0xF8FA);
+
+        // superframe header bytes 2..11, no init / xorout: the code over data + CRC is zero
+        let mut header = vec![0x58, 0x07, 0x20, 0x3E, 0xA0, 0x55, 0x27, 0x00, 0xF1];
+        assert_eq!(calc_crc_fire_code(&header), 0xE9DD);
+        header.extend(0xE9DDu16.to_be_bytes());
+        assert_eq!(calc_crc_fire_code(&header), 0x0000);
+    }
+
+    #[test]
+    fn endpoint_ipv4() {
+        assert_eq!(
+            parse_endpoint("10.0.0.1:9000"),
+            Ok(Endpoint::new("10.0.0.1", 9000))
+        );
+        assert_eq!(Endpoint::new("10.0.0.1", 9000).to_string(), "10.0.0.1:9000");
+    }
+
+    #[test]
+    fn endpoint_ipv6() {
+        let endpoint = parse_endpoint("[::1]:9000").unwrap();
+        assert_eq!(endpoint.host, "::1");
+        assert_eq!(endpoint.port, 9000);
+        assert_eq!(endpoint.to_string(), "[::1]:9000");
+
+        assert_eq!(
+            "[2001:db8::2]:65535".parse::<Endpoint>(),
+            Ok(Endpoint::new("2001:db8::2", 65535))
+        );
+
+        // listen addresses are given with or without brackets
+        assert_eq!(Endpoint::new("[::]", 9000).to_string(), "[::]:9000");
+        assert_eq!(Endpoint::new("::", 9000).to_string(), "[::]:9000");
+    }
+
+    #[test]
+    fn endpoint_hostname() {
+        assert_eq!(
+            parse_endpoint("edi.example.com:8851"),
+            Ok(Endpoint::new("edi.example.com", 8851))
+        );
+        assert_eq!(
+            parse_endpoint("localhost:1"),
+            Ok(Endpoint::new("localhost", 1))
+        );
+    }
+
+    #[test]
+    fn endpoint_malformed() {
+        let missing_port = |s: &str| EndpointError::MissingPort { s: s.to_string() };
+        let invalid_port = |s: &str| EndpointError::InvalidPort { s: s.to_string() };
+        let missing_host = |s: &str| EndpointError::MissingHost { s: s.to_string() };
+
+        for s in ["localhost", "[::1]", "[::1]9000", ""] {
+            assert_eq!(parse_endpoint(s), Err(missing_port(s)), "{s}");
+        }
+        for s in [
+            "localhost:",
+            "localhost:0",
+            "localhost:65536",
+            "localhost:x",
+            "[::1]:",
+        ] {
+            assert_eq!(parse_endpoint(s), Err(invalid_port(s)), "{s}");
+        }
+        for s in [":9000", "[]:9000"] {
+            assert_eq!(parse_endpoint(s), Err(missing_host(s)), "{s}");
+        }
+        assert_eq!(
+            parse_endpoint("::1:9000"),
+            Err(EndpointError::UnbracketedIpv6 {
+                s: "::1:9000".to_string()
+            })
+        );
+    }
 }
@@ -0,0 +1,61 @@
+// the event bus is process global: keep these in their own test binary and in a single test,
+// so no other source touches the user count meanwhile
+use std::time::Duration;
+
+use shared::dab::bus::{emit_event, event_bus_users, init_event_bus, DabEvent};
+use shared::dab::DabSource;
+use tokio::sync::mpsc::error::TryRecvError;
+
+#[tokio::test]
+async fn shared_event_bus() {
+    assert_eq!(event_bus_users(), 0);
+
+    // shutting down one of two sources keeps the bus open for the other
+    let mut rx = init_event_bus();
+    let mut first = DabSource::builder().build();
+    let mut second = DabSource::builder().build();
+    assert_eq!(event_bus_users(), 2);
+
+    first.shutdown();
+    assert_eq!(event_bus_users(), 1);
+    emit_event(DabEvent::NoAudio { scid: 1 });
+    assert!(matches!(rx.try_recv(), Ok(DabEvent::NoAudio { scid: 1 })));
+
+    // repeated shutdown and drop don't release twice
+    first.shutdown();
+    drop(first);
+    assert_eq!(event_bus_users(), 1);
+
+    // dropping without shutdown releases but leaves the bus open
+    for _ in 0..100 {
+        drop(DabSource::builder().build());
+    }
+    assert_eq!(event_bus_users(), 1);
+    assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+
+    // the last shutdown closes it
+    second.shutdown();
+    assert_eq!(event_bus_users(), 0);
+    assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+    drop(second);
+    assert_eq!(event_bus_users(), 0);
+
+    // create / shutdown cycles: every event handler finishes, no tasks pile up
+    for _ in 0..50 {
+        let mut rx = init_event_bus();
+        let handler = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        let mut source = DabSource::builder().build();
+        source.shutdown();
+        tokio::time::timeout(Duration::from_secs(1), handler)
+            .await
+            .expect("event handler still running")
+            .unwrap();
+    }
+    assert_eq!(event_bus_users(), 0);
+    assert_eq!(
+        tokio::runtime::Handle::current()
+            .metrics()
+            .num_alive_tasks(),
+        0
+    );
+}
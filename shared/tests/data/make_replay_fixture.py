@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+"""
+generates replay.edi, a synthetic raw EDI capture (AF packets as written by
+`edinburgh --save-raw`) for tests/replay.rs:
+
+- ensemble 0xCEDB "Edinburgh Test"
+- SId 0xC221 "Edinburgh One": DAB (MP2), SubCh 1, 128 kbit/s, EST payload
+- SId 0xC222 "Edinburgh Two": DAB+, SubCh 2, FIC only (no EST)
+- a bogus AF header (LEN past the end of the file) in front of the first packet
+
+usage: python3 make_replay_fixture.py > replay.edi
+"""
+
+import struct
+import sys
+
+FRAMES = 84  # ~2 seconds
+
+
+def crc16_ccitt(data):
+    crc = 0xFFFF
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x1021) if crc & 0x8000 else crc << 1
+            crc &= 0xFFFF
+    return crc ^ 0xFFFF
+
+
+def fib(figs):
+    data = bytes(figs).ljust(30, b"\xff")
+    return data + struct.pack(">H", crc16_ccitt(data))
+
+
+def fig(fig_type, data):
+    return bytes([(fig_type << 5) | len(data)]) + bytes(data)
+
+
+def label(text):
+    return text.encode("latin-1").ljust(16, b" ") + b"\xff\x00"
+
+
+# FIG 0/0 ensemble, 0/1 subchannels (short form UEP), 0/2 services
+MCI = (
+    fig(0, [0x00, 0xCE, 0xDB, 0x00, 0x00])
+    + fig(0, [0x01, 0x04, 0x00, 33, 0x08, 0x40, 14])
+    + fig(0, [0x02, 0xC2, 0x21, 0x01, 0x00, 0x06, 0xC2, 0x22, 0x01, 0x3F, 0x0A])
+)
+ENSEMBLE_LABEL = fig(1, b"\x00\xce\xdb" + label("Edinburgh Test"))
+SERVICE_LABELS = [
+    fig(1, b"\x01\xc2\x21" + label("Edinburgh One")),
+    fig(1, b"\x01\xc2\x22" + label("Edinburgh Two")),
+]
+
+# MPEG-1 layer II, 128 kbit/s, 48 kHz: 384 bytes, one frame per 24 ms
+MP2_FRAME = b"\xff\xfc\x84\x04".ljust(384, b"\x00")
+
+
+def tag(name, value):
+    return name + struct.pack(">I", len(value) * 8) + value
+
+
+def af_packet(seq, tags):
+    payload = b"".join(tags)
+    data = b"AF" + struct.pack(">IH", len(payload), seq) + b"\x90T" + payload
+    return data + struct.pack(">H", crc16_ccitt(data))
+
+
+def main():
+    out = bytearray(b"AF\x00\x10\x00\x00\x00\x00\x90T")
+
+    for n in range(FRAMES):
+        # FICF, mode I
+        fic = fib(MCI) + fib(ENSEMBLE_LABEL) + fib(SERVICE_LABELS[n % 2])
+        deti = bytes([0x40, 0x00, 0xFF, 0x40, 0x00, 0x00]) + fic
+        # SSTC: SCID 1, SAD 0, TPL 0
+        est = bytes([0x04, 0x00, 0x00]) + MP2_FRAME
+        out += af_packet(n, [tag(b"deti", deti), tag(b"est\x01", est)])
+
+    sys.stdout.buffer.write(out)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,55 @@
+// end-to-end: a synthetic capture (see data/make_replay_fixture.py) through DabSource
+use std::path::PathBuf;
+
+use shared::dab::bus::DabEvent;
+use shared::dab::replay::{replay_file, split_packets};
+use shared::dab::Ensemble;
+
+fn fixture() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/replay.edi")
+}
+
+fn last_ensemble(events: &[DabEvent]) -> &Ensemble {
+    events
+        .iter()
+        .rev()
+        .find_map(|event| match event {
+            DabEvent::EnsembleUpdated(ensemble) => Some(ensemble),
+            _ => None,
+        })
+        .expect("no EnsembleUpdated event")
+}
+
+#[test]
+fn fixture_packets() {
+    let data = std::fs::read(fixture()).unwrap();
+
+    // the bogus header in front of the first packet is skipped
+    assert_eq!(split_packets(&data).len(), 84);
+}
+
+#[tokio::test]
+async fn replay_ensemble_and_audio() {
+    let events = replay_file(fixture(), None).await.unwrap();
+
+    let ensemble = last_ensemble(&events);
+    assert_eq!(ensemble.eid, Some(0xCEDB));
+    assert_eq!(ensemble.label.as_deref(), Some("Edinburgh Test"));
+    assert!(ensemble.complete);
+
+    let mut labels: Vec<_> = ensemble
+        .services
+        .iter()
+        .filter_map(|s| s.label.as_deref())
+        .collect();
+    labels.sort();
+    assert_eq!(labels, ["Edinburgh One", "Edinburgh Two"]);
+
+    assert!(events.iter().any(|event| matches!(
+        event,
+        DabEvent::AudioFormatDetected { scid: 1, format } if format.codec() == "MP2"
+    )));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, DabEvent::Mp2FramesExtracted(r) if r.scid == 1)));
+}
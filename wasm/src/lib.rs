@@ -1,4 +1,5 @@
 use log::{self, Level};
+use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
@@ -10,14 +11,19 @@ use futures::lock::Mutex;
 use futures::StreamExt;
 
 use shared::dab::bus::{init_event_bus, DabEvent};
-use shared::dab::DabSource;
+use shared::dab::{DabSource, Ensemble};
 use shared::utils;
+use web_sys::js_sys::Uint8Array;
 
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct EDI {
     inner: Rc<Mutex<DabSource>>,
     event_target: web_sys::EventTarget,
+    // last ensemble update, readable without awaiting inner
+    ensemble: Rc<RefCell<Option<Ensemble>>>,
+    // reused by feed_array, feeding is serialized by the inner lock
+    feed_buf: Rc<RefCell<Vec<u8>>>,
 }
 
 #[wasm_bindgen]
@@ -39,6 +45,8 @@ impl EDI {
         let edi = EDI {
             inner: edi_source,
             event_target,
+            ensemble: Rc::new(RefCell::new(None)),
+            feed_buf: Rc::new(RefCell::new(Vec::new())),
         };
 
         let edi_clone = edi.clone();
@@ -47,13 +55,22 @@ impl EDI {
             while let Some(event) = event_rx.next().await {
                 let js_event = match &event {
                     DabEvent::EnsembleUpdated(ensemble) => {
+                        edi_clone.ensemble.replace(Some(ensemble.clone()));
                         let data = to_value(&ensemble).unwrap();
                         Some(Self::create_event("ensemble_updated", &data))
                     }
+                    DabEvent::EnsembleChanged(delta) => {
+                        let data = to_value(&delta).unwrap();
+                        Some(Self::create_event("ensemble_changed", &data))
+                    }
                     DabEvent::AacpFramesExtracted(aac) => {
                         let data = to_value(&aac).unwrap();
                         Some(Self::create_event("aac_segment", &data))
                     }
+                    DabEvent::AudioFormatDetected { .. } => {
+                        let data = to_value(&event).unwrap();
+                        Some(Self::create_event("audio_format", &data))
+                    }
                     DabEvent::MotImageReceived(mot) => {
                         let data = to_value(&mot).unwrap();
                         Some(Self::create_event("mot_image", &data))
@@ -62,6 +79,10 @@ impl EDI {
                         let data = to_value(&dl).unwrap();
                         Some(Self::create_event("dl_object", &data))
                     }
+                    DabEvent::AnnouncementActive { .. } => {
+                        let data = to_value(&event).unwrap();
+                        Some(Self::create_event("announcement", &data))
+                    }
                     _ => None,
                 };
 
@@ -88,13 +109,63 @@ impl EDI {
         Ok(())
     }
 
+    // like feed(), but copies the frame from the JS heap only once, into a reused buffer.
+    // the copy happens once the previous feed is done: don't modify the array before the promise resolves
+    #[wasm_bindgen(js_name = feedArray)]
+    pub async fn feed_array(&self, data: Uint8Array) -> Result<(), JsValue> {
+        let mut inner = self.inner.lock().await;
+
+        let mut buf = self.feed_buf.take();
+        buf.resize(data.length() as usize, 0);
+        data.copy_to(&mut buf);
+
+        inner.feed(&buf).await;
+
+        self.feed_buf.replace(buf);
+        Ok(())
+    }
+
     #[wasm_bindgen]
     pub async fn reset(&self) -> Result<(), JsValue> {
         let mut inner = self.inner.lock().await;
         inner.reset();
+        self.ensemble.replace(None);
+        Ok(())
+    }
+
+    // close the event bus, no further events are dispatched
+    #[wasm_bindgen]
+    pub async fn shutdown(&self) -> Result<(), JsValue> {
+        let mut inner = self.inner.lock().await;
+        inner.shutdown();
         Ok(())
     }
 
+    // current ensemble snapshot, null until the first update
+    #[wasm_bindgen]
+    pub fn ensemble(&self) -> JsValue {
+        match self.ensemble.borrow().as_ref() {
+            Some(ensemble) => to_value(ensemble).unwrap(),
+            None => JsValue::NULL,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn services(&self) -> JsValue {
+        match self.ensemble.borrow().as_ref() {
+            Some(ensemble) => to_value(&ensemble.services).unwrap(),
+            None => JsValue::NULL,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn subchannels(&self) -> JsValue {
+        match self.ensemble.borrow().as_ref() {
+            Some(ensemble) => to_value(&ensemble.subchannels).unwrap(),
+            None => JsValue::NULL,
+        }
+    }
+
     #[wasm_bindgen(js_name = addEventListener)]
     pub fn add_event_listener(&self, event: &str, cb: &web_sys::js_sys::Function) {
         self.event_target
@@ -6,3 +6,126 @@ extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
 
 wasm_bindgen_test_configure!(run_in_browser);
+
+use edinburgh_wasm::EDI;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::js_sys::{Array, Promise, Reflect, Uint8Array};
+
+// replay fixture of the shared crate, split into AF packets (header 10 bytes, CRC 2 bytes).
+// an "AF" whose LEN runs past the end is skipped
+fn fixture_packets() -> Vec<&'static [u8]> {
+    let data: &'static [u8] = include_bytes!("../../shared/tests/data/replay.edi");
+    let mut packets = Vec::new();
+    let mut offset = 0;
+
+    while offset + 10 <= data.len() {
+        if &data[offset..offset + 2] != b"AF" {
+            offset += 1;
+            continue;
+        }
+
+        let len = u32::from_be_bytes([
+            data[offset + 2],
+            data[offset + 3],
+            data[offset + 4],
+            data[offset + 5],
+        ]) as usize;
+        let end = offset + 10 + len + 2;
+
+        if end > data.len() {
+            offset += 1;
+            continue;
+        }
+
+        packets.push(&data[offset..end]);
+        offset = end;
+    }
+
+    packets
+}
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &JsValue::from_str(key)).unwrap()
+}
+
+// events are dispatched by a spawned task: yield until done() or give up
+async fn until(done: impl Fn() -> bool) -> bool {
+    for _ in 0..1000 {
+        if done() {
+            return true;
+        }
+        JsFuture::from(Promise::resolve(&JsValue::NULL))
+            .await
+            .unwrap();
+    }
+    done()
+}
+
+fn complete(edi: &EDI) -> bool {
+    let ensemble = edi.ensemble();
+    !ensemble.is_null() && get(&ensemble, "complete").as_bool() == Some(true)
+}
+
+#[wasm_bindgen_test]
+async fn accessors() {
+    let edi = EDI::new();
+
+    // null until the first ensemble update
+    assert!(edi.ensemble().is_null());
+    assert!(edi.services().is_null());
+    assert!(edi.subchannels().is_null());
+
+    for packet in fixture_packets() {
+        edi.feed(packet).await.unwrap();
+    }
+    assert!(until(|| complete(&edi)).await);
+
+    let ensemble = edi.ensemble();
+    assert_eq!(get(&ensemble, "eid").as_f64(), Some(0xCEDB as f64));
+    assert_eq!(
+        get(&ensemble, "label").as_string().as_deref(),
+        Some("Edinburgh Test")
+    );
+
+    let mut services: Vec<(f64, String)> = Array::from(&edi.services())
+        .iter()
+        .map(|s| {
+            let sid = get(&s, "sid").as_f64().unwrap();
+            (sid, get(&s, "label").as_string().unwrap())
+        })
+        .collect();
+    services.sort_by(|a, b| a.0.total_cmp(&b.0));
+    assert_eq!(
+        services,
+        [
+            (0xC221 as f64, "Edinburgh One".to_string()),
+            (0xC222 as f64, "Edinburgh Two".to_string())
+        ]
+    );
+
+    let mut subchannels: Vec<f64> = Array::from(&edi.subchannels())
+        .iter()
+        .map(|s| get(&s, "id").as_f64().unwrap())
+        .collect();
+    subchannels.sort_by(f64::total_cmp);
+    assert_eq!(subchannels, [1.0, 2.0]);
+}
+
+#[wasm_bindgen_test]
+async fn feed_array() {
+    let edi = EDI::new();
+
+    // the reused buffer shrinks to the packet: a larger array first
+    edi.feed_array(Uint8Array::from(&[0u8; 1024][..]))
+        .await
+        .unwrap();
+
+    for packet in fixture_packets() {
+        edi.feed_array(Uint8Array::from(packet)).await.unwrap();
+    }
+    assert!(until(|| complete(&edi)).await);
+
+    assert_eq!(get(&edi.ensemble(), "eid").as_f64(), Some(0xCEDB as f64));
+    assert_eq!(Array::from(&edi.services()).length(), 2);
+}